@@ -1,2 +1,2 @@
-pub use settings::Settings;
+pub use settings::{ClientOutputBufferLimits, OutputBufferLimit, Settings};
 mod settings;
\ No newline at end of file
@@ -1,5 +1,11 @@
 use std::net::SocketAddr;
 use std::time::Duration;
+use crate::protocol::resp::parser::{DEFAULT_MAX_BULK_LEN, DEFAULT_MAX_MULTIBULK_LEN};
+use crate::storage::eviction::{EvictionConfig, EvictionPolicy};
+use crate::storage::EncodingLimits;
+
+// redis refuses a proto-max-bulk-len below 1mb
+const MIN_PROTO_MAX_BULK_LEN: usize = 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -7,12 +13,182 @@ pub struct Settings {
     pub max_connections: usize,
     pub timeout: Option<Duration>,
     pub max_memory: usize,      // in bytes
+    // maxmemory-policy and maxmemory-samples
+    pub eviction: EvictionConfig,
+    pub client_output_buffer_limits: ClientOutputBufferLimits,
+    // largest bulk string and element count a client request may declare
+    pub proto_max_bulk_len: usize,
+    pub proto_max_multibulk_len: usize,
+    // when small aggregates switch to their full-size encoding
+    pub encoding_limits: EncodingLimits,
+}
+
+// a client is dropped as soon as its pending output reaches the hard limit, or once it
+// has stayed at or above the soft limit for `soft_limit_duration`; 0 disables a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    pub hard_limit_bytes: usize,
+    pub soft_limit_bytes: usize,
+    pub soft_limit_duration: Duration,
+}
+
+impl OutputBufferLimit {
+    pub const fn new(hard_limit_bytes: usize, soft_limit_bytes: usize, soft_limit_seconds: u64) -> Self {
+        Self {
+            hard_limit_bytes,
+            soft_limit_bytes,
+            soft_limit_duration: Duration::from_secs(soft_limit_seconds),
+        }
+    }
+}
+
+// there is no pubsub or replication, so every client is a normal one and only that
+// class of redis' client-output-buffer-limit exists here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientOutputBufferLimits {
+    pub normal: OutputBufferLimit,
+}
+
+impl ClientOutputBufferLimits {
+    // parses the redis.conf form: `<class> <hard> <soft> <soft seconds>`, repeated
+    pub fn apply(&mut self, spec: &str) -> Result<(), String> {
+        let fields: Vec<&str> = spec.split_whitespace().collect();
+        if fields.is_empty() || !fields.len().is_multiple_of(4) {
+            return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+        }
+
+        let mut updated = *self;
+        for group in fields.chunks(4) {
+            match group[0].to_ascii_lowercase().as_str() {
+                "normal" => {}
+                "pubsub" | "replica" | "slave" => {
+                    return Err(format!(
+                        "Client class {} is not supported, only normal clients have an output buffer limit.",
+                        group[0]
+                    ))
+                }
+                _ => return Err(format!("Invalid client class specified in buffer limit configuration: {}", group[0])),
+            }
+            let hard = parse_memory(group[1])?;
+            let soft = parse_memory(group[2])?;
+            let soft_seconds = group[3]
+                .parse::<u64>()
+                .map_err(|_| "Error in soft_seconds setting in buffer limit configuration.".to_string())?;
+            updated.normal = OutputBufferLimit::new(hard, soft, soft_seconds);
+        }
+
+        *self = updated;
+        Ok(())
+    }
+}
+
+impl Default for ClientOutputBufferLimits {
+    fn default() -> Self {
+        // same default as redis.conf
+        Self { normal: OutputBufferLimit::new(0, 0, 0) }
+    }
 }
 
 impl Settings {
     pub fn new() -> Self {
         Self::default()
     }
+
+    // builds settings from redis-server style `--name value` command line arguments
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut settings = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument '{}'", arg))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for '--{}'", name))?;
+            settings.set(name, &value)?;
+        }
+
+        Ok(settings)
+    }
+
+    // applies a single directive using its redis.conf name
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name.to_ascii_lowercase().as_str() {
+            "bind" | "listen-addr" => {
+                self.listen_addr = value
+                    .parse()
+                    .map_err(|_| format!("invalid listen address '{}'", value))?;
+            }
+            "port" => {
+                let port = value
+                    .parse::<u16>()
+                    .map_err(|_| format!("invalid port '{}'", value))?;
+                self.listen_addr.set_port(port);
+            }
+            "maxclients" => {
+                self.max_connections = value
+                    .parse()
+                    .map_err(|_| format!("invalid maxclients '{}'", value))?;
+            }
+            "maxmemory" => self.max_memory = parse_memory(value)?,
+            "maxmemory-policy" => {
+                self.eviction.policy = EvictionPolicy::parse(value)
+                    .ok_or_else(|| format!("invalid maxmemory-policy '{}'", value))?;
+            }
+            "maxmemory-samples" => {
+                let samples = parse_count(name, value)?;
+                if samples == 0 {
+                    return Err("maxmemory-samples must be greater than 0".to_string());
+                }
+                self.eviction.samples = samples;
+            }
+            "lfu-log-factor" => {
+                self.eviction.lfu.log_factor = value
+                    .parse()
+                    .map_err(|_| format!("invalid lfu-log-factor '{}'", value))?;
+            }
+            "lfu-decay-time" => {
+                self.eviction.lfu.decay_time = value
+                    .parse()
+                    .map_err(|_| format!("invalid lfu-decay-time '{}'", value))?;
+            }
+            "client-output-buffer-limit" => self.client_output_buffer_limits.apply(value)?,
+            "proto-max-bulk-len" => {
+                let len = parse_memory(value)?;
+                if len < MIN_PROTO_MAX_BULK_LEN {
+                    return Err(format!("proto-max-bulk-len must be at least 1mb, got '{}'", value));
+                }
+                self.proto_max_bulk_len = len;
+            }
+            "proto-max-multibulk-len" => {
+                self.proto_max_multibulk_len = value
+                    .parse()
+                    .map_err(|_| format!("invalid proto-max-multibulk-len '{}'", value))?;
+            }
+            // the ziplist names are the pre-7.0 spellings, still accepted by redis
+            "hash-max-listpack-entries" | "hash-max-ziplist-entries" => {
+                self.encoding_limits.hash.max_entries = parse_count(name, value)?;
+            }
+            "hash-max-listpack-value" | "hash-max-ziplist-value" => {
+                self.encoding_limits.hash.max_value = parse_count(name, value)?;
+            }
+            "set-max-intset-entries" => {
+                self.encoding_limits.set.max_intset_entries = parse_count(name, value)?;
+            }
+            "set-max-listpack-entries" => {
+                self.encoding_limits.set.listpack.max_entries = parse_count(name, value)?;
+            }
+            "set-max-listpack-value" => {
+                self.encoding_limits.set.listpack.max_value = parse_count(name, value)?;
+            }
+            "hll-sparse-max-bytes" => {
+                self.encoding_limits.hll_sparse_max_bytes = parse_count(name, value)?;
+            }
+            _ => return Err(format!("unknown config directive '{}'", name)),
+        }
+        Ok(())
+    }
 }
 
 impl Default for Settings {
@@ -22,6 +198,130 @@ impl Default for Settings {
             max_connections: 10_000,
             timeout: None,
             max_memory: 0,  // 0 means unlimited
+            eviction: EvictionConfig::default(),
+            client_output_buffer_limits: ClientOutputBufferLimits::default(),
+            proto_max_bulk_len: DEFAULT_MAX_BULK_LEN,
+            proto_max_multibulk_len: DEFAULT_MAX_MULTIBULK_LEN,
+            encoding_limits: EncodingLimits::default(),
         }
     }
-}
\ No newline at end of file
+}
+
+fn parse_count(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("invalid {} '{}'", name, value))
+}
+
+// parses memory amounts the way redis.conf does: 1k = 1000, 1kb = 1024, and so on
+pub fn parse_memory(value: &str) -> Result<usize, String> {
+    let lower = value.to_ascii_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lower.len());
+    let (digits, unit) = lower.split_at(split);
+
+    let multiplier: usize = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid memory amount '{}'", value)),
+    };
+
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid memory amount '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("100").unwrap(), 100);
+        assert_eq!(parse_memory("1k").unwrap(), 1000);
+        assert_eq!(parse_memory("1kb").unwrap(), 1024);
+        assert_eq!(parse_memory("32MB").unwrap(), 32 * 1024 * 1024);
+        assert!(parse_memory("12xb").is_err());
+        assert!(parse_memory("mb").is_err());
+    }
+
+    #[test]
+    fn test_client_output_buffer_limit() {
+        let mut settings = Settings::new();
+        settings
+            .set("client-output-buffer-limit", "normal 1mb 512kb 10 NORMAL 2mb 1mb 20")
+            .unwrap();
+
+        let limits = settings.client_output_buffer_limits;
+        assert_eq!(limits.normal, OutputBufferLimit::new(2 * 1024 * 1024, 1024 * 1024, 20));
+    }
+
+    #[test]
+    fn test_client_output_buffer_limit_rejects_bad_spec() {
+        let mut settings = Settings::new();
+        assert!(settings.set("client-output-buffer-limit", "normal 1mb 512kb").is_err());
+        assert!(settings.set("client-output-buffer-limit", "bogus 0 0 0").is_err());
+        // the classes redis has for clients that do not exist here are refused, not ignored
+        let err = settings.set("client-output-buffer-limit", "normal 1mb 512kb 10 pubsub 0 0 0").unwrap_err();
+        assert!(err.contains("Client class pubsub is not supported"));
+        assert!(settings.set("client-output-buffer-limit", "replica 0 0 0").is_err());
+        assert_eq!(settings.client_output_buffer_limits, ClientOutputBufferLimits::default());
+    }
+
+    #[test]
+    fn test_from_args() {
+        let args = ["--port", "7000", "--maxclients", "16"].iter().map(|s| s.to_string());
+        let settings = Settings::from_args(args).unwrap();
+        assert_eq!(settings.listen_addr.port(), 7000);
+        assert_eq!(settings.max_connections, 16);
+        assert!(Settings::from_args(vec!["--port".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_proto_limits() {
+        let mut settings = Settings::new();
+        settings.set("proto-max-bulk-len", "2mb").unwrap();
+        settings.set("proto-max-multibulk-len", "1000").unwrap();
+        assert_eq!(settings.proto_max_bulk_len, 2 * 1024 * 1024);
+        assert_eq!(settings.proto_max_multibulk_len, 1000);
+        assert!(settings.set("proto-max-bulk-len", "1kb").is_err());
+        assert!(settings.set("proto-max-multibulk-len", "-1").is_err());
+    }
+
+    #[test]
+    fn test_encoding_limits() {
+        let mut settings = Settings::new();
+        settings.set("hash-max-listpack-entries", "16").unwrap();
+        settings.set("hash-max-ziplist-value", "32").unwrap();
+        assert_eq!(settings.encoding_limits.hash, crate::storage::ListpackLimits::new(16, 32));
+        settings.set("set-max-intset-entries", "1000").unwrap();
+        assert_eq!(settings.encoding_limits.set.max_intset_entries, 1000);
+        settings.set("hll-sparse-max-bytes", "0").unwrap();
+        assert_eq!(settings.encoding_limits.hll_sparse_max_bytes, 0);
+        assert!(settings.set("hash-max-listpack-value", "big").is_err());
+    }
+
+    #[test]
+    fn test_maxmemory() {
+        let mut settings = Settings::new();
+        assert_eq!(settings.eviction.policy, EvictionPolicy::NoEviction);
+        settings.set("maxmemory", "100mb").unwrap();
+        settings.set("maxmemory-policy", "volatile-ttl").unwrap();
+        settings.set("maxmemory-samples", "10").unwrap();
+        assert_eq!(settings.max_memory, 100 * 1024 * 1024);
+        assert_eq!((settings.eviction.policy, settings.eviction.samples), (EvictionPolicy::VolatileTtl, 10));
+        assert!(settings.set("maxmemory-policy", "lru").is_err());
+        assert!(settings.set("maxmemory-samples", "0").is_err());
+
+        settings.set("lfu-log-factor", "0").unwrap();
+        settings.set("lfu-decay-time", "5").unwrap();
+        assert_eq!(settings.eviction.lfu, crate::storage::eviction::LfuConfig { log_factor: 0, decay_time: 5 });
+        assert!(settings.set("lfu-decay-time", "-1").is_err());
+    }
+}
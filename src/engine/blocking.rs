@@ -0,0 +1,71 @@
+// src/engine/blocking.rs
+use std::time::Instant;
+use crate::engine::reply::Reply;
+use crate::storage::{Command, StreamReadId};
+
+// what running a request produced: a reply now, or a client to park until one of its
+// keys receives data or its timeout passes
+#[derive(Debug)]
+pub enum Outcome {
+    Reply(Reply),
+    Blocked(Blocked),
+}
+
+// a blocking command that found nothing to pop; it is retried as-is when a key is ready
+#[derive(Debug)]
+pub struct Blocked {
+    keys: Vec<Vec<u8>>,
+    deadline: Option<Instant>,
+    command: Command,
+}
+
+impl Blocked {
+    pub(super) fn new(command: Command, now: Instant) -> Self {
+        let (keys, timeout) = match &command {
+            Command::BPop { keys, timeout, .. } | Command::BZPop { keys, timeout, .. } => (keys.clone(), *timeout),
+            Command::BLMove { source, timeout, .. } => (vec![source.clone()], *timeout),
+            Command::XRead { keys, timeout, .. } | Command::XReadGroup { keys, timeout, .. } => {
+                (keys.clone(), *timeout)
+            }
+            _ => (vec![], None),
+        };
+
+        Self {
+            keys,
+            deadline: timeout.map(|timeout| now + timeout),
+            command,
+        }
+    }
+
+    pub fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    // None blocks forever
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub(super) fn command(&self) -> &Command {
+        &self.command
+    }
+
+    // BLPOP / BRPOP / BZPOPMIN / BZPOPMAX / XREAD / XREADGROUP time out with a null array,
+    // BLMOVE / BRPOPLPUSH with a null bulk
+    pub fn timeout_reply(&self) -> Reply {
+        match self.command {
+            Command::BLMove { .. } => Reply::Null,
+            _ => Reply::NullArray,
+        }
+    }
+}
+
+// XREAD / XREADGROUP only block with BLOCK, and XREADGROUP only when reading new entries
+pub(super) fn is_blocking(command: &Command) -> bool {
+    match command {
+        Command::BPop { .. } | Command::BLMove { .. } | Command::BZPop { .. } => true,
+        Command::XRead { block, .. } => *block,
+        Command::XReadGroup { block, ids, .. } => *block && ids.iter().all(|id| *id == StreamReadId::New),
+        _ => false,
+    }
+}
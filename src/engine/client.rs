@@ -0,0 +1,18 @@
+use crate::protocol::resp::types::ProtocolVersion;
+
+// connection-level state that commands such as HELLO read and change
+#[derive(Debug, Default, Clone)]
+pub struct ClientContext {
+    pub id: u64,
+    pub protocol: ProtocolVersion,
+    pub name: Option<Vec<u8>>,
+}
+
+impl ClientContext {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            ..Self::default()
+        }
+    }
+}
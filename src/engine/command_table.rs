@@ -0,0 +1,1977 @@
+// src/engine/command_table.rs
+use std::collections::HashMap;
+use bytes::Bytes;
+use crate::engine::reply::Reply;
+use crate::engine::router;
+use crate::storage::{Command, CommandQuery};
+use crate::utils::error::{RedisError, Result};
+
+// parses a full argv, command name included, into a command for the executor
+pub type CommandHandler = fn(&[Bytes]) -> Result<Command>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Write,
+    ReadOnly,
+    DenyOom,
+    Fast,
+    Admin,
+    PubSub,
+    NoScript,
+    // may park the client until a key is ready
+    Blocking,
+}
+
+impl Flag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::Write => "write",
+            Flag::ReadOnly => "readonly",
+            Flag::DenyOom => "denyoom",
+            Flag::Fast => "fast",
+            Flag::Admin => "admin",
+            Flag::PubSub => "pubsub",
+            Flag::NoScript => "noscript",
+            Flag::Blocking => "blocking",
+        }
+    }
+}
+
+pub struct CommandSpec {
+    // lowercase, as reported by COMMAND
+    pub name: &'static str,
+    // like redis: positive means exactly that many arguments, negative means at least -arity;
+    // the command name itself counts
+    pub arity: i64,
+    pub flags: &'static [Flag],
+    // argv positions of the keys; last_key is negative when counted from the end, and
+    // first_key is 0 for commands without keys
+    pub first_key: i64,
+    pub last_key: i64,
+    pub step: i64,
+    pub group: &'static str,
+    pub since: &'static str,
+    pub summary: &'static str,
+    pub handler: CommandHandler,
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn check_arity(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity >= 0 {
+            argc == self.arity
+        } else {
+            argc >= -self.arity
+        }
+    }
+
+    // argv indexes holding keys, following first/last/step
+    pub fn key_positions(&self, argc: usize) -> Vec<usize> {
+        if self.first_key <= 0 {
+            return vec![];
+        }
+
+        let last = if self.last_key < 0 { argc as i64 + self.last_key } else { self.last_key };
+        let last = last.min(argc as i64 - 1);
+        (self.first_key..=last)
+            .step_by(self.step.max(1) as usize)
+            .map(|pos| pos as usize)
+            .collect()
+    }
+
+    // acl categories, derived the way redis derives the implicit ones
+    fn categories(&self) -> Vec<String> {
+        let mut categories = Vec::new();
+        if self.has_flag(Flag::Write) {
+            categories.push("@write");
+        }
+        if self.has_flag(Flag::ReadOnly) {
+            categories.push("@read");
+        }
+        if self.has_flag(Flag::Admin) {
+            categories.push("@admin");
+            categories.push("@dangerous");
+        }
+        if self.has_flag(Flag::PubSub) {
+            categories.push("@pubsub");
+        }
+        categories.push(if self.has_flag(Flag::Fast) { "@fast" } else { "@slow" });
+
+        let mut categories: Vec<String> = categories.into_iter().map(String::from).collect();
+        categories.push(format!("@{}", self.group));
+        categories
+    }
+
+    fn key_specs(&self) -> Vec<Reply> {
+        if self.first_key <= 0 {
+            return vec![];
+        }
+
+        let access = if self.has_flag(Flag::Write) { "RW" } else { "RO" };
+        // redis expresses the last key relative to the first one unless it counts from the end
+        let last_key = if self.last_key < 0 { self.last_key } else { self.last_key - self.first_key };
+        vec![Reply::Map(vec![
+            (Reply::bulk("flags"), Reply::Set(vec![Reply::Status(access.to_string())])),
+            (Reply::bulk("begin_search"), Reply::Map(vec![
+                (Reply::bulk("type"), Reply::bulk("index")),
+                (Reply::bulk("spec"), Reply::Map(vec![
+                    (Reply::bulk("index"), Reply::Integer(self.first_key)),
+                ])),
+            ])),
+            (Reply::bulk("find_keys"), Reply::Map(vec![
+                (Reply::bulk("type"), Reply::bulk("range")),
+                (Reply::bulk("spec"), Reply::Map(vec![
+                    (Reply::bulk("lastkey"), Reply::Integer(last_key)),
+                    (Reply::bulk("keystep"), Reply::Integer(self.step)),
+                    (Reply::bulk("limit"), Reply::Integer(0)),
+                ])),
+            ])),
+        ])]
+    }
+
+    // one COMMAND / COMMAND INFO entry
+    fn info(&self) -> Reply {
+        Reply::Array(vec![
+            Reply::bulk(self.name),
+            Reply::Integer(self.arity),
+            Reply::Set(self.flags.iter().map(|flag| Reply::Status(flag.name().to_string())).collect()),
+            Reply::Integer(self.first_key),
+            Reply::Integer(self.last_key),
+            Reply::Integer(self.step),
+            Reply::Set(self.categories().into_iter().map(Reply::Status).collect()),
+            // tips
+            Reply::Array(vec![]),
+            Reply::Array(self.key_specs()),
+            // subcommands
+            Reply::Array(vec![]),
+        ])
+    }
+
+    fn docs(&self) -> Reply {
+        Reply::Map(vec![
+            (Reply::bulk("summary"), Reply::bulk(self.summary)),
+            (Reply::bulk("since"), Reply::bulk(self.since)),
+            (Reply::bulk("group"), Reply::bulk(self.group)),
+        ])
+    }
+}
+
+static COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "get",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Returns the string value of a key.",
+        handler: router::get,
+    },
+    CommandSpec {
+        name: "set",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist.",
+        handler: router::set,
+    },
+    CommandSpec {
+        name: "del",
+        arity: 2,
+        flags: &[Flag::Write],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "1.0.0",
+        summary: "Deletes a key.",
+        handler: router::del,
+    },
+    CommandSpec {
+        name: "exists",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "1.0.0",
+        summary: "Determines whether a key exists.",
+        handler: router::exists,
+    },
+    CommandSpec {
+        name: "expire",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "1.0.0",
+        summary: "Sets the expiration time of a key in seconds.",
+        handler: router::expire::expire,
+    },
+    CommandSpec {
+        name: "pexpire",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "2.6.0",
+        summary: "Sets the expiration time of a key in milliseconds.",
+        handler: router::expire::pexpire,
+    },
+    CommandSpec {
+        name: "expireat",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "1.2.0",
+        summary: "Sets the expiration time of a key to a Unix timestamp.",
+        handler: router::expire::expireat,
+    },
+    CommandSpec {
+        name: "pexpireat",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "2.6.0",
+        summary: "Sets the expiration time of a key to a Unix milliseconds timestamp.",
+        handler: router::expire::pexpireat,
+    },
+    CommandSpec {
+        name: "ttl",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "1.0.0",
+        summary: "Returns the expiration time in seconds of a key.",
+        handler: router::expire::ttl,
+    },
+    CommandSpec {
+        name: "pttl",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "2.6.0",
+        summary: "Returns the expiration time in milliseconds of a key.",
+        handler: router::expire::pttl,
+    },
+    CommandSpec {
+        name: "expiretime",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "7.0.0",
+        summary: "Returns the expiration time of a key as a Unix timestamp.",
+        handler: router::expire::expiretime,
+    },
+    CommandSpec {
+        name: "pexpiretime",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "7.0.0",
+        summary: "Returns the expiration time of a key as a Unix milliseconds timestamp.",
+        handler: router::expire::pexpiretime,
+    },
+    CommandSpec {
+        name: "persist",
+        arity: 2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "keyspace",
+        since: "2.2.0",
+        summary: "Removes the expiration time of a key.",
+        handler: router::expire::persist,
+    },
+    CommandSpec {
+        name: "incr",
+        arity: 2,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        handler: router::incr,
+    },
+    CommandSpec {
+        name: "decr",
+        arity: 2,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist.",
+        handler: router::decr,
+    },
+    CommandSpec {
+        name: "append",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.0.0",
+        summary: "Appends a string to the value of a key. Creates the key if it doesn't exist.",
+        handler: router::append,
+    },
+    CommandSpec {
+        name: "incrby",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        handler: router::string::incrby,
+    },
+    CommandSpec {
+        name: "decrby",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist.",
+        handler: router::string::decrby,
+    },
+    CommandSpec {
+        name: "incrbyfloat",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.6.0",
+        summary: "Increment the floating point value of a key by a number. Uses 0 as initial value if the key doesn't exist.",
+        handler: router::string::incrbyfloat,
+    },
+    CommandSpec {
+        name: "getrange",
+        arity: 4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.4.0",
+        summary: "Returns a substring of the string stored at a key.",
+        handler: router::string::getrange,
+    },
+    CommandSpec {
+        name: "setrange",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.2.0",
+        summary: "Overwrites a part of a string value with another by an offset. Creates the key if it doesn't exist.",
+        handler: router::string::setrange,
+    },
+    CommandSpec {
+        name: "strlen",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.2.0",
+        summary: "Returns the length of a string value.",
+        handler: router::string::strlen,
+    },
+    CommandSpec {
+        name: "getex",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "6.2.0",
+        summary: "Returns the string value of a key after setting its expiration time.",
+        handler: router::string::getex,
+    },
+    CommandSpec {
+        name: "getdel",
+        arity: 2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "6.2.0",
+        summary: "Returns the string value of a key after deleting the key.",
+        handler: router::string::getdel,
+    },
+    CommandSpec {
+        name: "setnx",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Set the string value of a key only when the key doesn't exist.",
+        handler: router::string::setnx,
+    },
+    CommandSpec {
+        name: "setex",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.0.0",
+        summary: "Sets the string value and expiration time of a key. Creates the key if it doesn't exist.",
+        handler: router::string::setex,
+    },
+    CommandSpec {
+        name: "psetex",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "string",
+        since: "2.6.0",
+        summary: "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist.",
+        handler: router::string::psetex,
+    },
+    CommandSpec {
+        name: "mget",
+        arity: -2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "string",
+        since: "1.0.0",
+        summary: "Atomically returns the string values of one or more keys.",
+        handler: router::string::mget,
+    },
+    CommandSpec {
+        name: "mset",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 2,
+        group: "string",
+        since: "1.0.1",
+        summary: "Atomically creates or modifies the string values of one or more keys.",
+        handler: router::string::mset,
+    },
+    CommandSpec {
+        name: "msetnx",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 2,
+        group: "string",
+        since: "1.0.1",
+        summary: "Atomically modifies the string values of one or more keys only when all keys don't exist.",
+        handler: router::string::msetnx,
+    },
+    CommandSpec {
+        name: "setbit",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "2.2.0",
+        summary: "Sets or clears the bit at offset of the string value. Creates the key if it doesn't exist.",
+        handler: router::bitmap::setbit,
+    },
+    CommandSpec {
+        name: "getbit",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "2.2.0",
+        summary: "Returns a bit value by offset.",
+        handler: router::bitmap::getbit,
+    },
+    CommandSpec {
+        name: "bitcount",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "2.6.0",
+        summary: "Counts the number of set bits (population counting) in a string.",
+        handler: router::bitmap::bitcount,
+    },
+    CommandSpec {
+        name: "bitpos",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "2.8.7",
+        summary: "Finds the first set (1) or clear (0) bit in a string.",
+        handler: router::bitmap::bitpos,
+    },
+    CommandSpec {
+        name: "bitop",
+        arity: -4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 2,
+        last_key: -1,
+        step: 1,
+        group: "bitmap",
+        since: "2.6.0",
+        summary: "Performs bitwise operations on multiple strings, and stores the result.",
+        handler: router::bitmap::bitop,
+    },
+    CommandSpec {
+        name: "bitfield",
+        arity: -2,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "3.2.0",
+        summary: "Performs arbitrary bitfield integer operations on strings.",
+        handler: router::bitmap::bitfield,
+    },
+    CommandSpec {
+        name: "bitfield_ro",
+        arity: -2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "bitmap",
+        since: "6.0.0",
+        summary: "Performs arbitrary read-only bitfield integer operations on strings.",
+        handler: router::bitmap::bitfield_ro,
+    },
+    CommandSpec {
+        name: "pfadd",
+        arity: -2,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hyperloglog",
+        since: "2.8.9",
+        summary: "Adds elements to a HyperLogLog key. Creates the key if it doesn't exist.",
+        handler: router::hyperloglog::pfadd,
+    },
+    CommandSpec {
+        name: "pfcount",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "hyperloglog",
+        since: "2.8.9",
+        summary: "Returns the approximated cardinality of the set(s) observed by the HyperLogLog key(s).",
+        handler: router::hyperloglog::pfcount,
+    },
+    CommandSpec {
+        name: "pfmerge",
+        arity: -2,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "hyperloglog",
+        since: "2.8.9",
+        summary: "Merges one or more HyperLogLog values into a single key.",
+        handler: router::hyperloglog::pfmerge,
+    },
+    CommandSpec {
+        name: "pfdebug",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Admin],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        group: "hyperloglog",
+        since: "2.8.9",
+        summary: "Internal commands for debugging HyperLogLog values.",
+        handler: router::hyperloglog::pfdebug,
+    },
+    CommandSpec {
+        name: "geoadd",
+        arity: -5,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "geo",
+        since: "3.2.0",
+        summary: "Adds one or more members to a geospatial index. The key is created if it doesn't exist.",
+        handler: router::geo::geoadd,
+    },
+    CommandSpec {
+        name: "geopos",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "geo",
+        since: "3.2.0",
+        summary: "Returns the longitude and latitude of members from a geospatial index.",
+        handler: router::geo::geopos,
+    },
+    CommandSpec {
+        name: "geodist",
+        arity: -4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "geo",
+        since: "3.2.0",
+        summary: "Returns the distance between two members of a geospatial index.",
+        handler: router::geo::geodist,
+    },
+    CommandSpec {
+        name: "geohash",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "geo",
+        since: "3.2.0",
+        summary: "Returns members from a geospatial index as geohash strings.",
+        handler: router::geo::geohash,
+    },
+    CommandSpec {
+        name: "geosearch",
+        arity: -7,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "geo",
+        since: "6.2.0",
+        summary: "Queries a geospatial index for members inside an area of a box or a circle.",
+        handler: router::geo::geosearch,
+    },
+    CommandSpec {
+        name: "geosearchstore",
+        arity: -8,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "geo",
+        since: "6.2.0",
+        summary: "Queries a geospatial index for members inside an area of a box or a circle, optionally stores the result.",
+        handler: router::geo::geosearchstore,
+    },
+    CommandSpec {
+        name: "lpush",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Prepends one or more elements to a list. Creates the key if it doesn't exist.",
+        handler: router::list::lpush,
+    },
+    CommandSpec {
+        name: "rpush",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Appends one or more elements to a list. Creates the key if it doesn't exist.",
+        handler: router::list::rpush,
+    },
+    CommandSpec {
+        name: "lpushx",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "2.2.0",
+        summary: "Prepends one or more elements to a list only when the list exists.",
+        handler: router::list::lpushx,
+    },
+    CommandSpec {
+        name: "rpushx",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "2.2.0",
+        summary: "Appends an element to a list only when the list exists.",
+        handler: router::list::rpushx,
+    },
+    CommandSpec {
+        name: "lpop",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Returns the first elements in a list after removing it. Deletes the list if the last element was popped.",
+        handler: router::list::lpop,
+    },
+    CommandSpec {
+        name: "rpop",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Returns and removes the last elements of a list. Deletes the list if the last element was popped.",
+        handler: router::list::rpop,
+    },
+    CommandSpec {
+        name: "llen",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Returns the length of a list.",
+        handler: router::list::llen,
+    },
+    CommandSpec {
+        name: "lrange",
+        arity: 4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Returns a range of elements from a list.",
+        handler: router::list::lrange,
+    },
+    CommandSpec {
+        name: "lindex",
+        arity: 3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Returns an element from a list by its index.",
+        handler: router::list::lindex,
+    },
+    CommandSpec {
+        name: "lset",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Sets the value of an element in a list by its index.",
+        handler: router::list::lset,
+    },
+    CommandSpec {
+        name: "linsert",
+        arity: 5,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "2.2.0",
+        summary: "Inserts an element before or after another element in a list.",
+        handler: router::list::linsert,
+    },
+    CommandSpec {
+        name: "lrem",
+        arity: 4,
+        flags: &[Flag::Write],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Removes elements from a list. Deletes the list if the last element was removed.",
+        handler: router::list::lrem,
+    },
+    CommandSpec {
+        name: "ltrim",
+        arity: 4,
+        flags: &[Flag::Write],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "1.0.0",
+        summary: "Removes elements from both ends a list. Deletes the list if all elements were trimmed.",
+        handler: router::list::ltrim,
+    },
+    CommandSpec {
+        name: "lpos",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "list",
+        since: "6.0.6",
+        summary: "Returns the index of matching elements in a list.",
+        handler: router::list::lpos,
+    },
+    CommandSpec {
+        name: "lmove",
+        arity: 5,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "list",
+        since: "6.2.0",
+        summary: "Returns an element after popping it from one list and pushing it to another. Deletes the list if the last element was moved.",
+        handler: router::list::lmove,
+    },
+    CommandSpec {
+        name: "rpoplpush",
+        arity: 3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "list",
+        since: "1.2.0",
+        summary: "Returns the last element of a list after removing and pushing it to another list. Deletes the list if the last element was popped.",
+        handler: router::list::rpoplpush,
+    },
+    CommandSpec {
+        name: "blpop",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Blocking],
+        first_key: 1,
+        last_key: -2,
+        step: 1,
+        group: "list",
+        since: "2.0.0",
+        summary: "Removes and returns the first element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        handler: router::list::blpop,
+    },
+    CommandSpec {
+        name: "brpop",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Blocking],
+        first_key: 1,
+        last_key: -2,
+        step: 1,
+        group: "list",
+        since: "2.0.0",
+        summary: "Removes and returns the last element in a list. Blocks until an element is available otherwise. Deletes the list if the last element was popped.",
+        handler: router::list::brpop,
+    },
+    CommandSpec {
+        name: "blmove",
+        arity: 6,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Blocking],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "list",
+        since: "6.2.0",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Blocks until an element is available otherwise. Deletes the list if the last element was moved.",
+        handler: router::list::blmove,
+    },
+    CommandSpec {
+        name: "brpoplpush",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Blocking],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "list",
+        since: "2.2.0",
+        summary: "Pops an element from a list, pushes it to another list and returns it. Block until an element is available otherwise. Deletes the list if the last element was popped.",
+        handler: router::list::brpoplpush,
+    },
+    CommandSpec {
+        name: "hset",
+        arity: -4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Creates or modifies the value of a field in a hash.",
+        handler: router::hash::hset,
+    },
+    CommandSpec {
+        name: "hsetnx",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Sets the value of a field in a hash only when the field doesn't exist.",
+        handler: router::hash::hsetnx,
+    },
+    CommandSpec {
+        name: "hget",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the value of a field in a hash.",
+        handler: router::hash::hget,
+    },
+    CommandSpec {
+        name: "hmget",
+        arity: -3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the values of all fields in a hash.",
+        handler: router::hash::hmget,
+    },
+    CommandSpec {
+        name: "hdel",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Deletes one or more fields and their values from a hash. Deletes the hash if no fields remain.",
+        handler: router::hash::hdel,
+    },
+    CommandSpec {
+        name: "hexists",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Determines whether a field exists in a hash.",
+        handler: router::hash::hexists,
+    },
+    CommandSpec {
+        name: "hlen",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns the number of fields in a hash.",
+        handler: router::hash::hlen,
+    },
+    CommandSpec {
+        name: "hkeys",
+        arity: 2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all fields in a hash.",
+        handler: router::hash::hkeys,
+    },
+    CommandSpec {
+        name: "hvals",
+        arity: 2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all values in a hash.",
+        handler: router::hash::hvals,
+    },
+    CommandSpec {
+        name: "hgetall",
+        arity: 2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Returns all fields and values in a hash.",
+        handler: router::hash::hgetall,
+    },
+    CommandSpec {
+        name: "hincrby",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.0.0",
+        summary: "Increments the integer value of a field in a hash by a number. Uses 0 as initial value if the field doesn't exist.",
+        handler: router::hash::hincrby,
+    },
+    CommandSpec {
+        name: "hincrbyfloat",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.6.0",
+        summary: "Increments the floating point value of a field by a number. Uses 0 as initial value if the field doesn't exist.",
+        handler: router::hash::hincrbyfloat,
+    },
+    CommandSpec {
+        name: "hstrlen",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "3.2.0",
+        summary: "Returns the length of the value of a field.",
+        handler: router::hash::hstrlen,
+    },
+    CommandSpec {
+        name: "hrandfield",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "6.2.0",
+        summary: "Returns one or more random fields from a hash.",
+        handler: router::hash::hrandfield,
+    },
+    CommandSpec {
+        name: "hscan",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "hash",
+        since: "2.8.0",
+        summary: "Iterates over fields and values of a hash.",
+        handler: router::hash::hscan,
+    },
+    CommandSpec {
+        name: "sadd",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Adds one or more members to a set. Creates the key if it doesn't exist.",
+        handler: router::set::sadd,
+    },
+    CommandSpec {
+        name: "srem",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Removes one or more members from a set. Deletes the set if the last member was removed.",
+        handler: router::set::srem,
+    },
+    CommandSpec {
+        name: "sismember",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Determines whether a member belongs to a set.",
+        handler: router::set::sismember,
+    },
+    CommandSpec {
+        name: "smismember",
+        arity: -3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "6.2.0",
+        summary: "Determines whether multiple members belong to a set.",
+        handler: router::set::smismember,
+    },
+    CommandSpec {
+        name: "smembers",
+        arity: 2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns all members of a set.",
+        handler: router::set::smembers,
+    },
+    CommandSpec {
+        name: "scard",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the number of members in a set.",
+        handler: router::set::scard,
+    },
+    CommandSpec {
+        name: "spop",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns one or more random members from a set after removing them. Deletes the set if the last member was popped.",
+        handler: router::set::spop,
+    },
+    CommandSpec {
+        name: "srandmember",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Gets one or more random members from a set.",
+        handler: router::set::srandmember,
+    },
+    CommandSpec {
+        name: "smove",
+        arity: 4,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Moves a member from one set to another.",
+        handler: router::set::smove,
+    },
+    CommandSpec {
+        name: "sinter",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the intersect of multiple sets.",
+        handler: router::set::sinter,
+    },
+    CommandSpec {
+        name: "sunion",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the union of multiple sets.",
+        handler: router::set::sunion,
+    },
+    CommandSpec {
+        name: "sdiff",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Returns the difference of multiple sets.",
+        handler: router::set::sdiff,
+    },
+    CommandSpec {
+        name: "sinterstore",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the intersect of multiple sets in a key.",
+        handler: router::set::sinterstore,
+    },
+    CommandSpec {
+        name: "sunionstore",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the union of multiple sets in a key.",
+        handler: router::set::sunionstore,
+    },
+    CommandSpec {
+        name: "sdiffstore",
+        arity: -3,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: -1,
+        step: 1,
+        group: "set",
+        since: "1.0.0",
+        summary: "Stores the difference of multiple sets in a key.",
+        handler: router::set::sdiffstore,
+    },
+    CommandSpec {
+        name: "sintercard",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        group: "set",
+        since: "7.0.0",
+        summary: "Returns the number of members of the intersect of multiple sets.",
+        handler: router::set::sintercard,
+    },
+    CommandSpec {
+        name: "sscan",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "set",
+        since: "2.8.0",
+        summary: "Iterates over members of a set.",
+        handler: router::set::sscan,
+    },
+    CommandSpec {
+        name: "zadd",
+        arity: -4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Adds one or more members to a sorted set, or updates their scores. Creates the key if it doesn't exist.",
+        handler: router::zset::zadd,
+    },
+    CommandSpec {
+        name: "zrem",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Removes one or more members from a sorted set. Deletes the sorted set if all members were removed.",
+        handler: router::zset::zrem,
+    },
+    CommandSpec {
+        name: "zscore",
+        arity: 3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Returns the score of a member in a sorted set.",
+        handler: router::zset::zscore,
+    },
+    CommandSpec {
+        name: "zincrby",
+        arity: 4,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Increments the score of a member in a sorted set.",
+        handler: router::zset::zincrby,
+    },
+    CommandSpec {
+        name: "zcard",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Returns the number of members in a sorted set.",
+        handler: router::zset::zcard,
+    },
+    CommandSpec {
+        name: "zcount",
+        arity: 4,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.0.0",
+        summary: "Returns the count of members in a sorted set that have scores within a range.",
+        handler: router::zset::zcount,
+    },
+    CommandSpec {
+        name: "zrank",
+        arity: -3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.0.0",
+        summary: "Returns the index of a member in a sorted set ordered by ascending scores.",
+        handler: router::zset::zrank,
+    },
+    CommandSpec {
+        name: "zrevrank",
+        arity: -3,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.0.0",
+        summary: "Returns the index of a member in a sorted set ordered by descending scores.",
+        handler: router::zset::zrevrank,
+    },
+    CommandSpec {
+        name: "zrange",
+        arity: -4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "1.2.0",
+        summary: "Returns members in a sorted set within a range of indexes, scores or lexicographical values.",
+        handler: router::zset::zrange,
+    },
+    CommandSpec {
+        name: "zrangestore",
+        arity: -5,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 2,
+        step: 1,
+        group: "sortedset",
+        since: "6.2.0",
+        summary: "Stores a range of members from sorted set in a key.",
+        handler: router::zset::zrangestore,
+    },
+    CommandSpec {
+        name: "zpopmin",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "5.0.0",
+        summary: "Returns the lowest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        handler: router::zset::zpopmin,
+    },
+    CommandSpec {
+        name: "zpopmax",
+        arity: -2,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "5.0.0",
+        summary: "Returns the highest-scoring members from a sorted set after removing them. Deletes the sorted set if the last member was popped.",
+        handler: router::zset::zpopmax,
+    },
+    CommandSpec {
+        name: "bzpopmin",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Blocking, Flag::Fast],
+        first_key: 1,
+        last_key: -2,
+        step: 1,
+        group: "sortedset",
+        since: "5.0.0",
+        summary: "Removes and returns the member with the lowest score from one or more sorted sets. Blocks until a member is available otherwise. Deletes the sorted set if the last element was popped.",
+        handler: router::zset::bzpopmin,
+    },
+    CommandSpec {
+        name: "bzpopmax",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Blocking, Flag::Fast],
+        first_key: 1,
+        last_key: -2,
+        step: 1,
+        group: "sortedset",
+        since: "5.0.0",
+        summary: "Removes and returns the member with the highest score from one or more sorted sets. Blocks until a member available otherwise. Deletes the sorted set if the last element was popped.",
+        handler: router::zset::bzpopmax,
+    },
+    CommandSpec {
+        name: "zunionstore",
+        arity: -4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.0.0",
+        summary: "Stores the union of multiple sorted sets in a key.",
+        handler: router::zset::zunionstore,
+    },
+    CommandSpec {
+        name: "zinterstore",
+        arity: -4,
+        flags: &[Flag::Write, Flag::DenyOom],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.0.0",
+        summary: "Stores the intersect of multiple sorted sets in a key.",
+        handler: router::zset::zinterstore,
+    },
+    CommandSpec {
+        name: "zscan",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "sortedset",
+        since: "2.8.0",
+        summary: "Iterates over members and scores of a sorted set.",
+        handler: router::zset::zscan,
+    },
+    CommandSpec {
+        name: "xadd",
+        arity: -5,
+        flags: &[Flag::Write, Flag::DenyOom, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Appends a new message to a stream. Creates the key if it doesn't exist.",
+        handler: router::stream::xadd,
+    },
+    CommandSpec {
+        name: "xtrim",
+        arity: -4,
+        flags: &[Flag::Write],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Deletes messages from the beginning of a stream.",
+        handler: router::stream::xtrim,
+    },
+    CommandSpec {
+        name: "xrange",
+        arity: -4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns the messages from a stream within a range of IDs.",
+        handler: router::stream::xrange,
+    },
+    CommandSpec {
+        name: "xrevrange",
+        arity: -4,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns the messages from a stream within a range of IDs in reverse order.",
+        handler: router::stream::xrevrange,
+    },
+    CommandSpec {
+        name: "xlen",
+        arity: 2,
+        flags: &[Flag::ReadOnly, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Return the number of messages in a stream.",
+        handler: router::stream::xlen,
+    },
+    CommandSpec {
+        name: "xdel",
+        arity: -3,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns the number of messages after removing them from a stream.",
+        handler: router::stream::xdel,
+    },
+    CommandSpec {
+        name: "xread",
+        arity: -4,
+        flags: &[Flag::ReadOnly, Flag::Blocking],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns messages from multiple streams with IDs greater than the ones requested. Blocks until a message is available otherwise.",
+        handler: router::stream::xread,
+    },
+    CommandSpec {
+        name: "xreadgroup",
+        arity: -7,
+        flags: &[Flag::Write, Flag::Blocking],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns new or historical messages from a stream for a consumer in a group. Blocks until a message is available otherwise.",
+        handler: router::stream::xreadgroup,
+    },
+    CommandSpec {
+        name: "xack",
+        arity: -4,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns the number of messages that were successfully acknowledged by the consumer group member of a stream.",
+        handler: router::stream::xack,
+    },
+    CommandSpec {
+        name: "xpending",
+        arity: -3,
+        flags: &[Flag::ReadOnly],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns the information and entries from a stream consumer group's pending entries list.",
+        handler: router::stream::xpending,
+    },
+    CommandSpec {
+        name: "xclaim",
+        arity: -6,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Changes, or acquires, ownership of a message in a consumer group, as if the message was delivered a consumer group member.",
+        handler: router::stream::xclaim,
+    },
+    CommandSpec {
+        name: "xautoclaim",
+        arity: -6,
+        flags: &[Flag::Write, Flag::Fast],
+        first_key: 1,
+        last_key: 1,
+        step: 1,
+        group: "stream",
+        since: "6.2.0",
+        summary: "Changes, or acquires, ownership of messages in a consumer group, as if the messages were delivered to as consumer group member.",
+        handler: router::stream::xautoclaim,
+    },
+    CommandSpec {
+        name: "xgroup",
+        arity: -2,
+        flags: &[Flag::Write],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Creates, destroys and manages consumer groups and their consumers.",
+        handler: router::stream::xgroup,
+    },
+    CommandSpec {
+        name: "xinfo",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        group: "stream",
+        since: "5.0.0",
+        summary: "Returns information about streams, consumer groups and consumers.",
+        handler: router::stream::xinfo,
+    },
+    CommandSpec {
+        name: "object",
+        arity: -2,
+        flags: &[Flag::ReadOnly],
+        first_key: 2,
+        last_key: 2,
+        step: 1,
+        group: "keyspace",
+        since: "2.2.3",
+        summary: "Returns the internal encoding, access frequency or idle time of a key.",
+        handler: router::object,
+    },
+    CommandSpec {
+        name: "hotkeys",
+        arity: -1,
+        flags: &[Flag::ReadOnly],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "keyspace",
+        since: "0.1.0",
+        summary: "Returns the most frequently accessed keys and their access counters.",
+        handler: router::hotkeys,
+    },
+    CommandSpec {
+        name: "debug",
+        arity: -2,
+        flags: &[Flag::Admin],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "server",
+        since: "1.0.0",
+        summary: "A container for debugging commands.",
+        handler: router::debug,
+    },
+    CommandSpec {
+        name: "flushdb",
+        arity: -1,
+        flags: &[Flag::Write],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "server",
+        since: "1.0.0",
+        summary: "Removes all keys from the current database.",
+        handler: router::flushdb,
+    },
+    CommandSpec {
+        name: "ping",
+        arity: -1,
+        flags: &[Flag::Fast],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "connection",
+        since: "1.0.0",
+        summary: "Returns the server's liveliness response.",
+        handler: router::ping,
+    },
+    CommandSpec {
+        name: "echo",
+        arity: 2,
+        flags: &[Flag::Fast],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "connection",
+        since: "1.0.0",
+        summary: "Returns the given string.",
+        handler: router::echo,
+    },
+    CommandSpec {
+        name: "hello",
+        arity: -1,
+        flags: &[Flag::NoScript, Flag::Fast],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "connection",
+        since: "6.0.0",
+        summary: "Handshakes with the Redis server.",
+        handler: router::hello,
+    },
+    CommandSpec {
+        name: "command",
+        arity: -1,
+        flags: &[],
+        first_key: 0,
+        last_key: 0,
+        step: 0,
+        group: "server",
+        since: "2.8.13",
+        summary: "Returns detailed information about all commands.",
+        handler: router::command,
+    },
+];
+
+// name -> spec lookup over the static table
+pub struct CommandTable {
+    commands: HashMap<&'static str, &'static CommandSpec>,
+}
+
+impl Default for CommandTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandTable {
+    pub fn new() -> Self {
+        Self {
+            commands: COMMANDS.iter().map(|spec| (spec.name, spec)).collect(),
+        }
+    }
+
+    // case-insensitive, command names are ascii
+    pub fn lookup(&self, name: &[u8]) -> Option<&'static CommandSpec> {
+        let name = std::str::from_utf8(name).ok()?.to_ascii_lowercase();
+        self.commands.get(name.as_str()).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // answers COMMAND and its subcommands
+    pub fn introspect(&self, query: CommandQuery) -> Result<Reply> {
+        match query {
+            CommandQuery::List => Ok(Reply::Array(COMMANDS.iter().map(CommandSpec::info).collect())),
+            CommandQuery::Count => Ok(Reply::Integer(self.len() as i64)),
+            CommandQuery::Info(names) if names.is_empty() => self.introspect(CommandQuery::List),
+            CommandQuery::Info(names) => Ok(Reply::Array(
+                names
+                    .iter()
+                    .map(|name| self.lookup(name).map_or(Reply::Null, CommandSpec::info))
+                    .collect(),
+            )),
+            CommandQuery::Docs(names) => {
+                let specs: Vec<&CommandSpec> = if names.is_empty() {
+                    COMMANDS.iter().collect()
+                } else {
+                    // unknown names are silently skipped, like redis
+                    names.iter().filter_map(|name| self.lookup(name)).collect()
+                };
+                Ok(Reply::Map(specs.into_iter().map(|spec| (Reply::bulk(spec.name), spec.docs())).collect()))
+            }
+            CommandQuery::GetKeys(argv) => {
+                let spec = self
+                    .lookup(&argv[0])
+                    .ok_or_else(|| RedisError::InvalidArgument("Invalid command specified".to_string()))?;
+                if !spec.check_arity(argv.len()) {
+                    return Err(RedisError::InvalidArgument(
+                        "Invalid number of arguments specified for command".to_string(),
+                    ));
+                }
+
+                let positions = spec.key_positions(argv.len());
+                if positions.is_empty() {
+                    return Err(RedisError::InvalidArgument("The command has no key arguments".to_string()));
+                }
+                Ok(Reply::Array(positions.into_iter().map(|pos| Reply::bulk(argv[pos].as_slice())).collect()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(parts: &[&str]) -> Vec<Vec<u8>> {
+        parts.iter().map(|part| part.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_table_is_consistent() {
+        let table = CommandTable::new();
+        assert_eq!(table.len(), COMMANDS.len(), "duplicate command name");
+        for spec in COMMANDS {
+            assert_eq!(spec.name, spec.name.to_ascii_lowercase());
+            assert!(spec.arity != 0, "{} has no arity", spec.name);
+            assert!(!(spec.has_flag(Flag::Write) && spec.has_flag(Flag::ReadOnly)), "{}", spec.name);
+            if spec.first_key > 0 {
+                assert!(spec.step > 0, "{} has keys but no step", spec.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_arity() {
+        let table = CommandTable::new();
+        let get = table.lookup(b"GeT").unwrap();
+        assert!(get.check_arity(2));
+        assert!(!get.check_arity(3));
+
+        let set = table.lookup(b"set").unwrap();
+        assert!(!set.check_arity(2));
+        assert!(set.check_arity(5));
+
+        assert!(table.lookup(b"nosuchcommand").is_none());
+        assert!(table.lookup(b"\xff").is_none());
+    }
+
+    #[test]
+    fn test_getkeys() {
+        let table = CommandTable::new();
+        assert_eq!(
+            table.introspect(CommandQuery::GetKeys(argv(&["set", "k", "v", "PX", "10"]))).unwrap(),
+            Reply::Array(vec![Reply::bulk("k")])
+        );
+        assert!(table.introspect(CommandQuery::GetKeys(argv(&["ping"]))).is_err());
+        assert!(table.introspect(CommandQuery::GetKeys(argv(&["get"]))).is_err());
+        assert!(table.introspect(CommandQuery::GetKeys(argv(&["nope", "k"]))).is_err());
+    }
+
+    #[test]
+    fn test_info_and_docs() {
+        let table = CommandTable::new();
+        assert_eq!(table.introspect(CommandQuery::Count).unwrap(), Reply::Integer(COMMANDS.len() as i64));
+
+        match table.introspect(CommandQuery::Info(argv(&["get", "nope"]))).unwrap() {
+            Reply::Array(entries) => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(entries[1], Reply::Null);
+                match &entries[0] {
+                    Reply::Array(info) => {
+                        assert_eq!(info[0], Reply::bulk("get"));
+                        assert_eq!(info[1], Reply::Integer(2));
+                        assert_eq!(info[2], Reply::Set(vec![
+                            Reply::Status("readonly".to_string()),
+                            Reply::Status("fast".to_string()),
+                        ]));
+                        assert_eq!(&info[3..6], &[Reply::Integer(1), Reply::Integer(1), Reply::Integer(1)]);
+                    }
+                    other => panic!("unexpected {:?}", other),
+                }
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match table.introspect(CommandQuery::Docs(argv(&["echo", "nope"]))).unwrap() {
+            Reply::Map(docs) => {
+                assert_eq!(docs.len(), 1);
+                assert_eq!(docs[0].0, Reply::bulk("echo"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -1,67 +1,748 @@
 // src/engine/executor.rs
-use crate::storage::{memory::MemoryStorage, Storage, StorageValue, Command};
-use crate::utils::error::Result;
+use crate::storage::{
+    eviction::EvictionConfig, memory::MemoryStorage, BitmapStorage, Command, DebugCommand, Delivered, EncodingLimits, GeoMatch,
+    GeoSearch, GeoStorage, HashStorage, HyperLogLogStorage, KeyExpiry, ListStorage, ObjectQuery, PfDebug,
+    SetCondition, SetExpiry, SetOptions, SetStorage, Storage, StorageValue, Stream, StreamEntry, StreamId,
+    StreamReadId, StreamStorage, StringStorage, TtlQuery, XGroupCommand, XInfoQuery, ZSetStorage,
+};
+use crate::utils::error::{Result, RedisError};
 use crate::storage::memory::ExpireCycleType;
+use crate::engine::reply::Reply;
+use crate::utils::clock::{MonotonicClock, SharedClock};
+use crate::protocol::resp::encoder::format_double;
 
 pub struct Executor {
     storage: MemoryStorage,
+    // keys that received list, sorted set or stream elements, so blocked clients waiting on
+    // them can be served
+    ready_keys: Vec<Vec<u8>>,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Executor {
     pub fn new() -> Self {
+        Self::with_clock(MonotonicClock::shared())
+    }
+
+    pub fn with_clock(clock: SharedClock) -> Self {
         Self {
-            storage: MemoryStorage::new(1024 * 1024) // 1MB default
+            storage: MemoryStorage::with_clock(1024 * 1024, clock), // 1MB default
+            ready_keys: Vec::new(),
         }
     }
 
-    pub fn execute(&mut self, command: Command) -> Result<String> {
+    pub fn execute(&mut self, command: Command) -> Result<Reply> {
         match command {
             Command::Get(key) => {
-                let entry = self.storage.get(&key)?;
-                Ok(match &entry.data {
-                    StorageValue::String(s) => s.clone(),
-                    StorageValue::List(l) => l.join(" ")
-                })
-            },
-            Command::Set(key, value, ttl) => {
-                self.storage.set(key, value, ttl)?;
-                Ok("OK".to_string())
+                match self.storage.get(&key) {
+                    Ok(entry) => match &entry.data {
+                        StorageValue::String(s) => Ok(Reply::bulk(s.as_slice())),
+                        _ => Err(RedisError::WrongType),
+                    },
+                    Err(RedisError::KeyNotFound) => Ok(Reply::Null),
+                    Err(e) => Err(e),
+                }
             },
+            Command::Set(key, value, options) => self.set(key, value, options),
             Command::Del(key) => {
                 let deleted = self.storage.delete(&key)?;
-                Ok(if deleted {
-                    "OK".to_string()
-                } else {
-                    "Key not found".to_string()
-                })
+                Ok(Reply::Integer(deleted as i64))
             },
             Command::Exists(key) => {
                 let exists = self.storage.exists(&key)?;
-                Ok(exists.to_string())
+                Ok(Reply::Integer(exists as i64))
             },
             Command::FlushDB => {
                 self.storage.clear()?;
-                Ok("OK".to_string())
+                Ok(Reply::ok())
+            },
+            Command::Ping(message) => {
+                Ok(match message {
+                    Some(message) => Reply::Bulk(message),
+                    None => Reply::Status("PONG".to_string()),
+                })
             },
-            Command::Expire(key, ttl) => {
-                self.storage.expire(&key, ttl)?;
-                Ok("OK".to_string())
+            Command::Echo(message) => Ok(Reply::Bulk(message)),
+            Command::Expire { key, ms, from_now, options } => {
+                // far enough out to overflow is as good as never
+                let at_ms = if from_now { (self.storage.now_ms() as i64).saturating_add(ms) } else { ms };
+                Ok(Reply::Integer(self.storage.expire(&key, at_ms, options)? as i64))
             },
+            Command::Ttl(key, query) => self.ttl(&key, query),
+            Command::Persist(key) => Ok(Reply::Integer(self.storage.persist(&key)? as i64)),
             Command::Incr(key) => {
                 let res = self.storage.incr(&key)?;
-                Ok(res.to_string())
+                Ok(Reply::Integer(res))
             },
             Command::Decr(key) => {
                 let res = self.storage.decr(&key)?;
-                Ok(res.to_string())
+                Ok(Reply::Integer(res))
             },
             Command::Append(key, value) => {
                 let res = self.storage.append(&key, &value)?;
-                Ok(res)
+                Ok(Reply::Integer(res as i64))
+            },
+            Command::IncrBy(key, delta) => Ok(Reply::Integer(self.storage.incr_by(&key, delta)?)),
+            Command::IncrByFloat(key, delta) => Ok(Reply::Bulk(self.storage.incr_by_float(&key, delta)?)),
+            Command::GetRange(key, start, end) => Ok(Reply::Bulk(self.storage.getrange(&key, start, end)?)),
+            Command::SetRange(key, offset, value) => {
+                Ok(Reply::Integer(self.storage.setrange(&key, offset, &value)? as i64))
+            },
+            Command::StrLen(key) => Ok(Reply::Integer(self.storage.strlen(&key)? as i64)),
+            Command::GetEx(key, expiry) => Ok(self.storage.getex(&key, expiry)?.map_or(Reply::Null, Reply::Bulk)),
+            Command::GetDel(key) => Ok(self.storage.getdel(&key)?.map_or(Reply::Null, Reply::Bulk)),
+            Command::MGet(keys) => Ok(Reply::Array(
+                self.storage.mget(&keys)?.into_iter().map(|value| value.map_or(Reply::Null, Reply::Bulk)).collect(),
+            )),
+            Command::MSet { pairs, only_new: false } => {
+                self.storage.mset(pairs, false)?;
+                Ok(Reply::ok())
+            },
+            Command::MSet { pairs, only_new: true } => Ok(Reply::Integer(self.storage.mset(pairs, true)? as i64)),
+            Command::SetBit(key, offset, on) => Ok(Reply::Integer(self.storage.setbit(&key, offset, on)? as i64)),
+            Command::GetBit(key, offset) => Ok(Reply::Integer(self.storage.getbit(&key, offset)? as i64)),
+            Command::BitCount(key, range) => Ok(Reply::Integer(self.storage.bitcount(&key, range)? as i64)),
+            Command::BitPos { key, bit, range, end_given } => {
+                Ok(Reply::Integer(self.storage.bitpos(&key, bit, range, end_given)?))
+            },
+            Command::BitOp { op, destination, keys } => {
+                Ok(Reply::Integer(self.storage.bitop(op, &destination, &keys)? as i64))
+            },
+            Command::BitField(key, ops) => Ok(Reply::Array(
+                self.storage
+                    .bitfield(&key, &ops)?
+                    .into_iter()
+                    .map(|value| value.map_or(Reply::Null, Reply::Integer))
+                    .collect(),
+            )),
+            Command::PfAdd(key, elements) => Ok(Reply::Integer(self.storage.pfadd(&key, &elements)? as i64)),
+            Command::PfCount(keys) => Ok(Reply::Integer(self.storage.pfcount(&keys)? as i64)),
+            Command::PfMerge(destination, keys) => {
+                self.storage.pfmerge(&destination, &keys)?;
+                Ok(Reply::ok())
+            },
+            Command::PfDebug(PfDebug::GetReg, key) => Ok(Reply::Array(
+                self.storage
+                    .pfdebug_registers(&key)?
+                    .into_iter()
+                    .map(|register| Reply::Integer(register as i64))
+                    .collect(),
+            )),
+            Command::PfDebug(PfDebug::Decode, key) => Ok(Reply::Status(self.storage.pfdebug_decode(&key)?)),
+            Command::PfDebug(PfDebug::Encoding, key) => {
+                Ok(Reply::Status(self.storage.pfdebug_encoding(&key)?.name().to_string()))
+            },
+            Command::PfDebug(PfDebug::ToDense, key) => {
+                Ok(Reply::Integer(self.storage.pfdebug_todense(&key)? as i64))
+            },
+            Command::Push { key, values, end, only_existing } => {
+                let len = self.storage.push(&key, values, end, only_existing)?;
+                if len > 0 {
+                    self.ready_keys.push(key);
+                }
+                Ok(Reply::Integer(len as i64))
+            },
+            Command::Pop { key, end, count: None } => {
+                let popped = self.storage.pop(&key, end, 1)?.and_then(|mut values| values.pop());
+                Ok(popped.map_or(Reply::Null, Reply::Bulk))
+            },
+            Command::Pop { key, end, count: Some(count) } => {
+                Ok(match self.storage.pop(&key, end, count)? {
+                    Some(values) => bulk_array(values),
+                    None => Reply::NullArray,
+                })
+            },
+            Command::LLen(key) => Ok(Reply::Integer(self.storage.llen(&key)? as i64)),
+            Command::LRange(key, start, stop) => Ok(bulk_array(self.storage.lrange(&key, start, stop)?)),
+            Command::LIndex(key, index) => {
+                Ok(self.storage.lindex(&key, index)?.map_or(Reply::Null, Reply::Bulk))
+            },
+            Command::LSet(key, index, value) => {
+                self.storage.lset(&key, index, value)?;
+                Ok(Reply::ok())
+            },
+            Command::LInsert { key, side, pivot, value } => {
+                Ok(Reply::Integer(self.storage.linsert(&key, side, &pivot, value)?))
+            },
+            Command::LRem(key, count, value) => {
+                Ok(Reply::Integer(self.storage.lrem(&key, count, &value)? as i64))
+            },
+            Command::LTrim(key, start, stop) => {
+                self.storage.ltrim(&key, start, stop)?;
+                Ok(Reply::ok())
+            },
+            Command::LPos { key, value, rank, count, maxlen } => {
+                let positions = self.storage.lpos(&key, &value, rank, count.unwrap_or(1), maxlen)?;
+                Ok(match count {
+                    Some(_) => Reply::Array(positions.into_iter().map(|i| Reply::Integer(i as i64)).collect()),
+                    None => positions.first().map_or(Reply::Null, |&i| Reply::Integer(i as i64)),
+                })
+            },
+            Command::LMove { source, destination, from, to } => {
+                let moved = self.storage.lmove(&source, &destination, from, to)?;
+                if moved.is_some() {
+                    self.ready_keys.push(destination);
+                }
+                Ok(moved.map_or(Reply::Null, Reply::Bulk))
+            },
+            Command::HSet { key, pairs } => Ok(Reply::Integer(self.storage.hset(&key, pairs)? as i64)),
+            Command::HSetNx { key, field, value } => {
+                Ok(Reply::Integer(self.storage.hsetnx(&key, field, value)? as i64))
+            },
+            Command::HGet(key, field) => Ok(self.storage.hget(&key, &field)?.map_or(Reply::Null, Reply::Bulk)),
+            Command::HMGet(key, fields) => Ok(Reply::Array(
+                self.storage
+                    .hmget(&key, &fields)?
+                    .into_iter()
+                    .map(|value| value.map_or(Reply::Null, Reply::Bulk))
+                    .collect(),
+            )),
+            Command::HDel(key, fields) => Ok(Reply::Integer(self.storage.hdel(&key, &fields)? as i64)),
+            Command::HExists(key, field) => Ok(Reply::Integer(self.storage.hexists(&key, &field)? as i64)),
+            Command::HLen(key) => Ok(Reply::Integer(self.storage.hlen(&key)? as i64)),
+            Command::HKeys(key) => Ok(bulk_array(self.storage.hkeys(&key)?)),
+            Command::HVals(key) => Ok(bulk_array(self.storage.hvals(&key)?)),
+            Command::HGetAll(key) => Ok(Reply::Map(
+                self.storage
+                    .hgetall(&key)?
+                    .into_iter()
+                    .map(|(field, value)| (Reply::Bulk(field), Reply::Bulk(value)))
+                    .collect(),
+            )),
+            Command::HIncrBy(key, field, delta) => Ok(Reply::Integer(self.storage.hincrby(&key, &field, delta)?)),
+            Command::HIncrByFloat(key, field, delta) => {
+                Ok(Reply::Bulk(self.storage.hincrbyfloat(&key, &field, delta)?))
+            },
+            Command::HStrLen(key, field) => Ok(Reply::Integer(self.storage.hstrlen(&key, &field)? as i64)),
+            Command::HRandField { key, count: None, .. } => {
+                let picked = self.storage.hrandfield(&key, 1)?.pop();
+                Ok(picked.map_or(Reply::Null, |(field, _)| Reply::Bulk(field)))
+            },
+            Command::HRandField { key, count: Some(count), with_values } => {
+                let picked = self.storage.hrandfield(&key, count)?;
+                Ok(Reply::Array(
+                    picked
+                        .into_iter()
+                        .flat_map(|(field, value)| {
+                            let value = with_values.then_some(Reply::Bulk(value));
+                            std::iter::once(Reply::Bulk(field)).chain(value)
+                        })
+                        .collect(),
+                ))
+            },
+            Command::HScan { key, cursor, options } => {
+                let (next, pairs) = self.storage.hscan(&key, cursor, &options)?;
+                let items = pairs
+                    .into_iter()
+                    .flat_map(|(field, value)| {
+                        let value = (!options.no_values).then_some(Reply::Bulk(value));
+                        std::iter::once(Reply::Bulk(field)).chain(value)
+                    })
+                    .collect();
+                Ok(Reply::Array(vec![Reply::bulk(next.to_string()), Reply::Array(items)]))
+            },
+            Command::SAdd(key, members) => Ok(Reply::Integer(self.storage.sadd(&key, members)? as i64)),
+            Command::SRem(key, members) => Ok(Reply::Integer(self.storage.srem(&key, &members)? as i64)),
+            Command::SIsMember(key, member) => Ok(Reply::Integer(self.storage.sismember(&key, &member)? as i64)),
+            Command::SMIsMember(key, members) => Ok(Reply::Array(
+                self.storage
+                    .smismember(&key, &members)?
+                    .into_iter()
+                    .map(|found| Reply::Integer(found as i64))
+                    .collect(),
+            )),
+            Command::SMembers(key) => Ok(bulk_set(self.storage.smembers(&key)?)),
+            Command::SCard(key) => Ok(Reply::Integer(self.storage.scard(&key)? as i64)),
+            Command::SPop { key, count: None } => {
+                Ok(self.storage.spop(&key, 1)?.pop().map_or(Reply::Null, Reply::Bulk))
+            },
+            Command::SPop { key, count: Some(count) } => Ok(bulk_set(self.storage.spop(&key, count)?)),
+            Command::SRandMember { key, count: None } => {
+                Ok(self.storage.srandmember(&key, 1)?.pop().map_or(Reply::Null, Reply::Bulk))
+            },
+            Command::SRandMember { key, count: Some(count) } => Ok(bulk_array(self.storage.srandmember(&key, count)?)),
+            Command::SMove { source, destination, member } => {
+                Ok(Reply::Integer(self.storage.smove(&source, &destination, &member)? as i64))
+            },
+            Command::SetOp(op, keys) => Ok(bulk_set(self.storage.set_op(op, &keys)?)),
+            Command::SetOpStore { op, destination, keys } => {
+                Ok(Reply::Integer(self.storage.set_op_store(op, &destination, &keys)? as i64))
+            },
+            Command::SInterCard { keys, limit } => Ok(Reply::Integer(self.storage.sintercard(&keys, limit)? as i64)),
+            Command::SScan { key, cursor, options } => {
+                let (next, members) = self.storage.sscan(&key, cursor, &options)?;
+                Ok(Reply::Array(vec![Reply::bulk(next.to_string()), bulk_array(members)]))
+            },
+            Command::ZAdd { key, mut members, options } if options.incr => {
+                let (delta, member) = members.pop().expect("ZADD INCR takes exactly one pair");
+                let score = self.storage.zincr(&key, member, delta, options)?;
+                if score.is_some() {
+                    self.ready_keys.push(key);
+                }
+                Ok(score.map_or(Reply::Null, Reply::Double))
+            },
+            Command::ZAdd { key, members, options } => {
+                let counted = self.storage.zadd(&key, members, options)?;
+                self.ready_keys.push(key);
+                Ok(Reply::Integer(counted as i64))
+            },
+            Command::ZRem(key, members) => Ok(Reply::Integer(self.storage.zrem(&key, &members)? as i64)),
+            Command::ZScore(key, member) => {
+                Ok(self.storage.zscore(&key, &member)?.map_or(Reply::Null, Reply::Double))
+            },
+            Command::ZIncrBy(key, delta, member) => {
+                let score = self.storage.zincr(&key, member, delta, Default::default())?;
+                self.ready_keys.push(key);
+                Ok(score.map_or(Reply::Null, Reply::Double))
+            },
+            Command::ZCard(key) => Ok(Reply::Integer(self.storage.zcard(&key)? as i64)),
+            Command::ZCount(key, range) => Ok(Reply::Integer(self.storage.zcount(&key, &range)? as i64)),
+            Command::ZRank { key, member, rev, with_score } => {
+                Ok(match self.storage.zrank(&key, &member, rev)? {
+                    Some((rank, score)) if with_score => {
+                        Reply::Array(vec![Reply::Integer(rank as i64), Reply::Double(score)])
+                    }
+                    Some((rank, _)) => Reply::Integer(rank as i64),
+                    None if with_score => Reply::NullArray,
+                    None => Reply::Null,
+                })
+            },
+            Command::ZRange(key, query) => {
+                Ok(scored_array(self.storage.zrange(&key, &query)?, query.with_scores))
+            },
+            Command::ZRangeStore { destination, source, query } => {
+                let len = self.storage.zrangestore(&destination, &source, &query)?;
+                self.ready_keys.push(destination);
+                Ok(Reply::Integer(len as i64))
+            },
+            Command::ZPop { key, end, count } => {
+                Ok(scored_array(self.storage.zpop(&key, end, count.unwrap_or(1))?, true))
+            },
+            Command::ZSetOpStore { op, destination, keys, weights, aggregate } => {
+                let len = self.storage.zset_op_store(op, &destination, &keys, &weights, aggregate)?;
+                self.ready_keys.push(destination);
+                Ok(Reply::Integer(len as i64))
+            },
+            Command::ZScan { key, cursor, options } => {
+                let (next, members) = self.storage.zscan(&key, cursor, &options)?;
+                // like redis, scores come back as strings here even for RESP3 clients
+                let items = members
+                    .into_iter()
+                    .flat_map(|(member, score)| [Reply::Bulk(member), Reply::bulk(format_double(score))])
+                    .collect();
+                Ok(Reply::Array(vec![Reply::bulk(next.to_string()), Reply::Array(items)]))
+            },
+            Command::GeoPos(key, members) => Ok(Reply::Array(
+                self.storage
+                    .geopos(&key, &members)?
+                    .into_iter()
+                    .map(|position| match position {
+                        Some((lon, lat)) => Reply::Array(vec![Reply::Double(lon), Reply::Double(lat)]),
+                        None => Reply::NullArray,
+                    })
+                    .collect(),
+            )),
+            Command::GeoDist { key, from, to, unit } => Ok(match self.storage.geodist(&key, &from, &to)? {
+                Some(dist) => Reply::bulk(format!("{:.4}", dist / unit)),
+                None => Reply::Null,
+            }),
+            Command::GeoHash(key, members) => Ok(Reply::Array(
+                self.storage
+                    .geohash(&key, &members)?
+                    .into_iter()
+                    .map(|hash| hash.map_or(Reply::Null, Reply::bulk))
+                    .collect(),
+            )),
+            Command::GeoSearch(key, query) => Ok(Reply::Array(
+                self.storage.geosearch(&key, &query)?.into_iter().map(|found| geo_match_reply(found, &query)).collect(),
+            )),
+            Command::GeoSearchStore { destination, source, query, store_dist } => {
+                let len = self.storage.geosearchstore(&destination, &source, &query, store_dist)?;
+                self.ready_keys.push(destination);
+                Ok(Reply::Integer(len as i64))
+            },
+            Command::XAdd { key, id, fields, trim, no_mkstream } => {
+                match self.storage.xadd(&key, id, fields, trim.as_ref(), no_mkstream)? {
+                    Some(id) => {
+                        self.ready_keys.push(key);
+                        Ok(Reply::bulk(id.to_string()))
+                    }
+                    None => Ok(Reply::Null),
+                }
+            },
+            Command::XTrim(key, trim) => Ok(Reply::Integer(self.storage.xtrim(&key, &trim)? as i64)),
+            Command::XRange { key, start, end, count, rev } => {
+                let entries = self.storage.xrange(&key, start, end, count, rev)?;
+                Ok(Reply::Array(entries.into_iter().map(entry_reply).collect()))
+            },
+            Command::XLen(key) => Ok(Reply::Integer(self.storage.xlen(&key)? as i64)),
+            Command::XDel(key, ids) => Ok(Reply::Integer(self.storage.xdel(&key, &ids)? as i64)),
+            command @ (Command::XRead { .. } | Command::XReadGroup { .. }) => {
+                Ok(self.read_streams(&command)?.unwrap_or(Reply::NullArray))
+            },
+            Command::XAck { key, group, ids } => Ok(Reply::Integer(self.storage.xack(&key, &group, &ids)? as i64)),
+            Command::XPending { key, group, range: None } => {
+                let summary = self.storage.xpending_summary(&key, &group)?;
+                let (first, last) = match summary.bounds {
+                    Some((first, last)) => (Reply::bulk(first.to_string()), Reply::bulk(last.to_string())),
+                    None => (Reply::Null, Reply::Null),
+                };
+                let consumers = if summary.consumers.is_empty() {
+                    Reply::NullArray
+                } else {
+                    Reply::Array(
+                        summary
+                            .consumers
+                            .into_iter()
+                            .map(|(name, count)| Reply::Array(vec![Reply::Bulk(name), Reply::bulk(count.to_string())]))
+                            .collect(),
+                    )
+                };
+                Ok(Reply::Array(vec![Reply::Integer(summary.count as i64), first, last, consumers]))
+            },
+            Command::XPending { key, group, range: Some(range) } => {
+                let pending = self.storage.xpending(&key, &group, &range)?;
+                Ok(Reply::Array(
+                    pending
+                        .into_iter()
+                        .map(|(id, consumer, idle, count)| {
+                            Reply::Array(vec![
+                                Reply::bulk(id.to_string()),
+                                Reply::Bulk(consumer),
+                                Reply::Integer(idle as i64),
+                                Reply::Integer(count as i64),
+                            ])
+                        })
+                        .collect(),
+                ))
+            },
+            Command::XClaim { key, group, consumer, min_idle, ids, options } => {
+                let claimed = self.storage.xclaim(&key, &group, &consumer, min_idle, &ids, &options)?;
+                Ok(delivered_reply(claimed, options.just_id))
+            },
+            Command::XAutoClaim { key, group, consumer, min_idle, start, count, just_id } => {
+                let (cursor, claimed, deleted) =
+                    self.storage.xautoclaim(&key, &group, &consumer, min_idle, start, count, just_id)?;
+                Ok(Reply::Array(vec![
+                    Reply::bulk(cursor.to_string()),
+                    delivered_reply(claimed, just_id),
+                    Reply::Array(deleted.into_iter().map(|id| Reply::bulk(id.to_string())).collect()),
+                ]))
+            },
+            Command::XGroup(command) => {
+                let result = self.storage.xgroup(&command)?;
+                match command {
+                    XGroupCommand::Create { .. } | XGroupCommand::SetId { .. } => Ok(Reply::ok()),
+                    XGroupCommand::Destroy { key, .. } => {
+                        // clients blocked on the group get to see it is gone
+                        if result > 0 {
+                            self.ready_keys.push(key);
+                        }
+                        Ok(Reply::Integer(result))
+                    }
+                    _ => Ok(Reply::Integer(result)),
+                }
+            },
+            Command::XInfo(query) => self.xinfo(query),
+            Command::Object(ObjectQuery::Encoding(key)) => {
+                Ok(self.storage.encoding(&key).map_or(Reply::Null, Reply::bulk))
+            },
+            Command::Object(ObjectQuery::Freq(key)) => {
+                self.require_lfu(true)?;
+                Ok(self.storage.frequency(&key).map_or(Reply::Null, |freq| Reply::Integer(freq as i64)))
+            },
+            Command::Object(ObjectQuery::IdleTime(key)) => {
+                self.require_lfu(false)?;
+                Ok(self.storage.idle_time(&key).map_or(Reply::Null, |idle| Reply::Integer(idle as i64)))
+            },
+            Command::HotKeys(count) => {
+                self.require_lfu(true)?;
+                Ok(Reply::Array(
+                    self.storage
+                        .hotkeys(count)
+                        .into_iter()
+                        .map(|(key, freq)| Reply::Array(vec![Reply::bulk(key), Reply::Integer(freq as i64)]))
+                        .collect(),
+                ))
+            },
+            Command::Debug(DebugCommand::SetActiveExpire(enabled)) => {
+                self.storage.set_active_expire(enabled);
+                Ok(Reply::ok())
+            },
+            command @ (Command::BPop { .. } | Command::BLMove { .. } | Command::BZPop { .. }) => {
+                // nothing to wait on here: without data this is an immediate timeout
+                Ok(self.try_unblock(&command)?.unwrap_or(match command {
+                    Command::BLMove { .. } => Reply::Null,
+                    _ => Reply::NullArray,
+                }))
+            },
+            Command::Hello { .. } => {
+                Err(RedisError::Internal("HELLO needs a client connection".to_string()))
+            }
+            Command::Command(_) => {
+                Err(RedisError::Internal("COMMAND is answered by the engine".to_string()))
+            }
+        }
+    }
+
+    // the non-blocking half of BLPOP / BRPOP / BLMOVE / BZPOPMIN / BZPOPMAX / XREAD /
+    // XREADGROUP: Ok(None) when every key is empty
+    pub fn try_unblock(&mut self, command: &Command) -> Result<Option<Reply>> {
+        match command {
+            Command::BPop { keys, end, .. } => {
+                for key in keys {
+                    if let Some(value) = self.storage.pop(key, *end, 1)?.and_then(|mut values| values.pop()) {
+                        return Ok(Some(Reply::Array(vec![Reply::bulk(key.as_slice()), Reply::Bulk(value)])));
+                    }
+                }
+                Ok(None)
+            }
+            Command::BLMove { source, destination, from, to, .. } => {
+                let moved = self.storage.lmove(source, destination, *from, *to)?;
+                if moved.is_some() {
+                    self.ready_keys.push(destination.clone());
+                }
+                Ok(moved.map(Reply::Bulk))
+            }
+            Command::BZPop { keys, end, .. } => {
+                for key in keys {
+                    if let Some((member, score)) = self.storage.zpop(key, *end, 1)?.pop() {
+                        return Ok(Some(Reply::Array(vec![
+                            Reply::bulk(key.as_slice()),
+                            Reply::Bulk(member),
+                            Reply::Double(score),
+                        ])));
+                    }
+                }
+                Ok(None)
+            }
+            Command::XRead { .. } | Command::XReadGroup { .. } => self.read_streams(command),
+            _ => Err(RedisError::Internal(format!("{} does not block", command))),
+        }
+    }
+
+    // pins the `$` ids of a blocking XREAD to the current last ids, so only entries added
+    // while the client waits are served
+    pub fn resolve_stream_ids(&mut self, command: Command) -> Result<Command> {
+        match command {
+            Command::XRead { keys, ids, count, block, timeout } => {
+                let ids = keys
+                    .iter()
+                    .zip(ids)
+                    .map(|(key, id)| match id {
+                        StreamReadId::Last => {
+                            let last = self.storage.xstream(key)?.map_or(StreamId::MIN, Stream::last_id);
+                            Ok(StreamReadId::After(last))
+                        }
+                        id => Ok(id),
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Command::XRead { keys, ids, count, block, timeout })
             }
+            command => Ok(command),
         }
     }
 
+    // XREAD / XREADGROUP; None when nothing was served
+    fn read_streams(&mut self, command: &Command) -> Result<Option<Reply>> {
+        let streams: Vec<(Vec<u8>, Reply)> = match command {
+            Command::XRead { keys, ids, count, .. } => {
+                // `$` reads nothing that already exists
+                let (keys, after): (Vec<_>, Vec<_>) = keys
+                    .iter()
+                    .zip(ids)
+                    .filter_map(|(key, id)| match id {
+                        StreamReadId::After(after) => Some((key.clone(), *after)),
+                        _ => None,
+                    })
+                    .unzip();
+                self.storage
+                    .xread(&keys, &after, *count)?
+                    .into_iter()
+                    .map(|(key, entries)| (key, Reply::Array(entries.into_iter().map(entry_reply).collect())))
+                    .collect()
+            }
+            Command::XReadGroup { group, consumer, keys, ids, count, no_ack, .. } => self
+                .storage
+                .xreadgroup(group, consumer, keys, ids, *count, *no_ack)?
+                .into_iter()
+                .map(|(key, entries)| (key, delivered_reply(entries, false)))
+                .collect(),
+            _ => return Err(RedisError::Internal(format!("{} does not read streams", command))),
+        };
+
+        if streams.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Reply::Array(
+            streams.into_iter().map(|(key, entries)| Reply::Array(vec![Reply::Bulk(key), entries])).collect(),
+        )))
+    }
+
+    fn xinfo(&mut self, query: XInfoQuery) -> Result<Reply> {
+        let now = self.storage.now_ms();
+        let key = match &query {
+            XInfoQuery::Stream(key) | XInfoQuery::Groups(key) | XInfoQuery::Consumers(key, _) => key,
+        };
+        let stream = self.storage.xstream(key)?.ok_or(RedisError::KeyNotFound)?;
+        let id = |id: StreamId| Reply::bulk(id.to_string());
+        let field = |name: &str, value: Reply| (Reply::bulk(name), value);
+
+        match &query {
+            XInfoQuery::Stream(_) => {
+                let entry = |entry: Option<&StreamEntry>| entry.cloned().map_or(Reply::Null, entry_reply);
+                let first_id = stream.first_entry().map_or(StreamId::MIN, |entry| entry.id);
+                Ok(Reply::Map(vec![
+                    field("length", Reply::Integer(stream.len() as i64)),
+                    field("radix-tree-keys", Reply::Integer(stream.node_count() as i64)),
+                    field("radix-tree-nodes", Reply::Integer(stream.node_count() as i64)),
+                    field("last-generated-id", id(stream.last_id())),
+                    field("max-deleted-entry-id", id(stream.max_deleted_id())),
+                    field("entries-added", Reply::Integer(stream.entries_added() as i64)),
+                    field("recorded-first-entry-id", id(first_id)),
+                    field("groups", Reply::Integer(stream.groups().count() as i64)),
+                    field("first-entry", entry(stream.first_entry())),
+                    field("last-entry", entry(stream.last_entry())),
+                ]))
+            }
+            XInfoQuery::Groups(_) => Ok(Reply::Array(
+                stream
+                    .groups()
+                    .map(|(name, group)| {
+                        let optional = |value: Option<u64>| value.map_or(Reply::Null, |value| Reply::Integer(value as i64));
+                        Reply::Map(vec![
+                            field("name", Reply::bulk(name)),
+                            field("consumers", Reply::Integer(group.consumers.len() as i64)),
+                            field("pending", Reply::Integer(group.pending.len() as i64)),
+                            field("last-delivered-id", id(group.last_delivered)),
+                            field("entries-read", optional(group.entries_read)),
+                            field("lag", optional(stream.lag(group))),
+                        ])
+                    })
+                    .collect(),
+            )),
+            XInfoQuery::Consumers(key, name) => {
+                let group = stream.group(name).ok_or_else(|| {
+                    RedisError::NoGroup(format!(
+                        "No such consumer group '{}' for key name '{}'",
+                        String::from_utf8_lossy(name),
+                        String::from_utf8_lossy(key)
+                    ))
+                })?;
+                Ok(Reply::Array(
+                    group
+                        .consumers
+                        .iter()
+                        .map(|(name, consumer)| {
+                            let inactive = consumer.active_at.map_or(-1, |at| now.saturating_sub(at) as i64);
+                            Reply::Map(vec![
+                                field("name", Reply::bulk(name.as_slice())),
+                                field("pending", Reply::Integer(consumer.pending.len() as i64)),
+                                field("idle", Reply::Integer(now.saturating_sub(consumer.seen_at) as i64)),
+                                field("inactive", Reply::Integer(inactive)),
+                            ])
+                        })
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    pub fn set_encoding_limits(&mut self, limits: EncodingLimits) {
+        self.storage.set_encoding_limits(limits);
+    }
+
+    // like redis, frequencies are only reported under an lfu policy and idle times only
+    // under the others, as the numbers of the other kind mean nothing there
+    fn require_lfu(&self, lfu: bool) -> Result<()> {
+        if self.storage.eviction_config().policy.lfu() == lfu {
+            return Ok(());
+        }
+        let message = if lfu {
+            "An LFU maxmemory policy is not selected, access frequency not tracked."
+        } else {
+            "An LFU maxmemory policy is selected, idle time not tracked."
+        };
+        Err(RedisError::InvalidArgument(format!(
+            "{} Please note that when switching between policies at runtime LRU and LFU data will take some time to adjust.",
+            message
+        )))
+    }
+
+    pub fn set_maxmemory(&mut self, max_memory: usize, eviction: EvictionConfig) {
+        self.storage.set_maxmemory(max_memory, eviction);
+    }
+
+    pub fn free_memory(&mut self) -> Result<()> {
+        self.storage.free_memory()
+    }
+
+    pub fn take_ready_keys(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.ready_keys)
+    }
+
+    fn set(&mut self, key: Vec<u8>, value: StorageValue, options: SetOptions) -> Result<Reply> {
+        let existing = match self.storage.get(&key) {
+            Ok(entry) => Some(entry),
+            Err(RedisError::KeyNotFound) => None,
+            Err(e) => return Err(e),
+        };
+
+        // GET fails on non-strings before anything is written
+        let old_value = match existing {
+            Some(entry) if options.get => match &entry.data {
+                StorageValue::String(s) => Some(s.clone()),
+                _ => return Err(RedisError::WrongType),
+            },
+            _ => None,
+        };
+        let old_expiry = existing.and_then(|entry| entry.expires_at);
+
+        let allowed = match options.condition {
+            Some(SetCondition::Nx) => existing.is_none(),
+            Some(SetCondition::Xx) => existing.is_some(),
+            None => true,
+        };
+
+        if allowed {
+            let expires_at = match options.expiry {
+                SetExpiry::Persist => None,
+                SetExpiry::At(at) => Some(at),
+                SetExpiry::After(after) => Some(self.storage.now() + after),
+                SetExpiry::Keep => old_expiry,
+            };
+            self.storage.set(key, value, expires_at)?;
+        }
+
+        Ok(match (options.get, allowed) {
+            (true, _) => old_value.map_or(Reply::Null, Reply::Bulk),
+            (false, true) => Reply::ok(),
+            (false, false) => Reply::Null,
+        })
+    }
+
+    // -2 for a missing key and -1 for one without a ttl, as redis replies
+    fn ttl(&mut self, key: &[u8], query: TtlQuery) -> Result<Reply> {
+        let at = match self.storage.expire_time(key)? {
+            KeyExpiry::Missing => return Ok(Reply::Integer(-2)),
+            KeyExpiry::Persistent => return Ok(Reply::Integer(-1)),
+            KeyExpiry::At(at) => at as i64,
+        };
+        let remaining = (at - self.storage.now_ms() as i64).max(0);
+        Ok(Reply::Integer(match query {
+            // rounded to the nearest second
+            TtlQuery::Ttl => (remaining + 500) / 1000,
+            TtlQuery::PTtl => remaining,
+            TtlQuery::ExpireTime => at / 1000,
+            TtlQuery::PExpireTime => at,
+        }))
+    }
+
     pub fn active_expire_cycle_fast(&mut self) {
         self.storage.active_expire_cycle(ExpireCycleType::Fast);
     }
@@ -71,6 +752,66 @@ impl Executor {
     }
 }
 
+fn bulk_array(values: Vec<Vec<u8>>) -> Reply {
+    Reply::Array(values.into_iter().map(Reply::Bulk).collect())
+}
+
+// members, each followed by its score when `with_scores` is set
+fn scored_array(members: Vec<(Vec<u8>, f64)>, with_scores: bool) -> Reply {
+    Reply::Array(
+        members
+            .into_iter()
+            .flat_map(|(member, score)| {
+                let score = with_scores.then_some(Reply::Double(score));
+                std::iter::once(Reply::Bulk(member)).chain(score)
+            })
+            .collect(),
+    )
+}
+
+// a RESP3 set, or a plain array for RESP2 clients
+fn bulk_set(values: Vec<Vec<u8>>) -> Reply {
+    Reply::Set(values.into_iter().map(Reply::Bulk).collect())
+}
+
+// a stream entry as `[id, [field, value, ...]]`
+fn entry_reply(entry: StreamEntry) -> Reply {
+    let fields = entry.fields.into_iter().flat_map(|(field, value)| [Reply::Bulk(field), Reply::Bulk(value)]);
+    Reply::Array(vec![Reply::bulk(entry.id.to_string()), Reply::Array(fields.collect())])
+}
+
+// entries handed to a consumer; ones deleted since delivery come back with a null body
+fn delivered_reply(entries: Vec<Delivered>, just_id: bool) -> Reply {
+    Reply::Array(
+        entries
+            .into_iter()
+            .map(|(id, fields)| match fields {
+                _ if just_id => Reply::bulk(id.to_string()),
+                Some(fields) => entry_reply(StreamEntry { id, fields }),
+                None => Reply::Array(vec![Reply::bulk(id.to_string()), Reply::NullArray]),
+            })
+            .collect(),
+    )
+}
+
+// a bare member, or `[member, dist?, hash?, [lon, lat]?]` when any WITH option is set
+fn geo_match_reply(found: GeoMatch, query: &GeoSearch) -> Reply {
+    if !(query.with_dist || query.with_hash || query.with_coord) {
+        return Reply::Bulk(found.member);
+    }
+    let mut item = vec![Reply::Bulk(found.member)];
+    if query.with_dist {
+        item.push(Reply::bulk(format!("{:.4}", found.dist / query.unit)));
+    }
+    if query.with_hash {
+        item.push(Reply::Integer(found.score as i64));
+    }
+    if query.with_coord {
+        item.push(Reply::Array(vec![Reply::Double(found.lon), Reply::Double(found.lat)]));
+    }
+    Reply::Array(item)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,107 +826,227 @@ mod tests {
     #[test]
     fn test_get_nonexistent_key() {
         let mut executor = Executor::new();
-        let result = executor.execute(Command::Get("nonexistent".to_string()));
-        assert!(result.is_err());
+        let result = executor.execute(Command::Get(b"nonexistent".to_vec()));
+        assert_eq!(result.unwrap(), Reply::Null);
     }
 
     #[test]
     fn test_set_and_get_string() {
         let mut executor = Executor::new();
         let set_result = executor.execute(Command::Set(
-            "key1".to_string(),
-            StorageValue::String("value1".to_string()),
-            None
+            b"key1".to_vec(),
+            StorageValue::String(b"value1".to_vec()),
+            SetOptions::default()
         ));
         assert!(set_result.is_ok());
-        assert_eq!(set_result.unwrap(), "OK");
+        assert_eq!(set_result.unwrap(), Reply::ok());
 
-        let get_result = executor.execute(Command::Get("key1".to_string()));
+        let get_result = executor.execute(Command::Get(b"key1".to_vec()));
         assert!(get_result.is_ok());
-        assert_eq!(get_result.unwrap(), "value1");
+        assert_eq!(get_result.unwrap(), Reply::bulk("value1"));
     }
 
     #[test]
     fn test_set_and_get_list() {
         let mut executor = Executor::new();
         let set_result = executor.execute(Command::Set(
-            "list1".to_string(),
-            StorageValue::List(vec!["item1".to_string(), "item2".to_string()]),
-            None
+            b"list1".to_vec(),
+            StorageValue::List(vec![b"item1".to_vec(), b"item2".to_vec()].into()),
+            SetOptions::default()
         ));
         assert!(set_result.is_ok());
 
-        let get_result = executor.execute(Command::Get("list1".to_string()));
-        assert!(get_result.is_ok());
-        assert_eq!(get_result.unwrap(), "item1 item2");
+        // like redis, GET only reads strings
+        let get_result = executor.execute(Command::Get(b"list1".to_vec()));
+        assert!(matches!(get_result, Err(RedisError::WrongType)));
     }
 
     #[test]
     fn test_delete_existing_key() {
         let mut executor = Executor::new();
         executor.execute(Command::Set(
-            "key1".to_string(),
-            StorageValue::String("value1".to_string()),
-            None
+            b"key1".to_vec(),
+            StorageValue::String(b"value1".to_vec()),
+            SetOptions::default()
         )).unwrap();
 
-        let del_result = executor.execute(Command::Del("key1".to_string()));
+        let del_result = executor.execute(Command::Del(b"key1".to_vec()));
         assert!(del_result.is_ok());
-        assert_eq!(del_result.unwrap(), "OK");
+        assert_eq!(del_result.unwrap(), Reply::Integer(1));
     }
 
     #[test]
     fn test_delete_nonexistent_key() {
         let mut executor = Executor::new();
-        let del_result = executor.execute(Command::Del("nonexistent".to_string()));
+        let del_result = executor.execute(Command::Del(b"nonexistent".to_vec()));
         assert!(del_result.is_ok());
-        assert_eq!(del_result.unwrap(), "Key not found");
+        assert_eq!(del_result.unwrap(), Reply::Integer(0));
     }
 
     #[test]
     fn test_exists() {
         let mut executor = Executor::new();
         executor.execute(Command::Set(
-            "key1".to_string(),
-            StorageValue::String("value1".to_string()),
-            None
+            b"key1".to_vec(),
+            StorageValue::String(b"value1".to_vec()),
+            SetOptions::default()
         )).unwrap();
 
-        let exists_result = executor.execute(Command::Exists("key1".to_string()));
+        let exists_result = executor.execute(Command::Exists(b"key1".to_vec()));
         assert!(exists_result.is_ok());
-        assert_eq!(exists_result.unwrap(), "true");
+        assert_eq!(exists_result.unwrap(), Reply::Integer(1));
 
-        let not_exists_result = executor.execute(Command::Exists("nonexistent".to_string()));
+        let not_exists_result = executor.execute(Command::Exists(b"nonexistent".to_vec()));
         assert!(not_exists_result.is_ok());
-        assert_eq!(not_exists_result.unwrap(), "false");
+        assert_eq!(not_exists_result.unwrap(), Reply::Integer(0));
     }
 
     #[test]
     fn test_flushdb() {
         let mut executor = Executor::new();
         executor.execute(Command::Set(
-            "key1".to_string(),
-            StorageValue::String("value1".to_string()),
-            None
+            b"key1".to_vec(),
+            StorageValue::String(b"value1".to_vec()),
+            SetOptions::default()
         )).unwrap();
 
         let clear_result = executor.execute(Command::FlushDB);
         assert!(clear_result.is_ok());
-        assert_eq!(clear_result.unwrap(), "OK");
+        assert_eq!(clear_result.unwrap(), Reply::ok());
 
-        let get_result = executor.execute(Command::Get("key1".to_string()));
-        assert!(get_result.is_err());
+        let get_result = executor.execute(Command::Get(b"key1".to_vec()));
+        assert_eq!(get_result.unwrap(), Reply::Null);
     }
 
     #[test]
     fn test_set_with_ttl() {
         let mut executor = Executor::new();
         let set_result = executor.execute(Command::Set(
-            "key1".to_string(),
-            StorageValue::String("value1".to_string()),
-            Some(std::time::SystemTime::now().checked_add(Duration::from_secs(1)).unwrap())
+            b"key1".to_vec(),
+            StorageValue::String(b"value1".to_vec()),
+            SetOptions::expire_at(std::time::SystemTime::now().checked_add(Duration::from_secs(1)).unwrap())
         ));
         assert!(set_result.is_ok());
-        assert_eq!(set_result.unwrap(), "OK");
+        assert_eq!(set_result.unwrap(), Reply::ok());
+    }
+
+    #[test]
+    fn test_set_conditions_and_get() {
+        let mut executor = Executor::new();
+        let set = |value: &str, options: SetOptions| {
+            Command::Set(b"k".to_vec(), StorageValue::String(value.as_bytes().to_vec()), options)
+        };
+        let nx = SetOptions { condition: Some(SetCondition::Nx), ..SetOptions::default() };
+        let xx = SetOptions { condition: Some(SetCondition::Xx), ..SetOptions::default() };
+        let get = SetOptions { get: true, ..SetOptions::default() };
+
+        assert_eq!(executor.execute(set("a", xx.clone())).unwrap(), Reply::Null);
+        assert_eq!(executor.execute(set("a", nx.clone())).unwrap(), Reply::ok());
+        assert_eq!(executor.execute(set("b", nx.clone())).unwrap(), Reply::Null);
+        assert_eq!(executor.execute(set("b", xx)).unwrap(), Reply::ok());
+        assert_eq!(executor.execute(set("c", get.clone())).unwrap(), Reply::bulk("b"));
+
+        // NX GET reports the old value and leaves it in place
+        let nx_get = SetOptions { get: true, ..nx };
+        assert_eq!(executor.execute(set("d", nx_get)).unwrap(), Reply::bulk("c"));
+        assert_eq!(executor.execute(Command::Get(b"k".to_vec())).unwrap(), Reply::bulk("c"));
+
+        executor.execute(Command::Set(b"list".to_vec(), StorageValue::List(vec![].into()), SetOptions::default())).unwrap();
+        let result = executor.execute(Command::Set(b"list".to_vec(), StorageValue::String(b"x".to_vec()), get));
+        assert!(matches!(result, Err(RedisError::WrongType)));
+    }
+
+    #[test]
+    fn test_set_keepttl() {
+        let mut executor = Executor::new();
+        let at = std::time::SystemTime::now() + Duration::from_secs(100);
+        let string = |value: &str| StorageValue::String(value.as_bytes().to_vec());
+
+        executor.execute(Command::Set(b"k".to_vec(), string("a"), SetOptions::expire_at(at))).unwrap();
+        let keep = SetOptions { expiry: SetExpiry::Keep, ..SetOptions::default() };
+        executor.execute(Command::Set(b"k".to_vec(), string("b"), keep)).unwrap();
+        assert_eq!(executor.storage.get(b"k").unwrap().expires_at, Some(at));
+
+        executor.execute(Command::Set(b"k".to_vec(), string("c"), SetOptions::default())).unwrap();
+        assert_eq!(executor.storage.get(b"k").unwrap().expires_at, None);
+    }
+
+    #[test]
+    fn test_ttl_family() {
+        use crate::storage::{ExpireOptions, ScoreComparison};
+        let mut executor = Executor::new();
+        let now = executor.storage.now_ms() as i64;
+        let mut run = |command: Command| executor.execute(command).unwrap();
+        let expire = |ms: i64, condition: Option<SetCondition>, comparison: Option<ScoreComparison>| Command::Expire {
+            key: b"k".to_vec(),
+            ms,
+            from_now: false,
+            options: ExpireOptions { condition, comparison },
+        };
+        let ttl = |query: TtlQuery| Command::Ttl(b"k".to_vec(), query);
+
+        // a missing key is not an error
+        assert_eq!(run(expire(now + 10_000, None, None)), Reply::Integer(0));
+        assert_eq!(run(ttl(TtlQuery::Ttl)), Reply::Integer(-2));
+        assert_eq!(run(Command::Persist(b"k".to_vec())), Reply::Integer(0));
+
+        run(Command::Set(b"k".to_vec(), StorageValue::String(b"v".to_vec()), SetOptions::default()));
+        assert_eq!(run(ttl(TtlQuery::PTtl)), Reply::Integer(-1));
+        // without a ttl the key counts as never expiring: GT fails, LT succeeds
+        assert_eq!(run(expire(now + 10_000, Some(SetCondition::Xx), None)), Reply::Integer(0));
+        assert_eq!(run(expire(now + 10_000, None, Some(ScoreComparison::Gt))), Reply::Integer(0));
+        assert_eq!(run(expire(now + 10_000, None, Some(ScoreComparison::Lt))), Reply::Integer(1));
+        assert_eq!(run(expire(now + 20_000, Some(SetCondition::Nx), None)), Reply::Integer(0));
+        assert_eq!(run(expire(now + 5_000, None, Some(ScoreComparison::Gt))), Reply::Integer(0));
+        assert_eq!(run(expire(now + 20_000, Some(SetCondition::Xx), Some(ScoreComparison::Gt))), Reply::Integer(1));
+
+        assert_eq!(run(ttl(TtlQuery::Ttl)), Reply::Integer(20));
+        assert_eq!(run(ttl(TtlQuery::PExpireTime)), Reply::Integer(now + 20_000));
+        assert_eq!(run(ttl(TtlQuery::ExpireTime)), Reply::Integer((now + 20_000) / 1000));
+        assert_eq!(run(Command::Persist(b"k".to_vec())), Reply::Integer(1));
+        assert_eq!(run(ttl(TtlQuery::ExpireTime)), Reply::Integer(-1));
+
+        // a deadline already in the past deletes the key
+        assert_eq!(run(expire(-1, None, None)), Reply::Integer(1));
+        assert_eq!(run(Command::Exists(b"k".to_vec())), Reply::Integer(0));
+    }
+
+    #[test]
+    fn test_object_freq_and_idletime_follow_the_policy() {
+        use crate::storage::eviction::EvictionPolicy;
+        let mut executor = Executor::new();
+        executor.execute(Command::Set(b"k".to_vec(), StorageValue::String(b"v".to_vec()), SetOptions::default())).unwrap();
+        let freq = || Command::Object(ObjectQuery::Freq(b"k".to_vec()));
+        let idletime = || Command::Object(ObjectQuery::IdleTime(b"k".to_vec()));
+
+        for policy in [EvictionPolicy::NoEviction, EvictionPolicy::AllKeysLru] {
+            executor.set_maxmemory(0, EvictionConfig { policy, ..EvictionConfig::default() });
+            let err = executor.execute(freq()).unwrap_err().to_string();
+            assert!(err.starts_with("ERR An LFU maxmemory policy is not selected, access frequency not tracked."));
+            assert!(executor.execute(Command::HotKeys(1)).is_err());
+            assert_eq!(executor.execute(idletime()).unwrap(), Reply::Integer(0));
+        }
+
+        executor.set_maxmemory(0, EvictionConfig { policy: EvictionPolicy::VolatileLfu, ..EvictionConfig::default() });
+        let err = executor.execute(idletime()).unwrap_err().to_string();
+        assert!(err.starts_with("ERR An LFU maxmemory policy is selected, idle time not tracked."));
+        assert!(matches!(executor.execute(freq()).unwrap(), Reply::Integer(_)));
+        assert!(matches!(executor.execute(Command::HotKeys(1)).unwrap(), Reply::Array(keys) if keys.len() == 1));
+    }
+
+    #[test]
+    fn test_integer_replies() {
+        let mut executor = Executor::new();
+        assert_eq!(executor.execute(Command::Incr(b"n".to_vec())).unwrap(), Reply::Integer(1));
+        assert_eq!(executor.execute(Command::Incr(b"n".to_vec())).unwrap(), Reply::Integer(2));
+        assert_eq!(executor.execute(Command::Decr(b"n".to_vec())).unwrap(), Reply::Integer(1));
+        assert_eq!(
+            executor.execute(Command::Append(b"s".to_vec(), b"abc".to_vec())).unwrap(),
+            Reply::Integer(3)
+        );
+        assert_eq!(
+            executor.execute(Command::Append(b"s".to_vec(), b"de".to_vec())).unwrap(),
+            Reply::Integer(5)
+        );
     }
 }
\ No newline at end of file
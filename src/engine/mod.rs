@@ -1,27 +1,153 @@
+mod blocking;
+mod client;
+mod command_table;
 mod executor;
+mod reply;
 mod router;
 
+pub use blocking::{Blocked, Outcome};
+pub use client::ClientContext;
+pub use command_table::{CommandSpec, CommandTable, Flag};
 pub use executor::Executor;
+pub use reply::Reply;
 pub use router::Router;
 
-use crate::protocol::resp::types::RESPType;
+use crate::protocol::resp::types::{ProtocolVersion, RESPType};
+use crate::storage::eviction::EvictionConfig;
+use crate::storage::{Command, EncodingLimits};
+use crate::utils::clock::{MonotonicClock, SharedClock};
+use crate::utils::error::RedisError;
+
+// advertised in HELLO; clients use it for feature detection, so report the redis
+// version whose command set we follow rather than our own crate version
+const REDIS_COMPAT_VERSION: &str = "7.2.0";
 
 pub struct Engine {
     router: Router,
     executor: Executor,
+    // shared with the storage, so blocking deadlines and expiry see the same time
+    clock: SharedClock,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Engine {
     pub fn new() -> Self {
+        Self::with_clock(MonotonicClock::shared())
+    }
+
+    pub fn with_clock(clock: SharedClock) -> Self {
         Self {
             router: Router::new(),
-            executor: Executor::new(),
+            executor: Executor::with_clock(clock.clone()),
+            clock,
         }
     }
 
-    pub fn process_command(&mut self, command: RESPType) -> Result<String, String> {
-        let cmd = self.router.route(&command).map_err(|e| e.to_string())?;
-        self.executor.execute(cmd).map_err(|e| e.to_string())
+    // runs a request for a caller that cannot park clients: a blocking command that would
+    // wait times out right away, the way redis treats them inside MULTI
+    pub fn process_command(&mut self, client: &mut ClientContext, command: RESPType) -> Result<Reply, RedisError> {
+        match self.dispatch(client, command)? {
+            Outcome::Reply(reply) => Ok(reply),
+            Outcome::Blocked(blocked) => Ok(blocked.timeout_reply()),
+        }
+    }
+
+    pub fn dispatch(&mut self, client: &mut ClientContext, command: RESPType) -> Result<Outcome, RedisError> {
+        let (spec, command) = self.router.resolve(&command)?;
+        // like redis, evict before every command, and refuse the ones that may grow memory
+        // while that cannot bring us back under maxmemory
+        if let Err(err) = self.executor.free_memory() {
+            if spec.has_flag(Flag::DenyOom) {
+                return Err(err);
+            }
+        }
+
+        match command {
+            Command::Hello { protover, auth, setname } => {
+                Self::hello(client, protover, auth, setname).map(Outcome::Reply)
+            }
+            Command::Command(query) => self.router.table().introspect(query).map(Outcome::Reply),
+            cmd if blocking::is_blocking(&cmd) => {
+                let cmd = self.executor.resolve_stream_ids(cmd)?;
+                match self.executor.try_unblock(&cmd)? {
+                    Some(reply) => Ok(Outcome::Reply(reply)),
+                    None => Ok(Outcome::Blocked(Blocked::new(cmd, self.clock.instant()))),
+                }
+            }
+            cmd => self.executor.execute(cmd).map(Outcome::Reply),
+        }
+    }
+
+    // serves a parked client again once one of its keys got data; None while there is still
+    // nothing to pop, or when the key now holds another type, which redis also waits out
+    pub fn retry_blocked(&mut self, blocked: &Blocked) -> Result<Option<Reply>, RedisError> {
+        match self.executor.try_unblock(blocked.command()) {
+            Err(RedisError::WrongType) => Ok(None),
+            result => result,
+        }
+    }
+
+    pub fn set_encoding_limits(&mut self, limits: EncodingLimits) {
+        self.executor.set_encoding_limits(limits);
+    }
+
+    // 0 lifts the limit, like maxmemory 0 in redis
+    pub fn set_maxmemory(&mut self, max_memory: usize, eviction: EvictionConfig) {
+        self.executor.set_maxmemory(max_memory, eviction);
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    // keys that received list, sorted set or stream elements since the last call
+    pub fn take_ready_keys(&mut self) -> Vec<Vec<u8>> {
+        self.executor.take_ready_keys()
+    }
+
+    fn hello(
+        client: &mut ClientContext,
+        protover: Option<i64>,
+        auth: Option<(Vec<u8>, Vec<u8>)>,
+        setname: Option<Vec<u8>>,
+    ) -> Result<Reply, RedisError> {
+        let protocol = match protover {
+            Some(version) => ProtocolVersion::from_number(version).ok_or(RedisError::NoProto)?,
+            None => client.protocol,
+        };
+
+        // there are no acl users yet: only the passwordless default user exists
+        if let Some((username, _password)) = auth {
+            if username != b"default" {
+                return Err(RedisError::WrongPass);
+            }
+        }
+
+        if let Some(name) = &setname {
+            if name.iter().any(|&b| !(b'!'..=b'~').contains(&b)) {
+                return Err(RedisError::InvalidClientName);
+            }
+        }
+
+        client.protocol = protocol;
+        if let Some(name) = setname {
+            client.name = (!name.is_empty()).then_some(name);
+        }
+
+        Ok(Reply::Map(vec![
+            (Reply::bulk("server"), Reply::bulk("redis")),
+            (Reply::bulk("version"), Reply::bulk(REDIS_COMPAT_VERSION)),
+            (Reply::bulk("proto"), Reply::Integer(protocol.number())),
+            (Reply::bulk("id"), Reply::Integer(client.id as i64)),
+            (Reply::bulk("mode"), Reply::bulk("standalone")),
+            (Reply::bulk("role"), Reply::bulk("master")),
+            (Reply::bulk("modules"), Reply::Array(vec![])),
+        ]))
     }
 
     pub fn active_expire_cycle_fast(&mut self) {
@@ -31,4 +157,229 @@ impl Engine {
     pub fn active_expire_cycle_slow(&mut self) {
         self.executor.active_expire_cycle_slow();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::eviction::EvictionPolicy;
+    use crate::utils::clock::{Clock, MockClock};
+    use bytes::Bytes;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn request(parts: &[&str]) -> RESPType {
+        RESPType::Array(
+            parts
+                .iter()
+                .map(|part| RESPType::BulkString(Some(Bytes::copy_from_slice(part.as_bytes()))))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_hello_switches_protocol() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(7);
+
+        let reply = engine.process_command(&mut client, request(&["HELLO", "3", "SETNAME", "worker"])).unwrap();
+        assert_eq!(client.protocol, ProtocolVersion::Resp3);
+        assert_eq!(client.name, Some(b"worker".to_vec()));
+        match reply {
+            Reply::Map(pairs) => {
+                assert!(pairs.contains(&(Reply::bulk("proto"), Reply::Integer(3))));
+                assert!(pairs.contains(&(Reply::bulk("id"), Reply::Integer(7))));
+            }
+            other => panic!("expected a map, got {:?}", other),
+        }
+
+        engine.process_command(&mut client, request(&["HELLO", "2"])).unwrap();
+        assert_eq!(client.protocol, ProtocolVersion::Resp2);
+    }
+
+    #[test]
+    fn test_blocking_pop() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+
+        let blocked = match engine.dispatch(&mut client, request(&["BLPOP", "a", "b", "0.5"])).unwrap() {
+            Outcome::Blocked(blocked) => blocked,
+            other => panic!("expected to block, got {:?}", other),
+        };
+        assert_eq!(blocked.keys(), &[b"a".to_vec(), b"b".to_vec()]);
+        assert!(blocked.deadline().is_some());
+        assert_eq!(engine.retry_blocked(&blocked).unwrap(), None);
+
+        engine.process_command(&mut client, request(&["RPUSH", "b", "x", "y"])).unwrap();
+        assert_eq!(engine.take_ready_keys(), vec![b"b".to_vec()]);
+        assert_eq!(
+            engine.retry_blocked(&blocked).unwrap(),
+            Some(Reply::Array(vec![Reply::bulk("b"), Reply::bulk("x")]))
+        );
+
+        // data already there is served without blocking
+        let reply = engine.process_command(&mut client, request(&["BRPOP", "b", "0"])).unwrap();
+        assert_eq!(reply, Reply::Array(vec![Reply::bulk("b"), Reply::bulk("y")]));
+
+        // callers that cannot park a client get the timeout reply straight away
+        assert_eq!(engine.process_command(&mut client, request(&["BLPOP", "b", "0"])).unwrap(), Reply::NullArray);
+        assert_eq!(engine.process_command(&mut client, request(&["BLMOVE", "b", "c", "LEFT", "LEFT", "0"])).unwrap(), Reply::Null);
+    }
+
+    #[test]
+    fn test_blocking_zpop() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+
+        let blocked = match engine.dispatch(&mut client, request(&["BZPOPMAX", "z", "0"])).unwrap() {
+            Outcome::Blocked(blocked) => blocked,
+            other => panic!("expected to block, got {:?}", other),
+        };
+
+        // another type showing up at the key keeps the client waiting
+        engine.process_command(&mut client, request(&["RPUSH", "z", "x"])).unwrap();
+        assert_eq!(engine.retry_blocked(&blocked).unwrap(), None);
+        engine.process_command(&mut client, request(&["DEL", "z"])).unwrap();
+
+        engine.process_command(&mut client, request(&["ZADD", "z", "1", "a", "2", "b"])).unwrap();
+        assert!(engine.take_ready_keys().contains(&b"z".to_vec()));
+        assert_eq!(
+            engine.retry_blocked(&blocked).unwrap(),
+            Some(Reply::Array(vec![Reply::bulk("z"), Reply::bulk("b"), Reply::Double(2.0)]))
+        );
+        assert_eq!(engine.process_command(&mut client, request(&["BZPOPMIN", "none", "0"])).unwrap(), Reply::NullArray);
+    }
+
+    #[test]
+    fn test_blocking_xread() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+        engine.process_command(&mut client, request(&["XADD", "s", "1-0", "f", "old"])).unwrap();
+
+        // `$` is pinned when the client parks, so the existing entry is never served
+        let blocked = match engine.dispatch(&mut client, request(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"])).unwrap() {
+            Outcome::Blocked(blocked) => blocked,
+            other => panic!("expected to block, got {:?}", other),
+        };
+        assert_eq!(engine.retry_blocked(&blocked).unwrap(), None);
+
+        engine.process_command(&mut client, request(&["XADD", "s", "2-0", "f", "new"])).unwrap();
+        assert!(engine.take_ready_keys().contains(&b"s".to_vec()));
+        let entry = Reply::Array(vec![Reply::bulk("2-0"), Reply::Array(vec![Reply::bulk("f"), Reply::bulk("new")])]);
+        assert_eq!(
+            engine.retry_blocked(&blocked).unwrap(),
+            Some(Reply::Array(vec![Reply::Array(vec![Reply::bulk("s"), Reply::Array(vec![entry])])]))
+        );
+
+        // reading history never blocks
+        engine.process_command(&mut client, request(&["XGROUP", "CREATE", "s", "g", "$"])).unwrap();
+        let history = request(&["XREADGROUP", "GROUP", "g", "c", "BLOCK", "0", "STREAMS", "s", "0"]);
+        assert!(matches!(engine.dispatch(&mut client, history).unwrap(), Outcome::Reply(_)));
+        let new = request(&["XREADGROUP", "GROUP", "g", "c", "BLOCK", "10", "STREAMS", "s", ">"]);
+        assert!(matches!(engine.dispatch(&mut client, new).unwrap(), Outcome::Blocked(_)));
+    }
+
+    #[test]
+    fn test_mock_clock_drives_expiry_and_deadlines() {
+        let clock = Arc::new(MockClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        let mut engine = Engine::with_clock(clock.clone());
+        let mut client = ClientContext::new(1);
+        let mut run = |engine: &mut Engine, parts: &[&str]| engine.process_command(&mut client, request(parts));
+
+        // relative ttls count from when the command runs, on the engine's clock
+        run(&mut engine, &["SET", "k", "v", "PX", "100"]).unwrap();
+        run(&mut engine, &["SET", "other", "v", "EX", "10"]).unwrap();
+        assert_eq!(run(&mut engine, &["PEXPIRETIME", "other"]).unwrap(), Reply::Integer(1_700_000_010_000));
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(run(&mut engine, &["PTTL", "k"]).unwrap(), Reply::Integer(0));
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(run(&mut engine, &["GET", "k"]).unwrap(), Reply::Null);
+
+        assert_eq!(run(&mut engine, &["DEBUG", "SET-ACTIVE-EXPIRE", "0"]).unwrap(), Reply::ok());
+        // with the cycle off, lookups that are not reads still see the deadline
+        for key in ["a", "b"] {
+            run(&mut engine, &["SET", key, "v", "PX", "50"]).unwrap();
+        }
+        clock.advance(Duration::from_millis(51));
+        assert_eq!(run(&mut engine, &["EXISTS", "a"]).unwrap(), Reply::Integer(0));
+        assert_eq!(run(&mut engine, &["DEL", "b"]).unwrap(), Reply::Integer(0));
+        let err = run(&mut engine, &["DEBUG", "JMAP"]).unwrap_err();
+        assert!(err.to_string().contains("Try DEBUG HELP"));
+
+        let blocked = match engine.dispatch(&mut client, request(&["BLPOP", "q", "0.5"])).unwrap() {
+            Outcome::Blocked(blocked) => blocked,
+            other => panic!("expected to block, got {:?}", other),
+        };
+        assert_eq!(blocked.deadline(), Some(clock.instant() + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_denyoom_commands_are_refused_over_maxmemory() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+        engine.set_maxmemory(4096, EvictionConfig::default());
+        let value = "x".repeat(256);
+
+        // the write that crosses the limit still goes through, as in redis
+        let mut pushed = 0;
+        while engine.process_command(&mut client, request(&["RPUSH", "list", &value])).is_ok() {
+            pushed += 1;
+            assert!(pushed < 100, "RPUSH was never refused");
+        }
+        for write in [&["RPUSH", "list", "x"][..], &["HSET", "hash", "f", "v"], &["ZADD", "zset", "1", "m"]] {
+            let err = engine.process_command(&mut client, request(write)).unwrap_err();
+            assert!(matches!(err, RedisError::OutOfMemory), "{:?} gave {:?}", write, err);
+        }
+
+        // reads and deletes still run, and free the room for writes again
+        assert_eq!(engine.process_command(&mut client, request(&["LLEN", "list"])).unwrap(), Reply::Integer(pushed));
+        assert_eq!(engine.process_command(&mut client, request(&["DEL", "list"])).unwrap(), Reply::Integer(1));
+        assert_eq!(engine.process_command(&mut client, request(&["HSET", "hash", "f", "v"])).unwrap(), Reply::Integer(1));
+
+        // with an eviction policy, old keys make way instead
+        let lru = EvictionConfig { policy: EvictionPolicy::AllKeysLru, ..EvictionConfig::default() };
+        engine.set_maxmemory(4096, lru);
+        for i in 0..100 {
+            engine.process_command(&mut client, request(&["RPUSH", &format!("list{}", i), &value])).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_hll_round_trips_through_get_and_set() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+        engine.process_command(&mut client, request(&["PFADD", "h", "a", "b", "c"])).unwrap();
+        engine.process_command(&mut client, request(&["PFCOUNT", "h"])).unwrap();
+
+        let bytes = match engine.process_command(&mut client, request(&["GET", "h"])).unwrap() {
+            Reply::Bulk(bytes) => bytes,
+            other => panic!("expected a bulk string, got {:?}", other),
+        };
+        let set = RESPType::Array(vec![
+            RESPType::BulkString(Some(Bytes::from_static(b"SET"))),
+            RESPType::BulkString(Some(Bytes::from_static(b"copy"))),
+            RESPType::BulkString(Some(Bytes::from(bytes.clone()))),
+        ]);
+        engine.process_command(&mut client, set).unwrap();
+        assert_eq!(engine.process_command(&mut client, request(&["GET", "copy"])).unwrap(), Reply::Bulk(bytes));
+        assert_eq!(engine.process_command(&mut client, request(&["PFCOUNT", "copy"])).unwrap(), Reply::Integer(3));
+    }
+
+    #[test]
+    fn test_hello_errors_keep_protocol() {
+        let mut engine = Engine::new();
+        let mut client = ClientContext::new(1);
+
+        let err = engine.process_command(&mut client, request(&["HELLO", "4"])).unwrap_err();
+        assert!(err.to_string().starts_with("NOPROTO"));
+
+        let err = engine.process_command(&mut client, request(&["HELLO", "3", "AUTH", "alice", "pw"])).unwrap_err();
+        assert!(err.to_string().starts_with("WRONGPASS"));
+
+        let err = engine.process_command(&mut client, request(&["HELLO", "3", "SETNAME", "has space"])).unwrap_err();
+        assert!(err.to_string().starts_with("ERR Client names"));
+
+        assert_eq!(client.protocol, ProtocolVersion::Resp2);
+        assert_eq!(client.name, None);
+    }
+}
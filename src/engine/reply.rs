@@ -0,0 +1,128 @@
+use crate::protocol::resp::encoder::format_double;
+use crate::protocol::resp::types::{ProtocolVersion, RESPType};
+
+// typed result of executing a command; each variant has exactly one wire representation
+// per protocol version, resp3-only types are downgraded the way redis does for resp2 clients
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Null,
+    // absent aggregate, e.g. LPOP with a count on a missing key
+    NullArray,
+    Array(Vec<Reply>),
+    Double(f64),
+    Boolean(bool),
+    BigNumber(String),
+    Map(Vec<(Reply, Reply)>),
+    Set(Vec<Reply>),
+    Push(Vec<Reply>),
+    Verbatim(String, Vec<u8>),
+}
+
+impl Reply {
+    pub fn ok() -> Self {
+        Reply::Status("OK".to_string())
+    }
+
+    pub fn bulk(value: impl Into<Vec<u8>>) -> Self {
+        Reply::Bulk(value.into())
+    }
+
+    pub fn into_resp(self, protocol: ProtocolVersion) -> RESPType {
+        let resp3 = protocol == ProtocolVersion::Resp3;
+        let convert = |items: Vec<Reply>| -> Vec<RESPType> {
+            items.into_iter().map(|item| item.into_resp(protocol)).collect()
+        };
+
+        match self {
+            Reply::Status(s) => RESPType::SimpleString(s),
+            Reply::Error(e) => RESPType::Error(e),
+            Reply::Integer(i) => RESPType::Integer(i),
+            Reply::Bulk(bytes) => RESPType::BulkString(Some(bytes.into())),
+            Reply::Null if resp3 => RESPType::Null,
+            Reply::Null => RESPType::BulkString(None),
+            Reply::NullArray if resp3 => RESPType::Null,
+            Reply::NullArray => RESPType::NullArray,
+            Reply::Array(items) => RESPType::Array(convert(items)),
+            Reply::Double(d) if resp3 => RESPType::Double(d),
+            Reply::Double(d) => RESPType::BulkString(Some(format_double(d).into_bytes().into())),
+            Reply::Boolean(b) if resp3 => RESPType::Boolean(b),
+            Reply::Boolean(b) => RESPType::Integer(b as i64),
+            Reply::BigNumber(n) if resp3 => RESPType::BigNumber(n),
+            Reply::BigNumber(n) => RESPType::BulkString(Some(n.into_bytes().into())),
+            Reply::Map(pairs) if resp3 => RESPType::Map(
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| (key.into_resp(protocol), value.into_resp(protocol)))
+                    .collect(),
+            ),
+            // resp2 flattens maps into [key, value, key, value, ...]
+            Reply::Map(pairs) => RESPType::Array(
+                pairs
+                    .into_iter()
+                    .flat_map(|(key, value)| [key.into_resp(protocol), value.into_resp(protocol)])
+                    .collect(),
+            ),
+            Reply::Set(items) if resp3 => RESPType::Set(convert(items)),
+            Reply::Set(items) => RESPType::Array(convert(items)),
+            Reply::Push(items) if resp3 => RESPType::Push(convert(items)),
+            Reply::Push(items) => RESPType::Array(convert(items)),
+            Reply::Verbatim(format, data) if resp3 => RESPType::Verbatim(format, data.into()),
+            Reply::Verbatim(_, data) => RESPType::BulkString(Some(data.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_reply_to_resp() {
+        let resp2 = ProtocolVersion::Resp2;
+        assert_eq!(Reply::ok().into_resp(resp2), RESPType::SimpleString("OK".to_string()));
+        assert_eq!(Reply::Integer(5).into_resp(resp2), RESPType::Integer(5));
+        assert_eq!(Reply::Null.into_resp(resp2), RESPType::BulkString(None));
+        assert_eq!(Reply::NullArray.into_resp(resp2), RESPType::NullArray);
+        assert_eq!(
+            Reply::bulk(b"\r\n\x00".to_vec()).into_resp(resp2),
+            RESPType::BulkString(Some(Bytes::from_static(b"\r\n\x00")))
+        );
+        assert_eq!(
+            Reply::Array(vec![Reply::Integer(1), Reply::Null]).into_resp(resp2),
+            RESPType::Array(vec![RESPType::Integer(1), RESPType::BulkString(None)])
+        );
+    }
+
+    #[test]
+    fn test_resp3_reply_downgrades_for_resp2() {
+        let map = Reply::Map(vec![(Reply::bulk("field"), Reply::Double(1.5))]);
+        assert_eq!(
+            map.clone().into_resp(ProtocolVersion::Resp2),
+            RESPType::Array(vec![
+                RESPType::BulkString(Some(Bytes::from_static(b"field"))),
+                RESPType::BulkString(Some(Bytes::from_static(b"1.5"))),
+            ])
+        );
+        assert_eq!(
+            map.into_resp(ProtocolVersion::Resp3),
+            RESPType::Map(vec![(RESPType::BulkString(Some(Bytes::from_static(b"field"))), RESPType::Double(1.5))])
+        );
+
+        assert_eq!(Reply::Null.into_resp(ProtocolVersion::Resp3), RESPType::Null);
+        assert_eq!(Reply::NullArray.into_resp(ProtocolVersion::Resp3), RESPType::Null);
+        assert_eq!(Reply::Boolean(true).into_resp(ProtocolVersion::Resp2), RESPType::Integer(1));
+        assert_eq!(
+            Reply::Set(vec![Reply::Integer(1)]).into_resp(ProtocolVersion::Resp2),
+            RESPType::Array(vec![RESPType::Integer(1)])
+        );
+        assert_eq!(
+            Reply::Verbatim("txt".to_string(), b"hi".to_vec()).into_resp(ProtocolVersion::Resp2),
+            RESPType::BulkString(Some(Bytes::from_static(b"hi")))
+        );
+    }
+}
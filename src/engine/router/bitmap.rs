@@ -0,0 +1,216 @@
+// src/engine/router/bitmap.rs
+use bytes::Bytes;
+use crate::storage::data_types::bitmap::MAX_BIT_OFFSET;
+use crate::storage::{BitFieldOp, BitFieldType, BitOp, BitRange, BitUnit, Command, Overflow};
+use crate::utils::error::{RedisError, Result};
+use super::parse_i64;
+
+fn bad_offset() -> RedisError {
+    RedisError::InvalidArgument("bit offset is not an integer or out of range".to_string())
+}
+
+fn offset(arg: &[u8]) -> Result<u64> {
+    parse_i64(arg)
+        .and_then(|offset| u64::try_from(offset).ok())
+        .filter(|&offset| offset <= MAX_BIT_OFFSET)
+        .ok_or_else(bad_offset)
+}
+
+// SETBIT key offset value
+pub(in crate::engine) fn setbit(argv: &[Bytes]) -> Result<Command> {
+    let on = match argv[3].as_ref() {
+        b"1" => true,
+        b"0" => false,
+        _ => return Err(RedisError::InvalidArgument("bit is not an integer or out of range".to_string())),
+    };
+    Ok(Command::SetBit(argv[1].to_vec(), offset(&argv[2])?, on))
+}
+
+pub(in crate::engine) fn getbit(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::GetBit(argv[1].to_vec(), offset(&argv[2])?))
+}
+
+fn unit(arg: &[u8]) -> Result<BitUnit> {
+    if arg.eq_ignore_ascii_case(b"BYTE") {
+        Ok(BitUnit::Byte)
+    } else if arg.eq_ignore_ascii_case(b"BIT") {
+        Ok(BitUnit::Bit)
+    } else {
+        Err(RedisError::SyntaxError)
+    }
+}
+
+fn index(arg: &[u8]) -> Result<i64> {
+    parse_i64(arg).ok_or(RedisError::NotInteger)
+}
+
+// BITCOUNT key [start end [BYTE | BIT]]
+pub(in crate::engine) fn bitcount(argv: &[Bytes]) -> Result<Command> {
+    let range = match &argv[2..] {
+        [] => None,
+        [start, end, rest @ ..] if rest.len() <= 1 => Some(BitRange {
+            start: index(start)?,
+            end: index(end)?,
+            unit: rest.first().map_or(Ok(BitUnit::Byte), |arg| unit(arg))?,
+        }),
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::BitCount(argv[1].to_vec(), range))
+}
+
+// BITPOS key bit [start [end [BYTE | BIT]]]
+pub(in crate::engine) fn bitpos(argv: &[Bytes]) -> Result<Command> {
+    let bit = match parse_i64(&argv[2]) {
+        Some(1) => true,
+        Some(0) => false,
+        Some(_) => return Err(RedisError::InvalidArgument("The bit argument must be 1 or 0.".to_string())),
+        None => return Err(RedisError::NotInteger),
+    };
+    let (range, end_given) = match &argv[3..] {
+        [] => (None, false),
+        [start] => (Some(BitRange { start: index(start)?, end: -1, unit: BitUnit::Byte }), false),
+        [start, end, rest @ ..] if rest.len() <= 1 => {
+            let unit = rest.first().map_or(Ok(BitUnit::Byte), |arg| unit(arg))?;
+            (Some(BitRange { start: index(start)?, end: index(end)?, unit }), true)
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::BitPos { key: argv[1].to_vec(), bit, range, end_given })
+}
+
+// BITOP AND | OR | XOR | NOT destkey key [key ...]
+pub(in crate::engine) fn bitop(argv: &[Bytes]) -> Result<Command> {
+    let op = match argv[1].to_ascii_uppercase().as_slice() {
+        b"AND" => BitOp::And,
+        b"OR" => BitOp::Or,
+        b"XOR" => BitOp::Xor,
+        b"NOT" => BitOp::Not,
+        _ => return Err(RedisError::SyntaxError),
+    };
+    let keys: Vec<Vec<u8>> = argv[3..].iter().map(|key| key.to_vec()).collect();
+    if op == BitOp::Not && keys.len() != 1 {
+        return Err(RedisError::InvalidArgument(
+            "BITOP NOT must be called with a single source key.".to_string(),
+        ));
+    }
+    Ok(Command::BitOp { op, destination: argv[2].to_vec(), keys })
+}
+
+fn field_type(arg: &[u8]) -> Result<BitFieldType> {
+    BitFieldType::parse(arg).ok_or_else(|| {
+        RedisError::InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.".to_string(),
+        )
+    })
+}
+
+// a plain bit offset, or `#n` for the n-th field of this type
+fn field_offset(arg: &[u8], ty: BitFieldType) -> Result<u64> {
+    let offset = match arg.strip_prefix(b"#") {
+        Some(index) => offset(index)?.checked_mul(ty.bits as u64).ok_or_else(bad_offset)?,
+        None => offset(arg)?,
+    };
+    if offset + ty.bits as u64 - 1 > MAX_BIT_OFFSET {
+        return Err(bad_offset());
+    }
+    Ok(offset)
+}
+
+// GET type offset | SET type offset value | INCRBY type offset increment | OVERFLOW WRAP | SAT | FAIL
+fn bitfield_ops(args: &[Bytes], read_only: bool) -> Result<Vec<BitFieldOp>> {
+    let mut ops = Vec::new();
+    let mut overflow = Overflow::default();
+    let mut rest = args;
+    while let Some((subcommand, tail)) = rest.split_first() {
+        let subcommand = subcommand.to_ascii_uppercase();
+        if read_only && subcommand != b"GET" {
+            return Err(RedisError::InvalidArgument("BITFIELD_RO only supports the GET subcommand".to_string()));
+        }
+        rest = match (subcommand.as_slice(), tail) {
+            (b"GET", [ty, offset, tail @ ..]) => {
+                let ty = field_type(ty)?;
+                ops.push(BitFieldOp::Get(ty, field_offset(offset, ty)?));
+                tail
+            }
+            (b"SET" | b"INCRBY", [ty, offset, value, tail @ ..]) => {
+                let ty = field_type(ty)?;
+                let offset = field_offset(offset, ty)?;
+                let value = parse_i64(value).ok_or(RedisError::NotInteger)?;
+                ops.push(match subcommand.as_slice() {
+                    b"SET" => BitFieldOp::Set(ty, offset, value, overflow),
+                    _ => BitFieldOp::IncrBy(ty, offset, value, overflow),
+                });
+                tail
+            }
+            (b"OVERFLOW", [mode, tail @ ..]) => {
+                overflow = match mode.to_ascii_uppercase().as_slice() {
+                    b"WRAP" => Overflow::Wrap,
+                    b"SAT" => Overflow::Sat,
+                    b"FAIL" => Overflow::Fail,
+                    _ => return Err(RedisError::InvalidArgument("Invalid OVERFLOW type specified".to_string())),
+                };
+                tail
+            }
+            _ => return Err(RedisError::SyntaxError),
+        };
+    }
+    Ok(ops)
+}
+
+// BITFIELD key [GET encoding offset | [OVERFLOW WRAP | SAT | FAIL] SET encoding offset value
+//   | INCRBY encoding offset increment ...]
+pub(in crate::engine) fn bitfield(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::BitField(argv[1].to_vec(), bitfield_ops(&argv[2..], false)?))
+}
+
+// BITFIELD_RO key [GET encoding offset ...]
+pub(in crate::engine) fn bitfield_ro(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::BitField(argv[1].to_vec(), bitfield_ops(&argv[2..], true)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_bit_arguments() {
+        assert!(error(setbit(&argv(&["SETBIT", "b", "-1", "1"]))).contains("bit offset"));
+        assert!(error(setbit(&argv(&["SETBIT", "b", "4294967296", "1"]))).contains("bit offset"));
+        assert!(error(setbit(&argv(&["SETBIT", "b", "1", "2"]))).contains("bit is not"));
+        assert!(matches!(bitcount(&argv(&["BITCOUNT", "b", "1"])), Err(RedisError::SyntaxError)));
+        match bitcount(&argv(&["BITCOUNT", "b", "0", "-1", "bit"])).unwrap() {
+            Command::BitCount(_, range) => assert_eq!(range, Some(BitRange { start: 0, end: -1, unit: BitUnit::Bit })),
+            other => panic!("unexpected {:?}", other),
+        }
+        match bitpos(&argv(&["BITPOS", "b", "0", "2"])).unwrap() {
+            Command::BitPos { bit, range, end_given, .. } => {
+                assert!(!bit && !end_given);
+                assert_eq!(range, Some(BitRange { start: 2, end: -1, unit: BitUnit::Byte }));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(bitpos(&argv(&["BITPOS", "b", "2"]))).contains("must be 1 or 0"));
+        assert!(error(bitop(&argv(&["BITOP", "NOT", "d", "a", "b"]))).contains("single source key"));
+        assert!(matches!(bitop(&argv(&["BITOP", "NAND", "d", "a"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_bitfield_arguments() {
+        let command = bitfield(&argv(&["BITFIELD", "f", "GET", "u8", "#2", "OVERFLOW", "SAT", "INCRBY", "i5", "3", "-1"]));
+        let u8_ = BitFieldType { signed: false, bits: 8 };
+        let i5 = BitFieldType { signed: true, bits: 5 };
+        match command.unwrap() {
+            Command::BitField(_, ops) => assert_eq!(
+                ops,
+                vec![BitFieldOp::Get(u8_, 16), BitFieldOp::IncrBy(i5, 3, -1, Overflow::Sat)]
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(bitfield(&argv(&["BITFIELD", "f", "GET", "u64", "0"]))).contains("Invalid bitfield type"));
+        assert!(error(bitfield(&argv(&["BITFIELD", "f", "OVERFLOW", "NONE"]))).contains("Invalid OVERFLOW"));
+        assert!(error(bitfield(&argv(&["BITFIELD", "f", "GET", "i8", "4294967290"]))).contains("bit offset"));
+        assert!(matches!(bitfield(&argv(&["BITFIELD", "f", "SET", "i8", "0"])), Err(RedisError::SyntaxError)));
+        assert!(error(bitfield_ro(&argv(&["BITFIELD_RO", "f", "SET", "i8", "0", "1"]))).contains("only supports"));
+    }
+}
@@ -0,0 +1,116 @@
+// src/engine/router/expire.rs
+use bytes::Bytes;
+use crate::storage::{Command, ExpireOptions, ScoreComparison, SetCondition, TtlQuery};
+use crate::utils::error::{RedisError, Result};
+use super::parse_i64;
+
+// [NX | XX] [GT | LT], in any order
+fn expire_options(args: &[Bytes]) -> Result<ExpireOptions> {
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    for option in args {
+        match option.to_ascii_uppercase().as_slice() {
+            b"NX" => nx = true,
+            b"XX" => xx = true,
+            b"GT" => gt = true,
+            b"LT" => lt = true,
+            _ => {
+                return Err(RedisError::InvalidArgument(format!(
+                    "Unsupported option {}",
+                    String::from_utf8_lossy(option)
+                )))
+            }
+        }
+    }
+    if nx && (xx || gt || lt) {
+        return Err(RedisError::InvalidArgument(
+            "NX and XX, GT or LT options at the same time are not compatible".to_string(),
+        ));
+    }
+    if gt && lt {
+        return Err(RedisError::InvalidArgument("GT and LT options at the same time are not compatible".to_string()));
+    }
+
+    let condition = if nx { Some(SetCondition::Nx) } else if xx { Some(SetCondition::Xx) } else { None };
+    let comparison = if gt { Some(ScoreComparison::Gt) } else if lt { Some(ScoreComparison::Lt) } else { None };
+    Ok(ExpireOptions { condition, comparison })
+}
+
+// the shared shape of the four commands: `unit_ms` per unit of the argument, counted from
+// now or from the epoch. unlike SET EX, zero and negative times are fine and delete the key
+fn expire_command(argv: &[Bytes], unit_ms: i64, from_now: bool, name: &str) -> Result<Command> {
+    let invalid = || RedisError::InvalidArgument(format!("invalid expire time in '{}' command", name));
+    let amount = parse_i64(&argv[2]).ok_or(RedisError::NotInteger)?;
+    let ms = amount.checked_mul(unit_ms).ok_or_else(invalid)?;
+    Ok(Command::Expire { key: argv[1].to_vec(), ms, from_now, options: expire_options(&argv[3..])? })
+}
+
+// EXPIRE key seconds [NX | XX | GT | LT]
+pub(in crate::engine) fn expire(argv: &[Bytes]) -> Result<Command> {
+    expire_command(argv, 1000, true, "expire")
+}
+
+// PEXPIRE key milliseconds [NX | XX | GT | LT]
+pub(in crate::engine) fn pexpire(argv: &[Bytes]) -> Result<Command> {
+    expire_command(argv, 1, true, "pexpire")
+}
+
+// EXPIREAT key unix-time-seconds [NX | XX | GT | LT]
+pub(in crate::engine) fn expireat(argv: &[Bytes]) -> Result<Command> {
+    expire_command(argv, 1000, false, "expireat")
+}
+
+// PEXPIREAT key unix-time-milliseconds [NX | XX | GT | LT]
+pub(in crate::engine) fn pexpireat(argv: &[Bytes]) -> Result<Command> {
+    expire_command(argv, 1, false, "pexpireat")
+}
+
+pub(in crate::engine) fn ttl(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Ttl(argv[1].to_vec(), TtlQuery::Ttl))
+}
+
+pub(in crate::engine) fn pttl(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Ttl(argv[1].to_vec(), TtlQuery::PTtl))
+}
+
+pub(in crate::engine) fn expiretime(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Ttl(argv[1].to_vec(), TtlQuery::ExpireTime))
+}
+
+pub(in crate::engine) fn pexpiretime(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Ttl(argv[1].to_vec(), TtlQuery::PExpireTime))
+}
+
+pub(in crate::engine) fn persist(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Persist(argv[1].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_expire_arguments() {
+        match pexpireat(&argv(&["PEXPIREAT", "k", "-5", "xx", "GT"])).unwrap() {
+            Command::Expire { ms, from_now, options, .. } => {
+                assert_eq!((ms, from_now), (-5, false));
+                assert_eq!(options.condition, Some(SetCondition::Xx));
+                assert_eq!(options.comparison, Some(ScoreComparison::Gt));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match expire(&argv(&["EXPIRE", "k", "10"])).unwrap() {
+            Command::Expire { ms, from_now, options, .. } => {
+                assert_eq!((ms, from_now), (10_000, true));
+                assert_eq!(options, ExpireOptions::default());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(expire(&argv(&["EXPIRE", "k", "1.5"])), Err(RedisError::NotInteger)));
+        assert!(error(expireat(&argv(&["EXPIREAT", "k", "9223372036854775807"]))).contains("invalid expire time in 'expireat'"));
+        assert!(error(expire(&argv(&["EXPIRE", "k", "1", "NX", "LT"]))).contains("NX and XX, GT or LT"));
+        assert!(error(pexpireat(&argv(&["PEXPIREAT", "k", "1", "gt", "nx"]))).contains("NX and XX, GT or LT"));
+        assert!(error(expire(&argv(&["EXPIRE", "k", "1", "GT", "LT"]))).contains("GT and LT options"));
+        assert!(error(expire(&argv(&["EXPIRE", "k", "1", "KEEPTTL"]))).contains("Unsupported option KEEPTTL"));
+    }
+}
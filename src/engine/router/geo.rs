@@ -0,0 +1,269 @@
+// src/engine/router/geo.rs
+use bytes::Bytes;
+use crate::storage::data_types::geohash;
+use crate::storage::{Command, GeoOrigin, GeoSearch, GeoShape, GeoSort, SetCondition, ZAddOptions};
+use crate::utils::error::{RedisError, Result};
+use super::{parse_f64, parse_i64};
+
+fn members(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+fn coordinate(arg: &[u8]) -> Result<f64> {
+    parse_f64(arg).ok_or(RedisError::NotFloat)
+}
+
+fn lon_lat(lon: &[u8], lat: &[u8]) -> Result<(f64, f64)> {
+    let (lon, lat) = (coordinate(lon)?, coordinate(lat)?);
+    if !geohash::valid(lon, lat) {
+        return Err(RedisError::InvalidArgument(format!("invalid longitude,latitude pair {:.6},{:.6}", lon, lat)));
+    }
+    Ok((lon, lat))
+}
+
+// meters per unit
+fn unit(arg: &[u8]) -> Result<f64> {
+    match arg.to_ascii_lowercase().as_slice() {
+        b"m" => Ok(1.0),
+        b"km" => Ok(1000.0),
+        b"ft" => Ok(0.3048),
+        b"mi" => Ok(1609.34),
+        _ => Err(RedisError::InvalidArgument("unsupported unit provided. please use M, KM, FT, MI".to_string())),
+    }
+}
+
+fn length(arg: &[u8], what: &str) -> Result<f64> {
+    let value = parse_f64(arg).ok_or_else(|| RedisError::InvalidArgument(format!("need numeric {}", what)))?;
+    if value < 0.0 {
+        return Err(RedisError::InvalidArgument(format!("{} cannot be negative", what)));
+    }
+    Ok(value)
+}
+
+// GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+pub(in crate::engine) fn geoadd(argv: &[Bytes]) -> Result<Command> {
+    let (mut nx, mut xx, mut ch) = (false, false, false);
+    let mut rest = &argv[2..];
+    while let Some((option, tail)) = rest.split_first() {
+        let flag = if option.eq_ignore_ascii_case(b"NX") {
+            &mut nx
+        } else if option.eq_ignore_ascii_case(b"XX") {
+            &mut xx
+        } else if option.eq_ignore_ascii_case(b"CH") {
+            &mut ch
+        } else {
+            break;
+        };
+        *flag = true;
+        rest = tail;
+    }
+    if nx && xx {
+        return Err(RedisError::InvalidArgument("XX and NX options at the same time are not compatible".to_string()));
+    }
+    if rest.is_empty() || !rest.len().is_multiple_of(3) {
+        return Err(RedisError::InvalidArgument(
+            "syntax error. Try GEOADD key [x1] [y1] [name1] [x2] [y2] [name2] ... ".to_string(),
+        ));
+    }
+
+    // a geo index is a sorted set scored by geohash, so this is a plain ZADD from here on
+    let members = rest
+        .chunks(3)
+        .map(|triple| {
+            let (lon, lat) = lon_lat(&triple[0], &triple[1])?;
+            Ok((geohash::encode(lon, lat), triple[2].to_vec()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let condition = if nx { Some(SetCondition::Nx) } else if xx { Some(SetCondition::Xx) } else { None };
+    let options = ZAddOptions { condition, ch, ..ZAddOptions::default() };
+    Ok(Command::ZAdd { key: argv[1].to_vec(), members, options })
+}
+
+// GEOPOS key [member [member ...]]
+pub(in crate::engine) fn geopos(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::GeoPos(argv[1].to_vec(), members(&argv[2..])))
+}
+
+// GEODIST key member1 member2 [M | KM | FT | MI]
+pub(in crate::engine) fn geodist(argv: &[Bytes]) -> Result<Command> {
+    let unit = match &argv[4..] {
+        [] => 1.0,
+        [arg] => unit(arg)?,
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::GeoDist { key: argv[1].to_vec(), from: argv[2].to_vec(), to: argv[3].to_vec(), unit })
+}
+
+// GEOHASH key [member [member ...]]
+pub(in crate::engine) fn geohash(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::GeoHash(argv[1].to_vec(), members(&argv[2..])))
+}
+
+// the options shared by GEOSEARCH and GEOSEARCHSTORE; the bool is STOREDIST
+fn search_options(args: &[Bytes], command: &str, store: bool) -> Result<(GeoSearch, bool)> {
+    let mut origin = None;
+    let mut shape = None;
+    let (mut from_count, mut by_count) = (0, 0);
+    let mut query = GeoSearch {
+        origin: GeoOrigin::LonLat(0.0, 0.0),
+        shape: GeoShape::Radius(0.0),
+        unit: 1.0,
+        sort: None,
+        count: None,
+        any: false,
+        with_coord: false,
+        with_dist: false,
+        with_hash: false,
+    };
+    let mut store_dist = false;
+
+    let mut rest = args;
+    while let Some((option, tail)) = rest.split_first() {
+        let option = option.to_ascii_uppercase();
+        rest = match (option.as_slice(), tail) {
+            (b"FROMMEMBER", [member, tail @ ..]) => {
+                from_count += 1;
+                origin = Some(GeoOrigin::Member(member.to_vec()));
+                tail
+            }
+            (b"FROMLONLAT", [lon, lat, tail @ ..]) => {
+                from_count += 1;
+                let (lon, lat) = lon_lat(lon, lat)?;
+                origin = Some(GeoOrigin::LonLat(lon, lat));
+                tail
+            }
+            (b"BYRADIUS", [radius, unit_arg, tail @ ..]) => {
+                by_count += 1;
+                let radius = length(radius, "radius")?;
+                query.unit = unit(unit_arg)?;
+                shape = Some(GeoShape::Radius(radius * query.unit));
+                tail
+            }
+            (b"BYBOX", [width, height, unit_arg, tail @ ..]) => {
+                by_count += 1;
+                let (width, height) = (length(width, "width")?, length(height, "height")?);
+                query.unit = unit(unit_arg)?;
+                shape = Some(GeoShape::Box(width * query.unit, height * query.unit));
+                tail
+            }
+            (b"ASC", tail) => {
+                query.sort = Some(GeoSort::Asc);
+                tail
+            }
+            (b"DESC", tail) => {
+                query.sort = Some(GeoSort::Desc);
+                tail
+            }
+            // ANY is only recognised straight after a COUNT
+            (b"COUNT", [count, tail @ ..]) => {
+                let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+                if count <= 0 {
+                    return Err(RedisError::InvalidArgument("COUNT must be > 0".to_string()));
+                }
+                query.count = Some(count as usize);
+                match tail.split_first() {
+                    Some((any, tail)) if any.eq_ignore_ascii_case(b"ANY") => {
+                        query.any = true;
+                        tail
+                    }
+                    _ => tail,
+                }
+            }
+            (b"WITHCOORD", tail) if !store => {
+                query.with_coord = true;
+                tail
+            }
+            (b"WITHDIST", tail) if !store => {
+                query.with_dist = true;
+                tail
+            }
+            (b"WITHHASH", tail) if !store => {
+                query.with_hash = true;
+                tail
+            }
+            (b"STOREDIST", tail) if store => {
+                store_dist = true;
+                tail
+            }
+            _ => return Err(RedisError::SyntaxError),
+        };
+    }
+
+    query.origin = match origin {
+        Some(origin) if from_count == 1 => origin,
+        _ => {
+            return Err(RedisError::InvalidArgument(format!(
+                "exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+                command
+            )))
+        }
+    };
+    query.shape = match shape {
+        Some(shape) if by_count == 1 => shape,
+        _ => {
+            return Err(RedisError::InvalidArgument(format!(
+                "exactly one of BYRADIUS and BYBOX can be specified for {}",
+                command
+            )))
+        }
+    };
+    Ok((query, store_dist))
+}
+
+// GEOSEARCH key FROMMEMBER member | FROMLONLAT longitude latitude
+//   BYRADIUS radius M | KM | FT | MI | BYBOX width height M | KM | FT | MI
+//   [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+pub(in crate::engine) fn geosearch(argv: &[Bytes]) -> Result<Command> {
+    let (query, _) = search_options(&argv[2..], "GEOSEARCH", false)?;
+    Ok(Command::GeoSearch(argv[1].to_vec(), query))
+}
+
+// GEOSEARCHSTORE destination source FROMMEMBER ... | FROMLONLAT ... BYRADIUS ... | BYBOX ...
+//   [ASC | DESC] [COUNT count [ANY]] [STOREDIST]
+pub(in crate::engine) fn geosearchstore(argv: &[Bytes]) -> Result<Command> {
+    let (query, store_dist) = search_options(&argv[3..], "GEOSEARCHSTORE", true)?;
+    Ok(Command::GeoSearchStore { destination: argv[1].to_vec(), source: argv[2].to_vec(), query, store_dist })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_geoadd_arguments() {
+        match geoadd(&argv(&["GEOADD", "g", "CH", "13.361389", "38.115556", "Palermo"])).unwrap() {
+            Command::ZAdd { members, options, .. } => {
+                assert_eq!(members, vec![(3479099956230698.0, b"Palermo".to_vec())]);
+                assert!(options.ch && options.condition.is_none());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(geoadd(&argv(&["GEOADD", "g", "181", "0", "m"]))).contains("invalid longitude,latitude pair 181.000000,0.000000"));
+        assert!(error(geoadd(&argv(&["GEOADD", "g", "NX", "XX", "1", "1", "m"]))).contains("not compatible"));
+        assert!(error(geoadd(&argv(&["GEOADD", "g", "1", "1"]))).contains("syntax error"));
+        assert!(error(geodist(&argv(&["GEODIST", "g", "a", "b", "yd"]))).contains("unsupported unit"));
+    }
+
+    #[test]
+    fn test_geosearch_arguments() {
+        let command = geosearch(&argv(&["GEOSEARCH", "g", "FROMLONLAT", "15", "37", "BYBOX", "400", "200", "km", "COUNT", "3", "ANY", "WITHDIST"]));
+        match command.unwrap() {
+            Command::GeoSearch(_, query) => {
+                assert_eq!(query.origin, GeoOrigin::LonLat(15.0, 37.0));
+                assert_eq!(query.shape, GeoShape::Box(400_000.0, 200_000.0));
+                assert_eq!((query.unit, query.count, query.any, query.with_dist), (1000.0, Some(3), true, true));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let both = ["GEOSEARCH", "g", "FROMMEMBER", "a", "FROMLONLAT", "1", "1", "BYRADIUS", "1", "m"];
+        assert!(error(geosearch(&argv(&both))).contains("exactly one of FROMMEMBER or FROMLONLAT"));
+        assert!(error(geosearch(&argv(&["GEOSEARCH", "g", "FROMMEMBER", "a"]))).contains("exactly one of BYRADIUS and BYBOX"));
+        let any = ["GEOSEARCH", "g", "FROMMEMBER", "a", "BYRADIUS", "1", "m", "ANY"];
+        assert!(matches!(geosearch(&argv(&any)), Err(RedisError::SyntaxError)));
+        let store = ["GEOSEARCHSTORE", "d", "g", "FROMMEMBER", "a", "BYRADIUS", "1", "m", "WITHDIST"];
+        assert!(matches!(geosearchstore(&argv(&store)), Err(RedisError::SyntaxError)));
+        let negative = ["GEOSEARCH", "g", "FROMMEMBER", "a", "BYRADIUS", "-1", "m"];
+        assert!(error(geosearch(&argv(&negative))).contains("radius cannot be negative"));
+    }
+}
@@ -0,0 +1,146 @@
+// src/engine/router/hash.rs
+use bytes::Bytes;
+use crate::storage::Command;
+use crate::utils::error::{RedisError, Result};
+use super::{parse_f64, parse_i64, scan_cursor, scan_options};
+
+fn field_list(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+// HSET key field value [field value ...]
+pub(in crate::engine) fn hset(argv: &[Bytes]) -> Result<Command> {
+    if !argv.len().is_multiple_of(2) {
+        return Err(RedisError::wrong_arg_count("hset", argv.len(), argv.len() - 1));
+    }
+    let pairs = argv[2..]
+        .chunks(2)
+        .map(|pair| (pair[0].to_vec(), pair[1].to_vec()))
+        .collect();
+    Ok(Command::HSet { key: argv[1].to_vec(), pairs })
+}
+
+pub(in crate::engine) fn hsetnx(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HSetNx {
+        key: argv[1].to_vec(),
+        field: argv[2].to_vec(),
+        value: argv[3].to_vec(),
+    })
+}
+
+pub(in crate::engine) fn hget(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HGet(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+pub(in crate::engine) fn hmget(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HMGet(argv[1].to_vec(), field_list(&argv[2..])))
+}
+
+pub(in crate::engine) fn hdel(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HDel(argv[1].to_vec(), field_list(&argv[2..])))
+}
+
+pub(in crate::engine) fn hexists(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HExists(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+pub(in crate::engine) fn hlen(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HLen(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn hkeys(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HKeys(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn hvals(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HVals(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn hgetall(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HGetAll(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn hincrby(argv: &[Bytes]) -> Result<Command> {
+    let delta = parse_i64(&argv[3]).ok_or(RedisError::NotInteger)?;
+    Ok(Command::HIncrBy(argv[1].to_vec(), argv[2].to_vec(), delta))
+}
+
+pub(in crate::engine) fn hincrbyfloat(argv: &[Bytes]) -> Result<Command> {
+    let delta = parse_f64(&argv[3]).ok_or(RedisError::NotFloat)?;
+    Ok(Command::HIncrByFloat(argv[1].to_vec(), argv[2].to_vec(), delta))
+}
+
+pub(in crate::engine) fn hstrlen(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HStrLen(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+// HRANDFIELD key [count [WITHVALUES]]
+pub(in crate::engine) fn hrandfield(argv: &[Bytes]) -> Result<Command> {
+    let (count, with_values) = match &argv[2..] {
+        [] => (None, false),
+        [count] => (Some(parse_i64(count).ok_or(RedisError::NotInteger)?), false),
+        [count, option] if option.eq_ignore_ascii_case(b"WITHVALUES") => {
+            let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+            // every pick becomes two reply elements, so keep that count representable
+            if count < -(i64::MAX / 2) {
+                return Err(RedisError::InvalidArgument("value is out of range".to_string()));
+            }
+            (Some(count), true)
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::HRandField { key: argv[1].to_vec(), count, with_values })
+}
+
+// HSCAN key cursor [MATCH pattern] [COUNT count] [NOVALUES]
+pub(in crate::engine) fn hscan(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::HScan {
+        key: argv[1].to_vec(),
+        cursor: scan_cursor(&argv[2])?,
+        options: scan_options(&argv[3..], true)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::argv;
+
+    #[test]
+    fn test_hset_needs_pairs() {
+        match hset(&argv(&["HSET", "h", "a", "1", "b", "2"])).unwrap() {
+            Command::HSet { pairs, .. } => assert_eq!(pairs.len(), 2),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            hset(&argv(&["HSET", "h", "a", "1", "b"])),
+            Err(RedisError::InvalidArgumentCount { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hrandfield_options() {
+        assert!(matches!(
+            hrandfield(&argv(&["HRANDFIELD", "h", "-3", "withvalues"])).unwrap(),
+            Command::HRandField { count: Some(-3), with_values: true, .. }
+        ));
+        assert!(matches!(hrandfield(&argv(&["HRANDFIELD", "h", "x"])), Err(RedisError::NotInteger)));
+        assert!(matches!(hrandfield(&argv(&["HRANDFIELD", "h", "1", "bogus"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_hscan_options() {
+        match hscan(&argv(&["HSCAN", "h", "0", "match", "f*", "COUNT", "100", "NOVALUES"])).unwrap() {
+            Command::HScan { cursor, options, .. } => {
+                assert_eq!(cursor, 0);
+                assert_eq!(options.pattern, Some(b"f*".to_vec()));
+                assert_eq!(options.count, 100);
+                assert!(options.no_values);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(hscan(&argv(&["HSCAN", "h", "abc"])).is_err());
+        assert!(matches!(hscan(&argv(&["HSCAN", "h", "0", "COUNT", "0"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(hscan(&argv(&["HSCAN", "h", "0", "MATCH"])), Err(RedisError::SyntaxError)));
+    }
+}
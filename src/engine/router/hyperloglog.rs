@@ -0,0 +1,62 @@
+// src/engine/router/hyperloglog.rs
+use bytes::Bytes;
+use crate::storage::{Command, PfDebug};
+use crate::utils::error::{RedisError, Result};
+
+fn keys(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+// PFADD key [element [element ...]]
+pub(in crate::engine) fn pfadd(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::PfAdd(argv[1].to_vec(), keys(&argv[2..])))
+}
+
+// PFCOUNT key [key ...]
+pub(in crate::engine) fn pfcount(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::PfCount(keys(&argv[1..])))
+}
+
+// PFMERGE destkey [sourcekey [sourcekey ...]]
+pub(in crate::engine) fn pfmerge(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::PfMerge(argv[1].to_vec(), keys(&argv[2..])))
+}
+
+// PFDEBUG GETREG | DECODE | ENCODING | TODENSE key
+pub(in crate::engine) fn pfdebug(argv: &[Bytes]) -> Result<Command> {
+    let subcommand = match argv[1].to_ascii_uppercase().as_slice() {
+        b"GETREG" => PfDebug::GetReg,
+        b"DECODE" => PfDebug::Decode,
+        b"ENCODING" => PfDebug::Encoding,
+        b"TODENSE" => PfDebug::ToDense,
+        _ => {
+            return Err(RedisError::InvalidArgument(format!(
+                "Unknown PFDEBUG subcommand '{}'",
+                String::from_utf8_lossy(&argv[1])
+            )))
+        }
+    };
+    Ok(Command::PfDebug(subcommand, argv[2].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::argv;
+
+    #[test]
+    fn test_pf_arguments() {
+        match pfmerge(&argv(&["PFMERGE", "d", "a", "b"])).unwrap() {
+            Command::PfMerge(destination, keys) => {
+                assert_eq!(destination, b"d");
+                assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(pfdebug(&argv(&["PFDEBUG", "todense", "h"])).unwrap(), Command::PfDebug(PfDebug::ToDense, _)));
+        match pfdebug(&argv(&["PFDEBUG", "SIMD", "h"])) {
+            Err(err) => assert!(err.to_string().contains("Unknown PFDEBUG subcommand 'SIMD'")),
+            Ok(command) => panic!("expected an error, got {:?}", command),
+        }
+    }
+}
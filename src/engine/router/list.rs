@@ -0,0 +1,293 @@
+// src/engine/router/list.rs
+use std::time::Duration;
+use bytes::Bytes;
+use crate::storage::{Command, ListEnd};
+use crate::utils::error::{RedisError, Result};
+use super::parse_i64;
+
+fn values(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+fn index(arg: &[u8]) -> Result<i64> {
+    parse_i64(arg).ok_or(RedisError::NotInteger)
+}
+
+// LEFT | RIGHT, as taken by LMOVE and BLMOVE
+pub(in crate::engine) fn list_end(arg: &[u8]) -> Result<ListEnd> {
+    if arg.eq_ignore_ascii_case(b"LEFT") {
+        Ok(ListEnd::Left)
+    } else if arg.eq_ignore_ascii_case(b"RIGHT") {
+        Ok(ListEnd::Right)
+    } else {
+        Err(RedisError::SyntaxError)
+    }
+}
+
+fn push(argv: &[Bytes], end: ListEnd, only_existing: bool) -> Result<Command> {
+    Ok(Command::Push {
+        key: argv[1].to_vec(),
+        values: values(&argv[2..]),
+        end,
+        only_existing,
+    })
+}
+
+pub(in crate::engine) fn lpush(argv: &[Bytes]) -> Result<Command> {
+    push(argv, ListEnd::Left, false)
+}
+
+pub(in crate::engine) fn rpush(argv: &[Bytes]) -> Result<Command> {
+    push(argv, ListEnd::Right, false)
+}
+
+pub(in crate::engine) fn lpushx(argv: &[Bytes]) -> Result<Command> {
+    push(argv, ListEnd::Left, true)
+}
+
+pub(in crate::engine) fn rpushx(argv: &[Bytes]) -> Result<Command> {
+    push(argv, ListEnd::Right, true)
+}
+
+// LPOP / RPOP key [count]
+fn pop(argv: &[Bytes], end: ListEnd) -> Result<Command> {
+    let count = match argv {
+        [_, _] => None,
+        [_, _, count] => {
+            let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+            let count = usize::try_from(count)
+                .map_err(|_| RedisError::InvalidArgument("value is out of range, must be positive".to_string()))?;
+            Some(count)
+        }
+        _ => {
+            let name = format!("{}pop", end.initial().to_ascii_lowercase());
+            return Err(RedisError::wrong_arg_count(name, 2, argv.len() - 1));
+        }
+    };
+    Ok(Command::Pop { key: argv[1].to_vec(), end, count })
+}
+
+pub(in crate::engine) fn lpop(argv: &[Bytes]) -> Result<Command> {
+    pop(argv, ListEnd::Left)
+}
+
+pub(in crate::engine) fn rpop(argv: &[Bytes]) -> Result<Command> {
+    pop(argv, ListEnd::Right)
+}
+
+pub(in crate::engine) fn llen(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LLen(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn lrange(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LRange(argv[1].to_vec(), index(&argv[2])?, index(&argv[3])?))
+}
+
+pub(in crate::engine) fn lindex(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LIndex(argv[1].to_vec(), index(&argv[2])?))
+}
+
+pub(in crate::engine) fn lset(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LSet(argv[1].to_vec(), index(&argv[2])?, argv[3].to_vec()))
+}
+
+// LINSERT key BEFORE | AFTER pivot element
+pub(in crate::engine) fn linsert(argv: &[Bytes]) -> Result<Command> {
+    let side = if argv[2].eq_ignore_ascii_case(b"BEFORE") {
+        ListEnd::Left
+    } else if argv[2].eq_ignore_ascii_case(b"AFTER") {
+        ListEnd::Right
+    } else {
+        return Err(RedisError::SyntaxError);
+    };
+
+    Ok(Command::LInsert {
+        key: argv[1].to_vec(),
+        side,
+        pivot: argv[3].to_vec(),
+        value: argv[4].to_vec(),
+    })
+}
+
+pub(in crate::engine) fn lrem(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LRem(argv[1].to_vec(), index(&argv[2])?, argv[3].to_vec()))
+}
+
+pub(in crate::engine) fn ltrim(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LTrim(argv[1].to_vec(), index(&argv[2])?, index(&argv[3])?))
+}
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+pub(in crate::engine) fn lpos(argv: &[Bytes]) -> Result<Command> {
+    let mut rank = 1;
+    let mut count = None;
+    let mut maxlen = 0;
+
+    for option in argv[3..].chunks(2) {
+        let (name, value) = match option {
+            [name, value] => (name, index(value)?),
+            _ => return Err(RedisError::SyntaxError),
+        };
+
+        if name.eq_ignore_ascii_case(b"RANK") {
+            if value == 0 {
+                return Err(RedisError::InvalidArgument(
+                    "RANK can't be zero: use 1 to start from the first match, 2 from the second ... \
+                     or use negative to start from the end of the list".to_string(),
+                ));
+            }
+            // i64::MIN has no positive counterpart
+            rank = value.max(-i64::MAX);
+        } else if name.eq_ignore_ascii_case(b"COUNT") {
+            let value = usize::try_from(value)
+                .map_err(|_| RedisError::InvalidArgument("COUNT can't be negative".to_string()))?;
+            count = Some(value);
+        } else if name.eq_ignore_ascii_case(b"MAXLEN") {
+            maxlen = usize::try_from(value)
+                .map_err(|_| RedisError::InvalidArgument("MAXLEN can't be negative".to_string()))?;
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+    }
+
+    Ok(Command::LPos {
+        key: argv[1].to_vec(),
+        value: argv[2].to_vec(),
+        rank,
+        count,
+        maxlen,
+    })
+}
+
+// LMOVE source destination LEFT | RIGHT LEFT | RIGHT
+pub(in crate::engine) fn lmove(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LMove {
+        source: argv[1].to_vec(),
+        destination: argv[2].to_vec(),
+        from: list_end(&argv[3])?,
+        to: list_end(&argv[4])?,
+    })
+}
+
+pub(in crate::engine) fn rpoplpush(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::LMove {
+        source: argv[1].to_vec(),
+        destination: argv[2].to_vec(),
+        from: ListEnd::Right,
+        to: ListEnd::Left,
+    })
+}
+
+// blocking timeouts are seconds, with decimals since redis 6; 0 waits forever
+pub(super) fn timeout(arg: &[u8]) -> Result<Option<Duration>> {
+    let seconds = std::str::from_utf8(arg)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds < i64::MAX as f64 / 1000.0)
+        .ok_or_else(|| RedisError::InvalidArgument("timeout is not a float or out of range".to_string()))?;
+
+    if seconds < 0.0 {
+        return Err(RedisError::InvalidArgument("timeout is negative".to_string()));
+    }
+    Ok((seconds > 0.0).then(|| Duration::from_secs_f64(seconds)))
+}
+
+// BLPOP / BRPOP key [key ...] timeout
+fn bpop(argv: &[Bytes], end: ListEnd) -> Result<Command> {
+    let (timeout_arg, keys) = argv[1..].split_last().expect("arity is checked");
+    Ok(Command::BPop {
+        keys: values(keys),
+        end,
+        timeout: timeout(timeout_arg)?,
+    })
+}
+
+pub(in crate::engine) fn blpop(argv: &[Bytes]) -> Result<Command> {
+    bpop(argv, ListEnd::Left)
+}
+
+pub(in crate::engine) fn brpop(argv: &[Bytes]) -> Result<Command> {
+    bpop(argv, ListEnd::Right)
+}
+
+// BLMOVE source destination LEFT | RIGHT LEFT | RIGHT timeout
+pub(in crate::engine) fn blmove(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::BLMove {
+        source: argv[1].to_vec(),
+        destination: argv[2].to_vec(),
+        from: list_end(&argv[3])?,
+        to: list_end(&argv[4])?,
+        timeout: timeout(&argv[5])?,
+    })
+}
+
+pub(in crate::engine) fn brpoplpush(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::BLMove {
+        source: argv[1].to_vec(),
+        destination: argv[2].to_vec(),
+        from: ListEnd::Right,
+        to: ListEnd::Left,
+        timeout: timeout(&argv[3])?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::argv;
+
+    #[test]
+    fn test_pop_count() {
+        assert!(matches!(lpop(&argv(&["LPOP", "q"])).unwrap(), Command::Pop { count: None, .. }));
+        assert!(matches!(rpop(&argv(&["RPOP", "q", "3"])).unwrap(), Command::Pop { count: Some(3), end: ListEnd::Right, .. }));
+        assert!(matches!(lpop(&argv(&["LPOP", "q", "-1"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(lpop(&argv(&["LPOP", "q", "x"])), Err(RedisError::NotInteger)));
+        assert!(matches!(lpop(&argv(&["LPOP", "q", "1", "2"])), Err(RedisError::InvalidArgumentCount { .. })));
+    }
+
+    #[test]
+    fn test_lpos_options() {
+        match lpos(&argv(&["LPOS", "q", "a", "rank", "-2", "COUNT", "0", "MAXLEN", "10"])).unwrap() {
+            Command::LPos { rank, count, maxlen, .. } => {
+                assert_eq!((rank, count, maxlen), (-2, Some(0), 10));
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert!(matches!(lpos(&argv(&["LPOS", "q", "a", "RANK", "0"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(lpos(&argv(&["LPOS", "q", "a", "COUNT", "-1"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(lpos(&argv(&["LPOS", "q", "a", "COUNT"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(lpos(&argv(&["LPOS", "q", "a", "BOGUS", "1"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_directions() {
+        assert!(matches!(
+            lmove(&argv(&["LMOVE", "a", "b", "right", "LEFT"])).unwrap(),
+            Command::LMove { from: ListEnd::Right, to: ListEnd::Left, .. }
+        ));
+        assert!(matches!(lmove(&argv(&["LMOVE", "a", "b", "UP", "LEFT"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(linsert(&argv(&["LINSERT", "q", "after", "p", "v"])).unwrap(), Command::LInsert { side: ListEnd::Right, .. }));
+        assert!(matches!(linsert(&argv(&["LINSERT", "q", "middle", "p", "v"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_blocking_timeouts() {
+        match blpop(&argv(&["BLPOP", "a", "b", "1.5"])).unwrap() {
+            Command::BPop { keys, timeout, end } => {
+                assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+                assert_eq!(timeout, Some(Duration::from_millis(1500)));
+                assert_eq!(end, ListEnd::Left);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert!(matches!(brpop(&argv(&["BRPOP", "a", "0"])).unwrap(), Command::BPop { timeout: None, .. }));
+        assert!(matches!(
+            brpoplpush(&argv(&["BRPOPLPUSH", "a", "b", "0"])).unwrap(),
+            Command::BLMove { from: ListEnd::Right, to: ListEnd::Left, timeout: None, .. }
+        ));
+        assert!(matches!(blpop(&argv(&["BLPOP", "a", "-1"])), Err(RedisError::InvalidArgument(msg)) if msg == "timeout is negative"));
+        assert!(matches!(blpop(&argv(&["BLPOP", "a", "soon"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(blpop(&argv(&["BLPOP", "a", "inf"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(blmove(&argv(&["BLMOVE", "a", "b", "LEFT", "UP", "1"])), Err(RedisError::SyntaxError)));
+    }
+}
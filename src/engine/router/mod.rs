@@ -0,0 +1,504 @@
+// src/engine/router/mod.rs
+use crate::engine::command_table::{CommandSpec, CommandTable};
+use crate::storage::{Command, CommandQuery, DebugCommand, ObjectQuery, ScanOptions, SetCondition, SetExpiry, SetOptions, StorageValue};
+use crate::utils::error::{RedisError, Result};
+use std::time::{Duration, UNIX_EPOCH};
+use bytes::Bytes;
+
+use crate::protocol::resp::types::RESPType;
+
+pub(super) mod bitmap;
+pub(super) mod expire;
+pub(super) mod geo;
+pub(super) mod hash;
+pub(super) mod hyperloglog;
+pub(super) mod list;
+pub(super) mod set;
+pub(super) mod stream;
+pub(super) mod string;
+pub(super) mod zset;
+
+// argument helpers shared by the per-command handler tests
+#[cfg(test)]
+mod test_helpers {
+    use crate::storage::Command;
+    use crate::utils::error::Result;
+    use bytes::Bytes;
+
+    pub fn argv(parts: &[&str]) -> Vec<Bytes> {
+        parts.iter().map(|part| Bytes::copy_from_slice(part.as_bytes())).collect()
+    }
+
+    pub fn error(result: Result<Command>) -> String {
+        match result {
+            Err(err) => err.to_string(),
+            Ok(command) => panic!("expected an error, got {:?}", command),
+        }
+    }
+}
+
+// resolves requests against the command table; the per-command handlers below only ever
+// see an argv whose arity has already been checked
+#[derive(Default)]
+pub struct Router {
+    table: CommandTable,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self {
+            table: CommandTable::new(),
+        }
+    }
+
+    pub fn table(&self) -> &CommandTable {
+        &self.table
+    }
+
+    pub fn route(&self, resp: &RESPType) -> Result<Command> {
+        self.resolve(resp).map(|(_, command)| command)
+    }
+
+    // the command along with its table entry, for callers that act on its flags
+    pub fn resolve(&self, resp: &RESPType) -> Result<(&'static CommandSpec, Command)> {
+        let argv = Self::argv(resp)?;
+        let spec = self.lookup(&argv)?;
+        Ok((spec, (spec.handler)(&argv)?))
+    }
+
+    // the table entry for an argv, once its arity is known to match
+    pub fn lookup(&self, argv: &[Bytes]) -> Result<&'static CommandSpec> {
+        let spec = self
+            .table
+            .lookup(&argv[0])
+            .ok_or_else(|| RedisError::invalid_command(String::from_utf8_lossy(&argv[0])))?;
+
+        if !spec.check_arity(argv.len()) {
+            let expected = spec.arity.unsigned_abs() as usize - 1;
+            return Err(RedisError::wrong_arg_count(spec.name, expected, argv.len() - 1));
+        }
+        Ok(spec)
+    }
+
+    fn argv(resp: &RESPType) -> Result<Vec<Bytes>> {
+        match resp {
+            RESPType::Array(parts) if !parts.is_empty() => parts
+                .iter()
+                .map(|part| match part {
+                    RESPType::BulkString(Some(bytes)) => Ok(bytes.clone()),
+                    _ => Err(RedisError::ParseError("expected bulk string arguments".to_string())),
+                })
+                .collect(),
+            _ => Err(RedisError::ParseError("expected a non-empty array".to_string())),
+        }
+    }
+}
+
+pub(super) fn get(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Get(argv[1].to_vec()))
+}
+
+// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds |
+//   EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+pub(super) fn set(argv: &[Bytes]) -> Result<Command> {
+    let mut options = SetOptions::default();
+    // the expiry option given so far; repeating the same one is fine, mixing them is not
+    let mut expiry_option: Option<Vec<u8>> = None;
+
+    let mut i = 3;
+    while i < argv.len() {
+        let option = argv[i].to_ascii_uppercase();
+        let compatible_expiry = expiry_option.as_ref().is_none_or(|prev| *prev == option);
+
+        match option.as_slice() {
+            b"NX" if options.condition != Some(SetCondition::Xx) => options.condition = Some(SetCondition::Nx),
+            b"XX" if options.condition != Some(SetCondition::Nx) => options.condition = Some(SetCondition::Xx),
+            b"GET" => options.get = true,
+            b"KEEPTTL" if compatible_expiry => options.expiry = SetExpiry::Keep,
+            b"EX" | b"PX" | b"EXAT" | b"PXAT" if compatible_expiry && i + 1 < argv.len() => {
+                i += 1;
+                options.expiry = expiry_arg(&argv[i], &option, "set")?;
+            }
+            _ => return Err(RedisError::SyntaxError),
+        }
+
+        if matches!(option.as_slice(), b"KEEPTTL" | b"EX" | b"PX" | b"EXAT" | b"PXAT") {
+            expiry_option = Some(option);
+        }
+        i += 1;
+    }
+
+    Ok(Command::Set(
+        argv[1].to_vec(),
+        StorageValue::String(argv[2].to_vec()),
+        options
+    ))
+}
+
+// turns an EX / PX / EXAT / PXAT argument into a deadline; relative ones are left for the
+// executor to anchor, so routing never reads the clock
+fn expiry_arg(value: &[u8], unit: &[u8], cmd: &str) -> Result<SetExpiry> {
+    let invalid = || RedisError::InvalidArgument(format!("invalid expire time in '{}' command", cmd));
+
+    let amount = parse_i64(value).ok_or(RedisError::NotInteger)?;
+    if amount <= 0 {
+        return Err(invalid());
+    }
+
+    let amount = amount as u64;
+    let (duration, absolute) = match unit {
+        b"EX" => (amount.checked_mul(1000).map(Duration::from_millis), false),
+        b"PX" => (Some(Duration::from_millis(amount)), false),
+        b"EXAT" => (amount.checked_mul(1000).map(Duration::from_millis), true),
+        _ => (Some(Duration::from_millis(amount)), true),
+    };
+
+    // redis keeps deadlines as signed milliseconds since the epoch
+    let duration = duration.filter(|d| d.as_millis() <= i64::MAX as u128).ok_or_else(invalid)?;
+    if !absolute {
+        return Ok(SetExpiry::After(duration));
+    }
+    UNIX_EPOCH.checked_add(duration).map(SetExpiry::At).ok_or_else(invalid)
+}
+
+pub(super) fn del(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Del(argv[1].to_vec()))
+}
+
+pub(super) fn exists(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Exists(argv[1].to_vec()))
+}
+
+pub(super) fn incr(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Incr(argv[1].to_vec()))
+}
+
+pub(super) fn decr(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Decr(argv[1].to_vec()))
+}
+
+pub(super) fn append(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Append(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+// FLUSHDB [ASYNC | SYNC]; there is no lazy freeing, both flush in place
+pub(super) fn flushdb(argv: &[Bytes]) -> Result<Command> {
+    match &argv[1..] {
+        [] => Ok(Command::FlushDB),
+        [mode] if mode.eq_ignore_ascii_case(b"ASYNC") || mode.eq_ignore_ascii_case(b"SYNC") => Ok(Command::FlushDB),
+        _ => Err(RedisError::SyntaxError),
+    }
+}
+
+pub(super) fn ping(argv: &[Bytes]) -> Result<Command> {
+    match argv {
+        [_] => Ok(Command::Ping(None)),
+        [_, message] => Ok(Command::Ping(Some(message.to_vec()))),
+        _ => Err(RedisError::wrong_arg_count("ping", 1, argv.len() - 1)),
+    }
+}
+
+pub(super) fn echo(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::Echo(argv[1].to_vec()))
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]]
+pub(super) fn hello(argv: &[Bytes]) -> Result<Command> {
+    let args = &argv[1..];
+    let mut protover = None;
+    let mut auth = None;
+    let mut setname = None;
+
+    if let Some(version) = args.first() {
+        let version = std::str::from_utf8(version)
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .ok_or_else(|| RedisError::InvalidArgument("Protocol version is not an integer or out of range".to_string()))?;
+        protover = Some(version);
+    }
+
+    let mut i = 1;
+    while i < args.len() {
+        let option = &args[i];
+        let remaining = args.len() - i - 1;
+        if option.eq_ignore_ascii_case(b"AUTH") && remaining >= 2 {
+            auth = Some((args[i + 1].to_vec(), args[i + 2].to_vec()));
+            i += 3;
+        } else if option.eq_ignore_ascii_case(b"SETNAME") && remaining >= 1 {
+            setname = Some(args[i + 1].to_vec());
+            i += 2;
+        } else {
+            return Err(RedisError::InvalidArgument(format!(
+                "Syntax error in HELLO option '{}'",
+                String::from_utf8_lossy(option)
+            )));
+        }
+    }
+
+    Ok(Command::Hello { protover, auth, setname })
+}
+
+// COMMAND [COUNT | INFO [name ...] | DOCS [name ...] | GETKEYS command [arg ...]]
+pub(super) fn command(argv: &[Bytes]) -> Result<Command> {
+    let subcommand = match argv.get(1) {
+        Some(subcommand) => subcommand.to_ascii_uppercase(),
+        None => return Ok(Command::Command(CommandQuery::List)),
+    };
+    let rest = || argv[2..].iter().map(|arg| arg.to_vec()).collect::<Vec<_>>();
+
+    let query = match subcommand.as_slice() {
+        b"COUNT" if argv.len() == 2 => CommandQuery::Count,
+        b"INFO" => CommandQuery::Info(rest()),
+        b"DOCS" => CommandQuery::Docs(rest()),
+        b"GETKEYS" if argv.len() >= 3 => CommandQuery::GetKeys(rest()),
+        b"COUNT" | b"GETKEYS" => {
+            return Err(RedisError::wrong_arg_count(
+                format!("command|{}", String::from_utf8_lossy(&subcommand).to_lowercase()),
+                if subcommand == b"COUNT" { 1 } else { 2 },
+                argv.len() - 1,
+            ))
+        }
+        _ => {
+            return Err(RedisError::InvalidArgument(format!(
+                "unknown subcommand '{}'. Try COMMAND HELP.",
+                String::from_utf8_lossy(&argv[1])
+            )))
+        }
+    };
+    Ok(Command::Command(query))
+}
+
+// OBJECT ENCODING | FREQ | IDLETIME key
+pub(super) fn object(argv: &[Bytes]) -> Result<Command> {
+    match &argv[1..] {
+        [subcommand, key] if subcommand.eq_ignore_ascii_case(b"ENCODING") => {
+            Ok(Command::Object(ObjectQuery::Encoding(key.to_vec())))
+        }
+        [subcommand, key] if subcommand.eq_ignore_ascii_case(b"FREQ") => {
+            Ok(Command::Object(ObjectQuery::Freq(key.to_vec())))
+        }
+        [subcommand, key] if subcommand.eq_ignore_ascii_case(b"IDLETIME") => {
+            Ok(Command::Object(ObjectQuery::IdleTime(key.to_vec())))
+        }
+        [subcommand, ..] => Err(RedisError::InvalidArgument(format!(
+            "unknown subcommand or wrong number of arguments for '{}'. Try OBJECT HELP.",
+            String::from_utf8_lossy(subcommand)
+        ))),
+        [] => Err(RedisError::wrong_arg_count("object", 1, 0)),
+    }
+}
+
+// DEBUG SET-ACTIVE-EXPIRE 0|1
+pub(super) fn debug(argv: &[Bytes]) -> Result<Command> {
+    match &argv[1..] {
+        [subcommand, enabled] if subcommand.eq_ignore_ascii_case(b"SET-ACTIVE-EXPIRE") => {
+            let enabled = parse_i64(enabled).ok_or(RedisError::NotInteger)?;
+            Ok(Command::Debug(DebugCommand::SetActiveExpire(enabled != 0)))
+        }
+        [subcommand, ..] => Err(RedisError::InvalidArgument(format!(
+            "unknown subcommand or wrong number of arguments for '{}'. Try DEBUG HELP.",
+            String::from_utf8_lossy(subcommand)
+        ))),
+        [] => Err(RedisError::wrong_arg_count("debug", 1, 0)),
+    }
+}
+
+// HOTKEYS [COUNT count]; the top 16 by default, as redis-cli --hotkeys prints
+pub(super) fn hotkeys(argv: &[Bytes]) -> Result<Command> {
+    match &argv[1..] {
+        [] => Ok(Command::HotKeys(16)),
+        [option, count] if option.eq_ignore_ascii_case(b"COUNT") => {
+            let count = parse_u64(count).ok_or(RedisError::NotInteger)?;
+            Ok(Command::HotKeys(count as usize))
+        }
+        _ => Err(RedisError::SyntaxError),
+    }
+}
+
+// the cursor argument of the *SCAN commands
+fn scan_cursor(arg: &[u8]) -> Result<u64> {
+    parse_u64(arg).ok_or_else(|| RedisError::InvalidArgument("invalid cursor".to_string()))
+}
+
+// [MATCH pattern] [COUNT count], plus [NOVALUES] where the command supports it
+fn scan_options(args: &[Bytes], allow_novalues: bool) -> Result<ScanOptions> {
+    let mut options = ScanOptions::default();
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        if option.eq_ignore_ascii_case(b"MATCH") {
+            let pattern = args.next().ok_or(RedisError::SyntaxError)?;
+            // `*` matches everything, so skip the filtering entirely
+            options.pattern = (pattern.as_ref() != b"*").then(|| pattern.to_vec());
+        } else if option.eq_ignore_ascii_case(b"COUNT") {
+            let count = args.next().ok_or(RedisError::SyntaxError)?;
+            let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+            if count < 1 {
+                return Err(RedisError::SyntaxError);
+            }
+            options.count = count as usize;
+        } else if allow_novalues && option.eq_ignore_ascii_case(b"NOVALUES") {
+            options.no_values = true;
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+    }
+
+    Ok(options)
+}
+
+// numeric arguments are the only ones that have to be valid text
+fn parse_u64(bytes: &[u8]) -> Option<u64> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+fn parse_i64(bytes: &[u8]) -> Option<i64> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+// like redis, NaN is never a valid argument; infinities are
+fn parse_f64(bytes: &[u8]) -> Option<f64> {
+    let value: f64 = std::str::from_utf8(bytes).ok()?.parse().ok()?;
+    (!value.is_nan()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn request(parts: &[&[u8]]) -> RESPType {
+        RESPType::Array(
+            parts
+                .iter()
+                .map(|part| RESPType::BulkString(Some(Bytes::copy_from_slice(part))))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_binary_key_and_value() {
+        let router = Router::new();
+        let key: &[u8] = b"\xff\x00key";
+        let value: &[u8] = b"\x08\x96\x01\xc3\x28";
+
+        match router.route(&request(&[b"SET", key, value])).unwrap() {
+            Command::Set(k, StorageValue::String(v), options) if options == SetOptions::default() => {
+                assert_eq!(k, key);
+                assert_eq!(v, value);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_hello_options() {
+        let router = Router::new();
+        match router.route(&request(&[b"HELLO", b"3", b"AUTH", b"default", b"pw", b"SETNAME", b"app"])).unwrap() {
+            Command::Hello { protover, auth, setname } => {
+                assert_eq!(protover, Some(3));
+                assert_eq!(auth, Some((b"default".to_vec(), b"pw".to_vec())));
+                assert_eq!(setname, Some(b"app".to_vec()));
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+
+        assert!(matches!(router.route(&request(&[b"HELLO"])).unwrap(), Command::Hello { protover: None, .. }));
+        assert!(router.route(&request(&[b"HELLO", b"three"])).is_err());
+        assert!(router.route(&request(&[b"HELLO", b"3", b"AUTH", b"default"])).is_err());
+        assert!(router.route(&request(&[b"HELLO", b"3", b"BOGUS"])).is_err());
+    }
+
+    #[test]
+    fn test_unknown_command_and_arity_errors() {
+        let router = Router::new();
+        assert!(matches!(
+            router.route(&request(&[b"NOSUCH", b"key"])),
+            Err(RedisError::InvalidCommand(name)) if name == "NOSUCH"
+        ));
+        assert!(matches!(
+            router.route(&request(&[b"GET"])),
+            Err(RedisError::InvalidArgumentCount { expected: 1, got: 0, .. })
+        ));
+        assert!(matches!(
+            router.route(&request(&[b"get", b"a", b"b"])),
+            Err(RedisError::InvalidArgumentCount { expected: 1, got: 2, .. })
+        ));
+        // variadic commands only enforce a minimum
+        assert!(matches!(router.route(&request(&[b"SET", b"k"])), Err(RedisError::InvalidArgumentCount { .. })));
+        assert!(matches!(router.route(&request(&[b"SET", b"k", b"v", b"EX"])), Err(RedisError::SyntaxError)));
+        assert!(router.route(&request(&[b"PING", b"a", b"b"])).is_err());
+    }
+
+    #[test]
+    fn test_command_subcommands() {
+        let router = Router::new();
+        assert!(matches!(router.route(&request(&[b"COMMAND"])).unwrap(), Command::Command(CommandQuery::List)));
+        assert!(matches!(router.route(&request(&[b"command", b"count"])).unwrap(), Command::Command(CommandQuery::Count)));
+        match router.route(&request(&[b"COMMAND", b"GETKEYS", b"GET", b"k"])).unwrap() {
+            Command::Command(CommandQuery::GetKeys(argv)) => assert_eq!(argv, vec![b"GET".to_vec(), b"k".to_vec()]),
+            other => panic!("unexpected command {:?}", other),
+        }
+        assert!(router.route(&request(&[b"COMMAND", b"GETKEYS"])).is_err());
+        assert!(router.route(&request(&[b"COMMAND", b"BOGUS"])).is_err());
+    }
+
+    fn set_options(args: &[&[u8]]) -> Result<SetOptions> {
+        let mut parts: Vec<&[u8]> = vec![b"SET", b"k", b"v"];
+        parts.extend_from_slice(args);
+        match Router::new().route(&request(&parts))? {
+            Command::Set(_, _, options) => Ok(options),
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_options() {
+        let options = set_options(&[b"nx", b"PX", b"30000"]).unwrap();
+        assert_eq!(options.condition, Some(SetCondition::Nx));
+        assert_eq!(options.expiry, SetExpiry::After(Duration::from_secs(30)));
+
+        assert_eq!(
+            set_options(&[b"EXAT", b"1700000000"]).unwrap().expiry,
+            SetExpiry::At(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            set_options(&[b"PXAT", b"1700000000123"]).unwrap().expiry,
+            SetExpiry::At(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123))
+        );
+
+        let options = set_options(&[b"XX", b"GET", b"KEEPTTL"]).unwrap();
+        assert_eq!(options.condition, Some(SetCondition::Xx));
+        assert_eq!(options.expiry, SetExpiry::Keep);
+        assert!(options.get);
+
+        // repeating an option is accepted, like redis
+        assert!(set_options(&[b"EX", b"1", b"EX", b"2", b"NX", b"NX"]).is_ok());
+    }
+
+    #[test]
+    fn test_set_rejects_invalid_combinations() {
+        for args in [
+            &[b"NX" as &[u8], b"XX"][..],
+            &[b"EX", b"10", b"PX", b"10"],
+            &[b"KEEPTTL", b"EX", b"10"],
+            &[b"PXAT", b"10", b"KEEPTTL"],
+            &[b"EX"],
+            &[b"BOGUS"],
+        ] {
+            assert!(matches!(set_options(args), Err(RedisError::SyntaxError)), "{:?}", args);
+        }
+
+        assert!(matches!(set_options(&[b"EX", b"ten"]), Err(RedisError::NotInteger)));
+        assert!(matches!(set_options(&[b"EX", b"0"]), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(set_options(&[b"PX", b"-5"]), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(set_options(&[b"EX", b"9223372036854775807"]), Err(RedisError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_numeric_argument_must_be_text() {
+        let router = Router::new();
+        assert!(router.route(&request(&[b"EXPIRE", b"key", b"\xff"])).is_err());
+        assert!(router.route(&request(&[b"EXPIRE", b"key", b"10"])).is_ok());
+    }
+}
@@ -0,0 +1,161 @@
+// src/engine/router/set.rs
+use bytes::Bytes;
+use crate::storage::{Command, SetOp};
+use crate::utils::error::{RedisError, Result};
+use super::{parse_i64, scan_cursor, scan_options};
+
+fn members(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+pub(in crate::engine) fn sadd(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SAdd(argv[1].to_vec(), members(&argv[2..])))
+}
+
+pub(in crate::engine) fn srem(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SRem(argv[1].to_vec(), members(&argv[2..])))
+}
+
+pub(in crate::engine) fn sismember(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SIsMember(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+pub(in crate::engine) fn smismember(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SMIsMember(argv[1].to_vec(), members(&argv[2..])))
+}
+
+pub(in crate::engine) fn smembers(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SMembers(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn scard(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SCard(argv[1].to_vec()))
+}
+
+// SPOP key [count]
+pub(in crate::engine) fn spop(argv: &[Bytes]) -> Result<Command> {
+    let count = match argv {
+        [_, _] => None,
+        [_, _, count] => {
+            let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+            let count = usize::try_from(count)
+                .map_err(|_| RedisError::InvalidArgument("value is out of range, must be positive".to_string()))?;
+            Some(count)
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::SPop { key: argv[1].to_vec(), count })
+}
+
+// SRANDMEMBER key [count]
+pub(in crate::engine) fn srandmember(argv: &[Bytes]) -> Result<Command> {
+    let count = match argv {
+        [_, _] => None,
+        [_, _, count] => Some(parse_i64(count).ok_or(RedisError::NotInteger)?),
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::SRandMember { key: argv[1].to_vec(), count })
+}
+
+pub(in crate::engine) fn smove(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SMove {
+        source: argv[1].to_vec(),
+        destination: argv[2].to_vec(),
+        member: argv[3].to_vec(),
+    })
+}
+
+pub(in crate::engine) fn sinter(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SetOp(SetOp::Inter, members(&argv[1..])))
+}
+
+pub(in crate::engine) fn sunion(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SetOp(SetOp::Union, members(&argv[1..])))
+}
+
+pub(in crate::engine) fn sdiff(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SetOp(SetOp::Diff, members(&argv[1..])))
+}
+
+fn store(argv: &[Bytes], op: SetOp) -> Result<Command> {
+    Ok(Command::SetOpStore {
+        op,
+        destination: argv[1].to_vec(),
+        keys: members(&argv[2..]),
+    })
+}
+
+pub(in crate::engine) fn sinterstore(argv: &[Bytes]) -> Result<Command> {
+    store(argv, SetOp::Inter)
+}
+
+pub(in crate::engine) fn sunionstore(argv: &[Bytes]) -> Result<Command> {
+    store(argv, SetOp::Union)
+}
+
+pub(in crate::engine) fn sdiffstore(argv: &[Bytes]) -> Result<Command> {
+    store(argv, SetOp::Diff)
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]
+pub(in crate::engine) fn sintercard(argv: &[Bytes]) -> Result<Command> {
+    let numkeys = parse_i64(&argv[1]).ok_or(RedisError::NotInteger)?;
+    if numkeys <= 0 {
+        return Err(RedisError::InvalidArgument("numkeys should be greater than 0".to_string()));
+    }
+    let numkeys = numkeys as usize;
+    let keys = argv
+        .get(2..2 + numkeys)
+        .ok_or_else(|| RedisError::InvalidArgument("Number of keys can't be greater than number of args".to_string()))?;
+
+    let limit = match &argv[2 + numkeys..] {
+        [] => 0,
+        [option, limit] if option.eq_ignore_ascii_case(b"LIMIT") => {
+            let limit = parse_i64(limit).ok_or(RedisError::NotInteger)?;
+            usize::try_from(limit).map_err(|_| RedisError::InvalidArgument("LIMIT can't be negative".to_string()))?
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::SInterCard { keys: members(keys), limit })
+}
+
+// SSCAN key cursor [MATCH pattern] [COUNT count]
+pub(in crate::engine) fn sscan(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::SScan {
+        key: argv[1].to_vec(),
+        cursor: scan_cursor(&argv[2])?,
+        options: scan_options(&argv[3..], false)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::argv;
+
+    #[test]
+    fn test_sintercard_arguments() {
+        match sintercard(&argv(&["SINTERCARD", "2", "a", "b", "limit", "5"])).unwrap() {
+            Command::SInterCard { keys, limit } => {
+                assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+                assert_eq!(limit, 5);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(sintercard(&argv(&["SINTERCARD", "0", "a"])).is_err());
+        assert!(sintercard(&argv(&["SINTERCARD", "3", "a", "b"])).is_err());
+        assert!(sintercard(&argv(&["SINTERCARD", "1", "a", "LIMIT", "-1"])).is_err());
+        assert!(matches!(sintercard(&argv(&["SINTERCARD", "1", "a", "b"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_counts() {
+        assert!(matches!(spop(&argv(&["SPOP", "s", "-1"])), Err(RedisError::InvalidArgument(_))));
+        assert!(matches!(
+            srandmember(&argv(&["SRANDMEMBER", "s", "-1"])).unwrap(),
+            Command::SRandMember { count: Some(-1), .. }
+        ));
+        // SSCAN has no NOVALUES
+        assert!(matches!(sscan(&argv(&["SSCAN", "s", "0", "NOVALUES"])), Err(RedisError::SyntaxError)));
+    }
+}
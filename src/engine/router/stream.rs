@@ -0,0 +1,576 @@
+// src/engine/router/stream.rs
+use bytes::Bytes;
+use std::time::Duration;
+use crate::storage::{
+    ClaimOptions, Command, PendingRange, StreamId, StreamReadId, StreamTrim, TrimStrategy, XAddId, XGroupCommand,
+    XInfoQuery,
+};
+use crate::utils::error::{RedisError, Result};
+use super::parse_i64;
+
+fn invalid_id() -> RedisError {
+    RedisError::InvalidArgument("Invalid stream ID specified as stream command argument".to_string())
+}
+
+// a full or ms-only id; the missing sequence is filled in with `missing_seq`
+fn stream_id(arg: &[u8], missing_seq: u64) -> Result<StreamId> {
+    StreamId::parse(arg, missing_seq).ok_or_else(invalid_id)
+}
+
+fn ids(args: &[Bytes]) -> Result<Vec<StreamId>> {
+    args.iter().map(|arg| stream_id(arg, 0)).collect()
+}
+
+fn non_negative(arg: &[u8]) -> Result<u64> {
+    let value = parse_i64(arg).ok_or(RedisError::NotInteger)?;
+    u64::try_from(value).map_err(|_| RedisError::InvalidArgument("value is out of range, must be positive".to_string()))
+}
+
+// MAXLEN | MINID [= | ~] threshold [LIMIT count], starting at the strategy keyword;
+// returns the trim and how many arguments it took
+fn trim_options(args: &[Bytes]) -> Result<(StreamTrim, usize)> {
+    let mut used = 1;
+    let approx = match args.get(1).map(|arg| arg.as_ref()) {
+        Some(b"~") => true,
+        Some(b"=") => false,
+        _ => {
+            used -= 1;
+            false
+        }
+    };
+    used += 1;
+    let threshold = args.get(used).ok_or(RedisError::SyntaxError)?;
+    let strategy = if args[0].eq_ignore_ascii_case(b"MAXLEN") {
+        let len = parse_i64(threshold).ok_or(RedisError::NotInteger)?;
+        let len = usize::try_from(len)
+            .map_err(|_| RedisError::InvalidArgument("The MAXLEN argument must be >= 0.".to_string()))?;
+        TrimStrategy::MaxLen(len)
+    } else {
+        TrimStrategy::MinId(stream_id(threshold, 0)?)
+    };
+    used += 1;
+
+    let mut limit = None;
+    if args.get(used).is_some_and(|arg| arg.eq_ignore_ascii_case(b"LIMIT")) {
+        let count = args.get(used + 1).ok_or(RedisError::SyntaxError)?;
+        if !approx {
+            return Err(RedisError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        limit = Some(non_negative(count)? as usize);
+        used += 2;
+    }
+    Ok((StreamTrim { strategy, approx, limit }, used))
+}
+
+fn is_trim_keyword(arg: &[u8]) -> bool {
+    arg.eq_ignore_ascii_case(b"MAXLEN") || arg.eq_ignore_ascii_case(b"MINID")
+}
+
+// XADD key [NOMKSTREAM] [MAXLEN | MINID [= | ~] threshold [LIMIT count]] <* | id> field value [field value ...]
+pub(in crate::engine) fn xadd(argv: &[Bytes]) -> Result<Command> {
+    let mut no_mkstream = false;
+    let mut trim = None;
+    let mut i = 2;
+    while i < argv.len() {
+        if argv[i].eq_ignore_ascii_case(b"NOMKSTREAM") {
+            no_mkstream = true;
+            i += 1;
+        } else if is_trim_keyword(&argv[i]) {
+            let (options, used) = trim_options(&argv[i..])?;
+            trim = Some(options);
+            i += used;
+        } else {
+            break;
+        }
+    }
+
+    let (id, fields) = argv[i..].split_first().ok_or_else(|| RedisError::wrong_arg_count("xadd", 4, argv.len() - 1))?;
+    if fields.is_empty() || !fields.len().is_multiple_of(2) {
+        return Err(RedisError::wrong_arg_count("xadd", 4, argv.len() - 1));
+    }
+    let id = if id.as_ref() == b"*" {
+        XAddId::Auto
+    } else {
+        match id.strip_suffix(b"-*") {
+            Some(ms) => XAddId::AutoSeq(stream_id(ms, 0)?.ms),
+            None => XAddId::Explicit(stream_id(id, 0)?),
+        }
+    };
+    let fields = fields.chunks(2).map(|pair| (pair[0].to_vec(), pair[1].to_vec())).collect();
+    Ok(Command::XAdd { key: argv[1].to_vec(), id, fields, trim, no_mkstream })
+}
+
+// XTRIM key MAXLEN | MINID [= | ~] threshold [LIMIT count]
+pub(in crate::engine) fn xtrim(argv: &[Bytes]) -> Result<Command> {
+    if !is_trim_keyword(&argv[2]) {
+        return Err(RedisError::SyntaxError);
+    }
+    let (trim, used) = trim_options(&argv[2..])?;
+    if 2 + used != argv.len() {
+        return Err(RedisError::SyntaxError);
+    }
+    Ok(Command::XTrim(argv[1].to_vec(), trim))
+}
+
+// a range bound: `-` / `+`, a bare ms filled in towards the outside, or `(` for exclusive
+fn range_bound(arg: &[u8], is_start: bool) -> Result<Option<StreamId>> {
+    match arg {
+        b"-" => Ok(Some(StreamId::MIN)),
+        b"+" => Ok(Some(StreamId::MAX)),
+        _ => {
+            let (exclusive, text) = match arg.strip_prefix(b"(") {
+                Some(text) => (true, text),
+                None => (false, arg),
+            };
+            let id = stream_id(text, if is_start { 0 } else { u64::MAX })?;
+            if !exclusive {
+                return Ok(Some(id));
+            }
+            // an exclusive bound past either end makes the range empty
+            Ok(if is_start { id.next() } else { id.prev() })
+        }
+    }
+}
+
+// XRANGE key start end [COUNT count] / XREVRANGE key end start [COUNT count]
+fn range(argv: &[Bytes], rev: bool) -> Result<Command> {
+    let (start, end) = if rev { (&argv[3], &argv[2]) } else { (&argv[2], &argv[3]) };
+    let count = match &argv[4..] {
+        [] => None,
+        [option, count] if option.eq_ignore_ascii_case(b"COUNT") => {
+            Some(parse_i64(count).ok_or(RedisError::NotInteger)?.max(0) as usize)
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    let (start, end) = match (range_bound(start, true)?, range_bound(end, false)?) {
+        (Some(start), Some(end)) => (start, end),
+        _ => (StreamId::MAX, StreamId::MIN),
+    };
+    Ok(Command::XRange { key: argv[1].to_vec(), start, end, count, rev })
+}
+
+pub(in crate::engine) fn xrange(argv: &[Bytes]) -> Result<Command> {
+    range(argv, false)
+}
+
+pub(in crate::engine) fn xrevrange(argv: &[Bytes]) -> Result<Command> {
+    range(argv, true)
+}
+
+pub(in crate::engine) fn xlen(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::XLen(argv[1].to_vec()))
+}
+
+pub(in crate::engine) fn xdel(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::XDel(argv[1].to_vec(), ids(&argv[2..])?))
+}
+
+// what XREAD and XREADGROUP share
+#[derive(Default)]
+struct ReadOptions {
+    count: Option<usize>,
+    block: bool,
+    timeout: Option<Duration>,
+    no_ack: bool,
+    keys: Vec<Vec<u8>>,
+    ids: Vec<StreamReadId>,
+}
+
+// [COUNT count] [BLOCK ms] [NOACK] STREAMS key [key ...] id [id ...]
+fn read_options(args: &[Bytes], command: &str, group: bool) -> Result<ReadOptions> {
+    let mut options = ReadOptions::default();
+    let mut i = 0;
+    loop {
+        let option = args.get(i).ok_or(RedisError::SyntaxError)?;
+        if option.eq_ignore_ascii_case(b"STREAMS") {
+            i += 1;
+            break;
+        } else if option.eq_ignore_ascii_case(b"COUNT") {
+            let count = parse_i64(args.get(i + 1).ok_or(RedisError::SyntaxError)?).ok_or(RedisError::NotInteger)?;
+            // like redis, a count of zero or less means no limit
+            options.count = (count > 0).then_some(count as usize);
+            i += 2;
+        } else if option.eq_ignore_ascii_case(b"BLOCK") {
+            let ms = parse_i64(args.get(i + 1).ok_or(RedisError::SyntaxError)?)
+                .ok_or_else(|| RedisError::InvalidArgument("timeout is not an integer or out of range".to_string()))?;
+            if ms < 0 {
+                return Err(RedisError::InvalidArgument("timeout is negative".to_string()));
+            }
+            options.block = true;
+            options.timeout = (ms > 0).then(|| Duration::from_millis(ms as u64));
+            i += 2;
+        } else if group && option.eq_ignore_ascii_case(b"NOACK") {
+            options.no_ack = true;
+            i += 1;
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+    }
+
+    let streams = &args[i..];
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Err(RedisError::InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '$' must be specified.",
+            command
+        )));
+    }
+    let (keys, ids) = streams.split_at(streams.len() / 2);
+    options.keys = keys.iter().map(|key| key.to_vec()).collect();
+    options.ids = ids
+        .iter()
+        .map(|id| match id.as_ref() {
+            b"$" => Ok(StreamReadId::Last),
+            b">" if group => Ok(StreamReadId::New),
+            b">" => Err(RedisError::InvalidArgument(
+                "The > ID can be specified only when calling XREADGROUP using the GROUP <group> <consumer> option."
+                    .to_string(),
+            )),
+            _ => Ok(StreamReadId::After(stream_id(id, 0)?)),
+        })
+        .collect::<Result<_>>()?;
+    Ok(options)
+}
+
+// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+pub(in crate::engine) fn xread(argv: &[Bytes]) -> Result<Command> {
+    let options = read_options(&argv[1..], "xread", false)?;
+    Ok(Command::XRead {
+        keys: options.keys,
+        ids: options.ids,
+        count: options.count,
+        block: options.block,
+        timeout: options.timeout,
+    })
+}
+
+// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]
+pub(in crate::engine) fn xreadgroup(argv: &[Bytes]) -> Result<Command> {
+    if !argv[1].eq_ignore_ascii_case(b"GROUP") {
+        return Err(RedisError::InvalidArgument(
+            "Missing GROUP option for XREADGROUP".to_string(),
+        ));
+    }
+    let options = read_options(&argv[4..], "xreadgroup", true)?;
+    Ok(Command::XReadGroup {
+        group: argv[2].to_vec(),
+        consumer: argv[3].to_vec(),
+        keys: options.keys,
+        ids: options.ids,
+        count: options.count,
+        no_ack: options.no_ack,
+        block: options.block,
+        timeout: options.timeout,
+    })
+}
+
+pub(in crate::engine) fn xack(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::XAck { key: argv[1].to_vec(), group: argv[2].to_vec(), ids: ids(&argv[3..])? })
+}
+
+// XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+pub(in crate::engine) fn xpending(argv: &[Bytes]) -> Result<Command> {
+    let (key, group) = (argv[1].to_vec(), argv[2].to_vec());
+    let mut args = &argv[3..];
+    if args.is_empty() {
+        return Ok(Command::XPending { key, group, range: None });
+    }
+
+    let mut min_idle = 0;
+    if args[0].eq_ignore_ascii_case(b"IDLE") {
+        let idle = args.get(1).ok_or(RedisError::SyntaxError)?;
+        min_idle = parse_i64(idle).ok_or(RedisError::NotInteger)?.max(0) as u64;
+        args = &args[2..];
+    }
+    let (start, end, count, consumer) = match args {
+        [start, end, count] => (start, end, count, None),
+        [start, end, count, consumer] => (start, end, count, Some(consumer.to_vec())),
+        _ => return Err(RedisError::SyntaxError),
+    };
+    let (start, end) = match (range_bound(start, true)?, range_bound(end, false)?) {
+        (Some(start), Some(end)) => (start, end),
+        _ => (StreamId::MAX, StreamId::MIN),
+    };
+    let count = parse_i64(count).ok_or(RedisError::NotInteger)?.max(0) as usize;
+    Ok(Command::XPending {
+        key,
+        group,
+        range: Some(PendingRange { min_idle, start, end, count, consumer }),
+    })
+}
+
+fn min_idle(arg: &[u8]) -> Result<u64> {
+    let idle = parse_i64(arg)
+        .ok_or_else(|| RedisError::InvalidArgument("Invalid min-idle-time argument for XCLAIM".to_string()))?;
+    Ok(idle.max(0) as u64)
+}
+
+// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+//   [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]
+pub(in crate::engine) fn xclaim(argv: &[Bytes]) -> Result<Command> {
+    let min_idle = min_idle(&argv[4])?;
+    let first_option = argv[5..]
+        .iter()
+        .position(|arg| StreamId::parse(arg, 0).is_none())
+        .map_or(argv.len(), |pos| pos + 5);
+    let ids = ids(&argv[5..first_option])?;
+    if ids.is_empty() {
+        return Err(invalid_id());
+    }
+
+    let mut options = ClaimOptions::default();
+    let mut rest = argv[first_option..].iter();
+    while let Some(option) = rest.next() {
+        let option = option.to_ascii_uppercase();
+        match option.as_slice() {
+            b"FORCE" => options.force = true,
+            b"JUSTID" => options.just_id = true,
+            b"IDLE" | b"TIME" | b"RETRYCOUNT" | b"LASTID" => {
+                let value = rest.next().ok_or(RedisError::SyntaxError)?;
+                match option.as_slice() {
+                    b"IDLE" => {
+                        let idle = parse_i64(value).ok_or(RedisError::NotInteger)?.max(0) as u64;
+                        options.idle = Some(idle);
+                        options.delivered_at = None;
+                    }
+                    b"TIME" => {
+                        let time = parse_i64(value).ok_or(RedisError::NotInteger)?.max(0) as u64;
+                        options.delivered_at = Some(time);
+                        options.idle = None;
+                    }
+                    b"RETRYCOUNT" => options.retry_count = Some(non_negative(value)?),
+                    _ => options.last_id = Some(stream_id(value, 0)?),
+                }
+            }
+            _ => {
+                return Err(RedisError::InvalidArgument(format!(
+                    "Unrecognized XCLAIM option '{}'",
+                    String::from_utf8_lossy(&option)
+                )))
+            }
+        }
+    }
+
+    Ok(Command::XClaim {
+        key: argv[1].to_vec(),
+        group: argv[2].to_vec(),
+        consumer: argv[3].to_vec(),
+        min_idle,
+        ids,
+        options,
+    })
+}
+
+// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+pub(in crate::engine) fn xautoclaim(argv: &[Bytes]) -> Result<Command> {
+    let min_idle = min_idle(&argv[4])?;
+    let start = match range_bound(&argv[5], true)? {
+        Some(start) => start,
+        None => return Err(invalid_id()),
+    };
+
+    let mut count = 100;
+    let mut just_id = false;
+    let mut rest = argv[6..].iter();
+    while let Some(option) = rest.next() {
+        if option.eq_ignore_ascii_case(b"COUNT") {
+            let value = parse_i64(rest.next().ok_or(RedisError::SyntaxError)?).ok_or(RedisError::NotInteger)?;
+            // candidates are capped at ten times the count
+            if !(1..=i64::MAX / 10).contains(&value) {
+                return Err(RedisError::InvalidArgument("COUNT must be > 0".to_string()));
+            }
+            count = value as usize;
+        } else if option.eq_ignore_ascii_case(b"JUSTID") {
+            just_id = true;
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+    }
+
+    Ok(Command::XAutoClaim {
+        key: argv[1].to_vec(),
+        group: argv[2].to_vec(),
+        consumer: argv[3].to_vec(),
+        min_idle,
+        start,
+        count,
+        just_id,
+    })
+}
+
+fn unknown_subcommand(subcommand: &[u8], command: &str) -> RedisError {
+    RedisError::InvalidArgument(format!(
+        "unknown subcommand or wrong number of arguments for '{}'. Try {} HELP.",
+        String::from_utf8_lossy(subcommand),
+        command
+    ))
+}
+
+// the id of XGROUP CREATE / SETID, where `$` means the stream's last id
+fn group_id(arg: &[u8]) -> Result<Option<StreamId>> {
+    if arg == b"$" {
+        return Ok(None);
+    }
+    stream_id(arg, 0).map(Some)
+}
+
+fn entries_read(arg: &[u8]) -> Result<u64> {
+    parse_i64(arg)
+        .and_then(|value| u64::try_from(value).ok())
+        .ok_or_else(|| RedisError::InvalidArgument("value for ENTRIESREAD must be positive or -1".to_string()))
+}
+
+// XGROUP CREATE key group id | $ [MKSTREAM] [ENTRIESREAD n] | SETID key group id | $ [ENTRIESREAD n]
+//   | DESTROY key group | CREATECONSUMER key group consumer | DELCONSUMER key group consumer
+pub(in crate::engine) fn xgroup(argv: &[Bytes]) -> Result<Command> {
+    let subcommand = argv[1].to_ascii_uppercase();
+    let args = &argv[2..];
+    let command = match (subcommand.as_slice(), args) {
+        (b"CREATE", [key, group, id, options @ ..]) => {
+            let (mut mkstream, mut read) = (false, None);
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                if option.eq_ignore_ascii_case(b"MKSTREAM") {
+                    mkstream = true;
+                } else if option.eq_ignore_ascii_case(b"ENTRIESREAD") {
+                    read = Some(entries_read(options.next().ok_or(RedisError::SyntaxError)?)?);
+                } else {
+                    return Err(RedisError::SyntaxError);
+                }
+            }
+            XGroupCommand::Create { key: key.to_vec(), group: group.to_vec(), id: group_id(id)?, mkstream, entries_read: read }
+        }
+        (b"SETID", [key, group, id, options @ ..]) => {
+            let read = match options {
+                [] => None,
+                [option, value] if option.eq_ignore_ascii_case(b"ENTRIESREAD") => Some(entries_read(value)?),
+                _ => return Err(RedisError::SyntaxError),
+            };
+            XGroupCommand::SetId { key: key.to_vec(), group: group.to_vec(), id: group_id(id)?, entries_read: read }
+        }
+        (b"DESTROY", [key, group]) => XGroupCommand::Destroy { key: key.to_vec(), group: group.to_vec() },
+        (b"CREATECONSUMER", [key, group, consumer]) => XGroupCommand::CreateConsumer {
+            key: key.to_vec(),
+            group: group.to_vec(),
+            consumer: consumer.to_vec(),
+        },
+        (b"DELCONSUMER", [key, group, consumer]) => XGroupCommand::DelConsumer {
+            key: key.to_vec(),
+            group: group.to_vec(),
+            consumer: consumer.to_vec(),
+        },
+        _ => return Err(unknown_subcommand(&argv[1], "XGROUP")),
+    };
+    Ok(Command::XGroup(command))
+}
+
+// XINFO STREAM key | GROUPS key | CONSUMERS key group
+pub(in crate::engine) fn xinfo(argv: &[Bytes]) -> Result<Command> {
+    let subcommand = argv[1].to_ascii_uppercase();
+    let query = match (subcommand.as_slice(), &argv[2..]) {
+        (b"STREAM", [key]) => XInfoQuery::Stream(key.to_vec()),
+        (b"GROUPS", [key]) => XInfoQuery::Groups(key.to_vec()),
+        (b"CONSUMERS", [key, group]) => XInfoQuery::Consumers(key.to_vec(), group.to_vec()),
+        _ => return Err(unknown_subcommand(&argv[1], "XINFO")),
+    };
+    Ok(Command::XInfo(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_xadd_arguments() {
+        match xadd(&argv(&["XADD", "s", "NOMKSTREAM", "MAXLEN", "~", "10", "LIMIT", "5", "7-*", "f", "v"])).unwrap() {
+            Command::XAdd { id, fields, trim, no_mkstream, .. } => {
+                assert_eq!(id, XAddId::AutoSeq(7));
+                assert_eq!(fields, vec![(b"f".to_vec(), b"v".to_vec())]);
+                assert_eq!(trim, Some(StreamTrim { strategy: TrimStrategy::MaxLen(10), approx: true, limit: Some(5) }));
+                assert!(no_mkstream);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match xadd(&argv(&["XADD", "s", "MINID", "5", "*", "f", "v"])).unwrap() {
+            Command::XAdd { id, trim, .. } => {
+                assert_eq!(id, XAddId::Auto);
+                assert_eq!(trim.unwrap().strategy, TrimStrategy::MinId(StreamId::new(5, 0)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(xadd(&argv(&["XADD", "s", "MAXLEN", "1", "LIMIT", "5", "*", "f", "v"]))).contains("LIMIT cannot"));
+        assert!(error(xadd(&argv(&["XADD", "s", "MAXLEN", "-1", "*", "f", "v"]))).contains(">= 0"));
+        assert!(error(xadd(&argv(&["XADD", "s", "1-x", "f", "v"]))).contains("Invalid stream ID"));
+        assert!(error(xadd(&argv(&["XADD", "s", "*", "f", "v", "g"]))).contains("wrong number"));
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        match xrange(&argv(&["XRANGE", "s", "(5", "7", "COUNT", "2"])).unwrap() {
+            Command::XRange { start, end, count, rev, .. } => {
+                assert_eq!((start, end), (StreamId::new(5, 1), StreamId::new(7, u64::MAX)));
+                assert_eq!((count, rev), (Some(2), false));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match xrevrange(&argv(&["XREVRANGE", "s", "+", "-"])).unwrap() {
+            Command::XRange { start, end, rev, .. } => {
+                assert_eq!((start, end, rev), (StreamId::MIN, StreamId::MAX, true));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(xrange(&argv(&["XRANGE", "s", "-", "+", "LIMIT", "1"])), Err(RedisError::SyntaxError)));
+    }
+
+    #[test]
+    fn test_read_arguments() {
+        match xreadgroup(&argv(&["XREADGROUP", "GROUP", "g", "c", "COUNT", "0", "BLOCK", "0", "NOACK", "STREAMS", "a", "b", ">", "1"])).unwrap() {
+            Command::XReadGroup { keys, ids, count, no_ack, block, timeout, .. } => {
+                assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+                assert_eq!(ids, vec![StreamReadId::New, StreamReadId::After(StreamId::new(1, 0))]);
+                assert_eq!(count, None);
+                assert!(no_ack && block && timeout.is_none());
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match xread(&argv(&["XREAD", "BLOCK", "100", "STREAMS", "a", "$"])).unwrap() {
+            Command::XRead { ids, block, timeout, .. } => {
+                assert_eq!(ids, vec![StreamReadId::Last]);
+                assert!(block);
+                assert_eq!(timeout, Some(Duration::from_millis(100)));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(xread(&argv(&["XREAD", "STREAMS", "a", "b", "0"]))).contains("Unbalanced"));
+        assert!(error(xread(&argv(&["XREAD", "STREAMS", "a", ">"]))).contains("XREADGROUP"));
+        assert!(matches!(xread(&argv(&["XREAD", "NOACK", "STREAMS", "a", "0"])), Err(RedisError::SyntaxError)));
+        assert!(error(xread(&argv(&["XREAD", "BLOCK", "-1", "STREAMS", "a", "0"]))).contains("negative"));
+    }
+
+    #[test]
+    fn test_group_arguments() {
+        match xclaim(&argv(&["XCLAIM", "s", "g", "c", "10", "1-0", "2", "RETRYCOUNT", "3", "JUSTID"])).unwrap() {
+            Command::XClaim { ids, min_idle, options, .. } => {
+                assert_eq!(ids, vec![StreamId::new(1, 0), StreamId::new(2, 0)]);
+                assert_eq!(min_idle, 10);
+                assert_eq!(options.retry_count, Some(3));
+                assert!(options.just_id && !options.force);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match xgroup(&argv(&["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"])).unwrap() {
+            Command::XGroup(XGroupCommand::Create { id, mkstream, entries_read, .. }) => {
+                assert_eq!((id, mkstream, entries_read), (None, true, None));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(xgroup(&argv(&["XGROUP", "DESTROY", "s"]))).contains("Try XGROUP HELP"));
+        assert!(error(xautoclaim(&argv(&["XAUTOCLAIM", "s", "g", "c", "0", "0", "COUNT", "0"]))).contains("COUNT"));
+        assert!(matches!(
+            xpending(&argv(&["XPENDING", "s", "g", "IDLE", "5", "-", "+"])),
+            Err(RedisError::SyntaxError)
+        ));
+    }
+}
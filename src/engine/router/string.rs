@@ -0,0 +1,135 @@
+// src/engine/router/string.rs
+use bytes::Bytes;
+use crate::storage::{Command, SetExpiry, SetOptions, StorageValue};
+use crate::utils::error::{RedisError, Result};
+use super::{expiry_arg, parse_f64, parse_i64};
+
+fn integer(arg: &[u8]) -> Result<i64> {
+    parse_i64(arg).ok_or(RedisError::NotInteger)
+}
+
+fn keys(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+// INCRBY key increment
+pub(in crate::engine) fn incrby(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::IncrBy(argv[1].to_vec(), integer(&argv[2])?))
+}
+
+// DECRBY key decrement
+pub(in crate::engine) fn decrby(argv: &[Bytes]) -> Result<Command> {
+    let delta = integer(&argv[2])?
+        .checked_neg()
+        .ok_or_else(|| RedisError::InvalidArgument("decrement would overflow".to_string()))?;
+    Ok(Command::IncrBy(argv[1].to_vec(), delta))
+}
+
+// INCRBYFLOAT key increment
+pub(in crate::engine) fn incrbyfloat(argv: &[Bytes]) -> Result<Command> {
+    let delta = parse_f64(&argv[2]).ok_or(RedisError::NotFloat)?;
+    Ok(Command::IncrByFloat(argv[1].to_vec(), delta))
+}
+
+// GETRANGE key start end
+pub(in crate::engine) fn getrange(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::GetRange(argv[1].to_vec(), integer(&argv[2])?, integer(&argv[3])?))
+}
+
+// SETRANGE key offset value
+pub(in crate::engine) fn setrange(argv: &[Bytes]) -> Result<Command> {
+    let offset = usize::try_from(integer(&argv[2])?)
+        .map_err(|_| RedisError::InvalidArgument("offset is out of range".to_string()))?;
+    Ok(Command::SetRange(argv[1].to_vec(), offset, argv[3].to_vec()))
+}
+
+pub(in crate::engine) fn strlen(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::StrLen(argv[1].to_vec()))
+}
+
+// GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds | PERSIST]
+pub(in crate::engine) fn getex(argv: &[Bytes]) -> Result<Command> {
+    let expiry = match &argv[2..] {
+        [] => SetExpiry::Keep,
+        [option] if option.eq_ignore_ascii_case(b"PERSIST") => SetExpiry::Persist,
+        [option, value] => {
+            let option = option.to_ascii_uppercase();
+            if !matches!(option.as_slice(), b"EX" | b"PX" | b"EXAT" | b"PXAT") {
+                return Err(RedisError::SyntaxError);
+            }
+            expiry_arg(value, &option, "getex")?
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::GetEx(argv[1].to_vec(), expiry))
+}
+
+pub(in crate::engine) fn getdel(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::GetDel(argv[1].to_vec()))
+}
+
+// SETNX key value; the same as an MSETNX of one key
+pub(in crate::engine) fn setnx(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::MSet { pairs: vec![(argv[1].to_vec(), argv[2].to_vec())], only_new: true })
+}
+
+// SETEX key seconds value
+pub(in crate::engine) fn setex(argv: &[Bytes]) -> Result<Command> {
+    let options = SetOptions { expiry: expiry_arg(&argv[2], b"EX", "setex")?, ..SetOptions::default() };
+    Ok(Command::Set(argv[1].to_vec(), StorageValue::String(argv[3].to_vec()), options))
+}
+
+// PSETEX key milliseconds value
+pub(in crate::engine) fn psetex(argv: &[Bytes]) -> Result<Command> {
+    let options = SetOptions { expiry: expiry_arg(&argv[2], b"PX", "psetex")?, ..SetOptions::default() };
+    Ok(Command::Set(argv[1].to_vec(), StorageValue::String(argv[3].to_vec()), options))
+}
+
+// MGET key [key ...]
+pub(in crate::engine) fn mget(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::MGet(keys(&argv[1..])))
+}
+
+fn pairs(argv: &[Bytes], name: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let args = &argv[1..];
+    if !args.len().is_multiple_of(2) {
+        return Err(RedisError::wrong_arg_count(name, args.len() + 1, args.len()));
+    }
+    Ok(args.chunks(2).map(|pair| (pair[0].to_vec(), pair[1].to_vec())).collect())
+}
+
+// MSET key value [key value ...]
+pub(in crate::engine) fn mset(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::MSet { pairs: pairs(argv, "mset")?, only_new: false })
+}
+
+// MSETNX key value [key value ...]
+pub(in crate::engine) fn msetnx(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::MSet { pairs: pairs(argv, "msetnx")?, only_new: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_string_arguments() {
+        assert!(matches!(decrby(&argv(&["DECRBY", "n", "5"])).unwrap(), Command::IncrBy(_, -5)));
+        assert!(error(decrby(&argv(&["DECRBY", "n", "-9223372036854775808"]))).contains("decrement would overflow"));
+        assert!(matches!(incrby(&argv(&["INCRBY", "n", "1.5"])), Err(RedisError::NotInteger)));
+        assert!(matches!(incrbyfloat(&argv(&["INCRBYFLOAT", "n", "x"])), Err(RedisError::NotFloat)));
+        assert!(error(setrange(&argv(&["SETRANGE", "s", "-1", "x"]))).contains("offset is out of range"));
+        assert!(error(setex(&argv(&["SETEX", "s", "0", "x"]))).contains("invalid expire time in 'setex'"));
+        assert!(error(mset(&argv(&["MSET", "a", "1", "b"]))).contains("wrong number of arguments for 'mset'"));
+    }
+
+    #[test]
+    fn test_getex_arguments() {
+        assert!(matches!(getex(&argv(&["GETEX", "k"])).unwrap(), Command::GetEx(_, SetExpiry::Keep)));
+        assert!(matches!(getex(&argv(&["GETEX", "k", "persist"])).unwrap(), Command::GetEx(_, SetExpiry::Persist)));
+        assert!(matches!(getex(&argv(&["GETEX", "k", "PX", "100"])).unwrap(), Command::GetEx(_, SetExpiry::After(_))));
+        assert!(matches!(getex(&argv(&["GETEX", "k", "EX", "1", "PERSIST"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(getex(&argv(&["GETEX", "k", "KEEPTTL", "1"])), Err(RedisError::SyntaxError)));
+    }
+}
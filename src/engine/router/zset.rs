@@ -0,0 +1,399 @@
+// src/engine/router/zset.rs
+use bytes::Bytes;
+use crate::storage::{
+    Aggregate, Command, LexBound, LexRange, ScoreBound, ScoreComparison, ScoreRange, SetCondition, SetOp,
+    ZAddOptions, ZEnd, ZRangeBy, ZRangeQuery,
+};
+use crate::utils::error::{RedisError, Result};
+use super::list::timeout;
+use super::{parse_f64, parse_i64, scan_cursor, scan_options};
+
+fn members(args: &[Bytes]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+fn score(arg: &[u8]) -> Result<f64> {
+    parse_f64(arg).ok_or(RedisError::NotFloat)
+}
+
+// ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+pub(in crate::engine) fn zadd(argv: &[Bytes]) -> Result<Command> {
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) = (false, false, false, false, false, false);
+    let mut rest = &argv[2..];
+    while let Some((option, tail)) = rest.split_first() {
+        let flag = if option.eq_ignore_ascii_case(b"NX") {
+            &mut nx
+        } else if option.eq_ignore_ascii_case(b"XX") {
+            &mut xx
+        } else if option.eq_ignore_ascii_case(b"GT") {
+            &mut gt
+        } else if option.eq_ignore_ascii_case(b"LT") {
+            &mut lt
+        } else if option.eq_ignore_ascii_case(b"CH") {
+            &mut ch
+        } else if option.eq_ignore_ascii_case(b"INCR") {
+            &mut incr
+        } else {
+            break;
+        };
+        *flag = true;
+        rest = tail;
+    }
+
+    if rest.is_empty() || !rest.len().is_multiple_of(2) {
+        return Err(RedisError::SyntaxError);
+    }
+    if nx && xx {
+        return Err(RedisError::InvalidArgument(
+            "XX and NX options at the same time are not compatible".to_string(),
+        ));
+    }
+    if [nx, gt, lt].iter().filter(|&&flag| flag).count() > 1 {
+        return Err(RedisError::InvalidArgument(
+            "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+        ));
+    }
+    if incr && rest.len() > 2 {
+        return Err(RedisError::InvalidArgument(
+            "INCR option supports a single increment-element pair".to_string(),
+        ));
+    }
+
+    let options = ZAddOptions {
+        condition: if nx { Some(SetCondition::Nx) } else if xx { Some(SetCondition::Xx) } else { None },
+        comparison: if gt { Some(ScoreComparison::Gt) } else if lt { Some(ScoreComparison::Lt) } else { None },
+        ch,
+        incr,
+    };
+    let members = rest
+        .chunks(2)
+        .map(|pair| Ok((score(&pair[0])?, pair[1].to_vec())))
+        .collect::<Result<_>>()?;
+    Ok(Command::ZAdd { key: argv[1].to_vec(), members, options })
+}
+
+pub(in crate::engine) fn zrem(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZRem(argv[1].to_vec(), members(&argv[2..])))
+}
+
+pub(in crate::engine) fn zscore(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZScore(argv[1].to_vec(), argv[2].to_vec()))
+}
+
+pub(in crate::engine) fn zincrby(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZIncrBy(argv[1].to_vec(), score(&argv[2])?, argv[3].to_vec()))
+}
+
+pub(in crate::engine) fn zcard(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZCard(argv[1].to_vec()))
+}
+
+// `1.5`, `(1.5`, `-inf` or `+inf`
+fn score_bound(arg: &[u8]) -> Result<ScoreBound> {
+    let (exclusive, value) = match arg.strip_prefix(b"(") {
+        Some(value) => (true, value),
+        None => (false, arg),
+    };
+    let value = parse_f64(value)
+        .ok_or_else(|| RedisError::InvalidArgument("min or max is not a float".to_string()))?;
+    Ok(ScoreBound { value, exclusive })
+}
+
+fn score_range(min: &[u8], max: &[u8]) -> Result<ScoreRange> {
+    Ok(ScoreRange { min: score_bound(min)?, max: score_bound(max)? })
+}
+
+// `-`, `+`, `[member` or `(member`
+fn lex_bound(arg: &[u8]) -> Result<LexBound> {
+    match arg {
+        b"-" => Ok(LexBound::Min),
+        b"+" => Ok(LexBound::Max),
+        [b'[', member @ ..] => Ok(LexBound::Inclusive(member.to_vec())),
+        [b'(', member @ ..] => Ok(LexBound::Exclusive(member.to_vec())),
+        _ => Err(RedisError::InvalidArgument("min or max not valid string range item".to_string())),
+    }
+}
+
+pub(in crate::engine) fn zcount(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZCount(argv[1].to_vec(), score_range(&argv[2], &argv[3])?))
+}
+
+// ZRANK / ZREVRANK key member [WITHSCORE]
+fn rank(argv: &[Bytes], rev: bool) -> Result<Command> {
+    let with_score = match &argv[3..] {
+        [] => false,
+        [option] if option.eq_ignore_ascii_case(b"WITHSCORE") => true,
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::ZRank { key: argv[1].to_vec(), member: argv[2].to_vec(), rev, with_score })
+}
+
+pub(in crate::engine) fn zrank(argv: &[Bytes]) -> Result<Command> {
+    rank(argv, false)
+}
+
+pub(in crate::engine) fn zrevrank(argv: &[Bytes]) -> Result<Command> {
+    rank(argv, true)
+}
+
+// start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES], shared by ZRANGE and
+// ZRANGESTORE; the latter has no WITHSCORES
+fn range_query(start: &[u8], stop: &[u8], options: &[Bytes], allow_scores: bool) -> Result<ZRangeQuery> {
+    let (mut by_score, mut by_lex, mut rev, mut with_scores) = (false, false, false, false);
+    let mut limit = None;
+
+    let mut rest = options;
+    while let Some((option, tail)) = rest.split_first() {
+        if option.eq_ignore_ascii_case(b"BYSCORE") {
+            by_score = true;
+        } else if option.eq_ignore_ascii_case(b"BYLEX") {
+            by_lex = true;
+        } else if option.eq_ignore_ascii_case(b"REV") {
+            rev = true;
+        } else if option.eq_ignore_ascii_case(b"WITHSCORES") && allow_scores {
+            with_scores = true;
+        } else if option.eq_ignore_ascii_case(b"LIMIT") {
+            match tail {
+                [offset, count, tail @ ..] => {
+                    let offset = parse_i64(offset).ok_or(RedisError::NotInteger)?;
+                    let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+                    limit = Some((offset, count));
+                    rest = tail;
+                    continue;
+                }
+                _ => return Err(RedisError::SyntaxError),
+            }
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+        rest = tail;
+    }
+
+    if by_score && by_lex {
+        return Err(RedisError::SyntaxError);
+    }
+    if limit.is_some() && !by_score && !by_lex {
+        return Err(RedisError::InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX".to_string(),
+        ));
+    }
+    if with_scores && by_lex {
+        return Err(RedisError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+
+    // with REV the bounds of a score or lex range come highest first
+    let (min, max) = if rev { (stop, start) } else { (start, stop) };
+    let by = if by_score {
+        ZRangeBy::Score(score_range(min, max)?)
+    } else if by_lex {
+        ZRangeBy::Lex(LexRange { min: lex_bound(min)?, max: lex_bound(max)? })
+    } else {
+        let index = |arg| parse_i64(arg).ok_or(RedisError::NotInteger);
+        ZRangeBy::Rank(index(start)?, index(stop)?)
+    };
+
+    // a negative offset returns nothing, a negative count everything after the offset
+    let (offset, limit) = match limit {
+        None => (0, None),
+        Some((offset, _)) if offset < 0 => (0, Some(0)),
+        Some((offset, count)) => (offset as usize, usize::try_from(count).ok()),
+    };
+    Ok(ZRangeQuery { by, rev, offset, limit, with_scores })
+}
+
+pub(in crate::engine) fn zrange(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZRange(argv[1].to_vec(), range_query(&argv[2], &argv[3], &argv[4..], true)?))
+}
+
+// ZRANGESTORE destination source start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+pub(in crate::engine) fn zrangestore(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZRangeStore {
+        destination: argv[1].to_vec(),
+        source: argv[2].to_vec(),
+        query: range_query(&argv[3], &argv[4], &argv[5..], false)?,
+    })
+}
+
+// ZPOPMIN / ZPOPMAX key [count]
+fn pop(argv: &[Bytes], end: ZEnd) -> Result<Command> {
+    let count = match argv {
+        [_, _] => None,
+        [_, _, count] => {
+            let count = parse_i64(count).ok_or(RedisError::NotInteger)?;
+            let count = usize::try_from(count)
+                .map_err(|_| RedisError::InvalidArgument("value is out of range, must be positive".to_string()))?;
+            Some(count)
+        }
+        _ => return Err(RedisError::SyntaxError),
+    };
+    Ok(Command::ZPop { key: argv[1].to_vec(), end, count })
+}
+
+pub(in crate::engine) fn zpopmin(argv: &[Bytes]) -> Result<Command> {
+    pop(argv, ZEnd::Min)
+}
+
+pub(in crate::engine) fn zpopmax(argv: &[Bytes]) -> Result<Command> {
+    pop(argv, ZEnd::Max)
+}
+
+// BZPOPMIN / BZPOPMAX key [key ...] timeout
+fn bpop(argv: &[Bytes], end: ZEnd) -> Result<Command> {
+    let (timeout_arg, keys) = argv[1..].split_last().expect("arity is checked");
+    Ok(Command::BZPop {
+        keys: members(keys),
+        end,
+        timeout: timeout(timeout_arg)?,
+    })
+}
+
+pub(in crate::engine) fn bzpopmin(argv: &[Bytes]) -> Result<Command> {
+    bpop(argv, ZEnd::Min)
+}
+
+pub(in crate::engine) fn bzpopmax(argv: &[Bytes]) -> Result<Command> {
+    bpop(argv, ZEnd::Max)
+}
+
+// ZUNIONSTORE / ZINTERSTORE destination numkeys key [key ...] [WEIGHTS weight [weight ...]]
+// [AGGREGATE SUM | MIN | MAX]
+fn store(argv: &[Bytes], op: SetOp) -> Result<Command> {
+    let numkeys = parse_i64(&argv[2]).ok_or(RedisError::NotInteger)?;
+    if numkeys < 1 {
+        return Err(RedisError::InvalidArgument(format!(
+            "at least 1 input key is needed for 'z{}store' command",
+            op.name().to_ascii_lowercase()
+        )));
+    }
+    let numkeys = numkeys as usize;
+    let keys = argv.get(3..3 + numkeys).ok_or(RedisError::SyntaxError)?;
+
+    let mut weights = Vec::new();
+    let mut aggregate = Aggregate::default();
+    let mut rest = &argv[3 + numkeys..];
+    while let Some((option, tail)) = rest.split_first() {
+        if option.eq_ignore_ascii_case(b"WEIGHTS") && tail.len() >= numkeys {
+            weights = tail[..numkeys]
+                .iter()
+                .map(|weight| {
+                    parse_f64(weight)
+                        .ok_or_else(|| RedisError::InvalidArgument("weight value is not a float".to_string()))
+                })
+                .collect::<Result<_>>()?;
+            rest = &tail[numkeys..];
+        } else if option.eq_ignore_ascii_case(b"AGGREGATE") && !tail.is_empty() {
+            aggregate = if tail[0].eq_ignore_ascii_case(b"SUM") {
+                Aggregate::Sum
+            } else if tail[0].eq_ignore_ascii_case(b"MIN") {
+                Aggregate::Min
+            } else if tail[0].eq_ignore_ascii_case(b"MAX") {
+                Aggregate::Max
+            } else {
+                return Err(RedisError::SyntaxError);
+            };
+            rest = &tail[1..];
+        } else {
+            return Err(RedisError::SyntaxError);
+        }
+    }
+
+    Ok(Command::ZSetOpStore {
+        op,
+        destination: argv[1].to_vec(),
+        keys: members(keys),
+        weights,
+        aggregate,
+    })
+}
+
+pub(in crate::engine) fn zunionstore(argv: &[Bytes]) -> Result<Command> {
+    store(argv, SetOp::Union)
+}
+
+pub(in crate::engine) fn zinterstore(argv: &[Bytes]) -> Result<Command> {
+    store(argv, SetOp::Inter)
+}
+
+// ZSCAN key cursor [MATCH pattern] [COUNT count]
+pub(in crate::engine) fn zscan(argv: &[Bytes]) -> Result<Command> {
+    Ok(Command::ZScan {
+        key: argv[1].to_vec(),
+        cursor: scan_cursor(&argv[2])?,
+        options: scan_options(&argv[3..], false)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::test_helpers::{argv, error};
+
+    #[test]
+    fn test_zadd_options() {
+        match zadd(&argv(&["ZADD", "z", "xx", "GT", "ch", "1", "a", "+inf", "b"])).unwrap() {
+            Command::ZAdd { members, options, .. } => {
+                assert_eq!(members, vec![(1.0, b"a".to_vec()), (f64::INFINITY, b"b".to_vec())]);
+                assert_eq!(options.condition, Some(SetCondition::Xx));
+                assert_eq!(options.comparison, Some(ScoreComparison::Gt));
+                assert!(options.ch && !options.incr);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(zadd(&argv(&["ZADD", "z", "NX", "XX", "1", "a"]))).contains("XX and NX"));
+        assert!(error(zadd(&argv(&["ZADD", "z", "NX", "LT", "1", "a"]))).contains("GT, LT, and/or NX"));
+        assert!(error(zadd(&argv(&["ZADD", "z", "INCR", "1", "a", "2", "b"]))).contains("single increment"));
+        assert!(matches!(zadd(&argv(&["ZADD", "z", "1", "a", "2"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(zadd(&argv(&["ZADD", "z", "CH"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(zadd(&argv(&["ZADD", "z", "nan", "a"])), Err(RedisError::NotFloat)));
+    }
+
+    #[test]
+    fn test_range_queries() {
+        match zrange(&argv(&["ZRANGE", "z", "(5", "-inf", "BYSCORE", "REV", "LIMIT", "1", "-1"])).unwrap() {
+            Command::ZRange(_, query) => {
+                let expected = score_range(b"-inf", b"(5").unwrap();
+                assert_eq!(query.by, ZRangeBy::Score(expected));
+                assert!(query.rev);
+                assert_eq!((query.offset, query.limit), (1, None));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match zrange(&argv(&["ZRANGE", "z", "[a", "+", "BYLEX"])).unwrap() {
+            Command::ZRange(_, query) => assert_eq!(
+                query.by,
+                ZRangeBy::Lex(LexRange { min: LexBound::Inclusive(b"a".to_vec()), max: LexBound::Max })
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(zrange(&argv(&["ZRANGE", "z", "0", "1", "LIMIT", "0", "1"]))).contains("LIMIT is only"));
+        assert!(error(zrange(&argv(&["ZRANGE", "z", "-", "+", "BYLEX", "WITHSCORES"]))).contains("WITHSCORES"));
+        assert!(error(zrange(&argv(&["ZRANGE", "z", "a", "b", "BYLEX"]))).contains("string range item"));
+        assert!(error(zcount(&argv(&["ZCOUNT", "z", "(x", "1"]))).contains("not a float"));
+        assert!(matches!(
+            zrangestore(&argv(&["ZRANGESTORE", "d", "z", "0", "1", "WITHSCORES"])),
+            Err(RedisError::SyntaxError)
+        ));
+    }
+
+    #[test]
+    fn test_store_arguments() {
+        match zinterstore(&argv(&["ZINTERSTORE", "d", "2", "a", "b", "WEIGHTS", "2", "0.5", "aggregate", "max"])).unwrap() {
+            Command::ZSetOpStore { op, keys, weights, aggregate, .. } => {
+                assert_eq!(op, SetOp::Inter);
+                assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+                assert_eq!(weights, vec![2.0, 0.5]);
+                assert_eq!(aggregate, Aggregate::Max);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(error(zunionstore(&argv(&["ZUNIONSTORE", "d", "0", "a"]))).contains("at least 1 input key"));
+        assert!(matches!(zunionstore(&argv(&["ZUNIONSTORE", "d", "3", "a"])), Err(RedisError::SyntaxError)));
+        assert!(matches!(
+            zunionstore(&argv(&["ZUNIONSTORE", "d", "2", "a", "b", "WEIGHTS", "1"])),
+            Err(RedisError::SyntaxError)
+        ));
+        assert!(error(zunionstore(&argv(&["ZUNIONSTORE", "d", "1", "a", "WEIGHTS", "x"]))).contains("weight value"));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod config;
+pub mod engine;
+pub mod network;
+pub mod protocol;
+pub mod storage;
+pub mod utils;
@@ -1,13 +1,11 @@
 use std::io;
-mod network;
-mod protocol;
-mod storage;
-mod utils;
-mod engine;
 
-use crate::network::server::Server;
+use rudist::config::Settings;
+use rudist::network::server::Server;
 
 fn main() -> io::Result<()> {
-    let server = Server::new()?;
+    let settings = Settings::from_args(std::env::args().skip(1))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut server = Server::new(settings)?;
     server.start()
-}
\ No newline at end of file
+}
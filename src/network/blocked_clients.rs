@@ -0,0 +1,132 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::time::Instant;
+use crate::engine::Blocked;
+
+// clients parked by BLPOP & co, keyed by connection; each key keeps its waiters in the
+// order they blocked so they are served first come, first served
+#[derive(Default)]
+pub struct BlockedClients {
+    waiting: HashMap<Vec<u8>, VecDeque<usize>>,
+    clients: HashMap<usize, Blocked>,
+    // ordered by deadline, so timeouts are found without scanning every blocked client
+    deadlines: BTreeSet<(Instant, usize)>,
+}
+
+impl BlockedClients {
+    pub fn block(&mut self, client: usize, blocked: Blocked) {
+        for key in blocked.keys() {
+            let waiters = self.waiting.entry(key.clone()).or_default();
+            // BLPOP k k only waits once on k
+            if !waiters.contains(&client) {
+                waiters.push_back(client);
+            }
+        }
+        if let Some(deadline) = blocked.deadline() {
+            self.deadlines.insert((deadline, client));
+        }
+        self.clients.insert(client, blocked);
+    }
+
+    pub fn unblock(&mut self, client: usize) -> Option<Blocked> {
+        let blocked = self.clients.remove(&client)?;
+        for key in blocked.keys() {
+            if let Some(waiters) = self.waiting.get_mut(key) {
+                waiters.retain(|&waiter| waiter != client);
+                if waiters.is_empty() {
+                    self.waiting.remove(key);
+                }
+            }
+        }
+        if let Some(deadline) = blocked.deadline() {
+            self.deadlines.remove(&(deadline, client));
+        }
+        Some(blocked)
+    }
+
+    pub fn is_blocked(&self, client: usize) -> bool {
+        self.clients.contains_key(&client)
+    }
+
+    pub fn get(&self, client: usize) -> Option<&Blocked> {
+        self.clients.get(&client)
+    }
+
+    // the client that has been waiting on `key` the longest
+    pub fn first_waiting(&self, key: &[u8]) -> Option<usize> {
+        self.waiting.get(key).and_then(|waiters| waiters.front().copied())
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.first().map(|&(deadline, _)| deadline)
+    }
+
+    pub fn timed_out(&self, now: Instant) -> Vec<usize> {
+        self.deadlines
+            .iter()
+            .take_while(|&&(deadline, _)| deadline <= now)
+            .map(|&(_, client)| client)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use crate::engine::{ClientContext, Engine, Outcome};
+    use crate::protocol::resp::types::RESPType;
+
+    fn blocked(engine: &mut Engine, parts: &[&str]) -> Blocked {
+        let request = RESPType::Array(
+            parts
+                .iter()
+                .map(|part| RESPType::BulkString(Some(Bytes::copy_from_slice(part.as_bytes()))))
+                .collect(),
+        );
+        match engine.dispatch(&mut ClientContext::new(0), request).unwrap() {
+            Outcome::Blocked(blocked) => blocked,
+            other => panic!("expected to block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_waiters_are_served_in_order() {
+        let mut engine = Engine::new();
+        let mut clients = BlockedClients::default();
+        clients.block(4, blocked(&mut engine, &["BLPOP", "q", "q", "0"]));
+        clients.block(2, blocked(&mut engine, &["BLPOP", "other", "q", "0"]));
+
+        assert_eq!(clients.first_waiting(b"q"), Some(4));
+        assert_eq!(clients.next_deadline(), None);
+        clients.unblock(4);
+        // the duplicate key did not queue client 4 twice
+        assert_eq!(clients.first_waiting(b"q"), Some(2));
+        clients.unblock(2);
+        assert_eq!(clients.first_waiting(b"q"), None);
+        assert!(clients.is_empty());
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let mut engine = Engine::new();
+        let mut clients = BlockedClients::default();
+        clients.block(1, blocked(&mut engine, &["BLPOP", "q", "10"]));
+        clients.block(2, blocked(&mut engine, &["BRPOPLPUSH", "q", "d", "0.1"]));
+        clients.block(3, blocked(&mut engine, &["BLPOP", "q", "0"]));
+
+        let soon = clients.next_deadline().unwrap();
+        assert_eq!(clients.timed_out(soon), vec![2]);
+        assert_eq!(clients.timed_out(soon + std::time::Duration::from_secs(60)), vec![2, 1]);
+        clients.unblock(2);
+        assert!(clients.next_deadline().unwrap() > soon);
+        assert_eq!(clients.len(), 2);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::{self, Read, Write};
+use std::time::Instant;
+use bytes::BytesMut;
+use mio::net::TcpStream;
+use crate::config::ClientOutputBufferLimits;
+use crate::engine::ClientContext;
+use crate::protocol::resp::parser::{Parsed, Parser};
+use crate::protocol::resp::types::RESPType;
+
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+
+// per-client state: the socket plus a query buffer that survives across reads,
+// so frames split over several tcp segments are reassembled before parsing
+pub struct Connection {
+    stream: TcpStream,
+    client: ClientContext,
+    // parsed frames are split off the front, so it only ever holds the unparsed tail
+    query_buffer: BytesMut,
+    // replies waiting for the socket to become writable
+    output_buffer: Vec<u8>,
+    // offset of the first byte of the output buffer not yet written to the socket
+    output_pos: usize,
+    soft_limit_reached_at: Option<Instant>,
+    close_after_reply: bool,
+}
+
+impl Connection {
+    pub fn new(id: u64, stream: TcpStream) -> Self {
+        Self {
+            stream,
+            client: ClientContext::new(id),
+            query_buffer: BytesMut::new(),
+            output_buffer: Vec::new(),
+            output_pos: 0,
+            soft_limit_reached_at: None,
+            close_after_reply: false,
+        }
+    }
+
+    pub fn stream_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    pub fn client_mut(&mut self) -> &mut ClientContext {
+        &mut self.client
+    }
+
+    // drains the socket into the query buffer; returns false once the peer has closed
+    pub fn read_available(&mut self) -> io::Result<bool> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.query_buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // pops the next complete frame off the query buffer, leaving a partial tail in place
+    pub fn next_frame(&mut self, parser: &Parser) -> Result<Option<RESPType>, String> {
+        match parser.parse_request(&mut self.query_buffer) {
+            Ok(Parsed::Frame(frame)) => Ok(Some(frame)),
+            Ok(Parsed::Incomplete) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn queue_reply(&mut self, bytes: &[u8]) {
+        self.output_buffer.extend_from_slice(bytes);
+    }
+
+    pub fn pending_output(&self) -> usize {
+        self.output_buffer.len() - self.output_pos
+    }
+
+    // writes as much pending output as the socket accepts without blocking;
+    // whatever is left is retried on the next writable event
+    pub fn flush(&mut self) -> io::Result<()> {
+        while self.output_pos < self.output_buffer.len() {
+            match self.stream.write(&self.output_buffer[self.output_pos..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => self.output_pos += n,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        if self.output_pos == self.output_buffer.len() {
+            self.output_buffer.clear();
+            self.output_pos = 0;
+        } else if self.output_pos >= self.output_buffer.len() / 2 {
+            // reclaim the written prefix once it dominates the buffer
+            self.output_buffer.drain(..self.output_pos);
+            self.output_pos = 0;
+        }
+        Ok(())
+    }
+
+    // redis client-output-buffer-limit semantics: the hard limit disconnects at once,
+    // the soft limit only after being continuously exceeded for its duration
+    pub fn output_limit_exceeded(&mut self, limits: &ClientOutputBufferLimits, now: Instant) -> bool {
+        let limit = limits.normal;
+        let pending = self.pending_output();
+
+        if limit.hard_limit_bytes > 0 && pending >= limit.hard_limit_bytes {
+            return true;
+        }
+
+        if limit.soft_limit_bytes > 0 && pending >= limit.soft_limit_bytes {
+            let since = *self.soft_limit_reached_at.get_or_insert(now);
+            return now.duration_since(since) >= limit.soft_limit_duration;
+        }
+
+        self.soft_limit_reached_at = None;
+        false
+    }
+
+    pub fn close_after_reply(&mut self) {
+        self.close_after_reply = true;
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.close_after_reply
+    }
+
+    // true once a connection marked for closing has written everything it owes
+    pub fn should_close(&self) -> bool {
+        self.close_after_reply && self.pending_output() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use crate::config::OutputBufferLimit;
+    use bytes::Bytes;
+
+    fn connection_pair() -> (Connection, std::net::TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        server.set_nonblocking(true).unwrap();
+        (Connection::new(1, TcpStream::from_std(server)), client)
+    }
+
+    fn limits(hard: usize, soft: usize, soft_seconds: u64) -> ClientOutputBufferLimits {
+        ClientOutputBufferLimits { normal: OutputBufferLimit::new(hard, soft, soft_seconds) }
+    }
+
+    #[test]
+    fn test_hard_limit() {
+        let (mut connection, _client) = connection_pair();
+        let limits = limits(10, 0, 0);
+
+        connection.queue_reply(b"123456789");
+        assert!(!connection.output_limit_exceeded(&limits, Instant::now()));
+        connection.queue_reply(b"0");
+        assert!(connection.output_limit_exceeded(&limits, Instant::now()));
+    }
+
+    #[test]
+    fn test_soft_limit_needs_to_persist() {
+        let (mut connection, _client) = connection_pair();
+        let limits = limits(0, 4, 10);
+        let start = Instant::now();
+
+        connection.queue_reply(b"12345");
+        assert!(!connection.output_limit_exceeded(&limits, start));
+        assert!(!connection.output_limit_exceeded(&limits, start + Duration::from_secs(5)));
+        assert!(connection.output_limit_exceeded(&limits, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_soft_limit_resets_when_drained() {
+        let (mut connection, mut client) = connection_pair();
+        let limits = limits(0, 4, 10);
+        let start = Instant::now();
+
+        connection.queue_reply(b"12345");
+        assert!(!connection.output_limit_exceeded(&limits, start));
+        connection.flush().unwrap();
+        assert_eq!(connection.pending_output(), 0);
+        assert!(!connection.output_limit_exceeded(&limits, start + Duration::from_secs(10)));
+
+        let mut received = [0; 5];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"12345");
+    }
+
+    #[test]
+    fn test_pipelined_frames_keep_partial_tail() {
+        let (mut connection, _client) = connection_pair();
+        connection.query_buffer.extend_from_slice(b"*1\r\n$1\r\nA\r\nB\r\n*1\r\n$1\r\nC");
+
+        let parser = Parser::default();
+        let frame = |part: &'static [u8]| RESPType::Array(vec![RESPType::BulkString(Some(Bytes::from_static(part)))]);
+        assert_eq!(connection.next_frame(&parser), Ok(Some(frame(b"A"))));
+        assert_eq!(connection.next_frame(&parser), Ok(Some(frame(b"B"))));
+        assert_eq!(connection.next_frame(&parser), Ok(None));
+        assert_eq!(&connection.query_buffer[..], b"*1\r\n$1\r\nC");
+    }
+}
@@ -1,57 +1,236 @@
-use std::io::{self};
-use std::net::TcpListener;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use mio::net::TcpListener;
+use mio::{Events, Interest, Poll, Token};
 use crate::network::io_multiplexer::IOMultiplexer;
-use std::time::{Instant,Duration};
+
+// the listener gets a token outside the range handed out by the connection slab
+const LISTENER: Token = Token(usize::MAX - 1);
+const EVENTS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum TimerKind {
+    ActiveExpireFast,
+    ActiveExpireSlow,
+    ClientsCron,
+}
+
+// periodic timer driven by the poll timeout, so nothing spins between deadlines
+#[derive(Debug)]
+struct Timer {
+    kind: TimerKind,
+    interval: Duration,
+    next_fire: Instant,
+}
+
+impl Timer {
+    fn new(kind: TimerKind, interval: Duration, now: Instant) -> Self {
+        Self { kind, interval, next_fire: now + interval }
+    }
+}
 
 pub struct EventLoop {
+    poll: Poll,
     listener: TcpListener,
     io_multiplexer: IOMultiplexer,
-    small_sleep_duration: Duration,
-    active_expiry_fast_duration: Duration,
-    active_expiry_slow_duration: Duration,
+    timers: Vec<Timer>,
 }
 
 impl EventLoop {
     pub fn new(
         address: &str, 
         io_multiplexer: IOMultiplexer,
-        small_sleep_duration: Duration,
         active_expiry_fast_duration: Duration,
         active_expiry_slow_duration: Duration,
+        clients_cron_duration: Duration,
     ) -> io::Result<Self> {
-        let listener = TcpListener::bind(address)?;
-        listener.set_nonblocking(true)?;
-        Ok(Self { listener, io_multiplexer, small_sleep_duration, active_expiry_fast_duration, active_expiry_slow_duration })
+        let addr: SocketAddr = address
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let poll = Poll::new()?;
+        let mut listener = TcpListener::bind(addr)?;
+        poll.registry().register(&mut listener, LISTENER, Interest::READABLE)?;
+
+        let now = io_multiplexer.now();
+        let timers = vec![
+            Timer::new(TimerKind::ActiveExpireFast, active_expiry_fast_duration, now),
+            Timer::new(TimerKind::ActiveExpireSlow, active_expiry_slow_duration, now),
+            Timer::new(TimerKind::ClientsCron, clients_cron_duration, now),
+        ];
+
+        Ok(Self { poll, listener, io_multiplexer, timers })
     }
 
-    pub fn run(&self) -> io::Result<()> {
-        let mut last_fast_cycle = Instant::now();
-        let mut last_slow_cycle = Instant::now();
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut events = Events::with_capacity(EVENTS_CAPACITY);
 
         loop {
-            // TODO: better "small sleep" implementation
-            std::thread::sleep(self.small_sleep_duration);
+            // block until a socket is ready or the nearest timer is due
+            let timeout = self.next_timeout();
+            self.run_once(&mut events, timeout)?;
+        }
+    }
 
+    // one turn of the loop: waits up to `timeout` for readiness, then serves the sockets,
+    // blocked clients and timers that are due
+    fn run_once(&mut self, events: &mut Events, timeout: Option<Duration>) -> io::Result<()> {
+        match self.poll.poll(events, timeout) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        for event in events.iter() {
+            match event.token() {
+                LISTENER => self.accept_connections()?,
+                _ => self.io_multiplexer.handle_event(self.poll.registry(), event),
+            }
+        }
+
+        self.io_multiplexer.expire_blocked_clients(self.poll.registry());
+        self.run_timers();
+        Ok(())
+    }
+
+    // edge-triggered: keep accepting until the backlog is drained
+    fn accept_connections(&mut self) -> io::Result<()> {
+        loop {
             match self.listener.accept() {
                 Ok((stream, _addr)) => {
-                    // println!("New connection from: {}", addr);
-                    self.io_multiplexer.add_connection(stream);
-                }
-                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                    // run active expiry only when there are no new connections
-                    if last_fast_cycle.elapsed() > self.active_expiry_fast_duration {
-                        self.io_multiplexer.active_expire_cycle_fast();
-                        last_fast_cycle = Instant::now();
-                    }
-                    
-                    if last_slow_cycle.elapsed() > self.active_expiry_slow_duration {
-                        self.io_multiplexer.active_expire_cycle_slow();
-                        last_slow_cycle = Instant::now();
-                    }
+                    self.io_multiplexer.add_connection(self.poll.registry(), stream)?;
                 }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
                 Err(e) => return Err(e),
             }
-            self.io_multiplexer.process_next_request()?;
         }
     }
+
+    // blocked clients time out precisely instead of waiting for the next timer
+    fn next_timeout(&self) -> Option<Duration> {
+        let now = self.io_multiplexer.now();
+        self.timers
+            .iter()
+            .map(|timer| timer.next_fire)
+            .chain(self.io_multiplexer.next_blocked_deadline())
+            .map(|deadline| deadline.saturating_duration_since(now))
+            .min()
+    }
+
+    fn run_timers(&mut self) {
+        let now = self.io_multiplexer.now();
+        for timer in self.timers.iter_mut() {
+            if timer.next_fire > now {
+                continue;
+            }
+
+            match timer.kind {
+                TimerKind::ActiveExpireFast => self.io_multiplexer.active_expire_cycle_fast(),
+                TimerKind::ActiveExpireSlow => self.io_multiplexer.active_expire_cycle_slow(),
+                TimerKind::ClientsCron => self.io_multiplexer.enforce_output_limits(self.poll.registry()),
+            }
+            timer.next_fire = now + timer.interval;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use crate::config::ClientOutputBufferLimits;
+    use crate::engine::Engine;
+    use crate::protocol::handler::Handler;
+    use crate::protocol::resp::parser::Parser;
+    use crate::storage::eviction::EvictionConfig;
+
+    fn event_loop() -> EventLoop {
+        let mut engine = Engine::new();
+        engine.set_maxmemory(0, EvictionConfig::default());
+        let io_multiplexer =
+            IOMultiplexer::new(Handler::new(engine), 16, ClientOutputBufferLimits::default(), Parser::default());
+        let second = Duration::from_secs(1);
+        EventLoop::new("127.0.0.1:0", io_multiplexer, second, second, second).unwrap()
+    }
+
+    // turns the loop until `done` holds, failing after a few seconds
+    fn run_until(event_loop: &mut EventLoop, mut done: impl FnMut(&EventLoop) -> bool) {
+        let mut events = Events::with_capacity(EVENTS_CAPACITY);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(event_loop) {
+            assert!(Instant::now() < deadline, "the event loop never got there");
+            event_loop.run_once(&mut events, Some(Duration::from_millis(10))).unwrap();
+        }
+    }
+
+    fn connect(event_loop: &EventLoop) -> TcpStream {
+        let stream = TcpStream::connect(event_loop.listener.local_addr().unwrap()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream
+    }
+
+    #[test]
+    fn test_large_reply_is_written_across_writable_events() {
+        // far more than the socket buffers take, so the reply goes out over many writes
+        const LEN: usize = 32 << 20;
+        let mut event_loop = event_loop();
+        let mut stream = connect(&event_loop);
+        stream.write_all(format!("SETRANGE big {} x\r\nGET big\r\n", LEN - 1).as_bytes()).unwrap();
+
+        run_until(&mut event_loop, |event_loop| event_loop.io_multiplexer.pending_output() > 0);
+        // the client only starts reading once the server is left holding output
+        let client = thread::spawn(move || {
+            let header = format!(":{}\r\n${}\r\n", LEN, LEN);
+            let mut received = vec![0; header.len() + LEN + 2];
+            stream.read_exact(&mut received).unwrap();
+            assert_eq!(&received[..header.len()], header.as_bytes());
+            assert!(received[header.len()..header.len() + LEN - 1].iter().all(|&byte| byte == 0));
+            assert_eq!(&received[header.len() + LEN - 1..], b"x\r\n");
+            stream
+        });
+        run_until(&mut event_loop, |_| client.is_finished());
+        let mut stream = client.join().unwrap();
+        assert_eq!(event_loop.io_multiplexer.pending_output(), 0);
+
+        // the connection is still usable afterwards
+        stream.write_all(b"STRLEN big\r\n").unwrap();
+        let mut reply = Vec::new();
+        run_until(&mut event_loop, |_| {
+            let mut chunk = [0; 64];
+            stream.set_nonblocking(true).unwrap();
+            if let Ok(n) = stream.read(&mut chunk) {
+                reply.extend_from_slice(&chunk[..n]);
+            }
+            reply.ends_with(b"\r\n")
+        });
+        assert_eq!(reply, format!(":{}\r\n", LEN).into_bytes());
+    }
+
+    #[test]
+    fn test_disconnected_clients_are_dropped() {
+        let mut event_loop = event_loop();
+        let mut blocked = connect(&event_loop);
+        blocked.write_all(b"BLPOP queue 0\r\n").unwrap();
+        let mut idle = connect(&event_loop);
+        run_until(&mut event_loop, |event_loop| event_loop.io_multiplexer.connection_count() == 2);
+
+        // a client that hangs up while blocked is unblocked and forgotten
+        drop(blocked);
+        run_until(&mut event_loop, |event_loop| event_loop.io_multiplexer.connection_count() == 1);
+
+        // so the element pushed next stays in the list
+        idle.write_all(b"RPUSH queue a\r\nLLEN queue\r\n").unwrap();
+        let client = thread::spawn(move || {
+            let mut reply = [0; 8];
+            idle.read_exact(&mut reply).unwrap();
+            assert_eq!(&reply, b":1\r\n:1\r\n");
+            idle
+        });
+        run_until(&mut event_loop, |_| client.is_finished());
+        drop(client.join().unwrap());
+        run_until(&mut event_loop, |event_loop| event_loop.io_multiplexer.connection_count() == 0);
+    }
 }
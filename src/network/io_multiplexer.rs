@@ -1,71 +1,229 @@
-use std::io::{self, Read, Write};
-use std::net::TcpStream;
-use std::sync::{Arc, Mutex};
-use crate::protocol::handler::Handler;
+use std::io::{self, Write};
+use std::time::Instant;
+use mio::event::Event;
+use mio::net::TcpStream;
+use mio::{Interest, Registry, Token};
+use slab::Slab;
+use crate::config::ClientOutputBufferLimits;
+use crate::network::blocked_clients::BlockedClients;
+use crate::network::connection::Connection;
+use crate::protocol::handler::{Handler, Response};
+use crate::protocol::resp::parser::Parser;
+use crate::protocol::resp::encoder::encode_resp;
 use crate::protocol::resp::types::RESPType;
-use crate::protocol::resp::parser::parse_resp;
-use crate::storage::memory::ExpireCycleType;
+use crate::utils::error::RedisError;
 
 pub struct IOMultiplexer {
-    connections: Arc<Mutex<Vec<TcpStream>>>,
-    handler: Arc<Mutex<Handler>>,
+    connections: Slab<Connection>,
+    next_client_id: u64,
+    handler: Handler,
+    max_connections: usize,
+    output_limits: ClientOutputBufferLimits,
+    parser: Parser,
+    blocked: BlockedClients,
 }
 
 impl IOMultiplexer {
     pub fn new(
         handler: Handler,
+        max_connections: usize,
+        output_limits: ClientOutputBufferLimits,
+        parser: Parser,
     ) -> Self {
         IOMultiplexer {
-            connections: Arc::new(Mutex::new(Vec::new())),
-            handler: Arc::new(Mutex::new(handler)),
+            connections: Slab::new(),
+            next_client_id: 1,
+            handler,
+            max_connections,
+            output_limits,
+            parser,
+            blocked: BlockedClients::default(),
         }
     }
 
-    pub fn add_connection(&self, stream: TcpStream) {
-        stream.set_nonblocking(true).unwrap();
-        let mut connections = self.connections.lock().unwrap();
-        connections.push(stream);
-    }
-
-    pub fn process_next_request(&self) -> io::Result<()> {
-        let mut connections = self.connections.lock().unwrap();
-        if let Some(mut stream) = connections.pop() {
-            let mut buffer = Vec::new();
-            let mut temp_buffer = [0; 1024];
-            
-            loop {
-                match stream.read(&mut temp_buffer) {
-                    Ok(0) => break, // conn clozed
-                    Ok(n) => {
-                        buffer.extend_from_slice(&temp_buffer[..n]);
-                        if buffer.contains(&b'\n') { break; }
+    pub fn add_connection(&mut self, registry: &Registry, mut stream: TcpStream) -> io::Result<()> {
+        if self.connections.len() >= self.max_connections {
+            // best effort, the socket is dropped right after
+            let _ = stream.write_all(b"-ERR max number of clients reached\r\n");
+            return Ok(());
+        }
+
+        let entry = self.connections.vacant_entry();
+        let token = Token(entry.key());
+        registry.register(&mut stream, token, Interest::READABLE | Interest::WRITABLE)?;
+        entry.insert(Connection::new(self.next_client_id, stream));
+        self.next_client_id += 1;
+        Ok(())
+    }
+
+    pub fn handle_event(&mut self, registry: &Registry, event: &Event) {
+        let client = event.token().0;
+        if !self.connections.contains(client) {
+            return; // stale event for a connection we already closed
+        }
+
+        let mut keep_open = !event.is_error();
+        if keep_open && event.is_readable() {
+            keep_open = self.process_readable(client).unwrap_or(false);
+        }
+
+        // flush on writable events as well as right after queuing new replies
+        self.finish(registry, client, keep_open);
+        self.serve_ready_keys(registry);
+    }
+
+    // returns false once the connection should be closed
+    fn process_readable(&mut self, client: usize) -> io::Result<bool> {
+        let open = self.connections[client].read_available()?;
+        Ok(self.process_commands(client) && open)
+    }
+
+    // runs every complete command in the query buffer, in order, until the client blocks;
+    // returns false once the connection should be closed
+    fn process_commands(&mut self, client: usize) -> bool {
+        let connection = &mut self.connections[client];
+
+        while !connection.is_closing() && !self.blocked.is_blocked(client) {
+            match connection.next_frame(&self.parser) {
+                // empty inline lines and `*0` requests are no-ops
+                Ok(Some(RESPType::Array(parts))) if parts.is_empty() => continue,
+                Ok(Some(frame)) => match self.handler.handle(connection.client_mut(), frame) {
+                    Response::Reply(response) => {
+                        connection.queue_reply(&encode_resp(&response));
+
+                        // a client that cannot keep up with its replies is dropped with them
+                        if connection.output_limit_exceeded(&self.output_limits, self.handler.now()) {
+                            return false;
+                        }
                     }
-                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
-                    Err(e) => return Err(e),
+                    // anything pipelined behind a blocking command waits until it is served
+                    Response::Blocked(blocked) => self.blocked.block(client, blocked),
+                },
+                Ok(None) => break,
+                Err(msg) => {
+                    // like redis: report the protocol error, then drop the client
+                    let response = RESPType::Error(RedisError::ParseError(msg).to_string());
+                    connection.queue_reply(&encode_resp(&response));
+                    connection.close_after_reply();
                 }
             }
-    
-            if !buffer.is_empty() {
-                let response = {
-                    let mut handler = self.handler.lock().unwrap();
-                    handler.handle(&buffer)
-                };
-    
-                stream.write_all(response.encode().as_bytes())?;
+        }
+
+        true
+    }
+
+    // flushes what the client is owed and closes it when it is done or broken
+    fn finish(&mut self, registry: &Registry, client: usize, mut keep_open: bool) {
+        let connection = match self.connections.get_mut(client) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        if keep_open && connection.pending_output() > 0 {
+            keep_open = connection.flush().is_ok();
+        }
+
+        if !keep_open || connection.should_close() {
+            self.close_connection(registry, Token(client));
+        }
+    }
+
+    // wakes clients blocked on keys that received data, oldest waiter first; serving one can
+    // make more keys ready (BLMOVE, or commands pipelined behind the blocking one), so this
+    // repeats until nothing is left to serve
+    fn serve_ready_keys(&mut self, registry: &Registry) {
+        loop {
+            let ready = self.handler.take_ready_keys();
+            if ready.is_empty() {
+                return;
+            }
+
+            for key in ready {
+                while let Some(client) = self.blocked.first_waiting(&key) {
+                    let connection = &mut self.connections[client];
+                    let blocked = self.blocked.get(client).expect("waiters are blocked clients");
+                    let response = match self.handler.retry_blocked(connection.client_mut(), blocked) {
+                        Some(response) => response,
+                        // drained again by an earlier waiter
+                        None => break,
+                    };
+
+                    self.blocked.unblock(client);
+                    connection.queue_reply(&encode_resp(&response));
+                    let keep_open = self.process_commands(client);
+                    self.finish(registry, client, keep_open);
+                }
             }
-            
-            connections.push(stream);
         }
-        Ok(())
     }
 
-    pub fn active_expire_cycle_fast(&self){
-        let mut handler = self.handler.lock().unwrap();
-        handler.active_expire_cycle_fast();
+    // answers blocked clients whose timeout has passed
+    pub fn expire_blocked_clients(&mut self, registry: &Registry) {
+        let now = self.handler.now();
+        for client in self.blocked.timed_out(now) {
+            let blocked = match self.blocked.unblock(client) {
+                Some(blocked) => blocked,
+                None => continue,
+            };
+
+            let connection = &mut self.connections[client];
+            let response = self.handler.blocked_timeout(connection.client_mut(), &blocked);
+            connection.queue_reply(&encode_resp(&response));
+            let keep_open = self.process_commands(client);
+            self.finish(registry, client, keep_open);
+        }
+        self.serve_ready_keys(registry);
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    // reply bytes queued for clients that their sockets have not taken yet
+    pub fn pending_output(&self) -> usize {
+        self.connections.iter().map(|(_, connection)| connection.pending_output()).sum()
+    }
+
+    // the engine's monotonic time, which every timer and timeout is measured against
+    pub fn now(&self) -> Instant {
+        self.handler.now()
+    }
+
+    pub fn next_blocked_deadline(&self) -> Option<Instant> {
+        self.blocked.next_deadline()
+    }
+
+    fn close_connection(&mut self, registry: &Registry, token: Token) {
+        self.blocked.unblock(token.0);
+        if let Some(mut connection) = self.connections.try_remove(token.0) {
+            let _ = registry.deregister(connection.stream_mut());
+        }
+    }
+
+    // soft limits are time based, so clients that stopped reading are re-checked periodically
+    pub fn enforce_output_limits(&mut self, registry: &Registry) {
+        let now = self.handler.now();
+        let output_limits = self.output_limits;
+        let over_limit: Vec<usize> = self
+            .connections
+            .iter_mut()
+            .filter_map(|(key, connection)| {
+                let exceeded = connection.pending_output() > 0
+                    && connection.output_limit_exceeded(&output_limits, now);
+                exceeded.then_some(key)
+            })
+            .collect();
+
+        for key in over_limit {
+            self.close_connection(registry, Token(key));
+        }
+    }
+
+    pub fn active_expire_cycle_fast(&mut self){
+        self.handler.active_expire_cycle_fast();
     }
 
-    pub fn active_expire_cycle_slow(&self){
-        let mut handler = self.handler.lock().unwrap();
-        handler.active_expire_cycle_slow();
+    pub fn active_expire_cycle_slow(&mut self){
+        self.handler.active_expire_cycle_slow();
     }
 }
@@ -1,3 +1,5 @@
 pub mod server;
 pub mod io_multiplexer;
 pub mod event_loop;
+pub mod connection;
+pub mod blocked_clients;
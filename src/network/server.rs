@@ -1,7 +1,10 @@
 use std::io;
+use std::time::Duration;
+use crate::config::Settings;
 use crate::network::event_loop::EventLoop;
 use crate::network::io_multiplexer::IOMultiplexer;
 use crate::protocol::handler::Handler;
+use crate::protocol::resp::parser::Parser;
 use crate::engine::Engine;
 
 
@@ -11,20 +14,33 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new() -> io::Result<Self> {
-        let address = "127.0.0.1:6379".to_string();
-        let engine = Engine::new();
+    pub fn new(settings: Settings) -> io::Result<Self> {
+        let address = settings.listen_addr.to_string();
+        let mut engine = Engine::new();
+        engine.set_encoding_limits(settings.encoding_limits);
+        engine.set_maxmemory(settings.max_memory, settings.eviction);
         let handler = Handler::new(engine);
-        let io_multiplexer = IOMultiplexer::new(handler);
-        let small_sleep_duration = std::time::Duration::from_millis(1);
-        let active_expiry_fast_duration = std::time::Duration::from_millis(10);
-        let active_expiry_slow_duration = std::time::Duration::from_millis(200);
-        let event_loop = EventLoop::new(&address, io_multiplexer,small_sleep_duration,active_expiry_fast_duration,active_expiry_slow_duration)?;
+        let io_multiplexer = IOMultiplexer::new(
+            handler,
+            settings.max_connections,
+            settings.client_output_buffer_limits,
+            Parser::new(settings.proto_max_bulk_len, settings.proto_max_multibulk_len),
+        );
+        let active_expiry_fast_duration = Duration::from_millis(10);
+        let active_expiry_slow_duration = Duration::from_millis(200);
+        let clients_cron_duration = Duration::from_millis(100);
+        let event_loop = EventLoop::new(
+            &address,
+            io_multiplexer,
+            active_expiry_fast_duration,
+            active_expiry_slow_duration,
+            clients_cron_duration,
+        )?;
         Ok(Server { address, event_loop })
     }
 
-    pub fn start(&self) -> io::Result<()> {
+    pub fn start(&mut self) -> io::Result<()> {
         println!("Starting server on {}", self.address);
         self.event_loop.run()
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,6 @@
 use crate::protocol::resp::types::RESPType;
-use crate::engine::Engine;
-use crate::protocol::resp::parser::parse_resp;
+use crate::engine::{Blocked, ClientContext, Engine, Outcome};
+use std::time::Instant;
 
 pub struct Handler{
     engine: Engine
@@ -11,23 +11,47 @@ impl Handler {
         engine: Engine,
     ) -> Self {
         Self {
-            engine: engine,
+            engine,
         }
     }
 }
+// a reply to send, or a client the network layer has to park
+pub enum Response {
+    Reply(RESPType),
+    Blocked(Blocked),
+}
+
 impl Handler {
-    pub fn handle(&mut self, buffer: &[u8]) -> RESPType {
-        let resp: RESPType = match parse_resp(buffer) {
-            Ok((resp, _)) => resp,
-            Err(e) => return RESPType::Error(e)
-        };
-
-        match self.engine.process_command(resp) {
-            Ok(result) => RESPType::SimpleString(result),
-            Err(e) => RESPType::Error(e)
+    pub fn handle(&mut self, client: &mut ClientContext, request: RESPType) -> Response {
+        match self.engine.dispatch(client, request) {
+            // encode with the protocol in effect after the command, so HELLO 3 answers in resp3
+            Ok(Outcome::Reply(reply)) => Response::Reply(reply.into_resp(client.protocol)),
+            Ok(Outcome::Blocked(blocked)) => Response::Blocked(blocked),
+            Err(e) => Response::Reply(RESPType::Error(e.to_string()))
+        }
+    }
+
+    // None while the client still has nothing to pop
+    pub fn retry_blocked(&mut self, client: &ClientContext, blocked: &Blocked) -> Option<RESPType> {
+        match self.engine.retry_blocked(blocked) {
+            Ok(reply) => reply.map(|reply| reply.into_resp(client.protocol)),
+            Err(e) => Some(RESPType::Error(e.to_string())),
         }
     }
 
+    pub fn blocked_timeout(&self, client: &ClientContext, blocked: &Blocked) -> RESPType {
+        blocked.timeout_reply().into_resp(client.protocol)
+    }
+
+    // the engine's monotonic time, which blocking deadlines are measured against
+    pub fn now(&self) -> Instant {
+        self.engine.clock().instant()
+    }
+
+    pub fn take_ready_keys(&mut self) -> Vec<Vec<u8>> {
+        self.engine.take_ready_keys()
+    }
+
     pub fn active_expire_cycle_fast(&mut self){
         self.engine.active_expire_cycle_fast();
     }
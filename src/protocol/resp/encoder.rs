@@ -1,28 +1,138 @@
-use std::io::Write as _;
 use crate::protocol::resp::types::RESPType;
 
-pub fn encode_resp(resp: &RESPType) -> Result<Vec<u8>, String> {
+const CRLF: &[u8] = b"\r\n";
+
+pub fn encode_resp(resp: &RESPType) -> Vec<u8> {
     let mut result = Vec::new();
+    encode_resp_into(resp, &mut result);
+    result
+}
+
+// appends the encoded frame to `result`, so nested arrays share one buffer
+pub fn encode_resp_into(resp: &RESPType, result: &mut Vec<u8>) {
     match resp {
-        RESPType::SimpleString(s) => write!(&mut result, "+{}\r\n", s).map_err(|e| e.to_string())?,
-        RESPType::Error(s) => write!(&mut result, "-{}\r\n", s).map_err(|e| e.to_string())?,
-        RESPType::Integer(i) => write!(&mut result, ":{}\r\n", i).map_err(|e| e.to_string())?,
+        RESPType::SimpleString(s) => push_line(result, b'+', s.as_bytes()),
+        RESPType::Error(s) => push_line(result, b'-', s.as_bytes()),
+        RESPType::Integer(i) => push_line(result, b':', i.to_string().as_bytes()),
         RESPType::BulkString(Some(s)) => {
-            write!(&mut result, "${}\r\n", s.len()).map_err(|e| e.to_string())?;
+            push_line(result, b'$', s.len().to_string().as_bytes());
             result.extend_from_slice(s);
-            result.push(b'\r');
-            result.push(b'\n');
+            result.extend_from_slice(CRLF);
         }
-        RESPType::BulkString(None) => {
-            result.extend_from_slice(b"$-1\r\n");
+        RESPType::BulkString(None) => push_line(result, b'$', b"-1"),
+        RESPType::Array(arr) => encode_aggregate(result, b'*', arr),
+        RESPType::NullArray => push_line(result, b'*', b"-1"),
+        RESPType::Null => push_line(result, b'_', b""),
+        RESPType::Boolean(b) => push_line(result, b'#', if *b { b"t" } else { b"f" }),
+        RESPType::Double(d) => push_line(result, b',', format_double(*d).as_bytes()),
+        RESPType::BigNumber(n) => push_line(result, b'(', n.as_bytes()),
+        RESPType::Map(pairs) => encode_pairs(result, b'%', pairs),
+        RESPType::Set(items) => encode_aggregate(result, b'~', items),
+        RESPType::Attribute(pairs, value) => {
+            encode_pairs(result, b'|', pairs);
+            encode_resp_into(value, result);
         }
-        RESPType::Array(arr) => {
-            write!(&mut result, "*{}\r\n", arr.len()).map_err(|e| e.to_string())?;
-            for item in arr {
-                let encoded = encode_resp(item)?;
-                result.extend_from_slice(&encoded);
-            }
+        RESPType::Push(items) => encode_aggregate(result, b'>', items),
+        RESPType::Verbatim(format, data) => {
+            push_line(result, b'=', (format.len() + 1 + data.len()).to_string().as_bytes());
+            result.extend_from_slice(format.as_bytes());
+            result.push(b':');
+            result.extend_from_slice(data);
+            result.extend_from_slice(CRLF);
         }
     }
-    Ok(result)
-}
\ No newline at end of file
+}
+
+// shortest text that parses back to the same value, with redis' spelling of the specials
+pub fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "inf".to_string() } else { "-inf".to_string() }
+    } else {
+        format!("{}", value)
+    }
+}
+
+fn encode_aggregate(result: &mut Vec<u8>, prefix: u8, items: &[RESPType]) {
+    push_line(result, prefix, items.len().to_string().as_bytes());
+    for item in items {
+        encode_resp_into(item, result);
+    }
+}
+
+fn encode_pairs(result: &mut Vec<u8>, prefix: u8, pairs: &[(RESPType, RESPType)]) {
+    push_line(result, prefix, pairs.len().to_string().as_bytes());
+    for (key, value) in pairs {
+        encode_resp_into(key, result);
+        encode_resp_into(value, result);
+    }
+}
+
+fn push_line(result: &mut Vec<u8>, prefix: u8, line: &[u8]) {
+    result.push(prefix);
+    result.extend_from_slice(line);
+    result.extend_from_slice(CRLF);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[test]
+    fn test_encode_scalars() {
+        assert_eq!(encode_resp(&RESPType::SimpleString("OK".to_string())), b"+OK\r\n");
+        assert_eq!(encode_resp(&RESPType::Error("ERR bad".to_string())), b"-ERR bad\r\n");
+        assert_eq!(encode_resp(&RESPType::Integer(-3)), b":-3\r\n");
+        assert_eq!(encode_resp(&RESPType::BulkString(None)), b"$-1\r\n");
+        assert_eq!(encode_resp(&RESPType::NullArray), b"*-1\r\n");
+    }
+
+    #[test]
+    fn test_encode_binary_bulk() {
+        let value = vec![0xff, b'\r', b'\n', 0x00];
+        assert_eq!(encode_resp(&RESPType::BulkString(Some(value.into()))), b"$4\r\n\xff\r\n\x00\r\n");
+    }
+
+    #[test]
+    fn test_encode_nested_array() {
+        let resp = RESPType::Array(vec![
+            RESPType::Integer(1),
+            RESPType::Array(vec![RESPType::BulkString(Some(Bytes::from_static(b"a")))]),
+        ]);
+        assert_eq!(encode_resp(&resp), b"*2\r\n:1\r\n*1\r\n$1\r\na\r\n");
+    }
+
+    #[test]
+    fn test_encode_resp3_scalars() {
+        assert_eq!(encode_resp(&RESPType::Null), b"_\r\n");
+        assert_eq!(encode_resp(&RESPType::Boolean(true)), b"#t\r\n");
+        assert_eq!(encode_resp(&RESPType::Boolean(false)), b"#f\r\n");
+        assert_eq!(encode_resp(&RESPType::Double(1.5)), b",1.5\r\n");
+        assert_eq!(encode_resp(&RESPType::Double(f64::NEG_INFINITY)), b",-inf\r\n");
+        assert_eq!(encode_resp(&RESPType::BigNumber("12345678901234567890".to_string())), b"(12345678901234567890\r\n");
+        assert_eq!(
+            encode_resp(&RESPType::Verbatim("txt".to_string(), Bytes::from_static(b"Some string"))),
+            b"=15\r\ntxt:Some string\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_resp3_aggregates() {
+        let map = RESPType::Map(vec![(RESPType::SimpleString("first".to_string()), RESPType::Integer(1))]);
+        assert_eq!(encode_resp(&map), b"%1\r\n+first\r\n:1\r\n");
+
+        let set = RESPType::Set(vec![RESPType::Integer(1), RESPType::Integer(2)]);
+        assert_eq!(encode_resp(&set), b"~2\r\n:1\r\n:2\r\n");
+
+        let push = RESPType::Push(vec![RESPType::BulkString(Some(Bytes::from_static(b"message")))]);
+        assert_eq!(encode_resp(&push), b">1\r\n$7\r\nmessage\r\n");
+
+        let attribute = RESPType::Attribute(
+            vec![(RESPType::SimpleString("ttl".to_string()), RESPType::Integer(3))],
+            Box::new(RESPType::Integer(7)),
+        );
+        assert_eq!(encode_resp(&attribute), b"|1\r\n+ttl\r\n:3\r\n:7\r\n");
+    }
+}
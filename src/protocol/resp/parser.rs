@@ -1,106 +1,459 @@
 use crate::protocol::resp::types::RESPType;
+use bytes::{Bytes, BytesMut};
+use std::fmt;
 use std::str;
 
 // ref: https://redis.io/docs/latest/develop/reference/protocol-spec
 
 const CRLF: &[u8] = b"\r\n";
+// same cap redis puts on a single inline command line and on unterminated count lines
+const INLINE_MAX_SIZE: usize = 64 * 1024;
+// a declared element count never reserves more than this up front
+const MAX_PREALLOC: usize = 1024;
+const MAX_NESTING: usize = 128;
 
-pub fn parse_resp(input: &[u8]) -> Result<(RESPType, usize), String> {
-    if input.is_empty() {
-        return Err("Empty input".to_string());
+// redis' proto-max-bulk-len default
+pub const DEFAULT_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+pub const DEFAULT_MAX_MULTIBULK_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub enum Parsed {
+    Frame(RESPType),
+    // the buffer ends before the frame does; nothing was consumed, wait for more bytes
+    Incomplete,
+}
+
+// the input can never become a valid frame
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
+}
 
-    match input[0] {
-        b'+' => parse_simple_string(input),
-        b'-' => parse_error(input),
-        b':' => parse_integer(input),
-        b'$' => parse_bulk_string(input),
-        b'*' => parse_array(input),
-        _ => Err(format!("Invalid RESP prefix: {}", input[0])),
+impl From<String> for ParseError {
+    fn from(msg: String) -> Self {
+        ParseError(msg)
     }
 }
 
-fn parse_simple_string(input: &[u8]) -> Result<(RESPType, usize), String> {
-    let (s, len) = read_until_crlf(&input[1..])?;
-    Ok((RESPType::SimpleString(s), len + 1))
+impl From<&str> for ParseError {
+    fn from(msg: &str) -> Self {
+        ParseError(msg.to_string())
+    }
 }
 
-fn parse_integer(input: &[u8]) -> Result<(RESPType, usize), String> {
-    let (s, len) = read_until_crlf(&input[1..])?;
-    let num = s.parse::<i64>().map_err(|e| e.to_string())?;
-    Ok((RESPType::Integer(num), len + 1))
+// outcome of walking one value: where it ends and, when materializing, the value itself
+enum Step {
+    Incomplete,
+    Done(usize, Option<RESPType>),
 }
 
-fn parse_bulk_string(input: &[u8]) -> Result<(RESPType, usize), String> {
-    let (length_str, len1) = read_until_crlf(&input[1..])?;
-    let length = length_str.parse::<i64>().map_err(|e| e.to_string())?;
+// single incremental parser for every resp2/resp3 type. a frame is walked twice: once over
+// the raw read buffer to validate it and find where it ends, without allocating, and once
+// after it has been split off that buffer, when bulk payloads become `Bytes` slices of it
+#[derive(Debug, Clone, Copy)]
+pub struct Parser {
+    max_bulk_len: usize,
+    max_multibulk_len: usize,
+}
 
-    if length == -1 {
-        return Ok((RESPType::BulkString(None), len1 + 1));
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BULK_LEN, DEFAULT_MAX_MULTIBULK_LEN)
     }
+}
 
-    let start = len1 + 1;
-    let end = start + length as usize;
-    
-    if input.len() < end + 2 {
-        return Err("Incomplete bulk string".to_string());
+impl Parser {
+    pub fn new(max_bulk_len: usize, max_multibulk_len: usize) -> Self {
+        Self { max_bulk_len, max_multibulk_len }
+    }
+
+    // parses any resp value off the front of `buf`
+    pub fn parse(&self, buf: &mut BytesMut) -> Result<Parsed, ParseError> {
+        self.extract(buf, |parser, input, src| parser.walk_value(input, src, 0, 0))
+    }
+
+    // client requests are multibulk arrays of bulk strings or, like in redis, anything not
+    // starting with `*` is an inline command: space separated arguments ended by a newline
+    pub fn parse_request(&self, buf: &mut BytesMut) -> Result<Parsed, ParseError> {
+        match buf.first() {
+            None => Ok(Parsed::Incomplete),
+            Some(b'*') => self.extract(buf, |parser, input, src| parser.walk_multibulk(input, src)),
+            Some(_) => parse_inline(buf),
+        }
+    }
+
+    fn extract<F>(&self, buf: &mut BytesMut, walk: F) -> Result<Parsed, ParseError>
+    where
+        F: Fn(&Self, &[u8], Option<&Bytes>) -> Result<Step, ParseError>,
+    {
+        let len = match walk(self, &buf[..], None)? {
+            Step::Incomplete => return Ok(Parsed::Incomplete),
+            Step::Done(len, _) => len,
+        };
+
+        let frame = buf.split_to(len).freeze();
+        match walk(self, &frame, Some(&frame))? {
+            Step::Done(_, Some(value)) => Ok(Parsed::Frame(value)),
+            _ => Err("inconsistent frame".into()),
+        }
     }
 
-    let data = input[start..end].to_vec();
-    if &input[end..end + 2] != CRLF {
-        return Err("Missing CRLF".to_string());
+    fn walk_value(&self, input: &[u8], src: Option<&Bytes>, pos: usize, depth: usize) -> Result<Step, ParseError> {
+        let prefix = match input.get(pos) {
+            Some(&prefix) => prefix,
+            None => return Ok(Step::Incomplete),
+        };
+        if depth > MAX_NESTING {
+            return Err("too deep nesting".into());
+        }
+        let (line, next) = match read_line(input, pos + 1)? {
+            Some(found) => found,
+            None => return Ok(Step::Incomplete),
+        };
+        let build = src.is_some();
+
+        let value = match prefix {
+            b'+' => build.then(|| Ok::<_, ParseError>(RESPType::SimpleString(utf8(line)?.to_string()))).transpose()?,
+            b'-' => build.then(|| Ok::<_, ParseError>(RESPType::Error(utf8(line)?.to_string()))).transpose()?,
+            b':' => {
+                let num = parse_int(line).ok_or("invalid integer")?;
+                build.then_some(RESPType::Integer(num))
+            }
+            b'_' => {
+                if !line.is_empty() {
+                    return Err("invalid null".into());
+                }
+                build.then_some(RESPType::Null)
+            }
+            b'#' => {
+                let value = match line {
+                    b"t" => true,
+                    b"f" => false,
+                    _ => return Err(format!("invalid boolean: {}", String::from_utf8_lossy(line)).into()),
+                };
+                build.then_some(RESPType::Boolean(value))
+            }
+            b',' => {
+                let value = match line {
+                    b"inf" => f64::INFINITY,
+                    b"-inf" => f64::NEG_INFINITY,
+                    _ => utf8(line)?.parse::<f64>().map_err(|_| "invalid double")?,
+                };
+                build.then_some(RESPType::Double(value))
+            }
+            b'(' => {
+                let digits = line.strip_prefix(b"-").unwrap_or(line);
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Err(format!("invalid big number: {}", String::from_utf8_lossy(line)).into());
+                }
+                build.then(|| RESPType::BigNumber(String::from_utf8_lossy(line).into_owned()))
+            }
+            b'$' | b'=' => {
+                let len = match self.bulk_len(line)? {
+                    Some(len) => len,
+                    None if prefix == b'$' => return Ok(Step::Done(next, build.then_some(RESPType::BulkString(None)))),
+                    None => return Err("invalid bulk length".into()),
+                };
+                let end = match payload_end(input, next, len)? {
+                    Some(end) => end,
+                    None => return Ok(Step::Incomplete),
+                };
+                if prefix == b'=' && (len < 4 || input[next + 3] != b':') {
+                    return Err("invalid verbatim string".into());
+                }
+
+                let value = src.map(|src| match prefix {
+                    b'$' => Ok(RESPType::BulkString(Some(src.slice(next..next + len)))),
+                    _ => Ok::<_, ParseError>(RESPType::Verbatim(
+                        utf8(&src[next..next + 3])?.to_string(),
+                        src.slice(next + 4..next + len),
+                    )),
+                });
+                return Ok(Step::Done(end, value.transpose()?));
+            }
+            b'*' | b'~' | b'>' => {
+                let count = match self.aggregate_len(line)? {
+                    Some(count) => count,
+                    // null array, kept as an empty one
+                    None => return Ok(Step::Done(next, build.then(|| RESPType::Array(vec![])))),
+                };
+
+                let mut items = Vec::with_capacity(if build { count.min(MAX_PREALLOC) } else { 0 });
+                let mut pos = next;
+                for _ in 0..count {
+                    match self.walk_value(input, src, pos, depth + 1)? {
+                        Step::Incomplete => return Ok(Step::Incomplete),
+                        Step::Done(end, item) => {
+                            pos = end;
+                            items.extend(item);
+                        }
+                    }
+                }
+
+                let value = build.then(|| match prefix {
+                    b'*' => RESPType::Array(items),
+                    b'~' => RESPType::Set(items),
+                    _ => RESPType::Push(items),
+                });
+                return Ok(Step::Done(pos, value));
+            }
+            b'%' | b'|' => {
+                let count = self.aggregate_len(line)?.ok_or("invalid map length")?;
+
+                let mut pairs = Vec::with_capacity(if build { count.min(MAX_PREALLOC) } else { 0 });
+                let mut pos = next;
+                for _ in 0..count {
+                    let key = match self.walk_value(input, src, pos, depth + 1)? {
+                        Step::Incomplete => return Ok(Step::Incomplete),
+                        Step::Done(end, key) => {
+                            pos = end;
+                            key
+                        }
+                    };
+                    match self.walk_value(input, src, pos, depth + 1)? {
+                        Step::Incomplete => return Ok(Step::Incomplete),
+                        Step::Done(end, value) => {
+                            pos = end;
+                            pairs.extend(key.zip(value));
+                        }
+                    }
+                }
+
+                if prefix == b'%' {
+                    return Ok(Step::Done(pos, build.then_some(RESPType::Map(pairs))));
+                }
+
+                // an attribute map is always followed by the value it annotates
+                return match self.walk_value(input, src, pos, depth + 1)? {
+                    Step::Incomplete => Ok(Step::Incomplete),
+                    Step::Done(end, value) => Ok(Step::Done(
+                        end,
+                        value.map(|value| RESPType::Attribute(pairs, Box::new(value))),
+                    )),
+                };
+            }
+            _ => return Err(format!("Invalid RESP prefix: {}", prefix).into()),
+        };
+
+        Ok(Step::Done(next, value))
     }
 
-    Ok((RESPType::BulkString(Some(data)), end + 2))
+    // `*<count>\r\n` followed by exactly `count` bulk strings, as redis expects from clients
+    fn walk_multibulk(&self, input: &[u8], src: Option<&Bytes>) -> Result<Step, ParseError> {
+        let (line, mut pos) = match read_line(input, 1)? {
+            Some(found) => found,
+            None => return Ok(Step::Incomplete),
+        };
+        let count = match parse_int(line) {
+            Some(count) if count <= 0 => 0,
+            Some(count) if count as u64 <= self.max_multibulk_len as u64 => count as usize,
+            _ => return Err("invalid multibulk length".into()),
+        };
+
+        let mut parts = Vec::with_capacity(if src.is_some() { count.min(MAX_PREALLOC) } else { 0 });
+        for _ in 0..count {
+            match input.get(pos) {
+                None => return Ok(Step::Incomplete),
+                Some(b'$') => {}
+                Some(&other) => return Err(format!("expected '$', got '{}'", other as char).into()),
+            }
+            let (line, next) = match read_line(input, pos + 1)? {
+                Some(found) => found,
+                None => return Ok(Step::Incomplete),
+            };
+            let len = self.bulk_len(line)?.ok_or("invalid bulk length")?;
+            pos = match payload_end(input, next, len)? {
+                Some(end) => end,
+                None => return Ok(Step::Incomplete),
+            };
+            if let Some(src) = src {
+                parts.push(RESPType::BulkString(Some(src.slice(next..next + len))));
+            }
+        }
+
+        Ok(Step::Done(pos, src.map(|_| RESPType::Array(parts))))
+    }
+
+    // None for the `-1` null length
+    fn bulk_len(&self, line: &[u8]) -> Result<Option<usize>, ParseError> {
+        match parse_int(line) {
+            Some(-1) => Ok(None),
+            Some(len) if len >= 0 && len as u64 <= self.max_bulk_len as u64 => Ok(Some(len as usize)),
+            _ => Err("invalid bulk length".into()),
+        }
+    }
+
+    // None for the `-1` null length
+    fn aggregate_len(&self, line: &[u8]) -> Result<Option<usize>, ParseError> {
+        match parse_int(line) {
+            Some(-1) => Ok(None),
+            Some(len) if len >= 0 && len as u64 <= self.max_multibulk_len as u64 => Ok(Some(len as usize)),
+            _ => Err("invalid multibulk length".into()),
+        }
+    }
 }
 
-fn parse_error(input: &[u8]) -> Result<(RESPType, usize), String> {
-    let (s, len) = read_until_crlf(&input[1..])?;
-    Ok((RESPType::Error(s), len + 1))
+// an empty line yields an empty array, which callers skip like redis does
+fn parse_inline(buf: &mut BytesMut) -> Result<Parsed, ParseError> {
+    let newline = match buf.iter().position(|&b| b == b'\n') {
+        Some(pos) => pos,
+        None if buf.len() > INLINE_MAX_SIZE => return Err("too big inline request".into()),
+        None => return Ok(Parsed::Incomplete),
+    };
+
+    let line = buf.split_to(newline + 1);
+    let line = &line[..newline];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let args = split_args(line).ok_or("unbalanced quotes in request")?;
+
+    let parts = args
+        .into_iter()
+        .map(|arg| RESPType::BulkString(Some(Bytes::from(arg))))
+        .collect();
+    Ok(Parsed::Frame(RESPType::Array(parts)))
 }
 
-fn parse_array(input: &[u8]) -> Result<(RESPType, usize), String> {
-    let (length_str, mut pos) = read_until_crlf(&input[1..])?;
-    let length = length_str.parse::<i64>().map_err(|e| e.to_string())?;
-   
-    pos += 1;
+// port of redis' sdssplitargs: "double quotes" understand \n \r \t \b \a \\ \" and \xHH,
+// 'single quotes' only \', and a closing quote must be followed by a space or the end
+fn split_args(line: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut pos = 0;
 
-    if length == -1 {
-        return Ok((RESPType::Array(vec![]), pos));
-    }
+    loop {
+        while pos < line.len() && line[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == line.len() {
+            return Some(args);
+        }
 
-    let mut items = Vec::with_capacity(length as usize);
-    
-    for _ in 0..length {
-        let (item, len) = parse_resp(&input[pos..])?;
-        items.push(item);
-        pos += len;
+        let mut current = Vec::new();
+        let mut in_double = false;
+        let mut in_single = false;
+
+        loop {
+            let c = line.get(pos).copied();
+            if in_double {
+                match c {
+                    None => return None,
+                    Some(b'\\') if pos + 3 < line.len()
+                        && line[pos + 1] == b'x'
+                        && line[pos + 2].is_ascii_hexdigit()
+                        && line[pos + 3].is_ascii_hexdigit() =>
+                    {
+                        let hex = str::from_utf8(&line[pos + 2..pos + 4]).ok()?;
+                        current.push(u8::from_str_radix(hex, 16).ok()?);
+                        pos += 3;
+                    }
+                    Some(b'\\') if pos + 1 < line.len() => {
+                        pos += 1;
+                        current.push(match line[pos] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    Some(b'"') => {
+                        if pos + 1 < line.len() && !line[pos + 1].is_ascii_whitespace() {
+                            return None;
+                        }
+                        pos += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else if in_single {
+                match c {
+                    None => return None,
+                    Some(b'\\') if line.get(pos + 1) == Some(&b'\'') => {
+                        pos += 1;
+                        current.push(b'\'');
+                    }
+                    Some(b'\'') => {
+                        if pos + 1 < line.len() && !line[pos + 1].is_ascii_whitespace() {
+                            return None;
+                        }
+                        pos += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else {
+                match c {
+                    None => break,
+                    Some(b' ' | b'\n' | b'\r' | b'\t' | 0) => break,
+                    Some(b'"') => in_double = true,
+                    Some(b'\'') => in_single = true,
+                    Some(other) => current.push(other),
+                }
+            }
+            pos += 1;
+        }
+
+        args.push(current);
     }
+}
 
-    // total length is start_pos + length of all items
-    Ok((RESPType::Array(items), pos))
+// returns the line starting at `start` and the position right after its CRLF
+fn read_line(input: &[u8], start: usize) -> Result<Option<(&[u8], usize)>, ParseError> {
+    let rest = input.get(start..).unwrap_or_default();
+    match rest.windows(CRLF.len()).position(|window| window == CRLF) {
+        Some(len) => Ok(Some((&rest[..len], start + len + CRLF.len()))),
+        None if rest.len() > INLINE_MAX_SIZE => Err("too big count string".into()),
+        None => Ok(None),
+    }
 }
 
-fn read_until_crlf(input: &[u8]) -> Result<(String, usize), String> {
-    if let Some(pos) = find_subsequence(input, CRLF) {
-        let s = str::from_utf8(&input[..pos])
-            .map_err(|e| e.to_string())?
-            .to_string();
-        Ok((s, pos + 2))
-    } else {
-        Err("CRLF not found".to_string())
+// end of a `len` byte payload starting at `start`, including its trailing CRLF
+fn payload_end(input: &[u8], start: usize, len: usize) -> Result<Option<usize>, ParseError> {
+    let end = start + len;
+    if input.len() < end + CRLF.len() {
+        return Ok(None);
     }
+    if &input[end..end + CRLF.len()] != CRLF {
+        return Err("Missing CRLF".into());
+    }
+    Ok(Some(end + CRLF.len()))
+}
+
+fn parse_int(line: &[u8]) -> Option<i64> {
+    str::from_utf8(line).ok()?.parse().ok()
 }
 
-fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    haystack.windows(needle.len())
-        .position(|window| window == needle)
+fn utf8(bytes: &[u8]) -> Result<&str, ParseError> {
+    str::from_utf8(bytes).map_err(|e| ParseError(e.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // parses a whole buffer, returning the frame and how many bytes it consumed
+    fn parse_resp(input: &[u8]) -> Result<(RESPType, usize), ParseError> {
+        let mut buf = BytesMut::from(input);
+        match Parser::default().parse(&mut buf)? {
+            Parsed::Frame(frame) => Ok((frame, input.len() - buf.len())),
+            Parsed::Incomplete => Err("incomplete".into()),
+        }
+    }
+
+    fn is_incomplete(input: &[u8]) -> bool {
+        let mut buf = BytesMut::from(input);
+        let incomplete = Parser::default().parse(&mut buf) == Ok(Parsed::Incomplete);
+        incomplete && buf.len() == input.len()
+    }
+
+    fn bulk(bytes: &'static [u8]) -> RESPType {
+        RESPType::BulkString(Some(Bytes::from_static(bytes)))
+    }
+
     #[test]
     fn test_simple_string() {
         let input = b"+OK\r\n";
@@ -129,7 +482,7 @@ mod tests {
     fn test_bulk_string() {
         let input = b"$5\r\nhello\r\n";
         let (result, len) = parse_resp(input).unwrap();
-        assert_eq!(result, RESPType::BulkString(Some(b"hello".to_vec())));
+        assert_eq!(result, bulk(b"hello"));
         assert_eq!(len, 11);
     }
 
@@ -148,8 +501,8 @@ mod tests {
         match result {
             RESPType::Array(items) => {
                 assert_eq!(items.len(), 2);
-                assert_eq!(items[0], RESPType::BulkString(Some(b"hello".to_vec())));
-                assert_eq!(items[1], RESPType::BulkString(Some(b"world".to_vec())));
+                assert_eq!(items[0], bulk(b"hello"));
+                assert_eq!(items[1], bulk(b"world"));
             }
             _ => panic!("Expected array"),
         }
@@ -164,6 +517,222 @@ mod tests {
         assert_eq!(len, 4);
     }
 
+    #[test]
+    fn test_incomplete_frames() {
+        assert!(is_incomplete(b""));
+        assert!(is_incomplete(b"+OK"));
+        assert!(is_incomplete(b"+OK\r"));
+        assert!(is_incomplete(b"$5\r\nhel"));
+        assert!(is_incomplete(b"$5\r\nhello"));
+        assert!(is_incomplete(b"*2\r\n$3\r\nGET\r\n"));
+        assert!(is_incomplete(b"%1\r\n+first\r\n"));
+        assert!(is_incomplete(b"|1\r\n+ttl\r\n:3\r\n"));
+    }
+
+    #[test]
+    fn test_pipelined_frames() {
+        let parser = Parser::default();
+        let mut buf = BytesMut::from(&b"*1\r\n$4\r\nPING\r\n*1\r\n$4\r\nPING\r\n*1\r\n$4"[..]);
+        let ping = RESPType::Array(vec![bulk(b"PING")]);
+
+        assert_eq!(parser.parse_request(&mut buf), Ok(Parsed::Frame(ping.clone())));
+        assert_eq!(parser.parse_request(&mut buf), Ok(Parsed::Frame(ping)));
+        assert_eq!(parser.parse_request(&mut buf), Ok(Parsed::Incomplete));
+        assert_eq!(&buf[..], b"*1\r\n$4");
+    }
+
+    #[test]
+    fn test_frame_split_across_reads() {
+        let parser = Parser::default();
+        let input = b"*2\r\n$3\r\nGET\r\n$10\r\n0123456789\r\n";
+        let mut buf = BytesMut::new();
+
+        for (i, byte) in input.iter().enumerate() {
+            buf.extend_from_slice(&[*byte]);
+            let parsed = parser.parse_request(&mut buf).unwrap();
+            if i + 1 < input.len() {
+                assert_eq!(parsed, Parsed::Incomplete);
+            } else {
+                assert_eq!(parsed, Parsed::Frame(RESPType::Array(vec![bulk(b"GET"), bulk(b"0123456789")])));
+            }
+        }
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_bulk_is_a_slice_of_the_read_buffer() {
+        let mut buf = BytesMut::from(&b"$5\r\nhello\r\n"[..]);
+        let start = buf.as_ptr() as usize;
+
+        match Parser::default().parse(&mut buf).unwrap() {
+            Parsed::Frame(RESPType::BulkString(Some(bytes))) => {
+                assert_eq!(bytes.as_ptr() as usize, start + 4);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_prefix() {
+        assert!(parse_resp(b"?foo\r\n").is_err());
+    }
+
+    #[test]
+    fn test_bulk_length_limit() {
+        let parser = Parser::new(8, 16);
+        let mut buf = BytesMut::from(&b"*1\r\n$9\r\n"[..]);
+        assert_eq!(parser.parse_request(&mut buf), Err(ParseError("invalid bulk length".to_string())));
+
+        let mut buf = BytesMut::from(&b"$-2\r\n"[..]);
+        assert_eq!(parser.parse(&mut buf), Err(ParseError("invalid bulk length".to_string())));
+
+        let mut buf = BytesMut::from(&b"*1\r\n$8\r\n"[..]);
+        assert_eq!(parser.parse_request(&mut buf), Ok(Parsed::Incomplete));
+    }
+
+    #[test]
+    fn test_multibulk_length_limit() {
+        // a hostile header must be rejected before anything is allocated for it
+        let mut buf = BytesMut::from(&b"*999999999\r\n"[..]);
+        assert_eq!(
+            Parser::default().parse_request(&mut buf),
+            Err(ParseError("invalid multibulk length".to_string()))
+        );
+
+        let mut buf = BytesMut::from(&b"*999999999\r\n"[..]);
+        assert_eq!(
+            Parser::default().parse(&mut buf),
+            Err(ParseError("invalid multibulk length".to_string()))
+        );
+
+        let parser = Parser::new(8, 2);
+        let mut buf = BytesMut::from(&b"*3\r\n"[..]);
+        assert!(parser.parse_request(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_request_requires_bulk_strings() {
+        let mut buf = BytesMut::from(&b"*1\r\n:1\r\n"[..]);
+        assert_eq!(
+            Parser::default().parse_request(&mut buf),
+            Err(ParseError("expected '$', got ':'".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unterminated_count_line() {
+        let mut input = b"*".to_vec();
+        input.extend(std::iter::repeat_n(b'1', INLINE_MAX_SIZE + 1));
+        let mut buf = BytesMut::from(&input[..]);
+        assert!(Parser::default().parse_request(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_resp3_scalars() {
+        assert_eq!(parse_resp(b"_\r\n").unwrap(), (RESPType::Null, 3));
+        assert_eq!(parse_resp(b"#t\r\n").unwrap(), (RESPType::Boolean(true), 4));
+        assert_eq!(parse_resp(b"#f\r\n").unwrap(), (RESPType::Boolean(false), 4));
+        assert_eq!(parse_resp(b",3.25\r\n").unwrap(), (RESPType::Double(3.25), 7));
+        assert_eq!(parse_resp(b",-inf\r\n").unwrap(), (RESPType::Double(f64::NEG_INFINITY), 7));
+        assert_eq!(
+            parse_resp(b"(-3492890328409238509324850943850943825024385\r\n").unwrap().0,
+            RESPType::BigNumber("-3492890328409238509324850943850943825024385".to_string())
+        );
+        assert_eq!(
+            parse_resp(b"=15\r\ntxt:Some string\r\n").unwrap(),
+            (RESPType::Verbatim("txt".to_string(), Bytes::from_static(b"Some string")), 22)
+        );
+        assert!(parse_resp(b"#x\r\n").is_err());
+        assert!(parse_resp(b"(12a\r\n").is_err());
+    }
+
+    #[test]
+    fn test_resp3_aggregates() {
+        let (map, len) = parse_resp(b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n").unwrap();
+        assert_eq!(map, RESPType::Map(vec![
+            (RESPType::SimpleString("first".to_string()), RESPType::Integer(1)),
+            (RESPType::SimpleString("second".to_string()), RESPType::Integer(2)),
+        ]));
+        assert_eq!(len, 29);
+
+        let (set, _) = parse_resp(b"~2\r\n:1\r\n:2\r\n").unwrap();
+        assert_eq!(set, RESPType::Set(vec![RESPType::Integer(1), RESPType::Integer(2)]));
+
+        let (push, _) = parse_resp(b">2\r\n+message\r\n+hello\r\n").unwrap();
+        assert_eq!(push, RESPType::Push(vec![
+            RESPType::SimpleString("message".to_string()),
+            RESPType::SimpleString("hello".to_string()),
+        ]));
+
+        let (attribute, _) = parse_resp(b"|1\r\n+ttl\r\n:3\r\n:7\r\n").unwrap();
+        assert_eq!(attribute, RESPType::Attribute(
+            vec![(RESPType::SimpleString("ttl".to_string()), RESPType::Integer(3))],
+            Box::new(RESPType::Integer(7)),
+        ));
+    }
+
+    fn inline(input: &[u8]) -> Vec<Vec<u8>> {
+        let mut buf = BytesMut::from(input);
+        match Parser::default().parse_request(&mut buf).unwrap() {
+            Parsed::Frame(RESPType::Array(parts)) => parts
+                .into_iter()
+                .map(|part| part.as_bytes().unwrap().to_vec())
+                .collect(),
+            other => panic!("expected array, got {:?}", other),
+        }
+    }
+
+    fn request_error(input: &[u8]) -> bool {
+        let mut buf = BytesMut::from(input);
+        Parser::default().parse_request(&mut buf).is_err()
+    }
+
+    #[test]
+    fn test_inline_command() {
+        let mut buf = BytesMut::from(&b"SET key value\r\n"[..]);
+        let result = Parser::default().parse_request(&mut buf).unwrap();
+        assert_eq!(result, Parsed::Frame(RESPType::Array(vec![bulk(b"SET"), bulk(b"key"), bulk(b"value")])));
+        assert!(buf.is_empty());
+
+        // netcat sends a bare newline
+        assert_eq!(inline(b"PING\n"), vec![b"PING".to_vec()]);
+        assert_eq!(inline(b"  GET   key  \n"), vec![b"GET".to_vec(), b"key".to_vec()]);
+        assert_eq!(inline(b"\r\n"), Vec::<Vec<u8>>::new());
+
+        let mut buf = BytesMut::from(&b"PING"[..]);
+        assert_eq!(Parser::default().parse_request(&mut buf), Ok(Parsed::Incomplete));
+    }
+
+    #[test]
+    fn test_inline_quoting() {
+        assert_eq!(
+            inline(b"SET \"hello world\" 'it\\'s'\n"),
+            vec![b"SET".to_vec(), b"hello world".to_vec(), b"it's".to_vec()]
+        );
+        assert_eq!(
+            inline(b"SET k \"a\\tb\\x41\\\"\\n\"\n"),
+            vec![b"SET".to_vec(), b"k".to_vec(), b"a\tbA\"\n".to_vec()]
+        );
+        assert_eq!(inline(b"ECHO \"\"\n"), vec![b"ECHO".to_vec(), b"".to_vec()]);
+        assert!(request_error(b"SET \"unterminated\n"));
+        assert!(request_error(b"SET \"a\"b\n"));
+        assert!(request_error(b"SET 'a\n"));
+    }
+
+    #[test]
+    fn test_inline_too_big() {
+        let input = vec![b'a'; INLINE_MAX_SIZE + 1];
+        assert!(request_error(&input));
+    }
+
+    #[test]
+    fn test_request_multibulk() {
+        let input = b"*1\r\n$4\r\nPING\r\n";
+        let mut buf = BytesMut::from(&input[..]);
+        let request = Parser::default().parse_request(&mut buf).unwrap();
+        assert_eq!(request, Parsed::Frame(parse_resp(input).unwrap().0));
+    }
+
     #[test]
     fn test_null_array() {
         let input = b"*-1\r\n";
@@ -171,4 +740,4 @@ mod tests {
         assert_eq!(result, RESPType::Array(vec![]));
         assert_eq!(len, 5);
     }
-}
\ No newline at end of file
+}
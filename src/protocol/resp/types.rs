@@ -1,12 +1,53 @@
 use std::fmt;
+use bytes::Bytes;
+use crate::protocol::resp::encoder::encode_resp;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum RESPType {
     SimpleString(String),
     Error(String),
     Integer(i64),
-    BulkString(Option<Vec<u8>>),
+    BulkString(Option<Bytes>),
     Array(Vec<RESPType>),
+    // resp2 `*-1`, only ever sent; the parser reads it back as an empty array
+    NullArray,
+    // resp3 only
+    Null,
+    Boolean(bool),
+    Double(f64),
+    BigNumber(String),
+    Map(Vec<(RESPType, RESPType)>),
+    Set(Vec<RESPType>),
+    // out-of-band metadata followed by the value it describes
+    Attribute(Vec<(RESPType, RESPType)>, Box<RESPType>),
+    Push(Vec<RESPType>),
+    // three byte format (`txt`, `mkd`) plus the payload
+    Verbatim(String, Bytes),
+}
+
+// protocol spoken on a connection, switched with HELLO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl ProtocolVersion {
+    pub fn from_number(version: i64) -> Option<Self> {
+        match version {
+            2 => Some(ProtocolVersion::Resp2),
+            3 => Some(ProtocolVersion::Resp3),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self) -> i64 {
+        match self {
+            ProtocolVersion::Resp2 => 2,
+            ProtocolVersion::Resp3 => 3,
+        }
+    }
 }
 
 impl RESPType {
@@ -26,64 +67,8 @@ impl RESPType {
         }
     }
 
-    pub fn parse(input: &[u8]) -> Result<Self, String> {
-        if input.is_empty() {
-            return Err("empty input".to_string());
-        }
-
-        match input[0] as char {
-            '*' => {
-                // parse array
-                let mut parts = Vec::new();
-                let mut pos = 0;
-                
-                // skip first line (*3\r\n)
-                while pos < input.len() && input[pos] != b'\n' { pos += 1; }
-                pos += 1;
-
-                // parse each bulk string
-                while pos < input.len() {
-                    if input[pos] as char != '$' {
-                        return Err("expected bulk string".to_string());
-                    }
-                    
-                    // skip length
-                    while pos < input.len() && input[pos] != b'\n' { pos += 1; }
-                    pos += 1;
-
-                    // read until \r\n
-                    let mut value = Vec::new();
-                    while pos < input.len() && input[pos] != b'\r' {
-                        value.push(input[pos]);
-                        pos += 1;
-                    }
-                    parts.push(RESPType::BulkString(Some(value)));
-                    pos += 2; // skip \r\n
-                }
-                
-                Ok(RESPType::Array(parts))
-            },
-            // ...
-            _ => Err("invalid resp format".to_string())
-        }
-    }
-
-    pub fn encode(&self) -> String {
-
-        match self {
-            RESPType::SimpleString(s) => format!("+{}\r\n", s),
-            RESPType::Error(e) => format!("-{}\r\n", e),
-            RESPType::Integer(i) => format!(":{}\r\n", i),
-            RESPType::BulkString(Some(bytes)) => format!("${}\r\n{}\r\n", bytes.len(), String::from_utf8_lossy(bytes)),
-            RESPType::BulkString(None) => "$-1\r\n".to_string(),
-            RESPType::Array(array) => {
-                let mut encoded = format!("*{}\r\n", array.len());
-                for item in array {
-                    encoded.push_str(&item.encode());
-                }
-                encoded
-            }
-        }
+    pub fn encode(&self) -> Vec<u8> {
+        encode_resp(self)
     }
 }
 
@@ -102,6 +87,34 @@ impl fmt::Display for RESPType {
                 }
                 Ok(())
             },
+            RESPType::NullArray => write!(f, "*-1"),
+            RESPType::Null => write!(f, "_"),
+            RESPType::Boolean(b) => write!(f, "#{}", if *b { 't' } else { 'f' }),
+            RESPType::Double(d) => write!(f, ",{}", d),
+            RESPType::BigNumber(n) => write!(f, "({}", n),
+            RESPType::Map(pairs) => {
+                write!(f, "%{}", pairs.len())?;
+                for (key, value) in pairs {
+                    write!(f, "\r\n{}\r\n{}", key, value)?;
+                }
+                Ok(())
+            },
+            RESPType::Set(items) | RESPType::Push(items) => {
+                let prefix = if matches!(self, RESPType::Set(_)) { '~' } else { '>' };
+                write!(f, "{}{}", prefix, items.len())?;
+                for item in items {
+                    write!(f, "\r\n{}", item)?;
+                }
+                Ok(())
+            },
+            RESPType::Attribute(pairs, value) => {
+                write!(f, "|{}", pairs.len())?;
+                for (key, attr) in pairs {
+                    write!(f, "\r\n{}\r\n{}", key, attr)?;
+                }
+                write!(f, "\r\n{}", value)
+            },
+            RESPType::Verbatim(format, data) => write!(f, "={}:{:?}", format, data),
         }
     }
 }
@@ -0,0 +1,133 @@
+use super::data_types::bitmap;
+use super::memory::MemoryStorage;
+use super::{BitFieldOp, BitOp, BitRange, Storage, StorageValue};
+use crate::utils::error::{RedisError, Result};
+
+// bit operations over string values
+pub trait BitmapStorage {
+    // the previous value of the bit
+    fn setbit(&mut self, key: &[u8], offset: u64, on: bool) -> Result<bool>;
+    fn getbit(&mut self, key: &[u8], offset: u64) -> Result<bool>;
+    fn bitcount(&mut self, key: &[u8], range: Option<BitRange>) -> Result<u64>;
+    fn bitpos(&mut self, key: &[u8], bit: bool, range: Option<BitRange>, end_given: bool) -> Result<i64>;
+    // the length of the stored result; an empty result deletes `destination`
+    fn bitop(&mut self, op: BitOp, destination: &[u8], keys: &[Vec<u8>]) -> Result<usize>;
+    // one reply per operation, None where an OVERFLOW FAIL refused a write
+    fn bitfield(&mut self, key: &[u8], ops: &[BitFieldOp]) -> Result<Vec<Option<i64>>>;
+}
+
+impl BitmapStorage for MemoryStorage {
+    fn setbit(&mut self, key: &[u8], offset: u64, on: bool) -> Result<bool> {
+        let len = (offset / 8 + 1) as usize;
+        self.grow_string(key, len, |bytes| bitmap::set_bit(bytes, offset, on))
+    }
+
+    fn getbit(&mut self, key: &[u8], offset: u64) -> Result<bool> {
+        Ok(self.redis_string(key)?.is_some_and(|bytes| bitmap::get_bit(bytes, offset)))
+    }
+
+    fn bitcount(&mut self, key: &[u8], range: Option<BitRange>) -> Result<u64> {
+        Ok(self.redis_string(key)?.map_or(0, |bytes| bitmap::count(bytes, range)))
+    }
+
+    fn bitpos(&mut self, key: &[u8], bit: bool, range: Option<BitRange>, end_given: bool) -> Result<i64> {
+        Ok(match self.redis_string(key)? {
+            Some(bytes) => bitmap::position(bytes, bit, range, end_given),
+            // a missing key is all zeros
+            None => if bit { -1 } else { 0 },
+        })
+    }
+
+    fn bitop(&mut self, op: BitOp, destination: &[u8], keys: &[Vec<u8>]) -> Result<usize> {
+        let result = {
+            let values = self.values(keys)?;
+            let inputs = values
+                .into_iter()
+                .map(|value| match value {
+                    Some(StorageValue::String(bytes)) => Ok(bytes.as_slice()),
+                    Some(_) => Err(RedisError::WrongType),
+                    None => Ok(&[][..]),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            bitmap::bitop(op, &inputs)
+        };
+
+        if result.is_empty() {
+            self.delete(destination)?;
+            return Ok(0);
+        }
+        let len = result.len();
+        Storage::set(self, destination.to_vec(), StorageValue::String(result), None)?;
+        Ok(len)
+    }
+
+    fn bitfield(&mut self, key: &[u8], ops: &[BitFieldOp]) -> Result<Vec<Option<i64>>> {
+        // like redis, the string is grown to cover every write before any of them runs
+        let len = ops.iter().map(BitFieldOp::write_len).max().unwrap_or(0);
+        if len == 0 {
+            let bytes = self.redis_string(key)?.unwrap_or_default();
+            return Ok(ops
+                .iter()
+                .map(|op| match *op {
+                    BitFieldOp::Get(ty, offset) => Some(bitmap::get_field(bytes, ty, offset)),
+                    // every write spans at least one byte
+                    _ => None,
+                })
+                .collect());
+        }
+        self.grow_string(key, len, |bytes| ops.iter().map(|&op| bitmap::bitfield(bytes, op)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{BitFieldType, Overflow};
+
+    #[test]
+    fn test_setbit_grows_and_accounts() {
+        let mut storage = MemoryStorage::new(1024);
+        assert!(!storage.setbit(b"b", 7, true).unwrap());
+        assert!(storage.setbit(b"b", 7, false).unwrap());
+        assert!(!storage.setbit(b"b", 100, true).unwrap());
+        let mut expected = vec![0u8; 13];
+        expected[12] = 0x08;
+        assert_eq!(storage.redis_string(b"b").unwrap(), Some(expected.as_slice()));
+        assert_eq!(storage.dataset_memory(), 13);
+        assert!(storage.getbit(b"b", 100).unwrap());
+        assert!(!storage.getbit(b"missing", 3).unwrap());
+
+        // growing past maxmemory leaves the value alone
+        assert!(matches!(storage.setbit(b"b", 8 * 2048, true), Err(RedisError::OutOfMemory)));
+        assert_eq!(storage.dataset_memory(), 13);
+    }
+
+    #[test]
+    fn test_bitop_and_types() {
+        let mut storage = MemoryStorage::new(1024);
+        Storage::set(&mut storage, b"a".to_vec(), StorageValue::String(b"\xf0\x0f".to_vec()), None).unwrap();
+        Storage::set(&mut storage, b"l".to_vec(), StorageValue::List(vec![].into()), None).unwrap();
+        let keys = vec![b"a".to_vec(), b"missing".to_vec()];
+        assert_eq!(storage.bitop(BitOp::Or, b"d", &keys).unwrap(), 2);
+        assert_eq!(storage.bitcount(b"d", None).unwrap(), 8);
+        assert_eq!(storage.bitop(BitOp::And, b"d", &[b"missing".to_vec()]).unwrap(), 0);
+        assert!(!storage.exists(b"d").unwrap());
+        assert!(matches!(storage.bitop(BitOp::Not, b"d", &[b"l".to_vec()]), Err(RedisError::WrongType)));
+        assert!(matches!(storage.getbit(b"l", 0), Err(RedisError::WrongType)));
+        assert_eq!(storage.bitpos(b"missing", false, None, false).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let mut storage = MemoryStorage::new(1024);
+        let u4 = BitFieldType { signed: false, bits: 4 };
+        let get = [BitFieldOp::Get(u4, 0)];
+        // reads never create the key
+        assert_eq!(storage.bitfield(b"f", &get).unwrap(), vec![Some(0)]);
+        assert!(!storage.exists(b"f").unwrap());
+
+        let ops = [BitFieldOp::IncrBy(u4, 12, 20, Overflow::Fail), BitFieldOp::Set(u4, 0, 3, Overflow::Wrap)];
+        assert_eq!(storage.bitfield(b"f", &ops).unwrap(), vec![None, Some(0)]);
+        assert_eq!(storage.redis_string(b"f").unwrap(), Some(&[0x30, 0x00][..]));
+    }
+}
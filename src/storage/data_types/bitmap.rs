@@ -0,0 +1,321 @@
+// bit level operations on string values; bit 0 is the most significant bit of the
+// first byte, as in redis
+
+// offsets are capped so a bitmap never needs more than 512MB, like redis' proto-max-bulk-len
+pub const MAX_BIT_OFFSET: u64 = (1 << 32) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+// a BITCOUNT / BITPOS range; negative indexes count from the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitRange {
+    pub start: i64,
+    pub end: i64,
+    pub unit: BitUnit,
+}
+
+impl BitRange {
+    // the inclusive bit positions this covers in a string of `len` bytes; None when empty
+    fn bits(&self, len: usize) -> Option<(u64, u64)> {
+        let total = match self.unit {
+            BitUnit::Byte => len as i64,
+            BitUnit::Bit => len as i64 * 8,
+        };
+        let resolve = |index: i64| if index < 0 { (index + total).max(0) } else { index };
+        let (start, end) = (resolve(self.start), resolve(self.end).min(total - 1));
+        if total == 0 || start > end {
+            return None;
+        }
+        Some(match self.unit {
+            BitUnit::Byte => (start as u64 * 8, end as u64 * 8 + 7),
+            BitUnit::Bit => (start as u64, end as u64),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+// how BITFIELD SET / INCRBY treat values that do not fit the field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    Wrap,
+    Sat,
+    Fail,
+}
+
+// i1 .. i64 and u1 .. u63
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl BitFieldType {
+    pub fn parse(text: &[u8]) -> Option<BitFieldType> {
+        let (signed, bits) = match text.split_first()? {
+            (b'i' | b'I', bits) => (true, bits),
+            (b'u' | b'U', bits) => (false, bits),
+            _ => return None,
+        };
+        let bits: u32 = std::str::from_utf8(bits).ok()?.parse().ok()?;
+        let max = if signed { 64 } else { 63 };
+        (1..=max).contains(&bits).then_some(BitFieldType { signed, bits })
+    }
+
+    fn min(&self) -> i128 {
+        if self.signed { -(1 << (self.bits - 1)) } else { 0 }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed { (1 << (self.bits - 1)) - 1 } else { (1 << self.bits) - 1 }
+    }
+
+    // fits `value` into the field the way `overflow` asks; None when FAIL refuses it
+    fn fit(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        let (min, max) = (self.min(), self.max());
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => Some((value - min).rem_euclid(1 << self.bits).wrapping_add(min) as i64),
+            Overflow::Sat => Some(value.clamp(min, max) as i64),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get(BitFieldType, u64),
+    Set(BitFieldType, u64, i64, Overflow),
+    IncrBy(BitFieldType, u64, i64, Overflow),
+}
+
+impl BitFieldOp {
+    // how many bytes a write needs the string to span; 0 for reads
+    pub fn write_len(&self) -> usize {
+        match *self {
+            BitFieldOp::Get(..) => 0,
+            BitFieldOp::Set(ty, offset, ..) | BitFieldOp::IncrBy(ty, offset, ..) => {
+                (offset + ty.bits as u64).div_ceil(8) as usize
+            }
+        }
+    }
+}
+
+pub fn get_bit(bytes: &[u8], offset: u64) -> bool {
+    let byte = bytes.get((offset / 8) as usize).copied().unwrap_or(0);
+    byte & (0x80 >> (offset % 8)) != 0
+}
+
+// the previous value; the string must already cover `offset`
+pub fn set_bit(bytes: &mut [u8], offset: u64, on: bool) -> bool {
+    let previous = get_bit(bytes, offset);
+    let mask = 0x80 >> (offset % 8);
+    let byte = &mut bytes[(offset / 8) as usize];
+    if on {
+        *byte |= mask;
+    } else {
+        *byte &= !mask;
+    }
+    previous
+}
+
+// the bytes holding bits start..=end, with the bits outside the range masked to `fill`
+fn masked(bytes: &[u8], start: u64, end: u64, fill: bool) -> impl Iterator<Item = (u64, u8)> + '_ {
+    let (first, last) = (start / 8, end / 8);
+    (first..=last).map(move |index| {
+        let mut byte = bytes[index as usize];
+        let mut keep = 0xffu8;
+        if index == first {
+            keep &= 0xff >> (start % 8);
+        }
+        if index == last {
+            keep &= 0xff << (7 - end % 8);
+        }
+        let outside = if fill { !keep } else { 0 };
+        byte = (byte & keep) | outside;
+        (index, byte)
+    })
+}
+
+pub fn count(bytes: &[u8], range: Option<BitRange>) -> u64 {
+    let (start, end) = match range {
+        Some(range) => match range.bits(bytes.len()) {
+            Some(bits) => bits,
+            None => return 0,
+        },
+        None if bytes.is_empty() => return 0,
+        None => (0, bytes.len() as u64 * 8 - 1),
+    };
+    masked(bytes, start, end, false).map(|(_, byte)| byte.count_ones() as u64).sum()
+}
+
+// BITPOS: the first `bit` in the range, or -1. a string is taken to be followed by zeros,
+// so a clear bit is found past the end unless the caller fixed where the range ends
+pub fn position(bytes: &[u8], bit: bool, range: Option<BitRange>, end_given: bool) -> i64 {
+    let (start, end) = match range {
+        Some(range) => match range.bits(bytes.len()) {
+            Some(bits) => bits,
+            None => return -1,
+        },
+        None if bytes.is_empty() => return if bit { -1 } else { 0 },
+        None => (0, bytes.len() as u64 * 8 - 1),
+    };
+
+    // bits outside the range are filled with the opposite of what we look for
+    let skip = if bit { 0x00 } else { 0xff };
+    for (index, byte) in masked(bytes, start, end, !bit) {
+        if byte != skip {
+            let found = if bit { byte.leading_zeros() } else { byte.leading_ones() };
+            return (index * 8 + found as u64) as i64;
+        }
+    }
+    if !bit && !end_given {
+        return end as i64 + 1;
+    }
+    -1
+}
+
+// BITOP; missing or shorter inputs count as zero bytes
+pub fn bitop(op: BitOp, inputs: &[&[u8]]) -> Vec<u8> {
+    let len = inputs.iter().map(|input| input.len()).max().unwrap_or(0);
+    (0..len)
+        .map(|index| {
+            let mut bytes = inputs.iter().map(|input| input.get(index).copied().unwrap_or(0));
+            let first = bytes.next().unwrap_or(0);
+            match op {
+                BitOp::And => bytes.fold(first, |acc, byte| acc & byte),
+                BitOp::Or => bytes.fold(first, |acc, byte| acc | byte),
+                BitOp::Xor => bytes.fold(first, |acc, byte| acc ^ byte),
+                BitOp::Not => !first,
+            }
+        })
+        .collect()
+}
+
+pub fn get_field(bytes: &[u8], ty: BitFieldType, offset: u64) -> i64 {
+    let mut value: u64 = 0;
+    for bit in offset..offset + ty.bits as u64 {
+        value = (value << 1) | get_bit(bytes, bit) as u64;
+    }
+    if ty.signed && ty.bits < 64 && value & (1 << (ty.bits - 1)) != 0 {
+        // sign extend
+        value |= u64::MAX << ty.bits;
+    }
+    value as i64
+}
+
+fn write_field(bytes: &mut [u8], ty: BitFieldType, offset: u64, value: i64) {
+    for i in 0..ty.bits as u64 {
+        let on = (value as u64 >> (ty.bits as u64 - 1 - i)) & 1 == 1;
+        set_bit(bytes, offset + i, on);
+    }
+}
+
+// runs one BITFIELD operation; writes need the string to span `write_len` bytes already.
+// GET gives the value, SET the old value and INCRBY the new one, None when FAIL refused
+pub fn bitfield(bytes: &mut [u8], op: BitFieldOp) -> Option<i64> {
+    match op {
+        BitFieldOp::Get(ty, offset) => Some(get_field(bytes, ty, offset)),
+        BitFieldOp::Set(ty, offset, value, overflow) => {
+            // like redis, unsigned fields see the value as its 64 bit pattern
+            let value = if ty.signed { value as i128 } else { value as u64 as i128 };
+            let value = ty.fit(value, overflow)?;
+            let old = get_field(bytes, ty, offset);
+            write_field(bytes, ty, offset, value);
+            Some(old)
+        }
+        BitFieldOp::IncrBy(ty, offset, increment, overflow) => {
+            let old = get_field(bytes, ty, offset);
+            let value = ty.fit(old as i128 + increment as i128, overflow)?;
+            write_field(bytes, ty, offset, value);
+            Some(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: i64, end: i64, unit: BitUnit) -> Option<BitRange> {
+        Some(BitRange { start, end, unit })
+    }
+
+    #[test]
+    fn test_bits() {
+        let mut bytes = vec![0u8; 2];
+        assert!(!set_bit(&mut bytes, 1, true));
+        assert!(set_bit(&mut bytes, 1, true));
+        set_bit(&mut bytes, 15, true);
+        assert_eq!(bytes, vec![0x40, 0x01]);
+        assert!(get_bit(&bytes, 15));
+        assert!(!get_bit(&bytes, 1000));
+    }
+
+    #[test]
+    fn test_count_and_position() {
+        let bytes = b"foobar";
+        assert_eq!(count(bytes, None), 26);
+        assert_eq!(count(bytes, range(1, 1, BitUnit::Byte)), 6);
+        assert_eq!(count(bytes, range(5, 30, BitUnit::Bit)), 17);
+        assert_eq!(count(bytes, range(-2, -1, BitUnit::Byte)), 7);
+        assert_eq!(count(bytes, range(3, 1, BitUnit::Byte)), 0);
+
+        let bytes = [0xff, 0xf0, 0x00];
+        assert_eq!(position(&bytes, false, None, false), 12);
+        assert_eq!(position(&bytes, true, range(2, -1, BitUnit::Byte), false), -1);
+        assert_eq!(position(&bytes, true, range(7, 15, BitUnit::Bit), true), 7);
+        assert_eq!(position(&[0xff], false, None, false), 8);
+        assert_eq!(position(&[0xff], false, range(0, -1, BitUnit::Byte), true), -1);
+        assert_eq!(position(&[], false, None, false), 0);
+        assert_eq!(position(&[], true, None, false), -1);
+    }
+
+    #[test]
+    fn test_bitop() {
+        let (a, b): (&[u8], &[u8]) = (&[0b1100, 0xff], &[0b1010]);
+        assert_eq!(bitop(BitOp::And, &[a, b]), vec![0b1000, 0]);
+        assert_eq!(bitop(BitOp::Or, &[a, b]), vec![0b1110, 0xff]);
+        assert_eq!(bitop(BitOp::Xor, &[a, b]), vec![0b0110, 0xff]);
+        assert_eq!(bitop(BitOp::Not, &[b]), vec![0xf5]);
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let u8_ = BitFieldType::parse(b"u8").unwrap();
+        let i5 = BitFieldType::parse(b"i5").unwrap();
+        assert!(BitFieldType::parse(b"u64").is_none());
+        assert!(BitFieldType::parse(b"i0").is_none());
+
+        let mut bytes = vec![0u8; 2];
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::Set(u8_, 4, 255, Overflow::Wrap)), Some(0));
+        assert_eq!(bytes, vec![0x0f, 0xf0]);
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(u8_, 4, 10, Overflow::Wrap)), Some(9));
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(u8_, 4, 300, Overflow::Sat)), Some(255));
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(u8_, 4, 1, Overflow::Fail)), None);
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::Set(u8_, 4, -1, Overflow::Sat)), Some(255));
+
+        let mut bytes = vec![0u8; 1];
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(i5, 0, 17, Overflow::Wrap)), Some(-15));
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::Get(i5, 0)), Some(-15));
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(i5, 0, -100, Overflow::Sat)), Some(-16));
+        let i64_ = BitFieldType::parse(b"i64").unwrap();
+        let mut bytes = vec![0u8; 8];
+        bitfield(&mut bytes, BitFieldOp::Set(i64_, 0, i64::MAX, Overflow::Wrap));
+        assert_eq!(bitfield(&mut bytes, BitFieldOp::IncrBy(i64_, 0, 1, Overflow::Wrap)), Some(i64::MIN));
+        assert_eq!(BitFieldOp::Set(u8_, 4, 0, Overflow::Wrap).write_len(), 2);
+    }
+}
@@ -0,0 +1,287 @@
+// 52-bit interleaved geohashes, bit compatible with the scores redis stores for geo members
+use std::f64::consts::PI;
+
+pub const STEP_MAX: u32 = 26;
+pub const LON_MIN: f64 = -180.0;
+pub const LON_MAX: f64 = 180.0;
+// web mercator cuts the poles off, so redis does too
+pub const LAT_MIN: f64 = -85.05112878;
+pub const LAT_MAX: f64 = 85.05112878;
+
+const EARTH_RADIUS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+// the area a search covers, in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    // width and height
+    Box(f64, f64),
+}
+
+// a geohash cell; `bits` holds `step` bits per axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    bits: u64,
+    step: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    lon: (f64, f64),
+    lat: (f64, f64),
+}
+
+// spreads the low 32 bits of `value` over the even bits of the result
+fn spread(value: u32) -> u64 {
+    let mut x = value as u64;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+fn squash(mut x: u64) -> u32 {
+    x &= 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    ((x | (x >> 16)) & 0x0000_0000_ffff_ffff) as u32
+}
+
+// latitude on the even bits, longitude on the odd ones, so the top bit splits longitudes
+fn interleave(lat: u32, lon: u32) -> u64 {
+    spread(lat) | (spread(lon) << 1)
+}
+
+fn deinterleave(bits: u64) -> (u32, u32) {
+    (squash(bits), squash(bits >> 1))
+}
+
+pub fn valid(lon: f64, lat: f64) -> bool {
+    (LON_MIN..=LON_MAX).contains(&lon) && (LAT_MIN..=LAT_MAX).contains(&lat)
+}
+
+fn encode_in(lon: f64, lat: f64, lat_range: (f64, f64), step: u32) -> Cell {
+    let cells = (1u64 << step) as f64;
+    let lat_offset = (lat - lat_range.0) / (lat_range.1 - lat_range.0) * cells;
+    let lon_offset = (lon - LON_MIN) / (LON_MAX - LON_MIN) * cells;
+    Cell { bits: interleave(lat_offset as u32, lon_offset as u32), step }
+}
+
+fn encode_at(lon: f64, lat: f64, step: u32) -> Cell {
+    encode_in(lon, lat, (LAT_MIN, LAT_MAX), step)
+}
+
+// the sorted set score of a point, which must be valid
+pub fn encode(lon: f64, lat: f64) -> f64 {
+    encode_at(lon, lat, STEP_MAX).bits as f64
+}
+
+fn area(cell: Cell) -> Area {
+    let (lat, lon) = deinterleave(cell.bits);
+    let cells = (1u64 << cell.step) as f64;
+    let lat_scale = (LAT_MAX - LAT_MIN) / cells;
+    let lon_scale = (LON_MAX - LON_MIN) / cells;
+    Area {
+        lon: (LON_MIN + lon as f64 * lon_scale, LON_MIN + (lon as f64 + 1.0) * lon_scale),
+        lat: (LAT_MIN + lat as f64 * lat_scale, LAT_MIN + (lat as f64 + 1.0) * lat_scale),
+    }
+}
+
+// the longitude and latitude at the centre of the cell a score names
+pub fn decode(score: f64) -> (f64, f64) {
+    let area = area(Cell { bits: score as u64, step: STEP_MAX });
+    let lon = ((area.lon.0 + area.lon.1) / 2.0).clamp(LON_MIN, LON_MAX);
+    let lat = ((area.lat.0 + area.lat.1) / 2.0).clamp(LAT_MIN, LAT_MAX);
+    (lon, lat)
+}
+
+// the standard 11 character geohash, which uses the full -90..90 latitude range
+pub fn to_string(score: f64) -> String {
+    let (lon, lat) = decode(score);
+    let bits = encode_in(lon, lat, (-90.0, 90.0), STEP_MAX).bits;
+    (0..11)
+        .map(|i| {
+            // 52 bits only fill ten characters; the last one is always '0'
+            let index = if i == 10 { 0 } else { (bits >> (52 - (i + 1) * 5)) & 0x1f };
+            BASE32[index as usize] as char
+        })
+        .collect()
+}
+
+fn to_radians(degrees: f64) -> f64 {
+    degrees * PI / 180.0
+}
+
+fn to_degrees(radians: f64) -> f64 {
+    radians * 180.0 / PI
+}
+
+// the haversine distance in meters
+pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (to_radians(lat1), to_radians(lat2));
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = (to_radians(lon2 - lon1) / 2.0).sin();
+    let a = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS * (to_radians(lat2) - to_radians(lat1)).abs()
+}
+
+// the distance from `center` to `point` when the point lies within `shape`
+pub fn within(shape: GeoShape, center: (f64, f64), point: (f64, f64)) -> Option<f64> {
+    let ((lon1, lat1), (lon2, lat2)) = (center, point);
+    match shape {
+        GeoShape::Radius(radius) => Some(distance(lon1, lat1, lon2, lat2)).filter(|&d| d <= radius),
+        GeoShape::Box(width, height) => {
+            if lat_distance(lat2, lat1) > height / 2.0 || distance(lon2, lat2, lon1, lat2) > width / 2.0 {
+                return None;
+            }
+            Some(distance(lon1, lat1, lon2, lat2))
+        }
+    }
+}
+
+// the finest step whose cells are still about as large as the search radius
+fn estimate_step(mut radius: f64, lat: f64) -> u32 {
+    if radius == 0.0 {
+        return STEP_MAX;
+    }
+    let mut step: i32 = 1;
+    while radius < MERCATOR_MAX {
+        radius *= 2.0;
+        step += 1;
+    }
+    // cells narrow towards the poles
+    step -= 2;
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, STEP_MAX as i32) as u32
+}
+
+// min lon, min lat, max lon, max lat
+fn bounding_box(shape: GeoShape, lon: f64, lat: f64) -> (f64, f64, f64, f64) {
+    let (width, height) = match shape {
+        GeoShape::Radius(radius) => (radius, radius),
+        GeoShape::Box(width, height) => (width / 2.0, height / 2.0),
+    };
+    let lat_delta = to_degrees(height / EARTH_RADIUS);
+    let lon_delta_top = to_degrees(width / EARTH_RADIUS / to_radians(lat + lat_delta).cos());
+    let lon_delta_bottom = to_degrees(width / EARTH_RADIUS / to_radians(lat - lat_delta).cos());
+    // the shape spans the most longitude on its side nearer the pole
+    let lon_delta = if lat < 0.0 { lon_delta_bottom } else { lon_delta_top };
+    (lon - lon_delta, lat - lat_delta, lon + lon_delta, lat + lat_delta)
+}
+
+fn neighbour(cell: Cell, d_lon: i64, d_lat: i64) -> Cell {
+    let (lat, lon) = deinterleave(cell.bits);
+    let mask = (1i64 << cell.step) - 1;
+    let lat = (lat as i64 + d_lat) & mask;
+    let lon = (lon as i64 + d_lon) & mask;
+    Cell { bits: interleave(lat as u32, lon as u32), step: cell.step }
+}
+
+// the cell around the centre and its eight neighbours, by longitude and latitude offset
+fn neighbourhood(cell: Cell) -> Vec<(i64, i64, Cell)> {
+    let mut cells = Vec::with_capacity(9);
+    for d_lat in -1..=1 {
+        for d_lon in -1..=1 {
+            cells.push((d_lon, d_lat, neighbour(cell, d_lon, d_lat)));
+        }
+    }
+    cells
+}
+
+// the score ranges, each [min, max), whose members may lie within `shape` around `center`;
+// like redis this is the centre's cell and its neighbours at a step wide enough to cover the shape
+pub fn search_ranges(shape: GeoShape, center: (f64, f64)) -> Vec<(f64, f64)> {
+    let (lon, lat) = center;
+    let (min_lon, min_lat, max_lon, max_lat) = bounding_box(shape, lon, lat);
+    let radius = match shape {
+        GeoShape::Radius(radius) => radius,
+        GeoShape::Box(width, height) => ((width / 2.0).powi(2) + (height / 2.0).powi(2)).sqrt(),
+    };
+
+    let mut step = estimate_step(radius, lat);
+    let mut cell = encode_at(lon, lat, step);
+    let north = area(neighbour(cell, 0, 1));
+    let south = area(neighbour(cell, 0, -1));
+    let east = area(neighbour(cell, 1, 0));
+    let west = area(neighbour(cell, -1, 0));
+    // the estimate can be a step too fine for the shape to fit within the neighbourhood
+    let too_small = north.lat.1 < max_lat || south.lat.0 > min_lat || east.lon.1 < max_lon || west.lon.0 > min_lon;
+    if step > 1 && too_small {
+        step -= 1;
+        cell = encode_at(lon, lat, step);
+    }
+
+    let centre = area(cell);
+    let mut ranges: Vec<(f64, f64)> = Vec::with_capacity(9);
+    for (d_lon, d_lat, neighbour) in neighbourhood(cell) {
+        // skip the neighbours on sides the shape does not reach past the centre's cell
+        if step >= 2 {
+            let useless = (d_lat == -1 && centre.lat.0 < min_lat)
+                || (d_lat == 1 && centre.lat.1 > max_lat)
+                || (d_lon == -1 && centre.lon.0 < min_lon)
+                || (d_lon == 1 && centre.lon.1 > max_lon);
+            if useless {
+                continue;
+            }
+        }
+        // at coarse steps several neighbours wrap around to the same cell
+        let shift = 52 - neighbour.step * 2;
+        let range = ((neighbour.bits << shift) as f64, ((neighbour.bits + 1) << shift) as f64);
+        if !ranges.contains(&range) {
+            ranges.push(range);
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_matches_redis() {
+        // GEOADD Sicily 13.361389 38.115556 "Palermo" 15.087269 37.502669 "Catania"
+        assert_eq!(encode(13.361389, 38.115556), 3479099956230698.0);
+        assert_eq!(encode(15.087269, 37.502669), 3479447370796909.0);
+        assert_eq!(to_string(3479099956230698.0), "sqc8b49rny0");
+        assert_eq!(to_string(3479447370796909.0), "sqdtr74hyu0");
+
+        let (lon, lat) = decode(3479099956230698.0);
+        assert!((lon - 13.361_389_338_970_184).abs() < 1e-12);
+        assert!((lat - 38.115_556_395_496_3).abs() < 1e-12);
+        let (lon2, lat2) = decode(3479447370796909.0);
+        assert!((distance(lon, lat, lon2, lat2) - 166274.1516).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_search_ranges_cover_the_shape() {
+        let center = (15.0, 37.0);
+        let shape = GeoShape::Radius(200_000.0);
+        let ranges = search_ranges(shape, center);
+        assert!(!ranges.is_empty() && ranges.len() <= 9);
+        // every point inside the shape falls in one of the ranges
+        for (lon, lat) in [(13.361389, 38.115556), (15.087269, 37.502669), (16.5, 36.2), (15.0, 38.7)] {
+            assert!(within(shape, center, (lon, lat)).is_some());
+            let score = encode(lon, lat);
+            assert!(ranges.iter().any(|&(min, max)| score >= min && score < max), "{} {}", lon, lat);
+        }
+
+        let shape = GeoShape::Box(400_000.0, 400_000.0);
+        assert!(within(shape, center, (13.361389, 38.115556)).is_some());
+        assert!(within(shape, center, (12.0, 37.0)).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use super::{scan_by_hash, DataType, ListpackLimits};
+
+// small hashes are a flat vector of pairs, searched linearly like a redis listpack; past
+// the configured limits they become a real hash table and never go back
+#[derive(Debug, Clone)]
+enum Entries {
+    Listpack(Vec<(Vec<u8>, Vec<u8>)>),
+    Table(HashMap<Vec<u8>, Vec<u8>>),
+}
+
+// a field and its value, borrowed from the hash
+pub type FieldRef<'a> = (&'a [u8], &'a [u8]);
+
+#[derive(Debug, Clone)]
+pub struct RedisHash {
+    entries: Entries,
+    // bytes held by fields and values, so memory_usage stays O(1)
+    bytes: usize,
+}
+
+impl Default for RedisHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RedisHash {
+    pub fn new() -> Self {
+        Self {
+            entries: Entries::Listpack(Vec::new()),
+            bytes: 0,
+        }
+    }
+
+    // the name OBJECT ENCODING reports
+    pub fn encoding(&self) -> &'static str {
+        match self.entries {
+            Entries::Listpack(_) => "listpack",
+            Entries::Table(_) => "hashtable",
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.entries {
+            Entries::Listpack(pairs) => pairs.len(),
+            Entries::Table(table) => table.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&[u8]> {
+        match &self.entries {
+            Entries::Listpack(pairs) => pairs.iter().find(|(f, _)| f == field).map(|(_, v)| v.as_slice()),
+            Entries::Table(table) => table.get(field).map(Vec::as_slice),
+        }
+    }
+
+    pub fn contains(&self, field: &[u8]) -> bool {
+        self.get(field).is_some()
+    }
+
+    // sets `field`, converting to a hash table first when the listpack would outgrow
+    // `limits`; returns true when the field is new
+    pub fn insert(&mut self, field: Vec<u8>, value: Vec<u8>, limits: ListpackLimits) -> bool {
+        if let Entries::Listpack(pairs) = &self.entries {
+            let len = pairs.len() + usize::from(!self.contains(&field));
+            if !limits.fits(len, &field) || !limits.fits(len, &value) {
+                self.convert();
+            }
+        }
+
+        let (field_len, value_len) = (field.len(), value.len());
+        let replaced = match &mut self.entries {
+            Entries::Listpack(pairs) => match pairs.iter_mut().find(|(f, _)| *f == field) {
+                Some((_, old)) => Some(std::mem::replace(old, value)),
+                None => {
+                    pairs.push((field, value));
+                    None
+                }
+            },
+            Entries::Table(table) => table.insert(field, value),
+        };
+
+        match replaced {
+            Some(old) => {
+                self.bytes = self.bytes - old.len() + value_len;
+                false
+            }
+            None => {
+                self.bytes += field_len + value_len;
+                true
+            }
+        }
+    }
+
+    fn convert(&mut self) {
+        if let Entries::Listpack(pairs) = &mut self.entries {
+            self.entries = Entries::Table(std::mem::take(pairs).into_iter().collect());
+        }
+    }
+
+    pub fn remove(&mut self, field: &[u8]) -> bool {
+        let removed = match &mut self.entries {
+            Entries::Listpack(pairs) => pairs
+                .iter()
+                .position(|(f, _)| f == field)
+                .map(|i| pairs.swap_remove(i)),
+            Entries::Table(table) => table.remove_entry(field),
+        };
+
+        match removed {
+            Some((field, value)) => {
+                self.bytes -= field.len() + value.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = FieldRef<'_>> + '_> {
+        match &self.entries {
+            Entries::Listpack(pairs) => Box::new(pairs.iter().map(|(f, v)| (f.as_slice(), v.as_slice()))),
+            Entries::Table(table) => Box::new(table.iter().map(|(f, v)| (f.as_slice(), v.as_slice()))),
+        }
+    }
+
+    // one HSCAN step; like redis a listpack is returned whole in a single call
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<FieldRef<'_>>) {
+        match &self.entries {
+            Entries::Listpack(_) => (0, self.iter().collect()),
+            Entries::Table(_) => scan_by_hash(self.iter().map(|pair| (pair.0, pair)), cursor, count),
+        }
+    }
+}
+
+impl DataType for RedisHash {
+    fn type_name(&self) -> &str {
+        "hash"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ListpackLimits = ListpackLimits::new(2, 8);
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut hash = RedisHash::new();
+        assert!(hash.insert(b"f".to_vec(), b"one".to_vec(), LIMITS));
+        assert!(!hash.insert(b"f".to_vec(), b"three".to_vec(), LIMITS));
+        assert_eq!(hash.get(b"f"), Some(&b"three"[..]));
+        assert_eq!(hash.memory_usage(), 6);
+
+        assert!(hash.remove(b"f"));
+        assert!(!hash.remove(b"f"));
+        assert!(hash.is_empty());
+        assert_eq!(hash.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_converts_past_limits() {
+        let mut hash = RedisHash::new();
+        hash.insert(b"a".to_vec(), b"1".to_vec(), LIMITS);
+        hash.insert(b"b".to_vec(), b"2".to_vec(), LIMITS);
+        // overwriting does not grow the hash
+        hash.insert(b"b".to_vec(), b"3".to_vec(), LIMITS);
+        assert_eq!(hash.encoding(), "listpack");
+
+        hash.insert(b"c".to_vec(), b"4".to_vec(), LIMITS);
+        assert_eq!(hash.encoding(), "hashtable");
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash.get(b"b"), Some(&b"3"[..]));
+
+        let mut long = RedisHash::new();
+        long.insert(b"a".to_vec(), b"more than eight".to_vec(), LIMITS);
+        assert_eq!(long.encoding(), "hashtable");
+    }
+}
@@ -0,0 +1,513 @@
+// HyperLogLog in the redis string format, so values round-trip through GET / SET:
+// a 16 byte header ("HYLL", the encoding, 3 unused bytes, then a cached cardinality
+// in little endian with the top bit of its last byte marking it stale), followed by
+// either 16384 packed 6 bit registers (dense) or a run-length opcode stream (sparse)
+
+const MAGIC: &[u8; 4] = b"HYLL";
+const HEADER_LEN: usize = 16;
+const P: u32 = 14;
+const Q: u32 = 64 - P;
+pub const REGISTERS: usize = 1 << P;
+const BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << BITS) - 1;
+const DENSE_LEN: usize = HEADER_LEN + (REGISTERS * BITS).div_ceil(8);
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const SEED: u64 = 0xadc8_3b19;
+
+// sparse opcodes: ZERO 00xxxxxx (1..64 zero registers), XZERO 01xxxxxx yyyyyyyy
+// (1..16384 zero registers) and VAL 1vvvvvxx (1..4 registers set to 1..32)
+const ZERO_MAX_LEN: usize = 64;
+const XZERO_MAX_LEN: usize = REGISTERS;
+const VAL_MAX_LEN: usize = 4;
+const VAL_MAX_VALUE: u8 = 32;
+
+// hll-sparse-max-bytes
+pub const DEFAULT_SPARSE_MAX_BYTES: usize = 3000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HllEncoding {
+    Dense,
+    Sparse,
+}
+
+impl HllEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HllEncoding::Dense => "dense",
+            HllEncoding::Sparse => "sparse",
+        }
+    }
+}
+
+pub type Registers = [u8; REGISTERS];
+
+// an empty sparse HyperLogLog
+pub fn new() -> Vec<u8> {
+    encode_sparse(&[0; REGISTERS]).expect("empty registers fit the sparse encoding")
+}
+
+// the encoding named by the header, or None for any other string; like redis, a sparse
+// body is only checked as it is decoded
+pub fn encoding(bytes: &[u8]) -> Option<HllEncoding> {
+    if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+        return None;
+    }
+    match bytes[4] {
+        0 if bytes.len() == DENSE_LEN => Some(HllEncoding::Dense),
+        1 => Some(HllEncoding::Sparse),
+        _ => None,
+    }
+}
+
+// murmurhash64a, as redis hashes elements
+fn murmur64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("chunk of 8"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &byte) in tail.iter().enumerate() {
+            h ^= (byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+// the register an element lands in and the length of its run of zeros plus one
+fn register_for(element: &[u8]) -> (usize, u8) {
+    let hash = murmur64a(element, SEED);
+    let index = (hash & (REGISTERS as u64 - 1)) as usize;
+    // the extra bit caps the count at Q + 1
+    let rest = (hash >> P) | (1 << Q);
+    (index, rest.trailing_zeros() as u8 + 1)
+}
+
+fn dense_get(registers: &[u8], index: usize) -> u8 {
+    let bit = index * BITS;
+    let (byte, shift) = (bit / 8, bit % 8);
+    let low = registers[byte] as u16;
+    let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((low | (high << 8)) >> shift) as u8) & REGISTER_MAX
+}
+
+fn dense_set(registers: &mut [u8], index: usize, value: u8) {
+    let bit = index * BITS;
+    let (byte, shift) = (bit / 8, bit % 8);
+    let mask = (REGISTER_MAX as u16) << shift;
+    let value = (value as u16) << shift;
+    registers[byte] = (registers[byte] & !(mask as u8)) | value as u8;
+    if let Some(next) = registers.get_mut(byte + 1) {
+        *next = (*next & !((mask >> 8) as u8)) | (value >> 8) as u8;
+    }
+}
+
+// the opcode at the start of `body`: its value, the registers it covers and its size
+fn sparse_op(body: &[u8]) -> Option<(u8, usize, usize)> {
+    let op = *body.first()?;
+    Some(match op >> 6 {
+        0b00 => (0, (op & 0x3f) as usize + 1, 1),
+        0b01 => (0, ((((op & 0x3f) as usize) << 8) | *body.get(1)? as usize) + 1, 2),
+        _ => (((op >> 2) & 0x1f) + 1, (op & 0x03) as usize + 1, 1),
+    })
+}
+
+// the (value, run length) pairs of a sparse body, None when it is malformed
+fn decode_sparse(body: &[u8]) -> Option<Vec<(u8, usize)>> {
+    let (mut runs, mut covered, mut offset) = (Vec::new(), 0, 0);
+    while offset < body.len() {
+        let (value, len, size) = sparse_op(&body[offset..])?;
+        runs.push((value, len));
+        covered += len;
+        offset += size;
+    }
+    (covered == REGISTERS).then_some(runs)
+}
+
+// appends the opcodes for `len` registers set to `value`; None when the value is too
+// large for the sparse encoding
+fn push_run(bytes: &mut Vec<u8>, value: u8, len: usize) -> Option<()> {
+    let mut left = len;
+    while left > 0 {
+        if value == 0 && left > ZERO_MAX_LEN {
+            let len = left.min(XZERO_MAX_LEN);
+            bytes.push(0x40 | ((len - 1) >> 8) as u8);
+            bytes.push(((len - 1) & 0xff) as u8);
+            left -= len;
+        } else if value == 0 {
+            bytes.push((left - 1) as u8);
+            left = 0;
+        } else if value <= VAL_MAX_VALUE {
+            let len = left.min(VAL_MAX_LEN);
+            bytes.push(0x80 | ((value - 1) << 2) | (len - 1) as u8);
+            left -= len;
+        } else {
+            return None;
+        }
+    }
+    Some(())
+}
+
+// None when some register is too large for the sparse encoding
+fn encode_sparse(registers: &Registers) -> Option<Vec<u8>> {
+    let mut bytes = header(HllEncoding::Sparse);
+    let mut index = 0;
+    while index < REGISTERS {
+        let value = registers[index];
+        let run = registers[index..].iter().take_while(|&&other| other == value).count();
+        push_run(&mut bytes, value, run)?;
+        index += run;
+    }
+    Some(bytes)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SparseSet {
+    Unchanged,
+    Updated,
+    // the new value needs the dense encoding
+    TooLarge,
+}
+
+// raises register `index` to `count` in a sparse value by rewriting only the opcode that
+// covers it, merged with its neighbours where they hold the same value, the way redis'
+// hllSparseSet does; None when the body is corrupt
+fn sparse_set(bytes: &mut Vec<u8>, index: usize, count: u8) -> Option<SparseSet> {
+    let (mut offset, mut first) = (HEADER_LEN, 0);
+    let mut previous = None;
+    loop {
+        let (value, len, size) = sparse_op(&bytes[offset..])?;
+        if index >= first + len {
+            previous = Some((offset, value, len));
+            first += len;
+            offset += size;
+            continue;
+        }
+        if value >= count {
+            return Some(SparseSet::Unchanged);
+        }
+        if count > VAL_MAX_VALUE {
+            return Some(SparseSet::TooLarge);
+        }
+
+        let (mut start, mut end) = (offset, offset + size);
+        let mut runs = Vec::with_capacity(5);
+        if let Some((previous_offset, previous_value, previous_len)) = previous {
+            start = previous_offset;
+            runs.push((previous_value, previous_len));
+        }
+        runs.extend([(value, index - first), (count, 1), (value, first + len - index - 1)]);
+        if let Some((next_value, next_len, next_size)) = sparse_op(&bytes[end..]) {
+            runs.push((next_value, next_len));
+            end += next_size;
+        }
+
+        let mut merged: Vec<(u8, usize)> = Vec::with_capacity(runs.len());
+        for (value, len) in runs.into_iter().filter(|&(_, len)| len > 0) {
+            match merged.last_mut() {
+                Some(last) if last.0 == value => last.1 += len,
+                _ => merged.push((value, len)),
+            }
+        }
+        let mut ops = Vec::with_capacity(8);
+        for (value, len) in merged {
+            push_run(&mut ops, value, len)?;
+        }
+        bytes.splice(start..end, ops);
+        return Some(SparseSet::Updated);
+    }
+}
+
+fn header(encoding: HllEncoding) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(DENSE_LEN);
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(match encoding {
+        HllEncoding::Dense => 0,
+        HllEncoding::Sparse => 1,
+    });
+    bytes.extend_from_slice(&[0; 3]);
+    // an empty cardinality cache, marked stale
+    bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x80]);
+    bytes
+}
+
+fn encode_dense(registers: &Registers) -> Vec<u8> {
+    let mut bytes = header(HllEncoding::Dense);
+    bytes.resize(DENSE_LEN, 0);
+    for (index, &value) in registers.iter().enumerate() {
+        dense_set(&mut bytes[HEADER_LEN..], index, value);
+    }
+    bytes
+}
+
+// every register of a HyperLogLog; None for any other string or a corrupt sparse body
+pub fn registers(bytes: &[u8]) -> Option<Registers> {
+    let mut registers = [0; REGISTERS];
+    match encoding(bytes)? {
+        HllEncoding::Dense => {
+            for (index, register) in registers.iter_mut().enumerate() {
+                *register = dense_get(&bytes[HEADER_LEN..], index);
+            }
+        }
+        HllEncoding::Sparse => {
+            let mut index = 0;
+            for (value, len) in decode_sparse(&bytes[HEADER_LEN..])? {
+                registers[index..index + len].fill(value);
+                index += len;
+            }
+        }
+    }
+    Some(registers)
+}
+
+// rewrites a sparse value as dense, keeping its cached count
+pub fn to_dense(bytes: &mut Vec<u8>) -> Option<()> {
+    let registers = registers(bytes)?;
+    let cache: [u8; 8] = bytes[8..16].try_into().expect("8 byte cache");
+    *bytes = encode_dense(&registers);
+    bytes[8..16].copy_from_slice(&cache);
+    Some(())
+}
+
+// `registers` encoded sparse while that takes at most `sparse_max` bytes, dense otherwise
+pub fn encode(registers: &Registers, sparse_max: usize) -> Vec<u8> {
+    match encode_sparse(registers) {
+        Some(sparse) if sparse.len() <= sparse_max => sparse,
+        _ => encode_dense(registers),
+    }
+}
+
+// PFADD on a HyperLogLog; true when a register changed, None when the sparse body is
+// corrupt. sparse values are updated in place and converted to dense once they outgrow
+// `sparse_max` or need a register above 32
+pub fn add<'a>(bytes: &mut Vec<u8>, elements: impl IntoIterator<Item = &'a [u8]>, sparse_max: usize) -> Option<bool> {
+    let mut changed = false;
+    for element in elements {
+        let (index, count) = register_for(element);
+        if encoding(bytes) == Some(HllEncoding::Sparse) {
+            match sparse_set(bytes, index, count)? {
+                SparseSet::Unchanged => continue,
+                SparseSet::Updated => {
+                    changed = true;
+                    if bytes.len() > sparse_max {
+                        to_dense(bytes)?;
+                    }
+                    continue;
+                }
+                SparseSet::TooLarge => to_dense(bytes)?,
+            }
+        }
+        if dense_get(&bytes[HEADER_LEN..], index) < count {
+            dense_set(&mut bytes[HEADER_LEN..], index, count);
+            changed = true;
+        }
+    }
+
+    if changed {
+        bytes[15] |= 0x80;
+    }
+    Some(changed)
+}
+
+pub fn cached_count(bytes: &[u8]) -> Option<u64> {
+    (bytes[15] & 0x80 == 0).then(|| u64::from_le_bytes(bytes[8..16].try_into().expect("8 byte cache")))
+}
+
+pub fn cache_count(bytes: &mut [u8], count: u64) {
+    bytes[8..16].copy_from_slice(&count.to_le_bytes());
+}
+
+// the max of each register, as PFMERGE and multi-key PFCOUNT combine values
+pub fn merge(into: &mut Registers, bytes: &[u8]) -> Option<()> {
+    for (register, value) in into.iter_mut().zip(registers(bytes)?) {
+        *register = (*register).max(value);
+    }
+    Some(())
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+// Ertl's improved estimator, as used by redis since 5.0
+pub fn estimate(registers: &Registers) -> u64 {
+    let mut histogram = [0u32; Q as usize + 2];
+    for &value in registers {
+        histogram[value as usize] += 1;
+    }
+
+    let m = REGISTERS as f64;
+    let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+    for &count in histogram[1..=Q as usize].iter().rev() {
+        z += count as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+// the sparse opcodes, as PFDEBUG DECODE prints them
+pub fn describe_sparse(bytes: &[u8]) -> Option<String> {
+    if encoding(bytes)? != HllEncoding::Sparse {
+        return None;
+    }
+    let (mut ops, mut offset) = (Vec::new(), HEADER_LEN);
+    while offset < bytes.len() {
+        let (value, len, size) = sparse_op(&bytes[offset..])?;
+        ops.push(match (value, size) {
+            (0, 1) => format!("Z:{}", len),
+            (0, _) => format!("XZ:{}", len),
+            _ => format!("v:{},{}", value, len),
+        });
+        offset += size;
+    }
+    Some(ops.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur() {
+        // reference values from the C implementation redis uses
+        assert_eq!(murmur64a(b"", SEED), 0xd8df_ea65_85bc_9732);
+        assert_eq!(murmur64a(b"hello world!", SEED), 0x0fc4_4401_1f57_220c);
+        assert_eq!(register_for(b"a").0, murmur64a(b"a", SEED) as usize & (REGISTERS - 1));
+    }
+
+    #[test]
+    fn test_sparse_round_trip() {
+        let empty = new();
+        assert_eq!(empty.len(), HEADER_LEN + 2);
+        assert_eq!(&empty[HEADER_LEN..], &[0x7f, 0xff]);
+        assert_eq!(encoding(&empty), Some(HllEncoding::Sparse));
+        assert_eq!(describe_sparse(&empty).unwrap(), "XZ:16384");
+
+        let mut registers = [0; REGISTERS];
+        registers[3] = 5;
+        registers[4] = 5;
+        registers[100] = 32;
+        let sparse = encode(&registers, DEFAULT_SPARSE_MAX_BYTES);
+        assert_eq!(encoding(&sparse), Some(HllEncoding::Sparse));
+        assert_eq!(describe_sparse(&sparse).unwrap(), "Z:3 v:5,2 XZ:95 v:32,1 XZ:16283");
+        assert_eq!(self::registers(&sparse), Some(registers));
+
+        // values above 32 only fit the dense encoding
+        registers[7] = 33;
+        let dense = encode(&registers, DEFAULT_SPARSE_MAX_BYTES);
+        assert_eq!(encoding(&dense), Some(HllEncoding::Dense));
+        assert_eq!(dense.len(), DENSE_LEN);
+        assert_eq!(self::registers(&dense), Some(registers));
+
+        assert_eq!(encoding(b"HYLL"), None);
+        assert_eq!(encoding(b"hello world, not an hll"), None);
+        // the header alone names the encoding; a bad sparse body shows up once decoded
+        let mut truncated = empty.clone();
+        truncated.pop();
+        assert_eq!(encoding(&truncated), Some(HllEncoding::Sparse));
+        assert_eq!(self::registers(&truncated), None);
+        assert_eq!(add(&mut truncated, [&b"a"[..]], DEFAULT_SPARSE_MAX_BYTES), None);
+    }
+
+    #[test]
+    fn test_sparse_set_in_place() {
+        let mut hll = new();
+        let mut expected = [0; REGISTERS];
+        for (index, count) in [(5, 3), (6, 3), (4, 3), (7, 2), (16383, 1), (0, 32), (6, 4), (6, 1)] {
+            let changed = sparse_set(&mut hll, index, count).unwrap();
+            assert_eq!(changed == SparseSet::Updated, expected[index] < count, "register {}", index);
+            expected[index] = expected[index].max(count);
+            assert_eq!(registers(&hll), Some(expected));
+        }
+        // equal neighbours are merged as they are written
+        assert_eq!(describe_sparse(&hll).unwrap(), "v:32,1 Z:3 v:3,2 v:4,1 v:2,1 XZ:16375 v:1,1");
+        assert_eq!(hll.len(), encode(&expected, DEFAULT_SPARSE_MAX_BYTES).len());
+
+        assert_eq!(sparse_set(&mut hll, 9, 33), Some(SparseSet::TooLarge));
+        let mut corrupt = hll[..hll.len() - 1].to_vec();
+        assert_eq!(sparse_set(&mut corrupt, 16383, 2), None);
+    }
+
+    #[test]
+    fn test_add_converts_to_dense() {
+        let elements: Vec<String> = (0..200).map(|i| format!("element:{}", i)).collect();
+        let mut expected = [0; REGISTERS];
+        for element in &elements {
+            let (index, count) = register_for(element.as_bytes());
+            expected[index] = expected[index].max(count);
+        }
+
+        let mut hll = new();
+        assert_eq!(add(&mut hll, elements.iter().map(|element| element.as_bytes()), DEFAULT_SPARSE_MAX_BYTES), Some(true));
+        assert_eq!(encoding(&hll), Some(HllEncoding::Sparse));
+        assert_eq!(registers(&hll), Some(expected));
+
+        // the value goes dense as soon as it outgrows the limit, keeping every register
+        let mut small = new();
+        assert_eq!(add(&mut small, elements.iter().map(|element| element.as_bytes()), 100), Some(true));
+        assert_eq!(encoding(&small), Some(HllEncoding::Dense));
+        assert_eq!(registers(&small), Some(expected));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut hll = new();
+        assert_eq!(add(&mut hll, [&b"a"[..], b"b", b"c"], DEFAULT_SPARSE_MAX_BYTES), Some(true));
+        assert_eq!(add(&mut hll, [&b"a"[..]], DEFAULT_SPARSE_MAX_BYTES), Some(false));
+        assert_eq!(estimate(&registers(&hll).unwrap()), 3);
+
+        let elements: Vec<String> = (0..20_000).map(|i| format!("element:{}", i)).collect();
+        add(&mut hll, elements.iter().map(|element| element.as_bytes()), DEFAULT_SPARSE_MAX_BYTES);
+        assert_eq!(encoding(&hll), Some(HllEncoding::Dense));
+        let count = estimate(&registers(&hll).unwrap()) as f64;
+        // the standard error is 0.81%
+        assert!((count - 20_003.0).abs() / 20_003.0 < 0.03, "estimate {}", count);
+
+        assert_eq!(cached_count(&hll), None);
+        cache_count(&mut hll, 42);
+        assert_eq!(cached_count(&hll), Some(42));
+    }
+}
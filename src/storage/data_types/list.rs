@@ -1,38 +1,193 @@
+use std::collections::VecDeque;
 use super::DataType;
 
-#[derive(Debug, Clone)]
+// which end of a list an operation works on; LEFT is the head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+impl ListEnd {
+    // L or R, as in LPUSH / RPUSH
+    pub fn initial(&self) -> char {
+        match self {
+            ListEnd::Left => 'L',
+            ListEnd::Right => 'R',
+        }
+    }
+}
+
+// deque so both ends are O(1); the byte size is tracked so memory_usage stays O(1) too
+#[derive(Debug, Clone, Default)]
 pub struct RedisList {
-    values: Vec<String>,
+    values: VecDeque<Vec<u8>>,
+    bytes: usize,
 }
 
 impl RedisList {
     pub fn new() -> Self {
-        Self { values: Vec::new() }
+        Self::default()
     }
 
-    pub fn push_head(&mut self, value: String) {
-        self.values.insert(0, value);
+    pub fn push_head(&mut self, value: Vec<u8>) {
+        self.bytes += value.len();
+        self.values.push_front(value);
     }
 
-    pub fn push_tail(&mut self, value: String) {
-        self.values.push(value);
+    pub fn push_tail(&mut self, value: Vec<u8>) {
+        self.bytes += value.len();
+        self.values.push_back(value);
     }
 
-    pub fn pop_head(&mut self) -> Option<String> {
-        self.values.pop()
+    pub fn pop_head(&mut self) -> Option<Vec<u8>> {
+        let value = self.values.pop_front()?;
+        self.bytes -= value.len();
+        Some(value)
     }
 
-    pub fn pop_tail(&mut self) -> Option<String> {
-        if self.values.is_empty() {
-            None
-        } else {
-            Some(self.values.remove(0))
+    pub fn pop_tail(&mut self) -> Option<Vec<u8>> {
+        let value = self.values.pop_back()?;
+        self.bytes -= value.len();
+        Some(value)
+    }
+
+    pub fn push(&mut self, end: ListEnd, value: Vec<u8>) {
+        match end {
+            ListEnd::Left => self.push_head(value),
+            ListEnd::Right => self.push_tail(value),
+        }
+    }
+
+    pub fn pop(&mut self, end: ListEnd) -> Option<Vec<u8>> {
+        match end {
+            ListEnd::Left => self.pop_head(),
+            ListEnd::Right => self.pop_tail(),
         }
     }
 
     pub fn len(&self) -> usize {
         self.values.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &[u8]> {
+        self.values.iter().map(Vec::as_slice)
+    }
+
+    // negative indexes count from the tail, -1 being the last element
+    fn resolve(&self, index: i64) -> Option<usize> {
+        let len = self.values.len() as i64;
+        let index = if index < 0 { len + index } else { index };
+        (0..len).contains(&index).then_some(index as usize)
+    }
+
+    pub fn get(&self, index: i64) -> Option<&[u8]> {
+        self.resolve(index).map(|i| self.values[i].as_slice())
+    }
+
+    // false when the index is out of range
+    pub fn set(&mut self, index: i64, value: Vec<u8>) -> bool {
+        match self.resolve(index) {
+            Some(i) => {
+                self.bytes = self.bytes - self.values[i].len() + value.len();
+                self.values[i] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // inclusive LRANGE-style bounds, clamped to the list; None when the range is empty
+    fn clamp_range(&self, start: i64, stop: i64) -> Option<(usize, usize)> {
+        let len = self.values.len() as i64;
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let stop = if stop < 0 { len + stop } else { stop.min(len - 1) };
+        (start <= stop && start < len).then_some((start as usize, stop as usize))
+    }
+
+    pub fn range(&self, start: i64, stop: i64) -> Vec<&[u8]> {
+        match self.clamp_range(start, stop) {
+            Some((start, stop)) => self.values.range(start..=stop).map(Vec::as_slice).collect(),
+            None => vec![],
+        }
+    }
+
+    // keeps only the given range, like LTRIM
+    pub fn trim(&mut self, start: i64, stop: i64) {
+        match self.clamp_range(start, stop) {
+            Some((start, stop)) => {
+                let tail: usize = self.values.drain(stop + 1..).map(|value| value.len()).sum();
+                let head: usize = self.values.drain(..start).map(|value| value.len()).sum();
+                self.bytes -= head + tail;
+            }
+            None => {
+                self.values.clear();
+                self.bytes = 0;
+            }
+        }
+    }
+
+    // inserts next to the first occurrence of `pivot`; returns the new length, None without a pivot
+    pub fn insert(&mut self, pivot: &[u8], value: Vec<u8>, side: ListEnd) -> Option<usize> {
+        let at = self.values.iter().position(|item| item == pivot)?;
+        let at = match side {
+            ListEnd::Left => at,
+            ListEnd::Right => at + 1,
+        };
+        self.bytes += value.len();
+        self.values.insert(at, value);
+        Some(self.values.len())
+    }
+
+    // LREM semantics: count > 0 removes from the head, count < 0 from the tail, 0 removes all
+    pub fn remove(&mut self, count: i64, value: &[u8]) -> usize {
+        let rank = if count < 0 { -1 } else { 1 };
+        let mut doomed = self.positions(value, rank, count.unsigned_abs() as usize, 0);
+        doomed.sort_unstable();
+
+        // a single pass keeps this O(n) however many elements go
+        let mut index = 0;
+        let mut next = doomed.iter().peekable();
+        self.values.retain(|_| {
+            let keep = next.next_if_eq(&&index).is_none();
+            index += 1;
+            keep
+        });
+
+        self.bytes -= doomed.len() * value.len();
+        doomed.len()
+    }
+
+    // LPOS: indexes of matches, starting at the rank-th match (negative ranks scan from the
+    // tail), at most `count` of them (0 = all), looking at no more than `maxlen` elements (0 = all)
+    pub fn positions(&self, value: &[u8], rank: i64, count: usize, maxlen: usize) -> Vec<usize> {
+        let limit = if count == 0 { usize::MAX } else { count };
+        let scan = if maxlen == 0 { self.values.len() } else { maxlen.min(self.values.len()) };
+        let skip = rank.unsigned_abs() as usize - 1;
+
+        let indexes: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+            Box::new(0..scan)
+        } else {
+            Box::new((self.values.len() - scan..self.values.len()).rev())
+        };
+
+        indexes
+            .filter(|&i| self.values[i] == value)
+            .skip(skip)
+            .take(limit)
+            .collect()
+    }
+}
+
+impl From<Vec<Vec<u8>>> for RedisList {
+    fn from(values: Vec<Vec<u8>>) -> Self {
+        let bytes = values.iter().map(Vec::len).sum();
+        Self { values: values.into(), bytes }
+    }
 }
 
 impl DataType for RedisList {
@@ -41,7 +196,7 @@ impl DataType for RedisList {
     }
 
     fn memory_usage(&self) -> usize {
-        self.values.iter().map(|s| s.len()).sum()
+        self.bytes
     }
 }
 
@@ -58,19 +213,19 @@ mod tests {
     #[test]
     fn test_push_head() {
         let mut list = RedisList::new();
-        list.push_head("first".to_string());
-        list.push_head("second".to_string());
+        list.push_head(b"first".to_vec());
+        list.push_head(b"second".to_vec());
         assert_eq!(list.len(), 2);
-        assert_eq!(list.pop_tail(), Some("second".to_string()));
+        assert_eq!(list.pop_head(), Some(b"second".to_vec()));
     }
 
     #[test]
     fn test_push_tail() {
         let mut list = RedisList::new();
-        list.push_tail("first".to_string());
-        list.push_tail("second".to_string());
+        list.push_tail(b"first".to_vec());
+        list.push_tail(b"second".to_vec());
         assert_eq!(list.len(), 2);
-        assert_eq!(list.pop_head(), Some("second".to_string()));
+        assert_eq!(list.pop_tail(), Some(b"second".to_vec()));
     }
 
     #[test]
@@ -88,12 +243,12 @@ mod tests {
     #[test]
     fn test_multiple_operations() {
         let mut list = RedisList::new();
-        list.push_head("1".to_string());
-        list.push_tail("2".to_string());
-        list.push_head("0".to_string());
+        list.push_head(b"1".to_vec());
+        list.push_tail(b"2".to_vec());
+        list.push_head(b"0".to_vec());
         assert_eq!(list.len(), 3);
-        assert_eq!(list.pop_tail(), Some("0".to_string()));
-        assert_eq!(list.pop_head(), Some("2".to_string()));
+        assert_eq!(list.pop_head(), Some(b"0".to_vec()));
+        assert_eq!(list.pop_tail(), Some(b"2".to_vec()));
         assert_eq!(list.len(), 1);
     }
 
@@ -101,8 +256,8 @@ mod tests {
     fn test_memory_usage() {
         let mut list = RedisList::new();
         assert_eq!(list.memory_usage(), 0);
-        list.push_tail("hello".to_string());
-        list.push_tail("world".to_string());
+        list.push_tail(b"hello".to_vec());
+        list.push_tail(b"world".to_vec());
         assert_eq!(list.memory_usage(), 10);
     }
 
@@ -115,9 +270,67 @@ mod tests {
     #[test]
     fn test_clone() {
         let mut list1 = RedisList::new();
-        list1.push_tail("test".to_string());
+        list1.push_tail(b"test".to_vec());
         let mut list2 = list1.clone();
         assert_eq!(list1.len(), list2.len());
         assert_eq!(list1.pop_head(), list2.pop_head());
     }
-}
\ No newline at end of file
+
+    fn list_of(items: &[&str]) -> RedisList {
+        items.iter().map(|item| item.as_bytes().to_vec()).collect::<Vec<_>>().into()
+    }
+
+    fn contents(list: &RedisList) -> Vec<String> {
+        list.iter().map(|item| String::from_utf8_lossy(item).into_owned()).collect()
+    }
+
+    #[test]
+    fn test_range_and_index() {
+        let list = list_of(&["a", "b", "c", "d"]);
+        assert_eq!(list.range(0, -1).len(), 4);
+        assert_eq!(list.range(1, 2), vec![b"b".as_slice(), b"c".as_slice()]);
+        assert_eq!(list.range(-2, 100), vec![b"c".as_slice(), b"d".as_slice()]);
+        assert!(list.range(3, 1).is_empty());
+        assert!(list.range(5, 10).is_empty());
+        assert_eq!(list.get(-1), Some(b"d".as_slice()));
+        assert_eq!(list.get(4), None);
+        assert_eq!(list.get(-5), None);
+    }
+
+    #[test]
+    fn test_set_insert_remove() {
+        let mut list = list_of(&["a", "b", "a", "c", "a"]);
+        assert!(list.set(-1, b"z".to_vec()));
+        assert!(!list.set(5, b"z".to_vec()));
+        assert_eq!(list.insert(b"c", b"x".to_vec(), ListEnd::Left), Some(6));
+        assert_eq!(list.insert(b"nope", b"x".to_vec(), ListEnd::Left), None);
+        assert_eq!(contents(&list), ["a", "b", "a", "x", "c", "z"]);
+
+        assert_eq!(list.remove(-1, b"a"), 1);
+        assert_eq!(contents(&list), ["a", "b", "x", "c", "z"]);
+        assert_eq!(list.remove(0, b"a"), 1);
+        assert_eq!(list.memory_usage(), 4);
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut list = list_of(&["a", "b", "c", "d", "e"]);
+        list.trim(1, -2);
+        assert_eq!(contents(&list), ["b", "c", "d"]);
+        list.trim(5, 10);
+        assert!(list.is_empty());
+        assert_eq!(list.memory_usage(), 0);
+    }
+
+    #[test]
+    fn test_positions() {
+        let list = list_of(&["a", "b", "c", "1", "2", "3", "c", "c"]);
+        assert_eq!(list.positions(b"c", 1, 1, 0), vec![2]);
+        assert_eq!(list.positions(b"c", 2, 1, 0), vec![6]);
+        assert_eq!(list.positions(b"c", -1, 1, 0), vec![7]);
+        assert_eq!(list.positions(b"c", 1, 0, 0), vec![2, 6, 7]);
+        assert_eq!(list.positions(b"c", -1, 2, 0), vec![7, 6]);
+        assert_eq!(list.positions(b"c", 1, 0, 3), vec![2]);
+        assert!(list.positions(b"x", 1, 0, 0).is_empty());
+    }
+}
@@ -1,7 +1,101 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 pub mod string;
+pub mod bitmap;
+pub mod geohash;
+pub mod hyperloglog;
 pub mod list;
+pub mod hash;
+pub mod set;
+pub mod skiplist;
+pub mod zset;
+pub mod stream;
 
 pub trait DataType {
     fn type_name(&self) -> &str;
     fn memory_usage(&self) -> usize;
-}
\ No newline at end of file
+}
+
+// how large a small aggregate may grow before it leaves its compact encoding, as in the
+// redis `*-max-listpack-entries` / `*-max-listpack-value` directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListpackLimits {
+    pub max_entries: usize,
+    // longest field or value, in bytes
+    pub max_value: usize,
+}
+
+impl ListpackLimits {
+    pub const fn new(max_entries: usize, max_value: usize) -> Self {
+        Self { max_entries, max_value }
+    }
+
+    pub fn fits(&self, len: usize, element: &[u8]) -> bool {
+        len <= self.max_entries && element.len() <= self.max_value
+    }
+}
+
+// one step of a cursor scan over a hash table. elements are visited in the order of a
+// fixed hash of their name and the cursor is the hash to resume from, so anything present
+// for the whole scan is returned even when the table grows or shrinks in between;
+// a returned cursor of 0 ends the scan
+pub(crate) fn scan_by_hash<'a, T>(
+    items: impl Iterator<Item = (&'a [u8], T)>,
+    cursor: u64,
+    count: usize,
+) -> (u64, Vec<T>) {
+    let mut pending: Vec<(u64, T)> = items
+        .map(|(name, item)| (scan_hash(name), item))
+        .filter(|&(hash, _)| hash >= cursor)
+        .collect();
+
+    if pending.len() <= count {
+        return (0, pending.into_iter().map(|(_, item)| item).collect());
+    }
+
+    pending.select_nth_unstable_by_key(count, |&(hash, _)| hash);
+    let boundary = pending[count].0;
+    // everything sharing the boundary hash is left for the next call, unless that would
+    // return nothing at all
+    let next = if pending.iter().any(|&(hash, _)| hash < boundary) {
+        pending.retain(|&(hash, _)| hash < boundary);
+        boundary
+    } else {
+        pending.retain(|&(hash, _)| hash == boundary);
+        boundary.wrapping_add(1)
+    };
+    (next, pending.into_iter().map(|(_, item)| item).collect())
+}
+
+fn scan_hash(name: &[u8]) -> u64 {
+    // DefaultHasher::new uses fixed keys, so cursors stay valid for the life of the process
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_scan_by_hash_visits_everything_once() {
+        let names: Vec<Vec<u8>> = (0..100).map(|i| format!("field:{}", i).into_bytes()).collect();
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = scan_by_hash(names.iter().map(|name| (name.as_slice(), name.clone())), cursor, 7);
+            assert!(batch.len() <= 7);
+            for name in batch {
+                assert!(seen.insert(name));
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(seen.len(), 100);
+    }
+}
@@ -0,0 +1,309 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use rand::Rng;
+use super::{scan_by_hash, DataType, ListpackLimits};
+
+// when a set leaves the intset and listpack encodings, as in the redis
+// `set-max-intset-entries` and `set-max-listpack-*` directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetLimits {
+    pub max_intset_entries: usize,
+    pub listpack: ListpackLimits,
+}
+
+impl Default for SetLimits {
+    fn default() -> Self {
+        Self {
+            max_intset_entries: 512,
+            listpack: ListpackLimits::new(128, 64),
+        }
+    }
+}
+
+// sets of integers are a sorted vector of i64 like a redis intset, other small sets a flat
+// vector searched linearly; past the limits they become a hash set and never go back
+#[derive(Debug, Clone)]
+enum Members {
+    // `width` is the bytes per element, 2, 4 or 8, widened as larger values arrive
+    IntSet { values: Vec<i64>, width: usize },
+    Listpack(Vec<Vec<u8>>),
+    Table(HashSet<Vec<u8>>),
+}
+
+#[derive(Debug, Clone)]
+pub struct RedisSet {
+    members: Members,
+    // member bytes for the listpack and table encodings; intsets are sized by their width
+    bytes: usize,
+}
+
+impl Default for RedisSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// only the canonical spelling of an integer goes into an intset, so members read back
+// byte for byte: "10" does, "010" and "+10" stay strings
+fn as_integer(member: &[u8]) -> Option<i64> {
+    let value: i64 = std::str::from_utf8(member).ok()?.parse().ok()?;
+    (value.to_string().as_bytes() == member).then_some(value)
+}
+
+fn int_width(value: i64) -> usize {
+    if i16::try_from(value).is_ok() {
+        2
+    } else if i32::try_from(value).is_ok() {
+        4
+    } else {
+        8
+    }
+}
+
+impl RedisSet {
+    pub fn new() -> Self {
+        Self {
+            members: Members::IntSet { values: Vec::new(), width: 2 },
+            bytes: 0,
+        }
+    }
+
+    // the name OBJECT ENCODING reports
+    pub fn encoding(&self) -> &'static str {
+        match self.members {
+            Members::IntSet { .. } => "intset",
+            Members::Listpack(_) => "listpack",
+            Members::Table(_) => "hashtable",
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.members {
+            Members::IntSet { values, .. } => values.len(),
+            Members::Listpack(members) => members.len(),
+            Members::Table(members) => members.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        match &self.members {
+            Members::IntSet { values, .. } => {
+                as_integer(member).is_some_and(|value| values.binary_search(&value).is_ok())
+            }
+            Members::Listpack(members) => members.iter().any(|m| m == member),
+            Members::Table(members) => members.contains(member),
+        }
+    }
+
+    // adds `member`, re-encoding first when the current encoding cannot hold it within
+    // `limits`; returns true when it was not already there
+    pub fn insert(&mut self, member: Vec<u8>, limits: SetLimits) -> bool {
+        if self.contains(&member) {
+            return false;
+        }
+
+        let len = self.len() + 1;
+        match &mut self.members {
+            Members::IntSet { values, width } => match as_integer(&member) {
+                Some(value) if len <= limits.max_intset_entries => {
+                    let position = values.binary_search(&value).unwrap_err();
+                    values.insert(position, value);
+                    *width = (*width).max(int_width(value));
+                    return true;
+                }
+                _ => {
+                    // integers print to at most 20 bytes, so only the new member can be too long
+                    let listpack = limits.listpack.fits(len, &member)
+                        && values.iter().all(|&value| limits.listpack.fits(len, value.to_string().as_bytes()));
+                    self.convert(listpack);
+                }
+            },
+            Members::Listpack(_) if !limits.listpack.fits(len, &member) => self.convert(false),
+            _ => {}
+        }
+
+        self.bytes += member.len();
+        match &mut self.members {
+            Members::Listpack(members) => members.push(member),
+            Members::Table(members) => {
+                members.insert(member);
+            }
+            Members::IntSet { .. } => unreachable!("intsets were converted above"),
+        }
+        true
+    }
+
+    // moves the members into a listpack or a hash table
+    fn convert(&mut self, listpack: bool) {
+        let members: Vec<Vec<u8>> = self.iter().map(Cow::into_owned).collect();
+        self.bytes = members.iter().map(Vec::len).sum();
+        self.members = if listpack {
+            Members::Listpack(members)
+        } else {
+            Members::Table(members.into_iter().collect())
+        };
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        let removed = match &mut self.members {
+            Members::IntSet { values, .. } => {
+                match as_integer(member).and_then(|value| values.binary_search(&value).ok()) {
+                    Some(position) => {
+                        values.remove(position);
+                        // intset widths only ever grow, like in redis
+                        return true;
+                    }
+                    None => false,
+                }
+            }
+            Members::Listpack(members) => match members.iter().position(|m| m == member) {
+                Some(position) => {
+                    members.swap_remove(position);
+                    true
+                }
+                None => false,
+            },
+            Members::Table(members) => members.remove(member),
+        };
+
+        if removed {
+            self.bytes -= member.len();
+        }
+        removed
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Cow<'_, [u8]>> + '_> {
+        match &self.members {
+            Members::IntSet { values, .. } => {
+                Box::new(values.iter().map(|value| Cow::Owned(value.to_string().into_bytes())))
+            }
+            Members::Listpack(members) => Box::new(members.iter().map(|m| Cow::Borrowed(m.as_slice()))),
+            Members::Table(members) => Box::new(members.iter().map(|m| Cow::Borrowed(m.as_slice()))),
+        }
+    }
+
+    // a uniformly chosen member; the hash table has no random access, so that walks it
+    pub fn random<R: Rng>(&self, rng: &mut R) -> Option<Cow<'_, [u8]>> {
+        if self.is_empty() {
+            return None;
+        }
+        let index = rng.gen_range(0..self.len());
+        match &self.members {
+            Members::IntSet { values, .. } => Some(Cow::Owned(values[index].to_string().into_bytes())),
+            Members::Listpack(members) => Some(Cow::Borrowed(members[index].as_slice())),
+            Members::Table(members) => members.iter().nth(index).map(|m| Cow::Borrowed(m.as_slice())),
+        }
+    }
+
+    pub fn pop_random<R: Rng>(&mut self, rng: &mut R) -> Option<Vec<u8>> {
+        let member = self.random(rng)?.into_owned();
+        self.remove(&member);
+        Some(member)
+    }
+
+    // one SSCAN step; like redis intsets and listpacks are returned whole
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<Cow<'_, [u8]>>) {
+        match &self.members {
+            Members::Table(members) => scan_by_hash(
+                members.iter().map(|m| (m.as_slice(), Cow::Borrowed(m.as_slice()))),
+                cursor,
+                count,
+            ),
+            _ => (0, self.iter().collect()),
+        }
+    }
+}
+
+impl DataType for RedisSet {
+    fn type_name(&self) -> &str {
+        "set"
+    }
+
+    fn memory_usage(&self) -> usize {
+        match &self.members {
+            Members::IntSet { values, width } => values.len() * width,
+            _ => self.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: SetLimits = SetLimits {
+        max_intset_entries: 3,
+        listpack: ListpackLimits::new(4, 8),
+    };
+
+    fn members(set: &RedisSet) -> Vec<Vec<u8>> {
+        let mut members: Vec<Vec<u8>> = set.iter().map(Cow::into_owned).collect();
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_intset_stays_sorted_and_widens() {
+        let mut set = RedisSet::new();
+        assert!(set.insert(b"5".to_vec(), LIMITS));
+        assert!(set.insert(b"-3".to_vec(), LIMITS));
+        assert!(!set.insert(b"5".to_vec(), LIMITS));
+        assert_eq!(set.encoding(), "intset");
+        assert_eq!(set.memory_usage(), 4);
+
+        set.insert(b"100000".to_vec(), LIMITS);
+        assert_eq!(set.memory_usage(), 12);
+        let ordered: Vec<Vec<u8>> = set.iter().map(Cow::into_owned).collect();
+        assert_eq!(ordered, vec![b"-3".to_vec(), b"5".to_vec(), b"100000".to_vec()]);
+
+        assert!(set.remove(b"5"));
+        assert!(!set.remove(b"05"));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_non_canonical_integers_are_strings() {
+        let mut set = RedisSet::new();
+        set.insert(b"1".to_vec(), LIMITS);
+        set.insert(b"01".to_vec(), LIMITS);
+        assert_eq!(set.encoding(), "listpack");
+        assert_eq!(members(&set), vec![b"01".to_vec(), b"1".to_vec()]);
+        assert!(set.contains(b"1"));
+        assert_eq!(set.memory_usage(), 3);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let mut set = RedisSet::new();
+        for member in ["1", "2", "3", "4"] {
+            set.insert(member.as_bytes().to_vec(), LIMITS);
+        }
+        // too many for the intset, still a small listpack
+        assert_eq!(set.encoding(), "listpack");
+        set.insert(b"five".to_vec(), LIMITS);
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), 5);
+        assert_eq!(set.memory_usage(), 8);
+
+        let mut long = RedisSet::new();
+        long.insert(b"a member longer than eight bytes".to_vec(), LIMITS);
+        assert_eq!(long.encoding(), "hashtable");
+    }
+
+    #[test]
+    fn test_pop_random_empties() {
+        let mut set = RedisSet::new();
+        set.insert(b"a".to_vec(), LIMITS);
+        set.insert(b"7".to_vec(), LIMITS);
+        let mut rng = rand::thread_rng();
+        let mut popped = vec![set.pop_random(&mut rng).unwrap(), set.pop_random(&mut rng).unwrap()];
+        popped.sort();
+        assert_eq!(popped, vec![b"7".to_vec(), b"a".to_vec()]);
+        assert_eq!(set.pop_random(&mut rng), None);
+        assert_eq!(set.memory_usage(), 0);
+    }
+}
@@ -0,0 +1,378 @@
+use rand::Rng;
+
+// redis' parameters: up to 32 levels, each one a quarter as dense as the one below
+const MAX_LEVEL: usize = 32;
+const LEVEL_PROBABILITY: f64 = 0.25;
+
+// nodes live in an arena and link to each other by index; NIL ends a chain and HEAD is
+// the sentinel that starts every level
+const NIL: usize = usize::MAX;
+const HEAD: usize = 0;
+
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    forward: usize,
+    // how many nodes the forward link skips over, which is what makes rank lookups O(log n)
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Vec<u8>,
+    score: f64,
+    backward: usize,
+    levels: Vec<Level>,
+}
+
+impl Node {
+    // (score, member) order, the order of a sorted set
+    fn before(&self, score: f64, member: &[u8]) -> bool {
+        self.score < score || (self.score == score && self.member.as_slice() < member)
+    }
+}
+
+// a skiplist ordered by (score, member), following the one in redis' t_zset.c
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    // arena slots of deleted nodes, reused by later inserts
+    free: Vec<usize>,
+    tail: usize,
+    len: usize,
+    level: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn random_level() -> usize {
+    let mut rng = rand::thread_rng();
+    let mut level = 1;
+    while level < MAX_LEVEL && rng.gen_bool(LEVEL_PROBABILITY) {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    pub fn new() -> Self {
+        let head = Node {
+            member: Vec::new(),
+            score: 0.0,
+            backward: NIL,
+            levels: vec![Level { forward: NIL, span: 0 }; MAX_LEVEL],
+        };
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: NIL,
+            len: 0,
+            level: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn forward(&self, node: usize, level: usize) -> usize {
+        self.nodes[node].levels[level].forward
+    }
+
+    fn span(&self, node: usize, level: usize) -> usize {
+        self.nodes[node].levels[level].span
+    }
+
+    // the last node before (score, member) on every level
+    fn predecessors(&self, score: f64, member: &[u8]) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i == self.level - 1 { 0 } else { rank[i + 1] };
+            loop {
+                let next = self.forward(x, i);
+                if next == NIL || !self.nodes[next].before(score, member) {
+                    break;
+                }
+                rank[i] += self.span(x, i);
+                x = next;
+            }
+            update[i] = x;
+        }
+        (update, rank)
+    }
+
+    // the caller guarantees the member is not in the list yet
+    pub fn insert(&mut self, score: f64, member: Vec<u8>) {
+        let (mut update, mut rank) = self.predecessors(score, &member);
+
+        let level = random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].levels[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            member,
+            score,
+            backward: if update[0] == HEAD { NIL } else { update[0] },
+            levels: vec![Level { forward: NIL, span: 0 }; level],
+        };
+        let x = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for i in 0..level {
+            let prev = update[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[x].levels[i] = Level {
+                forward: self.forward(prev, i),
+                span: self.span(prev, i) - skipped,
+            };
+            self.nodes[prev].levels[i] = Level { forward: x, span: skipped + 1 };
+        }
+        // levels above the new node now skip over one more
+        for (i, &prev) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[prev].levels[i].span += 1;
+        }
+
+        match self.forward(x, 0) {
+            NIL => self.tail = x,
+            next => self.nodes[next].backward = x,
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let (update, _) = self.predecessors(score, member);
+        let x = self.forward(update[0], 0);
+        if x == NIL || self.nodes[x].score != score || self.nodes[x].member != member {
+            return false;
+        }
+
+        for (i, &prev) in update.iter().enumerate().take(self.level) {
+            if self.forward(prev, i) == x {
+                self.nodes[prev].levels[i] = Level {
+                    forward: self.forward(x, i),
+                    span: self.span(prev, i) + self.span(x, i) - 1,
+                };
+            } else {
+                self.nodes[prev].levels[i].span -= 1;
+            }
+        }
+
+        let backward = self.nodes[x].backward;
+        match self.forward(x, 0) {
+            NIL => self.tail = backward,
+            next => self.nodes[next].backward = backward,
+        }
+        while self.level > 1 && self.forward(HEAD, self.level - 1) == NIL {
+            self.level -= 1;
+        }
+
+        self.nodes[x].member = Vec::new();
+        self.free.push(x);
+        self.len -= 1;
+        true
+    }
+
+    // 0-based position of (score, member), if present
+    pub fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let mut x = HEAD;
+        let mut rank = 0;
+        for i in (0..self.level).rev() {
+            loop {
+                let next = self.forward(x, i);
+                if next == NIL {
+                    break;
+                }
+                let node = &self.nodes[next];
+                if !(node.before(score, member) || (node.score == score && node.member == member)) {
+                    break;
+                }
+                rank += self.span(x, i);
+                x = next;
+            }
+            if x != HEAD && self.nodes[x].score == score && self.nodes[x].member == member {
+                return Some(rank - 1);
+            }
+        }
+        None
+    }
+
+    // the node at a 0-based rank
+    pub fn at_rank(&self, rank: usize) -> Option<usize> {
+        let target = rank + 1;
+        let mut x = HEAD;
+        let mut traversed = 0;
+        for i in (0..self.level).rev() {
+            loop {
+                let next = self.forward(x, i);
+                if next == NIL || traversed + self.span(x, i) > target {
+                    break;
+                }
+                traversed += self.span(x, i);
+                x = next;
+            }
+            if traversed == target {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    // the first node that is not below the range's start, if it is also inside its end;
+    // `below` and `inside` come from the range being searched
+    pub fn first_where<B, I>(&self, below: B, inside: I) -> Option<usize>
+    where
+        B: Fn(f64, &[u8]) -> bool,
+        I: Fn(f64, &[u8]) -> bool,
+    {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let next = self.forward(x, i);
+                if next == NIL || !below(self.nodes[next].score, &self.nodes[next].member) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        let x = self.forward(x, 0);
+        (x != NIL && inside(self.nodes[x].score, &self.nodes[x].member)).then_some(x)
+    }
+
+    // the last node that is not past the range's end, if it is also inside its start
+    pub fn last_where<U, I>(&self, up_to: U, inside: I) -> Option<usize>
+    where
+        U: Fn(f64, &[u8]) -> bool,
+        I: Fn(f64, &[u8]) -> bool,
+    {
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let next = self.forward(x, i);
+                if next == NIL || !up_to(self.nodes[next].score, &self.nodes[next].member) {
+                    break;
+                }
+                x = next;
+            }
+        }
+        (x != HEAD && inside(self.nodes[x].score, &self.nodes[x].member)).then_some(x)
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        match self.forward(HEAD, 0) {
+            NIL => None,
+            x => Some(x),
+        }
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        (self.tail != NIL).then_some(self.tail)
+    }
+
+    pub fn next(&self, node: usize) -> Option<usize> {
+        match self.forward(node, 0) {
+            NIL => None,
+            x => Some(x),
+        }
+    }
+
+    pub fn prev(&self, node: usize) -> Option<usize> {
+        match self.nodes[node].backward {
+            NIL => None,
+            x => Some(x),
+        }
+    }
+
+    pub fn get(&self, node: usize) -> (&[u8], f64) {
+        (&self.nodes[node].member, self.nodes[node].score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(list: &SkipList) -> Vec<(Vec<u8>, f64)> {
+        std::iter::successors(list.first(), |&x| list.next(x))
+            .map(|x| {
+                let (member, score) = list.get(x);
+                (member.to_vec(), score)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ordered_by_score_then_member() {
+        let mut list = SkipList::new();
+        list.insert(2.0, b"b".to_vec());
+        list.insert(1.0, b"z".to_vec());
+        list.insert(2.0, b"a".to_vec());
+        assert_eq!(
+            members(&list),
+            vec![(b"z".to_vec(), 1.0), (b"a".to_vec(), 2.0), (b"b".to_vec(), 2.0)]
+        );
+        assert_eq!(list.get(list.last().unwrap()).0, b"b");
+        assert_eq!(list.rank(2.0, b"a"), Some(1));
+        assert_eq!(list.rank(2.0, b"c"), None);
+    }
+
+    #[test]
+    fn test_ranks_survive_many_updates() {
+        let mut list = SkipList::new();
+        for i in 0..500 {
+            list.insert(i as f64, format!("m{}", i).into_bytes());
+        }
+        for i in (0..500).step_by(2) {
+            assert!(list.remove(i as f64, format!("m{}", i).as_bytes()));
+        }
+        assert!(!list.remove(0.0, b"m0"));
+        assert_eq!(list.len(), 250);
+
+        for rank in 0..250 {
+            let x = list.at_rank(rank).unwrap();
+            let (member, score) = list.get(x);
+            assert_eq!(score, (rank * 2 + 1) as f64);
+            assert_eq!(list.rank(score, member), Some(rank));
+        }
+        assert_eq!(list.at_rank(250), None);
+
+        // backward links are kept too
+        let last = list.last().unwrap();
+        assert_eq!(list.get(list.prev(last).unwrap()).1, 497.0);
+    }
+
+    #[test]
+    fn test_range_search() {
+        let mut list = SkipList::new();
+        for i in 1..=5 {
+            list.insert(i as f64, vec![b'a' + i as u8]);
+        }
+        let first = list.first_where(|score, _| score < 2.5, |score, _| score <= 4.0);
+        assert_eq!(list.get(first.unwrap()).1, 3.0);
+        let last = list.last_where(|score, _| score <= 4.0, |score, _| score >= 2.5);
+        assert_eq!(list.get(last.unwrap()).1, 4.0);
+        assert_eq!(list.first_where(|score, _| score < 9.0, |_, _| true), None);
+    }
+}
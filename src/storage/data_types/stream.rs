@@ -0,0 +1,746 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use super::DataType;
+
+// entries per node before a new one is started, redis' stream-node-max-entries
+const NODE_MAX_ENTRIES: usize = 100;
+
+// `<milliseconds>-<sequence>`, ordered numerically on both parts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId { ms: u64::MAX, seq: u64::MAX };
+
+    pub const fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    // `ms-seq`, or a bare `ms` whose sequence is `missing_seq`
+    pub fn parse(text: &[u8], missing_seq: u64) -> Option<StreamId> {
+        let text = std::str::from_utf8(text).ok()?;
+        let parse = |part: &str| part.bytes().all(|b| b.is_ascii_digit()).then(|| part.parse().ok()).flatten();
+        match text.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(parse(ms)?, parse(seq)?)),
+            None => Some(StreamId::new(parse(text)?, missing_seq)),
+        }
+    }
+
+    // the smallest id after this one
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    // the largest id before this one
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+// the id XADD is asked for: `*`, `<ms>-*` or a full id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    Auto,
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+pub type Fields = Vec<(Vec<u8>, Vec<u8>)>;
+
+// an entry as handed to a group reader; None fields for entries deleted since delivery
+pub type Delivered = (StreamId, Option<Fields>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    pub id: StreamId,
+    pub fields: Fields,
+}
+
+impl StreamEntry {
+    fn size(&self) -> usize {
+        std::mem::size_of::<StreamId>()
+            + self.fields.iter().map(|(field, value)| field.len() + value.len()).sum::<usize>()
+    }
+}
+
+// XTRIM / XADD trimming: keep at most a length, or drop everything below an id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    // `~`: only whole nodes are removed, so the stream may stay a little longer
+    pub approx: bool,
+    // at most this many entries go per call; only allowed with `~`
+    pub limit: Option<usize>,
+}
+
+// an entry delivered to a consumer and not acknowledged yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub consumer: Vec<u8>,
+    // unix milliseconds of the last delivery
+    pub delivered_at: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consumer {
+    // the consumer's share of the group's pending entries
+    pub pending: BTreeSet<StreamId>,
+    // unix milliseconds of the last interaction, and of the last successful read or claim
+    pub seen_at: u64,
+    pub active_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    // how many entries the group has read, when that can still be known
+    pub entries_read: Option<u64>,
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+// how XCLAIM sets the delivery of what it claims
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    // TIME unix-ms / IDLE ms, both overriding "now" as the delivery time; the router keeps
+    // only the last one given, and IDLE is counted back from when the command runs
+    pub delivered_at: Option<u64>,
+    pub idle: Option<u64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub just_id: bool,
+    pub last_id: Option<StreamId>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered: StreamId, entries_read: Option<u64>) -> Self {
+        Self { last_delivered, entries_read, ..Self::default() }
+    }
+
+    // creates `name` if needed and marks it as seen
+    fn consumer(&mut self, name: &[u8], now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_vec()).or_default();
+        consumer.seen_at = now;
+        consumer
+    }
+
+    // returns true when the consumer is new
+    pub fn create_consumer(&mut self, name: &[u8], now: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+        self.consumer(name, now);
+        true
+    }
+
+    // drops the consumer and its pending entries; returns how many it had
+    pub fn delete_consumer(&mut self, name: &[u8]) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    // (re)assigns a pending entry to `consumer`, taking it away from its previous owner
+    fn assign(&mut self, id: StreamId, consumer: &[u8], delivered_at: u64, delivery_count: u64) {
+        if let Some(previous) = self.pending.get(&id) {
+            if let Some(owner) = self.consumers.get_mut(&previous.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.pending.insert(id, PendingEntry { consumer: consumer.to_vec(), delivered_at, delivery_count });
+        self.consumers.entry(consumer.to_vec()).or_default().pending.insert(id);
+    }
+
+    fn unassign(&mut self, id: StreamId) -> bool {
+        match self.pending.remove(&id) {
+            Some(entry) => {
+                if let Some(owner) = self.consumers.get_mut(&entry.consumer) {
+                    owner.pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // XACK; returns how many ids were pending
+    pub fn ack(&mut self, ids: &[StreamId]) -> usize {
+        ids.iter().filter(|&&id| self.unassign(id)).count()
+    }
+}
+
+// entries are grouped into nodes keyed by their first id, the way redis hangs listpacks off
+// a radix tree of big-endian ids: lookups find a node by id, then search inside it
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    nodes: BTreeMap<StreamId, Vec<StreamEntry>>,
+    len: usize,
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    // every entry ever added, deleted or not
+    entries_added: u64,
+    groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+    bytes: usize,
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the name OBJECT ENCODING reports
+    pub fn encoding(&self) -> &'static str {
+        "stream"
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn first_entry(&self) -> Option<&StreamEntry> {
+        self.nodes.values().flatten().next()
+    }
+
+    pub fn last_entry(&self) -> Option<&StreamEntry> {
+        self.nodes.values().next_back().and_then(|node| node.last())
+    }
+
+    // the id XADD would give a new entry, or None when that would not be past the top
+    pub fn next_id(&self, requested: XAddId, now_ms: u64) -> Option<StreamId> {
+        let last = self.last_id;
+        let id = match requested {
+            XAddId::Auto if now_ms > last.ms => StreamId::new(now_ms, 0),
+            XAddId::Auto => last.next()?,
+            XAddId::AutoSeq(ms) if ms == last.ms => StreamId::new(ms, last.seq.checked_add(1)?),
+            XAddId::AutoSeq(ms) => StreamId::new(ms, 0),
+            XAddId::Explicit(id) => id,
+        };
+        (id > last).then_some(id)
+    }
+
+    // appends an entry; `id` must come from next_id
+    pub fn push(&mut self, id: StreamId, fields: Fields) {
+        let entry = StreamEntry { id, fields };
+        self.bytes += entry.size();
+        match self.nodes.values_mut().next_back() {
+            Some(node) if node.len() < NODE_MAX_ENTRIES => node.push(entry),
+            _ => {
+                self.nodes.insert(id, vec![entry]);
+            }
+        }
+        self.len += 1;
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    // the key of the node that would hold `id`
+    fn node_of(&self, id: StreamId) -> Option<StreamId> {
+        self.nodes.range(..=id).next_back().map(|(&key, _)| key)
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&StreamEntry> {
+        let node = &self.nodes[&self.node_of(id)?];
+        node.binary_search_by_key(&id, |entry| entry.id).ok().map(|index| &node[index])
+    }
+
+    // entries with ids in `start..=end`, lowest first or, with `rev`, highest first
+    pub fn range(&self, start: StreamId, end: StreamId, count: Option<usize>, rev: bool) -> Vec<&StreamEntry> {
+        if start > end {
+            return Vec::new();
+        }
+        let count = count.unwrap_or(usize::MAX);
+        if rev {
+            self.nodes
+                .range(..=end)
+                .rev()
+                .flat_map(|(_, node)| node.iter().rev())
+                .skip_while(|entry| entry.id > end)
+                .take_while(|entry| entry.id >= start)
+                .take(count)
+                .collect()
+        } else {
+            let from = self.node_of(start).unwrap_or(start);
+            self.nodes
+                .range(from..)
+                .flat_map(|(_, node)| node.iter())
+                .skip_while(|entry| entry.id < start)
+                .take_while(|entry| entry.id <= end)
+                .take(count)
+                .collect()
+        }
+    }
+
+    // removes one entry, keeping the node's key even if that was its first entry
+    fn remove_entry(&mut self, id: StreamId) -> bool {
+        let key = match self.node_of(id) {
+            Some(key) => key,
+            None => return false,
+        };
+        let node = self.nodes.get_mut(&key).expect("node_of returns existing keys");
+        let index = match node.binary_search_by_key(&id, |entry| entry.id) {
+            Ok(index) => index,
+            Err(_) => return false,
+        };
+        let entry = node.remove(index);
+        if node.is_empty() {
+            self.nodes.remove(&key);
+        }
+        self.bytes -= entry.size();
+        self.len -= 1;
+        true
+    }
+
+    // XDEL; returns how many of the ids existed
+    pub fn delete(&mut self, ids: &[StreamId]) -> usize {
+        let mut deleted = 0;
+        for &id in ids {
+            if self.remove_entry(id) {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+                deleted += 1;
+            }
+        }
+        deleted
+    }
+
+    // removes entries from the front as `trim` asks; returns how many went
+    pub fn trim(&mut self, trim: &StreamTrim) -> usize {
+        let limit = trim.limit.unwrap_or(usize::MAX);
+        let mut removed = 0;
+
+        while let Some((&key, node)) = self.nodes.iter().next() {
+            let first = &node[0];
+            let removable = match trim.strategy {
+                TrimStrategy::MaxLen(max) => self.len > max,
+                TrimStrategy::MinId(min) => first.id < min,
+            };
+            if !removable || removed >= limit {
+                break;
+            }
+
+            if trim.approx {
+                // only nodes that are entirely past the threshold go
+                let whole = match trim.strategy {
+                    TrimStrategy::MaxLen(max) => self.len - node.len() >= max,
+                    TrimStrategy::MinId(min) => node.last().is_some_and(|entry| entry.id < min),
+                };
+                if !whole || removed + node.len() > limit {
+                    break;
+                }
+                let node = self.nodes.remove(&key).expect("the node was just found");
+                self.bytes -= node.iter().map(StreamEntry::size).sum::<usize>();
+                self.len -= node.len();
+                removed += node.len();
+            } else {
+                let id = first.id;
+                self.remove_entry(id);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = (&[u8], &ConsumerGroup)> + '_ {
+        self.groups.iter().map(|(name, group)| (name.as_slice(), group))
+    }
+
+    pub fn group(&self, name: &[u8]) -> Option<&ConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn group_mut(&mut self, name: &[u8]) -> Option<&mut ConsumerGroup> {
+        self.groups.get_mut(name)
+    }
+
+    // returns false when the group already exists
+    pub fn create_group(&mut self, name: &[u8], group: ConsumerGroup) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(name.to_vec(), group);
+        true
+    }
+
+    pub fn destroy_group(&mut self, name: &[u8]) -> bool {
+        self.groups.remove(name).is_some()
+    }
+
+    // how many entries were added after the group's last delivered one, if that is still known
+    pub fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        if self.entries_added == 0 || group.last_delivered >= self.last_id {
+            return Some(0);
+        }
+        // a deletion among the unread entries makes the count unknowable without a scan
+        let read = group.entries_read.filter(|_| self.max_deleted_id <= group.last_delivered)?;
+        Some(self.entries_added.saturating_sub(read))
+    }
+
+    // XREADGROUP with `>`: entries past the group's last delivered id, which become pending
+    // for `consumer` unless `no_ack`; None when there is no such group
+    pub fn deliver_new(
+        &mut self,
+        group_name: &[u8],
+        consumer: &[u8],
+        count: Option<usize>,
+        no_ack: bool,
+        now: u64,
+    ) -> Option<Vec<StreamEntry>> {
+        let group = self.groups.get_mut(group_name)?;
+        let entries: Vec<StreamEntry> = match group.last_delivered.next() {
+            Some(start) => {
+                let nodes = &self.nodes;
+                let from = nodes.range(..=start).next_back().map_or(start, |(&key, _)| key);
+                nodes
+                    .range(from..)
+                    .flat_map(|(_, node)| node.iter())
+                    .skip_while(|entry| entry.id < start)
+                    .take(count.unwrap_or(usize::MAX))
+                    .cloned()
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        group.consumer(consumer, now);
+        for entry in &entries {
+            group.last_delivered = entry.id;
+            group.entries_read = group.entries_read.map(|read| read + 1);
+            if !no_ack {
+                group.assign(entry.id, consumer, now, 1);
+            }
+        }
+        if !entries.is_empty() {
+            group.consumer(consumer, now).active_at = Some(now);
+        }
+        Some(entries)
+    }
+
+    // XREADGROUP with an id: the consumer's own pending entries after `after`; entries
+    // deleted since their delivery come back without fields
+    pub fn pending_history(
+        &mut self,
+        group_name: &[u8],
+        consumer: &[u8],
+        after: StreamId,
+        count: Option<usize>,
+        now: u64,
+    ) -> Option<Vec<Delivered>> {
+        let group = self.groups.get_mut(group_name)?;
+        let ids: Vec<StreamId> = group
+            .consumer(consumer, now)
+            .pending
+            .range(after..)
+            .filter(|&&id| id > after)
+            .take(count.unwrap_or(usize::MAX))
+            .copied()
+            .collect();
+        Some(ids.into_iter().map(|id| (id, self.get(id).map(|entry| entry.fields.clone()))).collect())
+    }
+
+    // XCLAIM; entries deleted from the stream are dropped from the pending list instead
+    pub fn claim(
+        &mut self,
+        group_name: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+        now: u64,
+    ) -> Option<Vec<Delivered>> {
+        let group = self.groups.get_mut(group_name)?;
+        group.consumer(consumer, now);
+        let mut claimed = Vec::new();
+
+        for &id in ids {
+            let exists = self.nodes.range(..=id).next_back().is_some_and(|(_, node)| {
+                node.binary_search_by_key(&id, |entry| entry.id).is_ok()
+            });
+            let pending = match group.pending.get(&id) {
+                Some(pending) => pending,
+                None if options.force && exists => {
+                    group.assign(id, consumer, now, 0);
+                    &group.pending[&id]
+                }
+                None => continue,
+            };
+            if !exists {
+                group.unassign(id);
+                continue;
+            }
+            if min_idle > 0 && now.saturating_sub(pending.delivered_at) < min_idle {
+                continue;
+            }
+
+            let delivery_count = match options.retry_count {
+                Some(count) => count,
+                None if options.just_id => pending.delivery_count,
+                None => pending.delivery_count + 1,
+            };
+            let delivered_at = options.delivered_at.or(options.idle.map(|idle| now.saturating_sub(idle)));
+            group.assign(id, consumer, delivered_at.unwrap_or(now), delivery_count);
+            claimed.push(id);
+        }
+
+        if let Some(last_id) = options.last_id {
+            group.last_delivered = group.last_delivered.max(last_id);
+        }
+        if !claimed.is_empty() {
+            group.consumer(consumer, now).active_at = Some(now);
+        }
+        Some(self.with_fields(claimed, options.just_id))
+    }
+
+    // XAUTOCLAIM: scans the pending list from `start`, claiming entries idle for at least
+    // `min_idle`; returns the cursor to continue from (0-0 once done), what was claimed,
+    // and the ids that were dropped because their entries are gone
+    #[allow(clippy::too_many_arguments)]
+    pub fn auto_claim(
+        &mut self,
+        group_name: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+        now: u64,
+    ) -> Option<(StreamId, Vec<Delivered>, Vec<StreamId>)> {
+        let group = self.groups.get_mut(group_name)?;
+        group.consumer(consumer, now);
+
+        // like redis, look at no more than ten times COUNT pending entries per call
+        let mut attempts = count.saturating_mul(10);
+        let mut claimed = Vec::new();
+        let mut deleted = Vec::new();
+        let mut cursor = StreamId::MIN;
+        let candidates: Vec<(StreamId, u64, u64)> = group
+            .pending
+            .range(start..)
+            .map(|(&id, pending)| (id, pending.delivered_at, pending.delivery_count))
+            .collect();
+
+        for (id, delivered_at, delivery_count) in candidates {
+            if attempts == 0 || claimed.len() >= count {
+                cursor = id;
+                break;
+            }
+            attempts -= 1;
+
+            let exists = self.nodes.range(..=id).next_back().is_some_and(|(_, node)| {
+                node.binary_search_by_key(&id, |entry| entry.id).is_ok()
+            });
+            if !exists {
+                group.unassign(id);
+                deleted.push(id);
+                continue;
+            }
+            if now.saturating_sub(delivered_at) < min_idle {
+                continue;
+            }
+            let delivery_count = if just_id { delivery_count } else { delivery_count + 1 };
+            group.assign(id, consumer, now, delivery_count);
+            claimed.push(id);
+        }
+
+        if !claimed.is_empty() {
+            group.consumer(consumer, now).active_at = Some(now);
+        }
+        Some((cursor, self.with_fields(claimed, just_id), deleted))
+    }
+
+    fn with_fields(&self, ids: Vec<StreamId>, just_id: bool) -> Vec<Delivered> {
+        ids.into_iter()
+            .map(|id| {
+                let fields = (!just_id).then(|| self.get(id).map(|entry| entry.fields.clone())).flatten();
+                (id, fields)
+            })
+            .collect()
+    }
+}
+
+impl DataType for Stream {
+    fn type_name(&self) -> &str {
+        "stream"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(ms: u64, seq: u64) -> StreamId {
+        StreamId::new(ms, seq)
+    }
+
+    fn stream(count: u64) -> Stream {
+        let mut stream = Stream::new();
+        for ms in 1..=count {
+            stream.push(id(ms, 0), vec![(b"n".to_vec(), ms.to_string().into_bytes())]);
+        }
+        stream
+    }
+
+    fn ids(entries: Vec<&StreamEntry>) -> Vec<StreamId> {
+        entries.into_iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn test_ids() {
+        assert_eq!(StreamId::parse(b"5-3", 0), Some(id(5, 3)));
+        assert_eq!(StreamId::parse(b"5", u64::MAX), Some(id(5, u64::MAX)));
+        assert_eq!(StreamId::parse(b"5-", 0), None);
+        assert_eq!(StreamId::parse(b"-1", 0), None);
+        assert_eq!(StreamId::parse(b"+1-1", 0), None);
+        assert_eq!(id(1, u64::MAX).next(), Some(id(2, 0)));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(id(2, 0).prev(), Some(id(1, u64::MAX)));
+        assert_eq!(id(3, 4).to_string(), "3-4");
+
+        let mut stream = Stream::new();
+        assert_eq!(stream.next_id(XAddId::Explicit(StreamId::MIN), 10), None);
+        assert_eq!(stream.next_id(XAddId::AutoSeq(0), 10), Some(id(0, 1)));
+        stream.push(id(10, 5), vec![]);
+        assert_eq!(stream.next_id(XAddId::Auto, 7), Some(id(10, 6)));
+        assert_eq!(stream.next_id(XAddId::Auto, 12), Some(id(12, 0)));
+        assert_eq!(stream.next_id(XAddId::AutoSeq(10), 0), Some(id(10, 6)));
+        assert_eq!(stream.next_id(XAddId::AutoSeq(9), 0), None);
+        assert_eq!(stream.next_id(XAddId::Explicit(id(10, 5)), 0), None);
+    }
+
+    #[test]
+    fn test_ranges_across_nodes() {
+        let mut stream = stream(250);
+        assert_eq!(stream.node_count(), 3);
+        assert_eq!(ids(stream.range(id(99, 0), id(102, 0), None, false)), [id(99, 0), id(100, 0), id(101, 0), id(102, 0)]);
+        assert_eq!(ids(stream.range(StreamId::MIN, StreamId::MAX, Some(2), true)), [id(250, 0), id(249, 0)]);
+        assert_eq!(stream.get(id(150, 0)).unwrap().fields[0].1, b"150");
+
+        assert_eq!(stream.delete(&[id(101, 0), id(101, 0), id(999, 0)]), 1);
+        assert_eq!(stream.len(), 249);
+        assert_eq!(stream.max_deleted_id(), id(101, 0));
+        assert_eq!(ids(stream.range(id(100, 0), id(102, 0), None, false)), [id(100, 0), id(102, 0)]);
+        // the node keeps its key after losing its first entry
+        assert_eq!(ids(stream.range(id(101, 0), id(102, 0), None, false)), [id(102, 0)]);
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut stream = stream(250);
+        let approx = StreamTrim { strategy: TrimStrategy::MaxLen(120), approx: true, limit: None };
+        // only the first node can go whole
+        assert_eq!(stream.trim(&approx), 100);
+        assert_eq!(stream.len(), 150);
+
+        let exact = StreamTrim { strategy: TrimStrategy::MaxLen(120), approx: false, limit: None };
+        assert_eq!(stream.trim(&exact), 30);
+        assert_eq!(stream.first_entry().unwrap().id, id(131, 0));
+
+        let min_id = StreamTrim { strategy: TrimStrategy::MinId(id(200, 0)), approx: false, limit: None };
+        assert_eq!(stream.trim(&min_id), 69);
+        assert_eq!(stream.len(), 51);
+        assert_eq!(stream.entries_added(), 250);
+        assert_eq!(stream.memory_usage(), stream.range(StreamId::MIN, StreamId::MAX, None, false).iter().map(|e| e.size()).sum::<usize>());
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut stream = stream(3);
+        assert!(stream.create_group(b"g", ConsumerGroup::new(StreamId::MIN, Some(0))));
+        assert!(!stream.create_group(b"g", ConsumerGroup::default()));
+
+        let read = stream.deliver_new(b"g", b"alice", Some(2), false, 1000).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(stream.lag(stream.group(b"g").unwrap()), Some(1));
+        assert_eq!(stream.deliver_new(b"g", b"bob", None, false, 1000).unwrap().len(), 1);
+        assert!(stream.deliver_new(b"g", b"bob", None, false, 1000).unwrap().is_empty());
+        assert!(stream.deliver_new(b"nope", b"bob", None, false, 1000).is_none());
+
+        // history only covers the consumer's own entries, deleted ones without fields
+        stream.delete(&[id(1, 0)]);
+        let history = stream.pending_history(b"g", b"alice", StreamId::MIN, None, 1000).unwrap();
+        assert_eq!(history, vec![(id(1, 0), None), (id(2, 0), Some(vec![(b"n".to_vec(), b"2".to_vec())]))]);
+
+        // claiming needs the idle time, and drops entries that no longer exist
+        let claimed = stream.claim(b"g", b"bob", 500, &[id(1, 0), id(2, 0)], &ClaimOptions::default(), 1200).unwrap();
+        assert!(claimed.is_empty());
+        let group = stream.group(b"g").unwrap();
+        assert!(!group.pending.contains_key(&id(1, 0)));
+
+        let claimed = stream.claim(b"g", b"bob", 500, &[id(2, 0)], &ClaimOptions::default(), 2000).unwrap();
+        assert_eq!(claimed.len(), 1);
+        let group = stream.group(b"g").unwrap();
+        assert_eq!(group.pending[&id(2, 0)].consumer, b"bob");
+        assert_eq!(group.pending[&id(2, 0)].delivery_count, 2);
+        assert!(group.consumers[&b"alice".to_vec()].pending.is_empty());
+
+        let group = stream.group_mut(b"g").unwrap();
+        assert_eq!(group.ack(&[id(2, 0), id(3, 0), id(3, 0)]), 2);
+        assert!(group.pending.is_empty());
+        assert_eq!(group.delete_consumer(b"bob"), Some(0));
+    }
+
+    #[test]
+    fn test_auto_claim() {
+        let mut stream = stream(5);
+        stream.create_group(b"g", ConsumerGroup::default());
+        stream.deliver_new(b"g", b"alice", None, false, 0);
+        stream.delete(&[id(2, 0)]);
+
+        let (cursor, claimed, deleted) = stream.auto_claim(b"g", b"bob", 100, StreamId::MIN, 2, true, 1000).unwrap();
+        assert_eq!(cursor, id(4, 0));
+        assert_eq!(claimed, vec![(id(1, 0), None), (id(3, 0), None)]);
+        assert_eq!(deleted, vec![id(2, 0)]);
+
+        let (cursor, claimed, _) = stream.auto_claim(b"g", b"bob", 100, cursor, 10, false, 1000).unwrap();
+        assert_eq!(cursor, StreamId::MIN);
+        assert_eq!(claimed.len(), 2);
+        assert_eq!(stream.group(b"g").unwrap().consumers[&b"bob".to_vec()].pending.len(), 4);
+    }
+}
@@ -2,19 +2,19 @@ use super::DataType;
 
 #[derive(Debug, Clone)]
 pub struct RedisString {
-    value: String,
+    value: Vec<u8>,
 }
 
 impl RedisString {
-    pub fn new(value: String) -> Self {
+    pub fn new(value: Vec<u8>) -> Self {
         Self { value }
     }
 
-    pub fn get(&self) -> &str {
+    pub fn get(&self) -> &[u8] {
         &self.value
     }
 
-    pub fn set(&mut self, value: String) {
+    pub fn set(&mut self, value: Vec<u8>) {
         self.value = value;
     }
 }
@@ -35,41 +35,41 @@ mod tests {
 
     #[test]
     fn test_new() {
-        let s = RedisString::new("hello".to_string());
-        assert_eq!(s.get(), "hello");
+        let s = RedisString::new(b"hello".to_vec());
+        assert_eq!(s.get(), b"hello");
     }
 
     #[test]
     fn test_get() {
-        let s = RedisString::new("test value".to_string());
-        assert_eq!(s.get(), "test value");
+        let s = RedisString::new(b"test value".to_vec());
+        assert_eq!(s.get(), b"test value");
     }
 
     #[test]
     fn test_set() {
-        let mut s = RedisString::new("initial".to_string());
-        s.set("updated".to_string());
-        assert_eq!(s.get(), "updated");
+        let mut s = RedisString::new(b"initial".to_vec());
+        s.set(b"updated".to_vec());
+        assert_eq!(s.get(), b"updated");
     }
 
     #[test]
     fn test_type_name() {
-        let s = RedisString::new("test".to_string());
+        let s = RedisString::new(b"test".to_vec());
         assert_eq!(s.type_name(), "string");
     }
 
     #[test]
     fn test_memory_usage() {
-        let s = RedisString::new("hello".to_string());
+        let s = RedisString::new(b"hello".to_vec());
         assert_eq!(s.memory_usage(), 5);
 
-        let empty = RedisString::new("".to_string());
+        let empty = RedisString::new(b"".to_vec());
         assert_eq!(empty.memory_usage(), 0);
     }
 
     #[test]
     fn test_clone() {
-        let s1 = RedisString::new("original".to_string());
+        let s1 = RedisString::new(b"original".to_vec());
         let s2 = s1.clone();
         assert_eq!(s1.get(), s2.get());
     }
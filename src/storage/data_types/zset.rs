@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use super::skiplist::SkipList;
+use super::{scan_by_hash, DataType};
+
+// a member and its score, borrowed from the sorted set
+pub type ScoredRef<'a> = (&'a [u8], f64);
+
+// one end of a score range; `(1.5` is exclusive, `1.5` inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: ScoreBound,
+    pub max: ScoreBound,
+}
+
+impl ScoreRange {
+    fn above_min(&self, score: f64) -> bool {
+        if self.min.exclusive { score > self.min.value } else { score >= self.min.value }
+    }
+
+    fn below_max(&self, score: f64) -> bool {
+        if self.max.exclusive { score < self.max.value } else { score <= self.max.value }
+    }
+}
+
+// one end of a lexicographic range: `-` and `+` are the extremes, `[a` and `(a` include
+// and exclude `a`
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl LexRange {
+    fn above_min(&self, member: &[u8]) -> bool {
+        match &self.min {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(value) => member >= value.as_slice(),
+            LexBound::Exclusive(value) => member > value.as_slice(),
+        }
+    }
+
+    fn below_max(&self, member: &[u8]) -> bool {
+        match &self.max {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(value) => member <= value.as_slice(),
+            LexBound::Exclusive(value) => member < value.as_slice(),
+        }
+    }
+}
+
+// the skiplist keeps members ordered for ranges and ranks, the map answers score
+// lookups in O(1); like redis both are always kept in step
+#[derive(Debug, Clone, Default)]
+pub struct RedisZSet {
+    scores: HashMap<Vec<u8>, f64>,
+    list: SkipList,
+    // member bytes plus a score per member
+    bytes: usize,
+}
+
+impl RedisZSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the name OBJECT ENCODING reports
+    pub fn encoding(&self) -> &'static str {
+        "skiplist"
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    // adds or rescores `member`; returns the previous score
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        match self.scores.get_mut(&member) {
+            Some(current) => {
+                let previous = *current;
+                if previous != score {
+                    *current = score;
+                    self.list.remove(previous, &member);
+                    self.list.insert(score, member);
+                }
+                Some(previous)
+            }
+            None => {
+                self.bytes += member.len() + std::mem::size_of::<f64>();
+                self.list.insert(score, member.clone());
+                self.scores.insert(member, score);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.list.remove(score, member);
+                self.bytes -= member.len() + std::mem::size_of::<f64>();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 0-based position from the lowest score, or from the highest with `rev`
+    pub fn rank(&self, member: &[u8], rev: bool) -> Option<usize> {
+        let score = self.score(member)?;
+        let rank = self.list.rank(score, member)?;
+        Some(if rev { self.len() - 1 - rank } else { rank })
+    }
+
+    // nodes from `start` on, walking towards higher scores or, with `rev`, lower ones
+    fn walk(&self, start: Option<usize>, rev: bool) -> impl Iterator<Item = ScoredRef<'_>> + '_ {
+        std::iter::successors(start, move |&node| if rev { self.list.prev(node) } else { self.list.next(node) })
+            .map(|node| self.list.get(node))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ScoredRef<'_>> + '_ {
+        self.walk(self.list.first(), false)
+    }
+
+    // ranks `start..=stop`, already clamped to the set, counted from the end with `rev`
+    pub fn range_by_rank(&self, start: usize, stop: usize, rev: bool) -> Vec<ScoredRef<'_>> {
+        if start > stop || start >= self.len() {
+            return Vec::new();
+        }
+        let first = if rev { self.len() - 1 - start } else { start };
+        self.walk(self.list.at_rank(first), rev).take(stop - start + 1).collect()
+    }
+
+    fn first_in(&self, range: &ScoreRange) -> Option<usize> {
+        self.list.first_where(|score, _| !range.above_min(score), |score, _| range.below_max(score))
+    }
+
+    fn last_in(&self, range: &ScoreRange) -> Option<usize> {
+        self.list.last_where(|score, _| range.below_max(score), |score, _| range.above_min(score))
+    }
+
+    pub fn count(&self, range: &ScoreRange) -> usize {
+        match (self.first_in(range), self.last_in(range)) {
+            (Some(first), Some(last)) => {
+                let (first_member, first_score) = self.list.get(first);
+                let (last_member, last_score) = self.list.get(last);
+                let first_rank = self.list.rank(first_score, first_member).unwrap_or(0);
+                let last_rank = self.list.rank(last_score, last_member).unwrap_or(0);
+                last_rank + 1 - first_rank
+            }
+            _ => 0,
+        }
+    }
+
+    // members within `range`, skipping `offset` of them and returning at most `limit`
+    pub fn range_by_score(&self, range: &ScoreRange, rev: bool, offset: usize, limit: Option<usize>) -> Vec<ScoredRef<'_>> {
+        let start = if rev { self.last_in(range) } else { self.first_in(range) };
+        self.walk(start, rev)
+            .take_while(|&(_, score)| if rev { range.above_min(score) } else { range.below_max(score) })
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    // like range_by_score, by member; only meaningful when all scores are equal
+    pub fn range_by_lex(&self, range: &LexRange, rev: bool, offset: usize, limit: Option<usize>) -> Vec<ScoredRef<'_>> {
+        let start = if rev {
+            self.list.last_where(|_, member| range.below_max(member), |_, member| range.above_min(member))
+        } else {
+            self.list.first_where(|_, member| !range.above_min(member), |_, member| range.below_max(member))
+        };
+        self.walk(start, rev)
+            .take_while(|&(member, _)| if rev { range.above_min(member) } else { range.below_max(member) })
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    // removes up to `count` members from the low end, or the high end with `max`
+    pub fn pop(&mut self, max: bool, count: usize) -> Vec<(Vec<u8>, f64)> {
+        let mut popped = Vec::with_capacity(count.min(self.len()));
+        while popped.len() < count {
+            let node = if max { self.list.last() } else { self.list.first() };
+            let (member, score) = match node {
+                Some(node) => self.list.get(node),
+                None => break,
+            };
+            let member = member.to_vec();
+            self.remove(&member);
+            popped.push((member, score));
+        }
+        popped
+    }
+
+    // one ZSCAN step over the member map
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<ScoredRef<'_>>) {
+        scan_by_hash(
+            self.scores.iter().map(|(member, &score)| (member.as_slice(), (member.as_slice(), score))),
+            cursor,
+            count,
+        )
+    }
+}
+
+impl DataType for RedisZSet {
+    fn type_name(&self) -> &str {
+        "zset"
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zset(items: &[(&str, f64)]) -> RedisZSet {
+        let mut zset = RedisZSet::new();
+        for (member, score) in items {
+            zset.insert(member.as_bytes().to_vec(), *score);
+        }
+        zset
+    }
+
+    fn names(items: Vec<ScoredRef>) -> Vec<String> {
+        items.into_iter().map(|(member, _)| String::from_utf8_lossy(member).into_owned()).collect()
+    }
+
+    fn range(min: f64, min_exclusive: bool, max: f64, max_exclusive: bool) -> ScoreRange {
+        ScoreRange {
+            min: ScoreBound { value: min, exclusive: min_exclusive },
+            max: ScoreBound { value: max, exclusive: max_exclusive },
+        }
+    }
+
+    #[test]
+    fn test_insert_rescore_remove() {
+        let mut set = zset(&[("a", 1.0), ("b", 2.0)]);
+        assert_eq!(set.insert(b"a".to_vec(), 3.0), Some(1.0));
+        assert_eq!(set.rank(b"a", false), Some(1));
+        assert_eq!(set.rank(b"a", true), Some(0));
+        assert_eq!(set.memory_usage(), 18);
+
+        assert!(set.remove(b"a"));
+        assert!(!set.remove(b"a"));
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.memory_usage(), 9);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let set = zset(&[("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)]);
+        assert_eq!(names(set.range_by_rank(1, 2, false)), ["b", "c"]);
+        assert_eq!(names(set.range_by_rank(0, 0, true)), ["d"]);
+        assert_eq!(names(set.range_by_score(&range(1.0, true, 3.0, false), false, 0, None)), ["b", "c"]);
+        assert_eq!(names(set.range_by_score(&range(1.0, false, 4.0, false), true, 1, Some(2))), ["c", "b"]);
+        assert_eq!(set.count(&range(2.0, false, f64::INFINITY, false)), 3);
+        assert_eq!(set.count(&range(5.0, false, 9.0, false)), 0);
+
+        let lex = LexRange { min: LexBound::Exclusive(b"a".to_vec()), max: LexBound::Inclusive(b"c".to_vec()) };
+        let same = zset(&[("a", 0.0), ("b", 0.0), ("c", 0.0), ("d", 0.0)]);
+        assert_eq!(names(same.range_by_lex(&lex, false, 0, None)), ["b", "c"]);
+        assert_eq!(names(same.range_by_lex(&lex, true, 0, None)), ["c", "b"]);
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut set = zset(&[("a", 1.0), ("b", 2.0), ("c", 3.0)]);
+        assert_eq!(set.pop(true, 2), vec![(b"c".to_vec(), 3.0), (b"b".to_vec(), 2.0)]);
+        assert_eq!(set.pop(false, 5), vec![(b"a".to_vec(), 1.0)]);
+        assert!(set.is_empty());
+    }
+}
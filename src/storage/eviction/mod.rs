@@ -0,0 +1,276 @@
+// maxmemory eviction: the redis policies, approximated by sampling keys into a small pool
+// of the best candidates seen so far
+use rand::Rng;
+use super::sample_index::SampleIndex;
+
+pub const DEFAULT_SAMPLES: usize = 5;
+// redis' EVPOOL_SIZE
+const POOL_SIZE: usize = 16;
+// like redis, the access clock is 24 bits of seconds and wraps around every ~194 days
+const LRU_CLOCK_MAX: u64 = (1 << 24) - 1;
+// new keys start a little above zero so they are not the first to go under lfu
+const LFU_INIT_VAL: u8 = 5;
+// the decay clock counts minutes in 16 bits, wrapping every ~45 days
+const LFU_CLOCK_MAX: u64 = u16::MAX as u64;
+pub const DEFAULT_LFU_LOG_FACTOR: u32 = 10;
+pub const DEFAULT_LFU_DECAY_TIME: u64 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    VolatileLru,
+    AllKeysLfu,
+    VolatileLfu,
+    AllKeysRandom,
+    VolatileRandom,
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    // the maxmemory-policy names
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "noeviction" => Some(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Some(EvictionPolicy::AllKeysLru),
+            "volatile-lru" => Some(EvictionPolicy::VolatileLru),
+            "allkeys-lfu" => Some(EvictionPolicy::AllKeysLfu),
+            "volatile-lfu" => Some(EvictionPolicy::VolatileLfu),
+            "allkeys-random" => Some(EvictionPolicy::AllKeysRandom),
+            "volatile-random" => Some(EvictionPolicy::VolatileRandom),
+            "volatile-ttl" => Some(EvictionPolicy::VolatileTtl),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::AllKeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileRandom => "volatile-random",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+        }
+    }
+
+    // only keys with a ttl may be evicted
+    pub fn volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+
+    // access frequency is what OBJECT FREQ reports under these, idle time under the rest
+    pub fn lfu(&self) -> bool {
+        matches!(self, EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu)
+    }
+
+    // random policies skip the pool and evict whatever they sample
+    pub fn random(&self) -> bool {
+        matches!(self, EvictionPolicy::AllKeysRandom | EvictionPolicy::VolatileRandom)
+    }
+}
+
+// maxmemory-policy and maxmemory-samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionConfig {
+    pub policy: EvictionPolicy,
+    // keys sampled per eviction; more is closer to the exact policy, and slower
+    pub samples: usize,
+    pub lfu: LfuConfig,
+}
+
+impl Default for EvictionConfig {
+    fn default() -> Self {
+        Self { policy: EvictionPolicy::default(), samples: DEFAULT_SAMPLES, lfu: LfuConfig::default() }
+    }
+}
+
+// lfu-log-factor and lfu-decay-time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LfuConfig {
+    // how many hits it takes to saturate the counter; 10 is about a million
+    pub log_factor: u32,
+    // minutes for the counter to lose one point while the key is idle; 0 never decays
+    pub decay_time: u64,
+}
+
+impl Default for LfuConfig {
+    fn default() -> Self {
+        Self { log_factor: DEFAULT_LFU_LOG_FACTOR, decay_time: DEFAULT_LFU_DECAY_TIME }
+    }
+}
+
+// how recently and how often a key was used, kept on every entry: the last access in
+// seconds, and a morris counter with the minute it last decayed, 7 bytes in all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    clock: u32,
+    decayed_at: u16,
+    counter: u8,
+}
+
+fn lru_clock(now_secs: u64) -> u32 {
+    (now_secs & LRU_CLOCK_MAX) as u32
+}
+
+fn lfu_clock(now_secs: u64) -> u16 {
+    ((now_secs / 60) & LFU_CLOCK_MAX) as u16
+}
+
+// bumps the counter with probability 1 / ((counter - LFU_INIT_VAL) * log_factor + 1), so
+// it grows logarithmically with the number of hits
+fn log_incr(counter: u8, log_factor: u32) -> u8 {
+    if counter == u8::MAX {
+        return counter;
+    }
+    let base = counter.saturating_sub(LFU_INIT_VAL) as f64;
+    let p = 1.0 / (base * log_factor as f64 + 1.0);
+    if rand::thread_rng().gen::<f64>() < p { counter + 1 } else { counter }
+}
+
+impl Access {
+    pub fn new(now_secs: u64) -> Self {
+        Self { clock: lru_clock(now_secs), decayed_at: lfu_clock(now_secs), counter: LFU_INIT_VAL }
+    }
+
+    // records a read or write of the key
+    pub fn touch(&mut self, now_secs: u64, lfu: LfuConfig) {
+        self.clock = lru_clock(now_secs);
+        self.counter = log_incr(self.frequency(now_secs, lfu), lfu.log_factor);
+        self.decayed_at = lfu_clock(now_secs);
+    }
+
+    // seconds since the last access, allowing for one wrap of the clock
+    pub fn idle_secs(&self, now_secs: u64) -> u64 {
+        let now = lru_clock(now_secs) as u64;
+        (now + LRU_CLOCK_MAX + 1 - self.clock as u64) & LRU_CLOCK_MAX
+    }
+
+    // the counter after decaying it by one for every `decay_time` minutes since it last
+    // changed; nothing is written, so reading it is not an access
+    pub fn frequency(&self, now_secs: u64, lfu: LfuConfig) -> u8 {
+        if lfu.decay_time == 0 {
+            return self.counter;
+        }
+        let now = lfu_clock(now_secs) as u64;
+        let elapsed = (now + LFU_CLOCK_MAX + 1 - self.decayed_at as u64) & LFU_CLOCK_MAX;
+        let periods = elapsed / lfu.decay_time;
+        self.counter.saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+}
+
+// every key once, sampled by the allkeys-* policies
+pub type KeyIndex = SampleIndex<()>;
+
+// the best eviction candidates sampled so far, ordered by how idle they are
+#[derive(Debug, Default)]
+pub struct EvictionPool {
+    // ascending, so the best candidate is last
+    entries: Vec<(u64, Vec<u8>)>,
+}
+
+impl EvictionPool {
+    // keeps `key` when it beats the worst candidate or the pool still has room
+    pub fn offer(&mut self, key: &[u8], idle: u64) {
+        if let Some(existing) = self.entries.iter().position(|(_, candidate)| candidate == key) {
+            self.entries.remove(existing);
+        }
+        if self.entries.len() == POOL_SIZE && idle <= self.entries[0].0 {
+            return;
+        }
+        let at = self.entries.partition_point(|&(candidate, _)| candidate < idle);
+        self.entries.insert(at, (idle, key.to_vec()));
+        if self.entries.len() > POOL_SIZE {
+            self.entries.remove(0);
+        }
+    }
+
+    // the most idle candidate; it may have changed or gone since it was sampled
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.entries.pop().map(|(_, key)| key)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_index() {
+        let mut index = KeyIndex::default();
+        for key in [&b"a"[..], b"b", b"c", b"a"] {
+            index.insert(key, ());
+        }
+        assert_eq!(index.len(), 3);
+        index.remove(b"a");
+        index.remove(b"missing");
+        let mut sampled: Vec<&[u8]> = index.sample(&mut rand::thread_rng(), 10).map(|(key, _)| key).collect();
+        sampled.sort();
+        assert_eq!(sampled, [&b"b"[..], b"c"]);
+        index.remove(b"c");
+        index.remove(b"b");
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_pool_keeps_the_most_idle() {
+        let mut pool = EvictionPool::default();
+        for i in 0..40u64 {
+            pool.offer(format!("k{}", i).as_bytes(), i % 20);
+        }
+        pool.offer(b"k5", 100);
+        assert_eq!(pool.entries.len(), POOL_SIZE);
+        assert_eq!(pool.pop(), Some(b"k5".to_vec()));
+        assert!(pool.entries.iter().all(|&(idle, _)| idle >= 4));
+    }
+
+    #[test]
+    fn test_access_clock() {
+        let lfu = LfuConfig::default();
+        let mut access = Access::new(100);
+        assert_eq!(access.idle_secs(160), 60);
+        access.touch(LRU_CLOCK_MAX, lfu);
+        assert_eq!(access.idle_secs(LRU_CLOCK_MAX + 11), 11);
+        assert_eq!(EvictionPolicy::parse("ALLKEYS-LFU"), Some(EvictionPolicy::AllKeysLfu));
+        assert!(EvictionPolicy::VolatileTtl.volatile() && !EvictionPolicy::AllKeysLru.volatile());
+    }
+
+    #[test]
+    fn test_morris_counter() {
+        // a log factor of 0 counts every hit
+        let exact = LfuConfig { log_factor: 0, decay_time: 0 };
+        let mut access = Access::new(0);
+        for _ in 0..10 {
+            access.touch(0, exact);
+        }
+        assert_eq!(access.frequency(0, exact), LFU_INIT_VAL + 10);
+
+        // the default factor needs far more hits for the same count
+        let lfu = LfuConfig::default();
+        let mut access = Access::new(0);
+        for _ in 0..1000 {
+            access.touch(0, lfu);
+        }
+        let counter = access.frequency(0, lfu);
+        assert!(counter > LFU_INIT_VAL + 5 && counter < 100, "counter {}", counter);
+
+        // one point lost per idle minute, never below zero
+        assert_eq!(access.frequency(5 * 60, lfu), counter - 5);
+        assert_eq!(access.frequency(1000 * 60, lfu), 0);
+        assert_eq!(access.frequency(1000 * 60, LfuConfig { decay_time: 0, ..lfu }), counter);
+        assert_eq!(access.frequency(1000 * 60, LfuConfig { decay_time: 500, ..lfu }), counter - 2);
+    }
+}
@@ -1 +1,32 @@
-// TODO: modularize
\ No newline at end of file
+// the volatile keys, those with a ttl, kept apart from the keyspace so active expiry and
+// the volatile-* eviction policies only ever look at keys that can expire
+use std::time::SystemTime;
+use super::sample_index::SampleIndex;
+
+// each key with its deadline, so sampling never needs to touch the keyspace
+pub type VolatileIndex = SampleIndex<SystemTime>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_volatile_index() {
+        let now = SystemTime::now();
+        let mut index = VolatileIndex::default();
+        index.insert(b"a", now);
+        index.insert(b"b", now);
+        index.update(b"a", Some(now + Duration::from_secs(1)));
+        index.update(b"c", None);
+        assert_eq!(index.len(), 2);
+
+        let mut sampled: Vec<(&[u8], SystemTime)> = index.sample(&mut rand::thread_rng(), 5).collect();
+        sampled.sort();
+        assert_eq!(sampled, [(&b"a"[..], now + Duration::from_secs(1)), (&b"b"[..], now)]);
+
+        index.update(b"a", None);
+        index.remove(b"b");
+        assert!(index.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+use super::data_types::geohash;
+use super::memory::MemoryStorage;
+use super::{GeoOrigin, GeoSearch, GeoSort, RedisZSet, ScoreBound, ScoreRange};
+use crate::utils::error::{RedisError, Result};
+
+// a member GEOSEARCH found
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: Vec<u8>,
+    // from the search origin, in meters
+    pub dist: f64,
+    pub score: f64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+// geo indexes are sorted sets scored by geohash; GEOADD itself is a ZADD
+pub trait GeoStorage {
+    // the longitude and latitude of each member
+    fn geopos(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<Option<(f64, f64)>>>;
+    // in meters, None unless both members exist
+    fn geodist(&mut self, key: &[u8], from: &[u8], to: &[u8]) -> Result<Option<f64>>;
+    fn geohash(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<Option<String>>>;
+    fn geosearch(&mut self, key: &[u8], query: &GeoSearch) -> Result<Vec<GeoMatch>>;
+    // overwrites `destination` with the matches, or deletes it when there are none;
+    // returns the number of matches
+    fn geosearchstore(&mut self, destination: &[u8], source: &[u8], query: &GeoSearch, store_dist: bool)
+        -> Result<usize>;
+}
+
+fn search(zset: &RedisZSet, query: &GeoSearch) -> Result<Vec<GeoMatch>> {
+    let center = match &query.origin {
+        GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+        GeoOrigin::Member(member) => geohash::decode(zset.score(member).ok_or_else(|| {
+            RedisError::InvalidArgument("could not decode requested zset member".to_string())
+        })?),
+    };
+
+    let limit = query.count.unwrap_or(usize::MAX);
+    let mut matches = Vec::new();
+    'ranges: for (min, max) in geohash::search_ranges(query.shape, center) {
+        let range = ScoreRange {
+            min: ScoreBound { value: min, exclusive: false },
+            max: ScoreBound { value: max, exclusive: true },
+        };
+        for (member, score) in zset.range_by_score(&range, false, 0, None) {
+            let (lon, lat) = geohash::decode(score);
+            if let Some(dist) = geohash::within(query.shape, center, (lon, lat)) {
+                matches.push(GeoMatch { member: member.to_vec(), dist, score, lon, lat });
+                if query.any && matches.len() == limit {
+                    break 'ranges;
+                }
+            }
+        }
+    }
+
+    // a COUNT without ANY wants the nearest matches
+    match query.sort.or(query.count.filter(|_| !query.any).map(|_| GeoSort::Asc)) {
+        Some(GeoSort::Asc) => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+        Some(GeoSort::Desc) => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+        None => {}
+    }
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+impl GeoStorage for MemoryStorage {
+    fn geopos(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<Option<(f64, f64)>>> {
+        let zset = self.redis_zset(key)?;
+        Ok(members
+            .iter()
+            .map(|member| zset.and_then(|zset| zset.score(member)).map(geohash::decode))
+            .collect())
+    }
+
+    fn geodist(&mut self, key: &[u8], from: &[u8], to: &[u8]) -> Result<Option<f64>> {
+        let zset = match self.redis_zset(key)? {
+            Some(zset) => zset,
+            None => return Ok(None),
+        };
+        let (from, to) = match (zset.score(from), zset.score(to)) {
+            (Some(from), Some(to)) => (geohash::decode(from), geohash::decode(to)),
+            _ => return Ok(None),
+        };
+        Ok(Some(geohash::distance(from.0, from.1, to.0, to.1)))
+    }
+
+    fn geohash(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<Option<String>>> {
+        let zset = self.redis_zset(key)?;
+        Ok(members
+            .iter()
+            .map(|member| zset.and_then(|zset| zset.score(member)).map(geohash::to_string))
+            .collect())
+    }
+
+    fn geosearch(&mut self, key: &[u8], query: &GeoSearch) -> Result<Vec<GeoMatch>> {
+        match self.redis_zset(key)? {
+            Some(zset) => search(zset, query),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn geosearchstore(&mut self, destination: &[u8], source: &[u8], query: &GeoSearch, store_dist: bool)
+        -> Result<usize> {
+        let mut stored = RedisZSet::new();
+        for found in self.geosearch(source, query)? {
+            let score = if store_dist { found.dist / query.unit } else { found.score };
+            stored.insert(found.member, score);
+        }
+        self.store_zset(destination, stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{GeoShape, Storage, StorageValue, ZAddOptions, ZSetStorage};
+
+    fn sicily() -> MemoryStorage {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        let members = [(13.361389, 38.115556, "Palermo"), (15.087269, 37.502669, "Catania"), (12.758489, 38.788135, "edge1")]
+            .iter()
+            .map(|&(lon, lat, name)| (geohash::encode(lon, lat), name.as_bytes().to_vec()))
+            .collect();
+        storage.zadd(b"Sicily", members, ZAddOptions::default()).unwrap();
+        storage
+    }
+
+    fn query(origin: GeoOrigin, shape: GeoShape) -> GeoSearch {
+        GeoSearch {
+            origin,
+            shape,
+            unit: 1000.0,
+            sort: None,
+            count: None,
+            any: false,
+            with_coord: false,
+            with_dist: false,
+            with_hash: false,
+        }
+    }
+
+    fn names(matches: &[GeoMatch]) -> Vec<&str> {
+        matches.iter().map(|found| std::str::from_utf8(&found.member).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_pos_dist_hash() {
+        let mut storage = sicily();
+        let dist = storage.geodist(b"Sicily", b"Palermo", b"Catania").unwrap().unwrap();
+        assert!((dist - 166274.1516).abs() < 1e-3);
+        assert_eq!(storage.geodist(b"Sicily", b"Palermo", b"Rome").unwrap(), None);
+        let hashes = storage.geohash(b"Sicily", &[b"Palermo".to_vec(), b"Rome".to_vec()]).unwrap();
+        assert_eq!(hashes, vec![Some("sqc8b49rny0".to_string()), None]);
+        let positions = storage.geopos(b"missing", &[b"Palermo".to_vec()]).unwrap();
+        assert_eq!(positions, vec![None]);
+
+        Storage::set(&mut storage, b"s".to_vec(), StorageValue::String(b"x".to_vec()), None).unwrap();
+        assert!(matches!(storage.geopos(b"s", &[b"a".to_vec()]), Err(RedisError::WrongType)));
+    }
+
+    #[test]
+    fn test_search() {
+        let mut storage = sicily();
+        let mut by_radius = query(GeoOrigin::LonLat(15.0, 37.0), GeoShape::Radius(200_000.0));
+        by_radius.sort = Some(GeoSort::Asc);
+        assert_eq!(names(&storage.geosearch(b"Sicily", &by_radius).unwrap()), ["Catania", "Palermo"]);
+        by_radius.sort = Some(GeoSort::Desc);
+        by_radius.count = Some(1);
+        assert_eq!(names(&storage.geosearch(b"Sicily", &by_radius).unwrap()), ["Palermo"]);
+
+        let by_box = query(GeoOrigin::Member(b"Palermo".to_vec()), GeoShape::Box(400_000.0, 400_000.0));
+        let found = storage.geosearch(b"Sicily", &by_box).unwrap();
+        let mut found = names(&found);
+        found.sort();
+        assert_eq!(found, ["Catania", "Palermo", "edge1"]);
+        let missing = query(GeoOrigin::Member(b"Rome".to_vec()), GeoShape::Radius(1.0));
+        assert!(storage.geosearch(b"Sicily", &missing).unwrap_err().to_string().contains("could not decode"));
+
+        assert_eq!(storage.geosearchstore(b"near", b"Sicily", &by_radius, true).unwrap(), 1);
+        let stored = storage.zscore(b"near", b"Palermo").unwrap().unwrap();
+        assert!((stored - 190.4424).abs() < 1e-3);
+        assert_eq!(storage.geosearchstore(b"near", b"missing", &by_radius, false).unwrap(), 0);
+        assert!(!storage.exists(b"near").unwrap());
+    }
+}
@@ -0,0 +1,305 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use super::memory::MemoryStorage;
+use super::data_types::hash::FieldRef;
+use super::{RedisHash, ScanOptions, StorageValue};
+use crate::utils::error::{RedisError, Result};
+use crate::utils::glob;
+
+type Pair = (Vec<u8>, Vec<u8>);
+
+// hash commands, in the same raw-bytes shape as ListStorage
+pub trait HashStorage {
+    // returns how many of the fields are new
+    fn hset(&mut self, key: &[u8], pairs: Vec<Pair>) -> Result<usize>;
+    fn hsetnx(&mut self, key: &[u8], field: Vec<u8>, value: Vec<u8>) -> Result<bool>;
+    fn hget(&mut self, key: &[u8], field: &[u8]) -> Result<Option<Vec<u8>>>;
+    fn hmget(&mut self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>>;
+    fn hdel(&mut self, key: &[u8], fields: &[Vec<u8>]) -> Result<usize>;
+    fn hexists(&mut self, key: &[u8], field: &[u8]) -> Result<bool>;
+    fn hlen(&mut self, key: &[u8]) -> Result<usize>;
+    fn hkeys(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>>;
+    fn hvals(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>>;
+    fn hgetall(&mut self, key: &[u8]) -> Result<Vec<Pair>>;
+    fn hincrby(&mut self, key: &[u8], field: &[u8], delta: i64) -> Result<i64>;
+    // returns the new value as stored, formatted like redis would
+    fn hincrbyfloat(&mut self, key: &[u8], field: &[u8], delta: f64) -> Result<Vec<u8>>;
+    fn hstrlen(&mut self, key: &[u8], field: &[u8]) -> Result<usize>;
+    // distinct fields for a positive count, possibly repeated ones for a negative count
+    fn hrandfield(&mut self, key: &[u8], count: i64) -> Result<Vec<Pair>>;
+    fn hscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Pair>)>;
+}
+
+fn as_hash(value: &mut StorageValue) -> Result<&mut RedisHash> {
+    match value {
+        StorageValue::Hash(hash) => Ok(hash),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+fn to_pair((field, value): FieldRef) -> Pair {
+    (field.to_vec(), value.to_vec())
+}
+
+impl MemoryStorage {
+    fn hash(&mut self, key: &[u8]) -> Result<Option<&RedisHash>> {
+        match self.value(key) {
+            Some(StorageValue::Hash(hash)) => Ok(Some(hash)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // read-modify-write of a single field, creating the hash when needed
+    fn update_field<T, F>(&mut self, key: &[u8], field: &[u8], f: F) -> Result<T>
+    where
+        F: FnOnce(Option<&[u8]>) -> Result<(T, Vec<u8>)>,
+    {
+        let limits = self.encoding_limits().hash;
+        self.modify_or_insert(key, || StorageValue::Hash(RedisHash::new()), |stored| {
+            let hash = as_hash(stored)?;
+            let (result, value) = f(hash.get(field))?;
+            hash.insert(field.to_vec(), value, limits);
+            Ok(result)
+        })
+    }
+}
+
+impl HashStorage for MemoryStorage {
+    fn hset(&mut self, key: &[u8], pairs: Vec<Pair>) -> Result<usize> {
+        let limits = self.encoding_limits().hash;
+        self.modify_or_insert(key, || StorageValue::Hash(RedisHash::new()), |stored| {
+            let hash = as_hash(stored)?;
+            let mut added = 0;
+            for (field, value) in pairs {
+                added += usize::from(hash.insert(field, value, limits));
+            }
+            Ok(added)
+        })
+    }
+
+    fn hsetnx(&mut self, key: &[u8], field: Vec<u8>, value: Vec<u8>) -> Result<bool> {
+        if self.hexists(key, &field)? {
+            return Ok(false);
+        }
+        self.hset(key, vec![(field, value)])?;
+        Ok(true)
+    }
+
+    fn hget(&mut self, key: &[u8], field: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.hash(key)?.and_then(|hash| hash.get(field)).map(<[u8]>::to_vec))
+    }
+
+    fn hmget(&mut self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        let hash = self.hash(key)?;
+        Ok(fields
+            .iter()
+            .map(|field| hash.and_then(|hash| hash.get(field)).map(<[u8]>::to_vec))
+            .collect())
+    }
+
+    fn hdel(&mut self, key: &[u8], fields: &[Vec<u8>]) -> Result<usize> {
+        Ok(self
+            .modify(key, |stored| {
+                let hash = as_hash(stored)?;
+                Ok(fields.iter().filter(|field| hash.remove(field)).count())
+            })?
+            .unwrap_or(0))
+    }
+
+    fn hexists(&mut self, key: &[u8], field: &[u8]) -> Result<bool> {
+        Ok(self.hash(key)?.is_some_and(|hash| hash.contains(field)))
+    }
+
+    fn hlen(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.hash(key)?.map_or(0, RedisHash::len))
+    }
+
+    fn hkeys(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .hash(key)?
+            .map(|hash| hash.iter().map(|(field, _)| field.to_vec()).collect())
+            .unwrap_or_default())
+    }
+
+    fn hvals(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .hash(key)?
+            .map(|hash| hash.iter().map(|(_, value)| value.to_vec()).collect())
+            .unwrap_or_default())
+    }
+
+    fn hgetall(&mut self, key: &[u8]) -> Result<Vec<Pair>> {
+        Ok(self
+            .hash(key)?
+            .map(|hash| hash.iter().map(to_pair).collect())
+            .unwrap_or_default())
+    }
+
+    fn hincrby(&mut self, key: &[u8], field: &[u8], delta: i64) -> Result<i64> {
+        self.update_field(key, field, |current| {
+            let current = match current {
+                Some(value) => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<i64>().ok())
+                    .ok_or_else(|| RedisError::InvalidArgument("hash value is not an integer".to_string()))?,
+                None => 0,
+            };
+            let updated = current
+                .checked_add(delta)
+                .ok_or_else(|| RedisError::InvalidArgument("increment or decrement would overflow".to_string()))?;
+            Ok((updated, updated.to_string().into_bytes()))
+        })
+    }
+
+    fn hincrbyfloat(&mut self, key: &[u8], field: &[u8], delta: f64) -> Result<Vec<u8>> {
+        self.update_field(key, field, |current| {
+            let current = match current {
+                Some(value) => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| RedisError::InvalidArgument("hash value is not a float".to_string()))?,
+                None => 0.0,
+            };
+            let updated = current + delta;
+            if !updated.is_finite() {
+                return Err(RedisError::InvalidArgument("increment would produce NaN or Infinity".to_string()));
+            }
+            let formatted = updated.to_string().into_bytes();
+            Ok((formatted.clone(), formatted))
+        })
+    }
+
+    fn hstrlen(&mut self, key: &[u8], field: &[u8]) -> Result<usize> {
+        Ok(self.hash(key)?.and_then(|hash| hash.get(field)).map_or(0, <[u8]>::len))
+    }
+
+    fn hrandfield(&mut self, key: &[u8], count: i64) -> Result<Vec<Pair>> {
+        let pairs: Vec<FieldRef> = match self.hash(key)? {
+            Some(hash) => hash.iter().collect(),
+            None => return Ok(Vec::new()),
+        };
+
+        let mut rng = rand::thread_rng();
+        Ok(if count >= 0 {
+            pairs
+                .choose_multiple(&mut rng, count as usize)
+                .map(|&pair| to_pair(pair))
+                .collect()
+        } else {
+            (0..count.unsigned_abs())
+                .map(|_| to_pair(pairs[rng.gen_range(0..pairs.len())]))
+                .collect()
+        })
+    }
+
+    fn hscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Pair>)> {
+        let hash = match self.hash(key)? {
+            Some(hash) => hash,
+            None => return Ok((0, Vec::new())),
+        };
+
+        // like redis, MATCH filters what a step visited rather than steering the step, so
+        // a call may return fewer elements than COUNT, or none, before the scan ends
+        let (next, pairs) = hash.scan(cursor, options.count);
+        let pairs = pairs
+            .into_iter()
+            .filter(|(field, _)| options.pattern.as_deref().is_none_or(|pattern| glob::matches(pattern, field)))
+            .map(to_pair)
+            .collect();
+        Ok((next, pairs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{EncodingLimits, ListpackLimits, Storage};
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<Pair> {
+        items
+            .iter()
+            .map(|(field, value)| (field.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_hset_hdel_and_memory() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.hset(b"h", pairs(&[("a", "1"), ("b", "2")])).unwrap(), 2);
+        assert_eq!(storage.hset(b"h", pairs(&[("a", "10"), ("c", "3")])).unwrap(), 1);
+        assert_eq!(storage.hget(b"h", b"a").unwrap(), Some(b"10".to_vec()));
+        assert_eq!(storage.dataset_memory(), 7);
+
+        assert_eq!(storage.hdel(b"h", &[b"a".to_vec(), b"zz".to_vec()]).unwrap(), 1);
+        assert_eq!(storage.hdel(b"h", &[b"b".to_vec(), b"c".to_vec()]).unwrap(), 2);
+        // the emptied hash is gone
+        assert!(!storage.exists(b"h").unwrap());
+        assert_eq!(storage.dataset_memory(), 0);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut storage = MemoryStorage::new(1024);
+        storage.set(b"s".to_vec(), StorageValue::String(b"x".to_vec()), None).unwrap();
+        assert!(matches!(storage.hset(b"s", pairs(&[("a", "1")])), Err(RedisError::WrongType)));
+        assert!(matches!(storage.hget(b"s", b"a"), Err(RedisError::WrongType)));
+        assert!(matches!(storage.hlen(b"s"), Err(RedisError::WrongType)));
+    }
+
+    #[test]
+    fn test_hincrby() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.hincrby(b"h", b"n", 5).unwrap(), 5);
+        assert_eq!(storage.hincrby(b"h", b"n", -7).unwrap(), -2);
+        storage.hset(b"h", pairs(&[("max", &i64::MAX.to_string()), ("s", "abc")])).unwrap();
+        assert!(storage.hincrby(b"h", b"max", 1).is_err());
+        assert!(storage.hincrby(b"h", b"s", 1).is_err());
+
+        assert_eq!(storage.hincrbyfloat(b"h", b"f", 10.5).unwrap(), b"10.5".to_vec());
+        assert_eq!(storage.hincrbyfloat(b"h", b"n", 0.5).unwrap(), b"-1.5".to_vec());
+        assert!(storage.hincrbyfloat(b"h", b"f", f64::INFINITY).is_err());
+        assert_eq!(storage.hget(b"h", b"f").unwrap(), Some(b"10.5".to_vec()));
+    }
+
+    #[test]
+    fn test_hrandfield_counts() {
+        let mut storage = MemoryStorage::new(1024);
+        assert!(storage.hrandfield(b"h", 5).unwrap().is_empty());
+        storage.hset(b"h", pairs(&[("a", "1"), ("b", "2"), ("c", "3")])).unwrap();
+
+        let mut distinct = storage.hrandfield(b"h", 10).unwrap();
+        distinct.sort();
+        assert_eq!(distinct, pairs(&[("a", "1"), ("b", "2"), ("c", "3")]));
+        assert_eq!(storage.hrandfield(b"h", -10).unwrap().len(), 10);
+        assert!(storage.hrandfield(b"h", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_hscan_with_small_limits() {
+        let mut storage = MemoryStorage::new(1 << 20);
+        storage.set_encoding_limits(EncodingLimits { hash: ListpackLimits::new(4, 64), ..EncodingLimits::default() });
+        let fields: Vec<Pair> = (0..50).map(|i| (format!("f{}", i).into_bytes(), b"v".to_vec())).collect();
+        storage.hset(b"h", fields).unwrap();
+        assert_eq!(storage.encoding(b"h"), Some("hashtable"));
+
+        let options = ScanOptions { pattern: Some(b"f1*".to_vec()), count: 5, no_values: false };
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = storage.hscan(b"h", cursor, &options).unwrap();
+            seen.extend(batch.into_iter().map(|(field, _)| field));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        let mut expected: Vec<Vec<u8>> = std::iter::once(b"f1".to_vec())
+            .chain((10..20).map(|i| format!("f{}", i).into_bytes()))
+            .collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+}
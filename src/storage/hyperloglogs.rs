@@ -0,0 +1,184 @@
+use super::data_types::hyperloglog::{self as hll, HllEncoding, Registers, REGISTERS};
+use super::memory::MemoryStorage;
+use super::{Storage, StorageValue};
+use crate::utils::error::{RedisError, Result};
+
+// HyperLogLogs are plain strings in the redis format, so any string key may be one
+pub trait HyperLogLogStorage {
+    // true when a register changed or the key was created
+    fn pfadd(&mut self, key: &[u8], elements: &[Vec<u8>]) -> Result<bool>;
+    // the estimated size of the union; a single key caches its count in the header
+    fn pfcount(&mut self, keys: &[Vec<u8>]) -> Result<u64>;
+    fn pfmerge(&mut self, destination: &[u8], keys: &[Vec<u8>]) -> Result<()>;
+    // PFDEBUG GETREG; like redis this converts the value to dense first
+    fn pfdebug_registers(&mut self, key: &[u8]) -> Result<Vec<u8>>;
+    // PFDEBUG DECODE, for sparse values only
+    fn pfdebug_decode(&mut self, key: &[u8]) -> Result<String>;
+    fn pfdebug_encoding(&mut self, key: &[u8]) -> Result<HllEncoding>;
+    // PFDEBUG TODENSE; true when the value was sparse
+    fn pfdebug_todense(&mut self, key: &[u8]) -> Result<bool>;
+}
+
+fn as_hll(value: &mut StorageValue) -> Result<&mut Vec<u8>> {
+    match value {
+        StorageValue::String(bytes) if hll::encoding(bytes).is_some() => Ok(bytes),
+        StorageValue::String(_) => Err(RedisError::InvalidHll),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+fn missing() -> RedisError {
+    RedisError::InvalidArgument("The specified key does not exist".to_string())
+}
+
+impl MemoryStorage {
+    // the registers at every key merged together; missing keys add nothing
+    fn union_registers(&mut self, keys: &[Vec<u8>]) -> Result<Registers> {
+        let mut registers = [0; REGISTERS];
+        for value in self.values(keys)?.into_iter().flatten() {
+            match value {
+                StorageValue::String(bytes) if hll::encoding(bytes).is_some() => {
+                    hll::merge(&mut registers, bytes).ok_or(RedisError::CorruptHll)?
+                }
+                StorageValue::String(_) => return Err(RedisError::InvalidHll),
+                _ => return Err(RedisError::WrongType),
+            }
+        }
+        Ok(registers)
+    }
+
+    // runs `f` on the HyperLogLog at `key`, which must exist
+    fn with_hll<T>(&mut self, key: &[u8], f: impl FnOnce(&mut Vec<u8>) -> T) -> Result<T> {
+        self.modify(key, |value| Ok(f(as_hll(value)?)))?.ok_or_else(missing)
+    }
+}
+
+impl HyperLogLogStorage for MemoryStorage {
+    fn pfadd(&mut self, key: &[u8], elements: &[Vec<u8>]) -> Result<bool> {
+        let created = self.value(key).is_none();
+        let sparse_max = self.encoding_limits().hll_sparse_max_bytes;
+        let changed = self.modify_or_insert(key, || StorageValue::String(hll::new()), |value| {
+            let bytes = as_hll(value)?;
+            hll::add(bytes, elements.iter().map(Vec::as_slice), sparse_max).ok_or(RedisError::CorruptHll)
+        })?;
+        Ok(created || changed)
+    }
+
+    fn pfcount(&mut self, keys: &[Vec<u8>]) -> Result<u64> {
+        if let [key] = keys {
+            let count = self.modify(key, |value| {
+                let bytes = as_hll(value)?;
+                if let Some(count) = hll::cached_count(bytes) {
+                    return Ok(count);
+                }
+                let count = hll::estimate(&hll::registers(bytes).ok_or(RedisError::CorruptHll)?);
+                hll::cache_count(bytes, count);
+                Ok(count)
+            })?;
+            return Ok(count.unwrap_or(0));
+        }
+        Ok(hll::estimate(&self.union_registers(keys)?))
+    }
+
+    fn pfmerge(&mut self, destination: &[u8], keys: &[Vec<u8>]) -> Result<()> {
+        let mut sources = keys.to_vec();
+        sources.push(destination.to_vec());
+        let registers = self.union_registers(&sources)?;
+        let merged = hll::encode(&registers, self.encoding_limits().hll_sparse_max_bytes);
+
+        // an existing destination keeps its expiry
+        if self.value(destination).is_some() {
+            self.modify(destination, |value| {
+                *value = StorageValue::String(merged);
+                Ok(())
+            })?;
+            return Ok(());
+        }
+        Storage::set(self, destination.to_vec(), StorageValue::String(merged), None)
+    }
+
+    fn pfdebug_registers(&mut self, key: &[u8]) -> Result<Vec<u8>> {
+        self.pfdebug_todense(key)?;
+        self.with_hll(key, |bytes| hll::registers(bytes).map(|registers| registers.to_vec()))?
+            .ok_or(RedisError::CorruptHll)
+    }
+
+    fn pfdebug_decode(&mut self, key: &[u8]) -> Result<String> {
+        self.with_hll(key, |bytes| hll::describe_sparse(bytes))?
+            .ok_or_else(|| RedisError::InvalidArgument("HLL encoding is not sparse".to_string()))
+    }
+
+    fn pfdebug_encoding(&mut self, key: &[u8]) -> Result<HllEncoding> {
+        self.with_hll(key, |bytes| hll::encoding(bytes).expect("checked by as_hll"))
+    }
+
+    fn pfdebug_todense(&mut self, key: &[u8]) -> Result<bool> {
+        self.with_hll(key, |bytes| match hll::encoding(bytes) {
+            Some(HllEncoding::Dense) => Some(false),
+            _ => hll::to_dense(bytes).map(|()| true),
+        })?
+        .ok_or(RedisError::CorruptHll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::EncodingLimits;
+
+    fn elements(range: std::ops::Range<u32>) -> Vec<Vec<u8>> {
+        range.map(|i| format!("user:{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_pfadd_and_pfcount() {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        assert!(storage.pfadd(b"h", &[]).unwrap());
+        assert!(!storage.pfadd(b"h", &[]).unwrap());
+        assert_eq!(storage.pfcount(&[b"h".to_vec()]).unwrap(), 0);
+
+        assert!(storage.pfadd(b"h", &elements(0..100)).unwrap());
+        assert!(!storage.pfadd(b"h", &elements(0..100)).unwrap());
+        assert_eq!(storage.pfcount(&[b"h".to_vec()]).unwrap(), 100);
+        assert_eq!(storage.pfdebug_encoding(b"h").unwrap(), HllEncoding::Sparse);
+        assert_eq!(storage.pfcount(&[b"missing".to_vec()]).unwrap(), 0);
+
+        // the value is an ordinary string to everything else
+        let dump = match &storage.get(b"h").unwrap().data {
+            StorageValue::String(bytes) => bytes.clone(),
+            _ => panic!("expected a string"),
+        };
+        Storage::set(&mut storage, b"copy".to_vec(), StorageValue::String(dump), None).unwrap();
+        assert_eq!(storage.pfcount(&[b"copy".to_vec()]).unwrap(), 100);
+
+        Storage::set(&mut storage, b"s".to_vec(), StorageValue::String(b"plain".to_vec()), None).unwrap();
+        assert!(matches!(storage.pfadd(b"s", &elements(0..1)), Err(RedisError::InvalidHll)));
+        assert!(matches!(storage.pfcount(&[b"h".to_vec(), b"s".to_vec()]), Err(RedisError::InvalidHll)));
+        Storage::set(&mut storage, b"l".to_vec(), StorageValue::List(vec![].into()), None).unwrap();
+        assert!(matches!(storage.pfcount(&[b"l".to_vec()]), Err(RedisError::WrongType)));
+    }
+
+    #[test]
+    fn test_pfmerge_and_debug() {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        storage.set_encoding_limits(EncodingLimits { hll_sparse_max_bytes: 200, ..EncodingLimits::default() });
+        storage.pfadd(b"a", &elements(0..1000)).unwrap();
+        storage.pfadd(b"b", &elements(500..1500)).unwrap();
+        assert_eq!(storage.pfdebug_encoding(b"a").unwrap(), HllEncoding::Dense);
+
+        let keys = [b"a".to_vec(), b"b".to_vec()];
+        let union = storage.pfcount(&keys).unwrap();
+        assert!((union as f64 - 1500.0).abs() < 45.0, "union {}", union);
+        storage.pfmerge(b"d", &keys).unwrap();
+        assert_eq!(storage.pfcount(&[b"d".to_vec()]).unwrap(), union);
+
+        storage.pfadd(b"small", &elements(0..3)).unwrap();
+        let decoded = storage.pfdebug_decode(b"small").unwrap();
+        assert_eq!(decoded.matches("v:").count(), 3, "{}", decoded);
+        assert!(storage.pfdebug_todense(b"small").unwrap());
+        assert!(!storage.pfdebug_todense(b"small").unwrap());
+        assert!(storage.pfdebug_decode(b"small").unwrap_err().to_string().contains("not sparse"));
+        assert_eq!(storage.pfdebug_registers(b"small").unwrap().iter().filter(|&&r| r > 0).count(), 3);
+        assert!(storage.pfdebug_encoding(b"missing").unwrap_err().to_string().contains("does not exist"));
+    }
+}
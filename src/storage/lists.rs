@@ -0,0 +1,225 @@
+use super::memory::MemoryStorage;
+use super::{ListEnd, RedisList, StorageValue};
+use crate::utils::error::{RedisError, Result};
+
+// list commands; like the rest of the storage api these see raw bytes and leave
+// reply shaping to the executor
+pub trait ListStorage {
+    // LPUSH / RPUSH, or LPUSHX / RPUSHX with `only_existing`; returns the new length
+    fn push(&mut self, key: &[u8], values: Vec<Vec<u8>>, end: ListEnd, only_existing: bool) -> Result<usize>;
+    // pops up to `count` elements; None when the key does not exist
+    fn pop(&mut self, key: &[u8], end: ListEnd, count: usize) -> Result<Option<Vec<Vec<u8>>>>;
+    fn llen(&mut self, key: &[u8]) -> Result<usize>;
+    fn lrange(&mut self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>>;
+    fn lindex(&mut self, key: &[u8], index: i64) -> Result<Option<Vec<u8>>>;
+    fn lset(&mut self, key: &[u8], index: i64, value: Vec<u8>) -> Result<()>;
+    // returns the new length, -1 when the pivot is missing and 0 when the key is
+    fn linsert(&mut self, key: &[u8], side: ListEnd, pivot: &[u8], value: Vec<u8>) -> Result<i64>;
+    fn lrem(&mut self, key: &[u8], count: i64, value: &[u8]) -> Result<usize>;
+    fn ltrim(&mut self, key: &[u8], start: i64, stop: i64) -> Result<()>;
+    fn lpos(&mut self, key: &[u8], value: &[u8], rank: i64, count: usize, maxlen: usize) -> Result<Vec<usize>>;
+    // LMOVE / RPOPLPUSH; None when the source does not exist
+    fn lmove(&mut self, source: &[u8], destination: &[u8], from: ListEnd, to: ListEnd) -> Result<Option<Vec<u8>>>;
+}
+
+fn as_list(value: &mut StorageValue) -> Result<&mut RedisList> {
+    match value {
+        StorageValue::List(list) => Ok(list),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+impl MemoryStorage {
+    fn list(&mut self, key: &[u8]) -> Result<Option<&RedisList>> {
+        match self.value(key) {
+            Some(StorageValue::List(list)) => Ok(Some(list)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+}
+
+impl ListStorage for MemoryStorage {
+    fn push(&mut self, key: &[u8], values: Vec<Vec<u8>>, end: ListEnd, only_existing: bool) -> Result<usize> {
+        let push_all = |value: &mut StorageValue| {
+            let list = as_list(value)?;
+            for value in values {
+                list.push(end, value);
+            }
+            Ok(list.len())
+        };
+
+        if only_existing {
+            Ok(self.modify(key, push_all)?.unwrap_or(0))
+        } else {
+            self.modify_or_insert(key, || StorageValue::List(RedisList::new()), push_all)
+        }
+    }
+
+    fn pop(&mut self, key: &[u8], end: ListEnd, count: usize) -> Result<Option<Vec<Vec<u8>>>> {
+        self.modify(key, |value| {
+            let list = as_list(value)?;
+            let count = count.min(list.len());
+            Ok((0..count).filter_map(|_| list.pop(end)).collect())
+        })
+    }
+
+    fn llen(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.list(key)?.map_or(0, RedisList::len))
+    }
+
+    fn lrange(&mut self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .list(key)?
+            .map(|list| list.range(start, stop).into_iter().map(<[u8]>::to_vec).collect())
+            .unwrap_or_default())
+    }
+
+    fn lindex(&mut self, key: &[u8], index: i64) -> Result<Option<Vec<u8>>> {
+        Ok(self.list(key)?.and_then(|list| list.get(index)).map(<[u8]>::to_vec))
+    }
+
+    fn lset(&mut self, key: &[u8], index: i64, value: Vec<u8>) -> Result<()> {
+        self.modify(key, |stored| {
+            if as_list(stored)?.set(index, value) {
+                Ok(())
+            } else {
+                Err(RedisError::InvalidArgument("index out of range".to_string()))
+            }
+        })?
+        .ok_or(RedisError::KeyNotFound)
+    }
+
+    fn linsert(&mut self, key: &[u8], side: ListEnd, pivot: &[u8], value: Vec<u8>) -> Result<i64> {
+        let inserted = self.modify(key, |stored| Ok(as_list(stored)?.insert(pivot, value, side)))?;
+        Ok(match inserted {
+            Some(Some(len)) => len as i64,
+            Some(None) => -1,
+            None => 0,
+        })
+    }
+
+    fn lrem(&mut self, key: &[u8], count: i64, value: &[u8]) -> Result<usize> {
+        Ok(self
+            .modify(key, |stored| Ok(as_list(stored)?.remove(count, value)))?
+            .unwrap_or(0))
+    }
+
+    fn ltrim(&mut self, key: &[u8], start: i64, stop: i64) -> Result<()> {
+        self.modify(key, |stored| {
+            as_list(stored)?.trim(start, stop);
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn lpos(&mut self, key: &[u8], value: &[u8], rank: i64, count: usize, maxlen: usize) -> Result<Vec<usize>> {
+        Ok(self
+            .list(key)?
+            .map(|list| list.positions(value, rank, count, maxlen))
+            .unwrap_or_default())
+    }
+
+    fn lmove(&mut self, source: &[u8], destination: &[u8], from: ListEnd, to: ListEnd) -> Result<Option<Vec<u8>>> {
+        // both ends are type checked before anything moves
+        if self.list(source)?.is_none() {
+            return Ok(None);
+        }
+        self.list(destination)?;
+
+        let value = match self.modify(source, |stored| Ok(as_list(stored)?.pop(from)))? {
+            Some(Some(value)) => value,
+            _ => return Ok(None),
+        };
+        self.modify_or_insert(destination, || StorageValue::List(RedisList::new()), |stored| {
+            as_list(stored)?.push(to, value.clone());
+            Ok(())
+        })?;
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Storage;
+
+    fn items(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|value| value.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_push_pop_both_ends() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.push(b"q", items(&["a", "b"]), ListEnd::Left, false).unwrap(), 2);
+        assert_eq!(storage.push(b"q", items(&["c"]), ListEnd::Right, false).unwrap(), 3);
+        assert_eq!(storage.lrange(b"q", 0, -1).unwrap(), items(&["b", "a", "c"]));
+
+        assert_eq!(storage.pop(b"q", ListEnd::Right, 1).unwrap(), Some(items(&["c"])));
+        assert_eq!(storage.pop(b"q", ListEnd::Left, 10).unwrap(), Some(items(&["b", "a"])));
+        // the emptied list is gone, along with its memory
+        assert!(!storage.exists(b"q").unwrap());
+        assert_eq!(storage.pop(b"q", ListEnd::Left, 1).unwrap(), None);
+        assert_eq!(storage.dataset_memory(), 0);
+    }
+
+    #[test]
+    fn test_pushx_requires_existing_list() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.push(b"q", items(&["a"]), ListEnd::Left, true).unwrap(), 0);
+        assert!(!storage.exists(b"q").unwrap());
+        storage.push(b"q", items(&["a"]), ListEnd::Left, false).unwrap();
+        assert_eq!(storage.push(b"q", items(&["b"]), ListEnd::Right, true).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut storage = MemoryStorage::new(1024);
+        storage.set(b"s".to_vec(), StorageValue::String(b"x".to_vec()), None).unwrap();
+        assert!(matches!(storage.push(b"s", items(&["a"]), ListEnd::Left, false), Err(RedisError::WrongType)));
+        assert!(matches!(storage.llen(b"s"), Err(RedisError::WrongType)));
+
+        storage.push(b"q", items(&["a"]), ListEnd::Left, false).unwrap();
+        assert!(matches!(storage.lmove(b"q", b"s", ListEnd::Left, ListEnd::Left), Err(RedisError::WrongType)));
+        // nothing was popped from the source
+        assert_eq!(storage.llen(b"q").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_lset_errors() {
+        let mut storage = MemoryStorage::new(1024);
+        assert!(matches!(storage.lset(b"q", 0, b"x".to_vec()), Err(RedisError::KeyNotFound)));
+        storage.push(b"q", items(&["a"]), ListEnd::Left, false).unwrap();
+        assert!(matches!(storage.lset(b"q", 1, b"x".to_vec()), Err(RedisError::InvalidArgument(_))));
+        storage.lset(b"q", -1, b"xyz".to_vec()).unwrap();
+        assert_eq!(storage.lindex(b"q", 0).unwrap(), Some(b"xyz".to_vec()));
+        assert_eq!(storage.dataset_memory(), 3);
+    }
+
+    #[test]
+    fn test_lmove_rotates_and_creates() {
+        let mut storage = MemoryStorage::new(1024);
+        storage.push(b"q", items(&["a", "b", "c"]), ListEnd::Right, false).unwrap();
+
+        assert_eq!(storage.lmove(b"q", b"q", ListEnd::Right, ListEnd::Left).unwrap(), Some(b"c".to_vec()));
+        assert_eq!(storage.lrange(b"q", 0, -1).unwrap(), items(&["c", "a", "b"]));
+
+        assert_eq!(storage.lmove(b"q", b"done", ListEnd::Left, ListEnd::Right).unwrap(), Some(b"c".to_vec()));
+        assert_eq!(storage.lrange(b"done", 0, -1).unwrap(), items(&["c"]));
+        assert_eq!(storage.lmove(b"missing", b"done", ListEnd::Left, ListEnd::Right).unwrap(), None);
+    }
+
+    #[test]
+    fn test_linsert_lrem_ltrim() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.linsert(b"q", ListEnd::Left, b"a", b"x".to_vec()).unwrap(), 0);
+        storage.push(b"q", items(&["a", "b", "a"]), ListEnd::Right, false).unwrap();
+        assert_eq!(storage.linsert(b"q", ListEnd::Right, b"b", b"x".to_vec()).unwrap(), 4);
+        assert_eq!(storage.linsert(b"q", ListEnd::Right, b"zz", b"x".to_vec()).unwrap(), -1);
+        assert_eq!(storage.lrem(b"q", 0, b"a").unwrap(), 2);
+        assert_eq!(storage.lrange(b"q", 0, -1).unwrap(), items(&["b", "x"]));
+
+        storage.ltrim(b"q", 2, 5).unwrap();
+        assert!(!storage.exists(b"q").unwrap());
+    }
+}
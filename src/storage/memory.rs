@@ -1,93 +1,334 @@
 use std::collections::HashMap;
-use std::time::{SystemTime, Instant, Duration};
-use rand::seq::SliceRandom;
-use super::{Storage, StorageEntry, StorageValue};
+use std::time::{SystemTime, Instant, Duration, UNIX_EPOCH};
+use super::eviction::{Access, EvictionConfig, EvictionPolicy, EvictionPool, KeyIndex};
+use super::expiration::VolatileIndex;
+use super::{
+    EncodingLimits, ExpireOptions, KeyExpiry, ScoreComparison, SetCondition, Storage, StorageEntry, StorageValue,
+    StringStorage,
+};
+use crate::utils::clock::{MonotonicClock, SharedClock};
 use crate::utils::error::Result;
 use crate::utils::error::RedisError;
-use std::thread;
 
 
 // TODO: should we make this configurable?
 const ACTIVE_EXPIRE_CYCLE_LOOKUPS_PER_LOOP: usize = 20;  // how many keys to sample per loop
 const ACTIVE_EXPIRE_CYCLE_FAST_DURATION: Duration = Duration::from_millis(1);  // fast cycle duration
 const ACTIVE_EXPIRE_CYCLE_SLOW_DURATION: Duration = Duration::from_millis(25);  // slow cycle duration
-const ACTIVE_EXPIRE_CYCLE_THRESHOLD: f64 = 0.25;  // stop sampling if hit rate drops below 25%
+const ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE: f64 = 0.10;  // keep sampling while over 10% of a batch expired
+// weight of the latest cycle in the running stale ratio, as in redis
+const ACTIVE_EXPIRE_STALE_SMOOTHING: f64 = 0.05;
+// samples may all be protected or already gone; give up after this many rounds
+const EVICTION_ROUNDS: usize = 16;
 
 #[derive(Debug)]
 pub struct MemoryStorage {
-    data: HashMap<String, StorageEntry>,
+    data: HashMap<Vec<u8>, StorageEntry>,
     max_memory: usize,
+    // the values; the keys are counted apart, as redis reports dataset and overhead apart
     used_memory: usize,
+    key_memory: usize,
     last_expire_cycle: Instant,
+    last_fast_cycle: Option<Instant>,
+    // whether the last cycle stopped on its time limit rather than running out of work
+    expire_timelimit_exit: bool,
+    limits: EncodingLimits,
+    // every key, so eviction can sample without walking the map
+    keys: KeyIndex,
+    // just the keys with a ttl, for active expiry and the volatile policies
+    volatile: VolatileIndex,
+    // running average of the share of sampled volatile keys found expired
+    expired_stale_ratio: f64,
+    eviction: EvictionConfig,
+    pool: EvictionPool,
+    evicted_keys: usize,
+    // DEBUG SET-ACTIVE-EXPIRE 0 turns the background cycle off, leaving only lazy expiry
+    active_expire: bool,
+    clock: SharedClock,
 }
 
 impl MemoryStorage {
     pub fn new(max_memory: usize) -> Self {
+        Self::with_clock(max_memory, MonotonicClock::shared())
+    }
+
+    pub fn with_clock(max_memory: usize, clock: SharedClock) -> Self {
         Self {
             data: HashMap::new(),
             max_memory,
             used_memory: 0,
-            last_expire_cycle: Instant::now(),
+            key_memory: 0,
+            last_expire_cycle: clock.instant(),
+            last_fast_cycle: None,
+            expire_timelimit_exit: false,
+            limits: EncodingLimits::default(),
+            keys: KeyIndex::default(),
+            volatile: VolatileIndex::default(),
+            expired_stale_ratio: 0.0,
+            eviction: EvictionConfig::default(),
+            pool: EvictionPool::default(),
+            evicted_keys: 0,
+            active_expire: true,
+            clock,
+        }
+    }
+
+    pub fn set_active_expire(&mut self, enabled: bool) {
+        self.active_expire = enabled;
+    }
+
+    // the time every deadline is measured against
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    // maxmemory and the policy used to get back under it; 0 means no limit
+    pub fn set_maxmemory(&mut self, max_memory: usize, eviction: EvictionConfig) {
+        self.max_memory = if max_memory == 0 { usize::MAX } else { max_memory };
+        self.eviction = eviction;
+        self.pool.clear();
+    }
+
+    pub fn eviction_config(&self) -> EvictionConfig {
+        self.eviction
+    }
+
+    // keys removed to stay under maxmemory since startup
+    pub fn evicted_keys(&self) -> usize {
+        self.evicted_keys
+    }
+
+    // only affects aggregates as they grow; existing ones are not re-encoded
+    pub fn set_encoding_limits(&mut self, limits: EncodingLimits) {
+        self.limits = limits;
+    }
+
+    pub(super) fn encoding_limits(&self) -> EncodingLimits {
+        self.limits
+    }
+
+    // wall-clock milliseconds, as used for stream ids and delivery times
+    pub fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+
+    // OBJECT ENCODING; None when the key does not exist
+    pub fn encoding(&mut self, key: &[u8]) -> Option<&'static str> {
+        self.value(key).map(StorageValue::encoding)
+    }
+
+    // like redis, OBJECT looks keys up without counting it as an access
+    fn peek_access(&mut self, key: &[u8]) -> Option<Access> {
+        if self.is_expired(key) {
+            self.remove_entry(key);
         }
+        self.data.get(key).map(|entry| entry.access)
     }
 
-    // redis-style active expiration cycle
+    // OBJECT IDLETIME: seconds since the key was last read or written
+    pub fn idle_time(&mut self, key: &[u8]) -> Option<u64> {
+        let now = self.now_secs();
+        self.peek_access(key).map(|access| access.idle_secs(now))
+    }
+
+    // OBJECT FREQ: the logarithmic access counter, decayed to now
+    pub fn frequency(&mut self, key: &[u8]) -> Option<u8> {
+        let now = self.now_secs();
+        let lfu = self.eviction.lfu;
+        self.peek_access(key).map(|access| access.frequency(now, lfu))
+    }
+
+    // the `count` live keys with the highest access counters, hottest first; this walks
+    // the whole keyspace, like redis-cli --hotkeys does with SCAN and OBJECT FREQ
+    pub fn hotkeys(&self, count: usize) -> Vec<(Vec<u8>, u8)> {
+        let (now, now_secs, lfu) = (self.now(), self.now_secs(), self.eviction.lfu);
+        let mut hot: Vec<(Vec<u8>, u8)> = self
+            .data
+            .iter()
+            .filter(|(_, entry)| entry.expires_at.is_none_or(|at| at >= now))
+            .map(|(key, entry)| (key.clone(), entry.access.frequency(now_secs, lfu)))
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        hot.truncate(count);
+        hot
+    }
+
+    // redis-style active expiration cycle, sampling only the keys that have a ttl
     pub fn active_expire_cycle(&mut self, cycle_type: ExpireCycleType) -> ExpireStats {
         let mut stats = ExpireStats::default();
-        let start = Instant::now();
+        if !self.active_expire {
+            return stats;
+        }
+        let start = self.clock.instant();
         let max_duration = match cycle_type {
             ExpireCycleType::Fast => ACTIVE_EXPIRE_CYCLE_FAST_DURATION,
             ExpireCycleType::Slow => ACTIVE_EXPIRE_CYCLE_SLOW_DURATION,
         };
 
-        // get all keys for sampling
-        let keys: Vec<String> = self.data.keys().cloned().collect();
+        // like redis, a fast cycle only runs when the last cycle ran out of time or enough
+        // sampled keys keep turning out expired, and never twice within two of its durations
+        if matches!(cycle_type, ExpireCycleType::Fast) {
+            if !self.expire_timelimit_exit && self.expired_stale_ratio < ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE {
+                stats.stopped_by_threshold = true;
+                return stats;
+            }
+            if self.last_fast_cycle.is_some_and(|last| start < last + ACTIVE_EXPIRE_CYCLE_FAST_DURATION * 2) {
+                return stats;
+            }
+            self.last_fast_cycle = Some(start);
+        }
+
+        let now = self.now();
         let mut rng = rand::thread_rng();
 
-        // while still within cycle duration
-        while start.elapsed() < max_duration {
+        while !self.volatile.is_empty() {
+            if self.clock.instant() - start >= max_duration {
+                stats.timelimit_exit = true;
+                break;
+            }
             stats.total_cycles += 1;
-            let mut expired_in_cycle = 0;
-
-            // sample random keys
-            for _ in 0..ACTIVE_EXPIRE_CYCLE_LOOKUPS_PER_LOOP {
-                if let Some(key) = keys.choose(&mut rng) {
-                    stats.keys_checked += 1;
-                    
-                    if let Some(entry) = self.data.get(key) {
-                        if let Some(expiry_time) = entry.expires_at {
-                            if expiry_time < SystemTime::now() {
-                                if let Some(entry) = self.data.remove(key) {
-                                    self.used_memory -= Self::estimate_size(&entry.data);
-                                    expired_in_cycle += 1;
-                                    stats.keys_expired += 1;
-                                }
-                            }
-                        }
-                    }
-                }
+
+            // sample random volatile keys, without replacement
+            let sampled = ACTIVE_EXPIRE_CYCLE_LOOKUPS_PER_LOOP.min(self.volatile.len());
+            let expired: Vec<Vec<u8>> = self
+                .volatile
+                .sample(&mut rng, sampled)
+                .filter(|&(_, deadline)| deadline < now)
+                .map(|(key, _)| key.to_vec())
+                .collect();
+            for key in &expired {
+                self.remove_entry(key);
             }
+            stats.keys_checked += sampled;
+            stats.keys_expired += expired.len();
 
-            // calculate hit rate for this cycle
-            let hit_rate = expired_in_cycle as f64 / ACTIVE_EXPIRE_CYCLE_LOOKUPS_PER_LOOP as f64;
-            
-            // stop if hit rate is too low (redis behavior)
-            if hit_rate < ACTIVE_EXPIRE_CYCLE_THRESHOLD {
+            // stop once few enough of the sampled keys were expired (redis behavior)
+            if (expired.len() as f64) <= sampled as f64 * ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE {
                 stats.stopped_by_threshold = true;
                 break;
             }
         }
 
-        self.last_expire_cycle = Instant::now();
-        stats.duration = start.elapsed();
+        if stats.keys_checked > 0 {
+            stats.stale_ratio = stats.keys_expired as f64 / stats.keys_checked as f64;
+        }
+        self.expire_timelimit_exit = stats.timelimit_exit;
+        self.expired_stale_ratio = stats.stale_ratio * ACTIVE_EXPIRE_STALE_SMOOTHING
+            + self.expired_stale_ratio * (1.0 - ACTIVE_EXPIRE_STALE_SMOOTHING);
+        self.last_expire_cycle = self.clock.instant();
+        stats.duration = self.last_expire_cycle - start;
         stats
     }
 
-    // passive expiration check (called during get operations)
-    fn check_expiry(&mut self, key: &str) -> bool {
-        if let Some(entry) = self.data.get(key) {
-            if let Some(expiry_time) = entry.expires_at {
-                if expiry_time < SystemTime::now() {
+    // the running share of sampled volatile keys that were already expired
+    pub fn expired_stale_ratio(&self) -> f64 {
+        self.expired_stale_ratio
+    }
+
+    fn estimate_size(value: &StorageValue) -> usize {
+        value.memory_usage()
+    }
+
+    fn now_secs(&self) -> u64 {
+        self.now_ms() / 1000
+    }
+
+    // every insertion and removal goes through these two, which keep the memory counters
+    // and the key indexes in step with the map
+    fn insert_entry(&mut self, key: Vec<u8>, entry: StorageEntry) {
+        self.used_memory += Self::estimate_size(&entry.data);
+        self.keys.insert(&key, ());
+        self.volatile.update(&key, entry.expires_at);
+        let key_len = key.len();
+        match self.data.insert(key, entry) {
+            Some(old) => self.used_memory -= Self::estimate_size(&old.data),
+            None => self.key_memory += key_len,
+        }
+    }
+
+    fn remove_entry(&mut self, key: &[u8]) -> Option<StorageEntry> {
+        let entry = self.data.remove(key)?;
+        self.used_memory -= Self::estimate_size(&entry.data);
+        self.key_memory -= key.len();
+        self.keys.remove(key);
+        self.volatile.remove(key);
+        Some(entry)
+    }
+
+    fn touch(&mut self, key: &[u8]) {
+        let (now, lfu) = (self.now_secs(), self.eviction.lfu);
+        if let Some(entry) = self.data.get_mut(key) {
+            entry.access.touch(now, lfu);
+        }
+    }
+
+    // evicts keys other than `protect` until `needed` more bytes fit under maxmemory
+    pub(super) fn make_room(&mut self, needed: usize, protect: &[&[u8]]) -> Result<()> {
+        while self.used_memory().saturating_add(needed) > self.max_memory {
+            if self.eviction.policy == EvictionPolicy::NoEviction || !self.evict_one(protect) {
+                return Err(RedisError::OutOfMemory);
+            }
+        }
+        Ok(())
+    }
+
+    // evicts until used memory is back under maxmemory; OOM when the policy or the lack of
+    // candidates does not allow it, which is when denyoom commands get refused
+    pub fn free_memory(&mut self) -> Result<()> {
+        self.make_room(0, &[])
+    }
+
+    // samples keys into the pool and evicts the best candidate; false when there is none
+    fn evict_one(&mut self, protect: &[&[u8]]) -> bool {
+        let (policy, lfu) = (self.eviction.policy, self.eviction.lfu);
+        let now_ms = self.now_ms();
+        let now_secs = now_ms / 1000;
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..EVICTION_ROUNDS {
+            let samples = self.eviction.samples;
+            let sampled: Vec<Vec<u8>> = if policy.volatile() {
+                self.volatile.sample(&mut rng, samples).map(|(key, _)| key.to_vec()).collect()
+            } else {
+                self.keys.sample(&mut rng, samples).map(|(key, _)| key.to_vec()).collect()
+            };
+            if sampled.is_empty() {
+                return false;
+            }
+
+            for key in sampled {
+                let entry = match self.data.get(&key) {
+                    Some(entry) if !protect.contains(&key.as_slice()) => entry,
+                    _ => continue,
+                };
+                if policy.random() {
+                    self.evict(&key);
+                    return true;
+                }
+                // higher is a better candidate
+                let score = match policy {
+                    EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+                        (u8::MAX - entry.access.frequency(now_secs, lfu)) as u64
+                    }
+                    EvictionPolicy::VolatileTtl => {
+                        let expires_ms = entry.expires_at.map_or(0, |at| {
+                            at.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |at| at.as_millis() as u64)
+                        });
+                        u64::MAX - expires_ms
+                    }
+                    _ => entry.access.idle_secs(now_secs),
+                };
+                self.pool.offer(&key, score);
+            }
+
+            // pool entries may have been deleted or lost their ttl since they were sampled
+            while let Some(key) = self.pool.pop() {
+                let eligible = match self.data.get(&key) {
+                    Some(entry) => !protect.contains(&key.as_slice()) && (!policy.volatile() || entry.expires_at.is_some()),
+                    None => false,
+                };
+                if eligible {
+                    self.evict(&key);
                     return true;
                 }
             }
@@ -95,10 +336,9 @@ impl MemoryStorage {
         false
     }
 
-    fn estimate_size(value: &StorageValue) -> usize {
-        match value {
-            StorageValue::String(s) => s.len(),
-            StorageValue::List(l) => l.iter().map(|s| s.len()).sum(),
+    fn evict(&mut self, key: &[u8]) {
+        if self.remove_entry(key).is_some() {
+            self.evicted_keys += 1;
         }
     }
 
@@ -106,23 +346,116 @@ impl MemoryStorage {
         self.max_memory 
     }
 
-    fn is_expired(&self, key: &str) -> bool {
+    // what maxmemory is held to: the values plus the keys, in the map and in both indexes
+    pub fn used_memory(&self) -> usize {
+        self.used_memory + self.key_memory + self.keys.memory_usage() + self.volatile.memory_usage()
+    }
+
+    // the values alone
+    pub fn dataset_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    fn is_expired(&self, key: &[u8]) -> bool {
         if let Some(entry) = self.data.get(key) {
             if let Some(expiry_time) = entry.expires_at {
-                return expiry_time < SystemTime::now();
+                return expiry_time < self.now();
             }
         }
         false
     }
 
-    // separate function to handle lazy deletion
-    fn lazy_delete(&mut self, key: &str) -> Result<()> {
-        if let Some(entry) = self.data.remove(key) {
-            self.used_memory -= Self::estimate_size(&entry.data);
+    // sets or clears the deadline of a live key; false when there is no such key
+    pub(super) fn set_expiry(&mut self, key: &[u8], expires_at: Option<SystemTime>) -> bool {
+        if self.is_expired(key) {
+            return false;
+        }
+        match self.data.get_mut(key) {
+            Some(entry) => {
+                entry.expires_at = expires_at;
+                self.volatile.update(key, expires_at);
+                true
+            }
+            None => false,
         }
+    }
+
+    // separate function to handle lazy deletion
+    fn lazy_delete(&mut self, key: &[u8]) -> Result<()> {
+        self.remove_entry(key);
         Ok(())
     }
-    
+
+    // the value at `key`, or None when it is missing or has just expired
+    pub(super) fn value(&mut self, key: &[u8]) -> Option<&StorageValue> {
+        match self.get(key) {
+            Ok(entry) => Some(&entry.data),
+            Err(_) => None,
+        }
+    }
+
+    // the values at several keys at once, for commands that read many keys together
+    pub(super) fn values(&mut self, keys: &[Vec<u8>]) -> Result<Vec<Option<&StorageValue>>> {
+        for key in keys {
+            if self.is_expired(key) {
+                self.lazy_delete(key)?;
+            }
+            self.touch(key);
+        }
+        Ok(keys.iter().map(|key| self.data.get(key.as_slice()).map(|entry| &entry.data)).collect())
+    }
+
+    // runs `f` on the value at `key` in place, keeping used_memory in sync and deleting
+    // collections that `f` leaves empty; Ok(None) when there is no such key. growth is not
+    // refused, but other keys are evicted afterwards if it took us over maxmemory, and under
+    // noeviction the engine refuses further denyoom commands until memory is freed
+    pub(super) fn modify<T, F>(&mut self, key: &[u8], f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(&mut StorageValue) -> Result<T>,
+    {
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
+        }
+        let (now, lfu) = (self.now_secs(), self.eviction.lfu);
+        let entry = match self.data.get_mut(key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        entry.access.touch(now, lfu);
+
+        let before = Self::estimate_size(&entry.data);
+        let result = f(&mut entry.data);
+        let after = Self::estimate_size(&entry.data);
+        let emptied = entry.data.is_empty_collection();
+
+        self.used_memory = self.used_memory - before + after;
+        if emptied {
+            self.lazy_delete(key)?;
+        } else if after > before && self.eviction.policy != EvictionPolicy::NoEviction {
+            // best effort: the write already happened, so running out of victims is not an error
+            let _ = self.make_room(0, &[key]);
+        }
+        result.map(Some)
+    }
+
+    // like `modify`, but a missing key is first created from `init`
+    pub(super) fn modify_or_insert<T, I, F>(&mut self, key: &[u8], init: I, f: F) -> Result<T>
+    where
+        I: FnOnce() -> StorageValue,
+        F: FnOnce(&mut StorageValue) -> Result<T>,
+    {
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
+        }
+        if !self.data.contains_key(key) {
+            let access = Access::new(self.now_secs());
+            self.insert_entry(key.to_vec(), StorageEntry { data: init(), expires_at: None, access });
+        }
+
+        let result = self.modify(key, f)?;
+        Ok(result.expect("key was just inserted"))
+    }
+
 }
 
 #[derive(Debug)]
@@ -137,197 +470,321 @@ pub struct ExpireStats {
     pub keys_expired: usize,
     pub total_cycles: usize,
     pub stopped_by_threshold: bool,
+    // ran out of time with keys left to check, which lets the next fast cycle run
+    pub timelimit_exit: bool,
     pub duration: Duration,
+    // expired / checked this run; redis' measure of how much effort expiry deserves
+    pub stale_ratio: f64,
 }
 
 impl Storage for MemoryStorage {
-    fn get(&mut self, key: &str) -> Result<&StorageEntry> {
+    fn get(&mut self, key: &[u8]) -> Result<&StorageEntry> {
         // passive expiration
-        if self.check_expiry(key) {
-            self.delete(key)?;
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
             return Err(RedisError::KeyNotFound);
         }
 
+        self.touch(key);
         self.data.get(key).ok_or(RedisError::KeyNotFound)
     }
 
-    fn set(&mut self, key: String, value: StorageValue, ttl: Option<SystemTime>) -> Result<()> {
-        let size = Self::estimate_size(&value);
-        
-        // if key exists, subtract its size first
-        if let Some(old_entry) = self.data.get(&key) {
-            self.used_memory -= Self::estimate_size(&old_entry.data);
-        }
-
-        if self.used_memory + size > self.max_memory {
-            return Err(RedisError::OutOfMemory);
+    fn set(&mut self, key: Vec<u8>, value: StorageValue, ttl: Option<SystemTime>) -> Result<()> {
+        if self.is_expired(&key) {
+            self.lazy_delete(&key)?;
         }
+        // the old value is only released once the new one is known to fit
+        let size = Self::estimate_size(&value);
+        let replaced = self.data.get(&key).map_or(0, |entry| Self::estimate_size(&entry.data));
+        self.make_room(size.saturating_sub(replaced), &[&key])?;
 
-        let entry = StorageEntry {
-            data: value,
-            expires_at: ttl,
+        // overwriting a key is a write to it, so it keeps its history
+        let (now, lfu) = (self.now_secs(), self.eviction.lfu);
+        let access = match self.data.get(&key) {
+            Some(entry) => {
+                let mut access = entry.access;
+                access.touch(now, lfu);
+                access
+            }
+            None => Access::new(now),
         };
-        
-        self.used_memory += size;
-        self.data.insert(key, entry);
+        self.insert_entry(key, StorageEntry { data: value, expires_at: ttl, access });
         Ok(())
     }
 
-    fn delete(&mut self, key: &str) -> Result<bool> {
-        if let Some(entry) = self.data.remove(key) {
-            self.used_memory -= Self::estimate_size(&entry.data);
-            Ok(true)
-        } else {
-            Ok(false)
+    fn delete(&mut self, key: &[u8]) -> Result<bool> {
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
         }
+        Ok(self.remove_entry(key).is_some())
     }
 
-    fn exists(&self, key: &str) -> Result<bool> {
+    // not an access, so it leaves the lru and lfu data alone
+    fn exists(&mut self, key: &[u8]) -> Result<bool> {
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
+        }
         Ok(self.data.contains_key(key))
     }
 
     fn clear(&mut self) -> Result<()> {
         self.data.clear();
+        self.keys.clear();
+        self.volatile.clear();
+        self.pool.clear();
         self.used_memory = 0;
+        self.key_memory = 0;
         Ok(())
     }
 
-    fn expire(&mut self, key: &str, ttl: u64) -> Result<()> {
-        if let Some(entry) = self.data.get_mut(key) {
-            entry.expires_at = Some(SystemTime::now() + Duration::from_secs(ttl));
-            Ok(())
+    fn expire(&mut self, key: &[u8], at_ms: i64, options: ExpireOptions) -> Result<bool> {
+        let current = match self.expire_time(key)? {
+            KeyExpiry::Missing => return Ok(false),
+            KeyExpiry::Persistent => None,
+            KeyExpiry::At(ms) => Some(ms as i64),
+        };
+        let allowed = match options.condition {
+            Some(SetCondition::Nx) => current.is_none(),
+            Some(SetCondition::Xx) => current.is_some(),
+            None => true,
+        } && match options.comparison {
+            Some(ScoreComparison::Gt) => current.is_some_and(|current| at_ms > current),
+            Some(ScoreComparison::Lt) => current.is_none_or(|current| at_ms < current),
+            None => true,
+        };
+        if !allowed {
+            return Ok(false);
+        }
+
+        // like redis, a deadline that has already passed deletes the key there and then
+        if at_ms <= self.now_ms() as i64 {
+            self.remove_entry(key);
         } else {
-            Err(RedisError::KeyNotFound)
+            self.set_expiry(key, Some(UNIX_EPOCH + Duration::from_millis(at_ms as u64)));
         }
+        Ok(true)
     }
 
-    fn incr(&mut self, key: &str) -> Result<i64> {
-        let value = match self.data.get_mut(key) {
-            Some(entry) => {
-                if let StorageValue::String(ref mut s) = entry.data {
-                    match s.parse::<i64>() {
-                        Ok(mut num) => {
-                            num += 1;
-                            *s = num.to_string();
-                            num
-                        }
-                        Err(_) => return Err(RedisError::NotInteger),
-                    }
-                } else {
-                    return Err(RedisError::NotInteger);
-                }
-            }
-            None => {
-                let entry = StorageEntry {
-                    data: StorageValue::String("1".to_string()),
-                    expires_at: None,
-                };
-                self.data.insert(key.to_string(), entry);
-                1
-            }
-        };
-        Ok(value)
+    fn persist(&mut self, key: &[u8]) -> Result<bool> {
+        Ok(matches!(self.expire_time(key)?, KeyExpiry::At(_)) && self.set_expiry(key, None))
     }
 
-    fn decr(&mut self, key: &str) -> Result<i64> {
-        let value = match self.data.get_mut(key) {
-            Some(entry) => {
-                if let StorageValue::String(ref mut s) = entry.data {
-                    match s.parse::<i64>() {
-                        Ok(mut num) => {
-                            num -= 1;
-                            *s = num.to_string();
-                            num
-                        }
-                        Err(_) => return Err(RedisError::NotInteger),
-                    }
-                } else {
-                    return Err(RedisError::NotInteger);
-                }
-            }
-            None => {
-                let entry = StorageEntry {
-                    data: StorageValue::String("-1".to_string()),
-                    expires_at: None,
-                };
-                self.data.insert(key.to_string(), entry);
-                -1
+    // not an access, so it leaves the lru and lfu data alone
+    fn expire_time(&mut self, key: &[u8]) -> Result<KeyExpiry> {
+        if self.is_expired(key) {
+            self.lazy_delete(key)?;
+        }
+        Ok(match self.data.get(key) {
+            None => KeyExpiry::Missing,
+            Some(StorageEntry { expires_at: None, .. }) => KeyExpiry::Persistent,
+            Some(StorageEntry { expires_at: Some(at), .. }) => {
+                KeyExpiry::At(at.duration_since(UNIX_EPOCH).map_or(0, |at| at.as_millis() as u64))
             }
-        };
-        Ok(value)
+        })
     }
 
-    fn append(&mut self, key: &str, value: &str) -> Result<String> {
-        let new_value = match self.data.get_mut(key) {
-            Some(entry) => {
-                if let StorageValue::String(ref mut s) = entry.data {
-                    s.push_str(value);
-                    s.clone()
-                } else {
-                    return Err(RedisError::WrongType);
-                }
-            }
-            None => {
-                let entry = StorageEntry {
-                    data: StorageValue::String(value.to_string()),
-                    expires_at: None,
-                };
-                self.data.insert(key.to_string(), entry);
-                value.to_string()
-            }
-        };
-        Ok(new_value)
+    fn incr(&mut self, key: &[u8]) -> Result<i64> {
+        self.incr_by(key, 1)
+    }
+
+    fn decr(&mut self, key: &[u8]) -> Result<i64> {
+        self.incr_by(key, -1)
+    }
+
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Result<usize> {
+        let start = self.redis_string(key)?.map_or(0, <[u8]>::len);
+        self.grow_string(key, start + value.len(), |bytes| {
+            bytes[start..].copy_from_slice(value);
+            bytes.len()
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::eviction::LfuConfig;
+    use crate::utils::clock::MockClock;
+    use std::sync::Arc;
+
+    fn mocked() -> (MemoryStorage, Arc<MockClock>) {
+        // on a minute boundary, so lfu decay periods line up with whole minutes
+        let clock = Arc::new(MockClock::new(UNIX_EPOCH + Duration::from_secs(28_333_334 * 60)));
+        (MemoryStorage::with_clock(1 << 20, clock.clone()), clock)
+    }
 
     #[test]
     fn test_passive_expiration() {
-        let mut storage = MemoryStorage::new(1024);
-        let now = SystemTime::now();
-        let ttl = now + Duration::from_millis(1);
+        let (mut storage, clock) = mocked();
+        let ttl = storage.now() + Duration::from_millis(1);
         
-        // set value with 1 second ttl
-        storage.set("key1".to_string(), 
-            StorageValue::String("value1".to_string()), 
+        // set value with 1ms ttl
+        storage.set(b"key1".to_vec(), 
+            StorageValue::String(b"value1".to_vec()), 
             Some(ttl)).unwrap();
 
         // value should exist initially
-        assert!(storage.exists("key1").unwrap());
+        assert!(storage.exists(b"key1").unwrap());
         
-        // wait for ttl to expire
-        thread::sleep(Duration::from_millis(5));
+        // a key is still there at its deadline and gone right after
+        clock.advance(Duration::from_millis(1));
+        assert!(storage.exists(b"key1").unwrap());
+        clock.advance(Duration::from_millis(1));
         
         // value should be gone after expiry
-        assert!(storage.get("key1").is_err());
+        assert!(storage.get(b"key1").is_err());
+    }
+
+    #[test]
+    fn test_expire_conditions_and_past_deadlines() {
+        let (mut storage, clock) = mocked();
+        let now = storage.now_ms() as i64;
+        let gt = ExpireOptions { comparison: Some(ScoreComparison::Gt), ..ExpireOptions::default() };
+        let lt = ExpireOptions { comparison: Some(ScoreComparison::Lt), ..ExpireOptions::default() };
+        storage.set(b"key".to_vec(), StorageValue::String(b"v".to_vec()), None).unwrap();
+
+        // a key without a ttl never expires, so no deadline is later than its own
+        assert!(!storage.expire(b"key", now + 10_000, gt).unwrap());
+        assert_eq!(storage.expire_time(b"key").unwrap(), KeyExpiry::Persistent);
+        assert!(storage.expire(b"key", now + 10_000, lt).unwrap());
+        assert_eq!(storage.expire_time(b"key").unwrap(), KeyExpiry::At(now as u64 + 10_000));
+        assert!(storage.expire(b"key", now + 20_000, gt).unwrap());
+        assert!(!storage.expire(b"key", now + 30_000, lt).unwrap());
+
+        // an EXPIREAT that has already passed deletes the key, and so does one due right now
+        assert!(storage.expire(b"key", now - 1_000, ExpireOptions::default()).unwrap());
+        assert_eq!(storage.expire_time(b"key").unwrap(), KeyExpiry::Missing);
+        assert!(storage.volatile.is_empty());
+        storage.set(b"key".to_vec(), StorageValue::String(b"v".to_vec()), None).unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert!(storage.expire(b"key", now + 1_000, ExpireOptions::default()).unwrap());
+        assert!(!storage.exists(b"key").unwrap());
+        assert!(!storage.expire(b"key", now + 10_000, ExpireOptions::default()).unwrap());
     }
 
     #[test]
     fn test_active_expiration() {
-        let mut storage = MemoryStorage::new(1024);
-        let now = SystemTime::now();
-        let ttl = now + Duration::from_millis(5);
+        let (mut storage, clock) = mocked();
+        let ttl = storage.now() + Duration::from_millis(5);
 
         // add multiple entries with ttl
         for i in 0..50 {
             storage.set(
-                format!("key{}", i),
-                StorageValue::String(format!("value{}", i)),
+                format!("key{}", i).into_bytes(),
+                StorageValue::String(format!("value{}", i).into_bytes()),
                 Some(ttl)
             ).unwrap();
         }
 
-        // wait for ttl
-        thread::sleep(Duration::from_millis(10));
+        // nothing is due yet
+        let stats = storage.active_expire_cycle(ExpireCycleType::Slow);
+        assert_eq!(stats.keys_expired, 0);
 
-        // run expiration cycle
+        // with the cycle switched off, due keys stay until they are looked up
+        clock.advance(Duration::from_millis(6));
+        storage.set_active_expire(false);
         let stats = storage.active_expire_cycle(ExpireCycleType::Slow);
-        
-        assert!(stats.keys_expired > 0);
-        assert!(stats.keys_checked > 0);
+        assert_eq!((stats.keys_checked, storage.data.len()), (0, 50));
+
+        storage.set_active_expire(true);
+        let stats = storage.active_expire_cycle(ExpireCycleType::Slow);
+        assert_eq!(stats.keys_expired, 50);
+        assert!(storage.data.is_empty() && storage.volatile.is_empty());
+    }
+
+    #[test]
+    fn test_fast_cycle_runs_after_a_slow_cycle_runs_out_of_time() {
+        let (mut storage, clock) = mocked();
+        let deadline = storage.now() + Duration::from_secs(1);
+        for i in 0..10_000 {
+            storage.set(format!("key{}", i).into_bytes(), StorageValue::String(b"v".to_vec()), Some(deadline)).unwrap();
+        }
+        clock.advance(Duration::from_secs(2));
+        // nothing has been found stale yet, so a fast cycle is not worth it
+        assert!(storage.active_expire_cycle(ExpireCycleType::Fast).stopped_by_threshold);
+
+        // every look at the clock now takes 100us, so the slow cycle cannot get through them all
+        clock.set_step(Duration::from_micros(100));
+        let slow = storage.active_expire_cycle(ExpireCycleType::Slow);
+        assert!(slow.timelimit_exit && slow.keys_expired > 0 && !storage.data.is_empty());
+        assert!(storage.expired_stale_ratio() < ACTIVE_EXPIRE_CYCLE_ACCEPTABLE_STALE);
+
+        // the running ratio is still low, but the slow cycle running out of time lets it in
+        let fast = storage.active_expire_cycle(ExpireCycleType::Fast);
+        assert!(fast.timelimit_exit && fast.keys_expired > 0);
+        // and it does not run again until twice its duration has passed
+        assert_eq!(storage.active_expire_cycle(ExpireCycleType::Fast).keys_checked, 0);
+
+        clock.set_step(Duration::ZERO);
+        clock.advance(ACTIVE_EXPIRE_CYCLE_FAST_DURATION * 2);
+        let fast = storage.active_expire_cycle(ExpireCycleType::Fast);
+        assert!(!fast.timelimit_exit && fast.keys_expired > 0);
+        assert!(storage.data.is_empty() && storage.volatile.is_empty());
+    }
+
+    #[test]
+    fn test_idle_time_and_lfu_decay_follow_the_clock() {
+        let (mut storage, clock) = mocked();
+        storage.set_maxmemory(0, EvictionConfig { lfu: LfuConfig { log_factor: 0, decay_time: 1 }, ..EvictionConfig::default() });
+        storage.set(b"key".to_vec(), StorageValue::String(b"v".to_vec()), None).unwrap();
+        storage.get(b"key").unwrap();
+        assert_eq!((storage.idle_time(b"key"), storage.frequency(b"key")), (Some(0), Some(6)));
+
+        // one decay period per minute, and idle time in whole seconds
+        clock.advance(Duration::from_secs(150));
+        assert_eq!((storage.idle_time(b"key"), storage.frequency(b"key")), (Some(150), Some(4)));
+
+        storage.get(b"key").unwrap();
+        assert_eq!((storage.idle_time(b"key"), storage.frequency(b"key")), (Some(0), Some(5)));
+    }
+
+    #[test]
+    fn test_hotkeys_rank_decayed_counters_and_skip_expired_keys() {
+        let (mut storage, clock) = mocked();
+        storage.set_maxmemory(0, EvictionConfig { lfu: LfuConfig { log_factor: 0, decay_time: 1 }, ..EvictionConfig::default() });
+        let soon = storage.now() + Duration::from_secs(1);
+        for (key, reads, expires_at) in [("hot", 3, None), ("warm", 1, None), ("doomed", 5, Some(soon))] {
+            storage.set(key.as_bytes().to_vec(), StorageValue::String(b"v".to_vec()), expires_at).unwrap();
+            for _ in 0..reads {
+                storage.get(key.as_bytes()).unwrap();
+            }
+        }
+        assert_eq!(storage.hotkeys(3), [(b"doomed".to_vec(), 10), (b"hot".to_vec(), 8), (b"warm".to_vec(), 6)]);
+
+        // two decay periods later the expired key is gone and the others have cooled
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(storage.hotkeys(3), [(b"hot".to_vec(), 6), (b"warm".to_vec(), 4)]);
+        assert_eq!(storage.frequency(b"doomed"), None);
+    }
+
+    #[test]
+    fn test_active_expiration_only_samples_volatile_keys() {
+        let (mut storage, _clock) = mocked();
+        let past = storage.now() - Duration::from_secs(1);
+        let later = storage.now() + Duration::from_secs(60);
+        for i in 0..1000 {
+            storage.set(format!("persistent{}", i).into_bytes(), StorageValue::String(b"v".to_vec()), None).unwrap();
+        }
+        for i in 0..10 {
+            let key = format!("volatile{}", i).into_bytes();
+            storage.set(key.clone(), StorageValue::String(b"v".to_vec()), Some(later)).unwrap();
+            assert!(storage.set_expiry(&key, Some(past)));
+        }
+        storage.set(b"kept".to_vec(), StorageValue::String(b"v".to_vec()), Some(later)).unwrap();
+        storage.set(b"persisted".to_vec(), StorageValue::String(b"v".to_vec()), Some(later)).unwrap();
+        assert!(storage.set_expiry(b"persisted", None));
+
+        // fast cycles wait until slow ones have found enough stale keys
+        assert_eq!(storage.active_expire_cycle(ExpireCycleType::Fast).keys_checked, 0);
+
+        let stats = storage.active_expire_cycle(ExpireCycleType::Slow);
+        // one round finds the 10 expired keys, the next only the one left, and stops there
+        assert_eq!((stats.keys_checked, stats.keys_expired), (12, 10));
+        assert!(stats.stopped_by_threshold);
+        assert!((stats.stale_ratio - 10.0 / 12.0).abs() < 1e-9);
+        assert!(storage.expired_stale_ratio() > 0.0);
+        assert_eq!(storage.volatile.len(), 1);
+        assert_eq!(storage.data.len(), 1002);
     }
 
     #[test]
@@ -336,15 +793,15 @@ mod tests {
             
             // should succeed - within limits
             assert!(storage.set(
-                "key1".to_string(),
-                StorageValue::String("123".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"123".to_vec()),
                 None
             ).is_ok());
 
             // should fail - exceeds memory limit
             assert!(storage.set(
-                "key2".to_string(), 
-                StorageValue::String("very long string".to_string()),
+                b"key2".to_vec(), 
+                StorageValue::String(b"very long string".to_vec()),
                 None
             ).is_err());
         }
@@ -354,23 +811,23 @@ mod tests {
             let mut storage = MemoryStorage::new(100);
             
             storage.set(
-                "key1".to_string(),
-                StorageValue::String("short".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"short".to_vec()),
                 None
             ).unwrap();
             let initial_memory = storage.used_memory;
 
             storage.set(
-                "key1".to_string(),
-                StorageValue::String("longer string".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"longer string".to_vec()),
                 None
             ).unwrap();
             
             assert!(storage.used_memory > initial_memory);
 
             storage.set(
-                "key1".to_string(),
-                StorageValue::String("tiny".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"tiny".to_vec()),
                 None
             ).unwrap();
 
@@ -382,15 +839,15 @@ mod tests {
             let mut storage = MemoryStorage::new(100);
             
             storage.set(
-                "key1".to_string(),
-                StorageValue::String("test value".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"test value".to_vec()),
                 None
             ).unwrap();
             
             let pre_delete_memory = storage.used_memory;
-            storage.delete("key1").unwrap();
+            storage.delete(b"key1").unwrap();
             
-            assert_eq!(storage.used_memory, 0);
+            assert_eq!((storage.used_memory, storage.used_memory()), (0, 0));
             assert!(storage.used_memory < pre_delete_memory);
         }
 
@@ -401,20 +858,20 @@ mod tests {
             
             // add expired entry
             storage.set(
-                "expired".to_string(),
-                StorageValue::String("value".to_string()),
+                b"expired".to_vec(),
+                StorageValue::String(b"value".to_vec()),
                 Some(now)
             ).unwrap();
 
             // add non-expired entry
             storage.set(
-                "valid".to_string(),
-                StorageValue::String("value".to_string()),
+                b"valid".to_vec(),
+                StorageValue::String(b"value".to_vec()),
                 Some(now + Duration::from_secs(30))
             ).unwrap();
 
-            assert!(storage.get("expired").is_err());
-            assert!(storage.get("valid").is_ok());
+            assert!(storage.get(b"expired").is_err());
+            assert!(storage.get(b"valid").is_ok());
         }
 
         #[test]
@@ -422,20 +879,125 @@ mod tests {
             let mut storage = MemoryStorage::new(100);
             
             storage.set(
-                "key1".to_string(),
-                StorageValue::String("value1".to_string()),
+                b"key1".to_vec(),
+                StorageValue::String(b"value1".to_vec()),
                 None
             ).unwrap();
             
             storage.set(
-                "key2".to_string(),
-                StorageValue::String("value2".to_string()),
+                b"key2".to_vec(),
+                StorageValue::String(b"value2".to_vec()),
                 None
             ).unwrap();
             
             storage.clear().unwrap();
             
-            assert!(storage.get("key1").is_err());
-            assert!(storage.get("key2").is_err());
+            assert!(storage.get(b"key1").is_err());
+            assert!(storage.get(b"key2").is_err());
         }
+
+        #[test]
+        fn test_binary_keys_and_values() {
+            let mut storage = MemoryStorage::new(100);
+            let key = b"\xff\x00bin".to_vec();
+
+            storage.set(key.clone(), StorageValue::String(b"\x00\x01".to_vec()), None).unwrap();
+            assert_eq!(storage.append(&key, b"\xfe").unwrap(), 3);
+
+            match &storage.get(&key).unwrap().data {
+                StorageValue::String(value) => assert_eq!(value, b"\x00\x01\xfe"),
+                _ => panic!("expected a string"),
+            }
+            assert!(matches!(storage.incr(&key), Err(RedisError::NotInteger)));
+        }
+
+    fn string(value: &str) -> StorageValue {
+        StorageValue::String(value.as_bytes().to_vec())
+    }
+
+    // room for four 10 byte strings under one letter keys, each key held three times
+    fn bounded(policy: EvictionPolicy) -> MemoryStorage {
+        let mut storage = MemoryStorage::new(0);
+        storage.set_maxmemory(52, EvictionConfig { policy, samples: 10, ..EvictionConfig::default() });
+        storage
+    }
+
+    #[test]
+    fn test_lru_evicts_the_idle_key() {
+        let mut storage = bounded(EvictionPolicy::AllKeysLru);
+        for key in ["a", "b", "c", "d"] {
+            storage.set(key.as_bytes().to_vec(), string("0123456789"), None).unwrap();
+        }
+        let an_hour_ago = storage.now_secs() - 3600;
+        storage.data.get_mut(&b"c"[..]).unwrap().access = Access::new(an_hour_ago);
+
+        storage.set(b"e".to_vec(), string("0123456789"), None).unwrap();
+        assert!(!storage.exists(b"c").unwrap());
+        assert_eq!((storage.data.len(), storage.keys.len(), storage.used_memory), (4, 4, 40));
+        assert_eq!(storage.used_memory(), 52);
+        assert_eq!(storage.evicted_keys(), 1);
+    }
+
+    #[test]
+    fn test_lfu_evicts_the_least_used_key() {
+        let mut storage = MemoryStorage::new(0);
+        let lfu = LfuConfig { log_factor: 0, decay_time: 1 };
+        storage.set_maxmemory(52, EvictionConfig { policy: EvictionPolicy::AllKeysLfu, samples: 10, lfu });
+        for key in ["a", "b", "c", "d"] {
+            storage.set(key.as_bytes().to_vec(), string("0123456789"), None).unwrap();
+        }
+        for key in ["a", "b", "d"] {
+            storage.get(key.as_bytes()).unwrap();
+        }
+        // reading the counter is not an access
+        assert_eq!(storage.frequency(b"c"), Some(5));
+        assert_eq!(storage.frequency(b"c"), Some(5));
+        assert_eq!(storage.frequency(b"a"), Some(6));
+        assert_eq!(storage.idle_time(b"a"), Some(0));
+        assert_eq!(storage.hotkeys(2), [(b"a".to_vec(), 6), (b"b".to_vec(), 6)]);
+
+        storage.set(b"e".to_vec(), string("0123456789"), None).unwrap();
+        assert!(!storage.exists(b"c").unwrap());
+        // overwriting keeps the key's history
+        storage.set(b"a".to_vec(), string("9876543210"), None).unwrap();
+        assert_eq!(storage.frequency(b"a"), Some(7));
+        assert_eq!(storage.frequency(b"missing"), None);
+    }
+
+    #[test]
+    fn test_volatile_policies_only_evict_keys_with_a_ttl() {
+        let later = SystemTime::now() + Duration::from_secs(60);
+        let mut storage = bounded(EvictionPolicy::VolatileTtl);
+        // keys with a ttl are also held by the volatile index
+        storage.set_maxmemory(56, storage.eviction_config());
+        storage.set(b"p".to_vec(), string("0123456789"), None).unwrap();
+        storage.set(b"l".to_vec(), string("0123456789"), Some(later + Duration::from_secs(60))).unwrap();
+        storage.set(b"s".to_vec(), string("0123456789"), Some(later)).unwrap();
+        storage.set(b"x".to_vec(), string("0123456789"), None).unwrap();
+        assert_eq!(storage.used_memory(), 56);
+
+        storage.set(b"y".to_vec(), string("0123456789"), None).unwrap();
+        assert!(!storage.exists(b"s").unwrap());
+        storage.set(b"z".to_vec(), string("0123456789"), None).unwrap();
+        assert!(!storage.exists(b"l").unwrap());
+        // only keys without a ttl are left
+        assert!(matches!(storage.set(b"w".to_vec(), string("0123456789"), None), Err(RedisError::OutOfMemory)));
+        assert!(storage.exists(b"p").unwrap());
+    }
+
+    #[test]
+    fn test_noeviction_and_oversized_writes() {
+        let mut storage = bounded(EvictionPolicy::NoEviction);
+        storage.set(b"a".to_vec(), string(&"x".repeat(49)), None).unwrap();
+        assert!(matches!(storage.append(b"a", b"!"), Err(RedisError::OutOfMemory)));
+
+        // a value larger than maxmemory cannot fit however much is evicted
+        let mut storage = bounded(EvictionPolicy::AllKeysRandom);
+        storage.set(b"a".to_vec(), string("0123456789"), None).unwrap();
+        assert!(matches!(storage.set(b"b".to_vec(), string(&"x".repeat(53)), None), Err(RedisError::OutOfMemory)));
+        // the key being written is never the one evicted to make room for it
+        storage.set(b"b".to_vec(), string(&"x".repeat(30)), None).unwrap();
+        assert!(storage.append(b"b", b"0123456789").is_ok());
+        assert_eq!(storage.data.keys().collect::<Vec<_>>(), [&b"b".to_vec()]);
+    }
 }
@@ -1,63 +1,1033 @@
-use crate::utils::error::{Result, RedisError};
-use std::time::SystemTime;
+use crate::utils::error::Result;
+use std::time::{Duration, SystemTime};
 use std::fmt;
-use crate::storage::data_types::string::RedisString;
+use data_types::DataType;
+
+pub use data_types::bitmap::{BitFieldOp, BitFieldType, BitOp, BitRange, BitUnit, Overflow};
+pub use data_types::geohash::GeoShape;
+pub use data_types::hash::RedisHash;
+pub use data_types::hyperloglog::HllEncoding;
+pub use data_types::list::{ListEnd, RedisList};
+pub use data_types::set::{RedisSet, SetLimits};
+pub use data_types::zset::{LexBound, LexRange, RedisZSet, ScoreBound, ScoreRange};
+pub use data_types::stream::{
+    ClaimOptions, ConsumerGroup, Delivered, Fields, Stream, StreamEntry, StreamId, StreamTrim, TrimStrategy, XAddId,
+};
+pub use data_types::ListpackLimits;
+pub use bitmaps::BitmapStorage;
+pub use geo::{GeoMatch, GeoStorage};
+pub use hashes::HashStorage;
+pub use hyperloglogs::HyperLogLogStorage;
+pub use lists::ListStorage;
+pub use sets::SetStorage;
+pub use streams::{PendingDetail, PendingSummary, StreamStorage};
+pub use strings::StringStorage;
+pub use zsets::ZSetStorage;
 
 pub mod memory;
 pub mod data_types;
+mod bitmaps;
+mod geo;
+mod hyperloglogs;
+mod lists;
+mod hashes;
+mod sets;
+mod zsets;
+mod streams;
+mod strings;
 pub mod eviction;
-// pub mod expiration;
+pub mod expiration;
+mod sample_index;
 
 #[derive(Debug,Clone)]
 pub struct StorageEntry {
     pub data: StorageValue,
     pub expires_at: Option<SystemTime>,
+    // recency and frequency of use, for the lru and lfu eviction policies
+    pub access: eviction::Access,
 }
 
+// keys and values are plain byte strings, redis never requires them to be utf-8
 #[derive(Debug,Clone)]
 pub enum StorageValue {
-    String(String),
-    List(Vec<String>),
-    // ...
+    String(Vec<u8>),
+    List(RedisList),
+    Hash(RedisHash),
+    Set(RedisSet),
+    ZSet(RedisZSet),
+    Stream(Stream),
+}
+
+impl StorageValue {
+    // bytes held by the value, kept O(1) so every write can account for it
+    pub fn memory_usage(&self) -> usize {
+        match self {
+            StorageValue::String(s) => s.len(),
+            StorageValue::List(list) => list.memory_usage(),
+            StorageValue::Hash(hash) => hash.memory_usage(),
+            StorageValue::Set(set) => set.memory_usage(),
+            StorageValue::ZSet(zset) => zset.memory_usage(),
+            StorageValue::Stream(stream) => stream.memory_usage(),
+        }
+    }
+
+    // aggregates are deleted as soon as they become empty, like in redis; streams are the
+    // exception, they keep their last id and consumer groups
+    pub fn is_empty_collection(&self) -> bool {
+        match self {
+            StorageValue::String(_) | StorageValue::Stream(_) => false,
+            StorageValue::List(list) => list.is_empty(),
+            StorageValue::Hash(hash) => hash.is_empty(),
+            StorageValue::Set(set) => set.is_empty(),
+            StorageValue::ZSet(zset) => zset.is_empty(),
+        }
+    }
+
+    // the internal representation, as reported by OBJECT ENCODING
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            // same cutoffs as redis: integers that fit a long, then strings short enough
+            // to be allocated along with their object header
+            StorageValue::String(s) if s.len() <= 20 && parse_i64(s).is_some() => "int",
+            StorageValue::String(s) if s.len() <= 44 => "embstr",
+            StorageValue::String(_) => "raw",
+            StorageValue::List(_) => "quicklist",
+            StorageValue::Hash(hash) => hash.encoding(),
+            StorageValue::Set(set) => set.encoding(),
+            StorageValue::ZSet(zset) => zset.encoding(),
+            StorageValue::Stream(stream) => stream.encoding(),
+        }
+    }
+}
+
+fn parse_i64(value: &[u8]) -> Option<i64> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+// size thresholds for the compact encodings, set from the redis.conf directives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodingLimits {
+    pub hash: ListpackLimits,
+    pub set: SetLimits,
+    // hll-sparse-max-bytes: the largest sparse HyperLogLog, header included
+    pub hll_sparse_max_bytes: usize,
+}
+
+impl Default for EncodingLimits {
+    fn default() -> Self {
+        // hash-max-listpack-entries 128, hash-max-listpack-value 64
+        Self {
+            hash: ListpackLimits::new(128, 64),
+            set: SetLimits::default(),
+            hll_sparse_max_bytes: data_types::hyperloglog::DEFAULT_SPARSE_MAX_BYTES,
+        }
+    }
 }
 
 pub trait Storage {
-    fn set(&mut self, key: String, value: StorageValue, ttl: Option<SystemTime>) -> Result<()>;
-    fn get(&mut self, key: &str) -> Result<&StorageEntry>;
-    fn delete(&mut self, key: &str) -> Result<bool>;
-    fn exists(&self, key: &str) -> Result<bool>;
+    fn set(&mut self, key: Vec<u8>, value: StorageValue, ttl: Option<SystemTime>) -> Result<()>;
+    fn get(&mut self, key: &[u8]) -> Result<&StorageEntry>;
+    fn delete(&mut self, key: &[u8]) -> Result<bool>;
+    fn exists(&mut self, key: &[u8]) -> Result<bool>;
     fn clear(&mut self) -> Result<()>;
-    fn incr(&mut self, key: &str) -> Result<i64>;
-    fn decr(&mut self, key: &str) -> Result<i64>;
-    fn expire(&mut self, key: &str, ttl: u64) -> Result<()>;
-    fn append(&mut self, key: &str, value: &str) -> Result<String>;
+    fn incr(&mut self, key: &[u8]) -> Result<i64>;
+    fn decr(&mut self, key: &[u8]) -> Result<i64>;
+    // sets the deadline, in milliseconds since the epoch, when `options` allow it; one
+    // already past deletes the key. false when the key is missing or nothing changed
+    fn expire(&mut self, key: &[u8], at_ms: i64, options: ExpireOptions) -> Result<bool>;
+    // drops the ttl; false when there was none
+    fn persist(&mut self, key: &[u8]) -> Result<bool>;
+    fn expire_time(&mut self, key: &[u8]) -> Result<KeyExpiry>;
+    fn append(&mut self, key: &[u8], value: &[u8]) -> Result<usize>;
 }
 
 #[derive(Debug)]
 pub enum Command {
-    Get(String),
-    Set(String, StorageValue, Option<SystemTime>),
-    Del(String),
-    Exists(String),
-    Expire(String, u64),
-    Incr(String),
-    Decr(String),
-    Append(String, String),
+    Get(Vec<u8>),
+    Set(Vec<u8>, StorageValue, SetOptions),
+    Del(Vec<u8>),
+    Exists(Vec<u8>),
+    // EXPIRE, PEXPIRE, EXPIREAT and PEXPIREAT, in milliseconds since the epoch, or from
+    // when the command runs with `from_now`
+    Expire {
+        key: Vec<u8>,
+        ms: i64,
+        from_now: bool,
+        options: ExpireOptions,
+    },
+    Ttl(Vec<u8>, TtlQuery),
+    Persist(Vec<u8>),
+    Incr(Vec<u8>),
+    Decr(Vec<u8>),
+    // INCRBY, and DECRBY with the delta negated
+    IncrBy(Vec<u8>, i64),
+    IncrByFloat(Vec<u8>, f64),
+    GetRange(Vec<u8>, i64, i64),
+    SetRange(Vec<u8>, usize, Vec<u8>),
+    StrLen(Vec<u8>),
+    // GETEX key [EX | PX | EXAT | PXAT | PERSIST]; Keep when no option is given
+    GetEx(Vec<u8>, SetExpiry),
+    GetDel(Vec<u8>),
+    MGet(Vec<Vec<u8>>),
+    // MSET, and MSETNX / SETNX with `only_new`, which reply with whether anything was set
+    MSet {
+        pairs: Vec<(Vec<u8>, Vec<u8>)>,
+        only_new: bool,
+    },
+    Append(Vec<u8>, Vec<u8>),
+    SetBit(Vec<u8>, u64, bool),
+    GetBit(Vec<u8>, u64),
+    BitCount(Vec<u8>, Option<BitRange>),
+    // BITPOS key bit [start [end [BYTE | BIT]]]; a range without an end runs to the last byte
+    BitPos {
+        key: Vec<u8>,
+        bit: bool,
+        range: Option<BitRange>,
+        end_given: bool,
+    },
+    BitOp {
+        op: BitOp,
+        destination: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+    },
+    // BITFIELD and BITFIELD_RO, each operation carrying the OVERFLOW mode in effect for it
+    BitField(Vec<u8>, Vec<BitFieldOp>),
+    PfAdd(Vec<u8>, Vec<Vec<u8>>),
+    PfCount(Vec<Vec<u8>>),
+    PfMerge(Vec<u8>, Vec<Vec<u8>>),
+    PfDebug(PfDebug, Vec<u8>),
     FlushDB,
+    Ping(Option<Vec<u8>>),
+    Echo(Vec<u8>),
+    // LPUSH / RPUSH, and LPUSHX / RPUSHX with `only_existing`
+    Push {
+        key: Vec<u8>,
+        values: Vec<Vec<u8>>,
+        end: ListEnd,
+        only_existing: bool,
+    },
+    // LPOP / RPOP; with a count the reply is always an array
+    Pop {
+        key: Vec<u8>,
+        end: ListEnd,
+        count: Option<usize>,
+    },
+    LLen(Vec<u8>),
+    LRange(Vec<u8>, i64, i64),
+    LIndex(Vec<u8>, i64),
+    LSet(Vec<u8>, i64, Vec<u8>),
+    LInsert {
+        key: Vec<u8>,
+        side: ListEnd,
+        pivot: Vec<u8>,
+        value: Vec<u8>,
+    },
+    LRem(Vec<u8>, i64, Vec<u8>),
+    LTrim(Vec<u8>, i64, i64),
+    LPos {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        rank: i64,
+        // None without COUNT, which replies with a single index instead of an array
+        count: Option<usize>,
+        maxlen: usize,
+    },
+    // LMOVE, and RPOPLPUSH as LMOVE source destination RIGHT LEFT
+    LMove {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        from: ListEnd,
+        to: ListEnd,
+    },
+    // BLPOP / BRPOP; a None timeout waits forever
+    BPop {
+        keys: Vec<Vec<u8>>,
+        end: ListEnd,
+        timeout: Option<Duration>,
+    },
+    // BLMOVE, and BRPOPLPUSH as BLMOVE source destination RIGHT LEFT
+    BLMove {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Option<Duration>,
+    },
+    // HSET key field value [field value ...]
+    HSet {
+        key: Vec<u8>,
+        pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    },
+    HSetNx {
+        key: Vec<u8>,
+        field: Vec<u8>,
+        value: Vec<u8>,
+    },
+    HGet(Vec<u8>, Vec<u8>),
+    HMGet(Vec<u8>, Vec<Vec<u8>>),
+    HDel(Vec<u8>, Vec<Vec<u8>>),
+    HExists(Vec<u8>, Vec<u8>),
+    HLen(Vec<u8>),
+    HKeys(Vec<u8>),
+    HVals(Vec<u8>),
+    HGetAll(Vec<u8>),
+    HIncrBy(Vec<u8>, Vec<u8>, i64),
+    HIncrByFloat(Vec<u8>, Vec<u8>, f64),
+    HStrLen(Vec<u8>, Vec<u8>),
+    HRandField {
+        key: Vec<u8>,
+        // None replies with a single field; negative counts may repeat fields
+        count: Option<i64>,
+        with_values: bool,
+    },
+    HScan {
+        key: Vec<u8>,
+        cursor: u64,
+        options: ScanOptions,
+    },
+    SAdd(Vec<u8>, Vec<Vec<u8>>),
+    SRem(Vec<u8>, Vec<Vec<u8>>),
+    SIsMember(Vec<u8>, Vec<u8>),
+    SMIsMember(Vec<u8>, Vec<Vec<u8>>),
+    SMembers(Vec<u8>),
+    SCard(Vec<u8>),
+    // without a count the reply is a single member
+    SPop {
+        key: Vec<u8>,
+        count: Option<usize>,
+    },
+    SRandMember {
+        key: Vec<u8>,
+        count: Option<i64>,
+    },
+    SMove {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+        member: Vec<u8>,
+    },
+    // SINTER / SUNION / SDIFF
+    SetOp(SetOp, Vec<Vec<u8>>),
+    // SINTERSTORE / SUNIONSTORE / SDIFFSTORE
+    SetOpStore {
+        op: SetOp,
+        destination: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+    },
+    SInterCard {
+        keys: Vec<Vec<u8>>,
+        // 0 means no limit
+        limit: usize,
+    },
+    SScan {
+        key: Vec<u8>,
+        cursor: u64,
+        options: ScanOptions,
+    },
+    ZAdd {
+        key: Vec<u8>,
+        members: Vec<(f64, Vec<u8>)>,
+        options: ZAddOptions,
+    },
+    ZRem(Vec<u8>, Vec<Vec<u8>>),
+    ZScore(Vec<u8>, Vec<u8>),
+    ZIncrBy(Vec<u8>, f64, Vec<u8>),
+    ZCard(Vec<u8>),
+    ZCount(Vec<u8>, ScoreRange),
+    // ZRANK / ZREVRANK
+    ZRank {
+        key: Vec<u8>,
+        member: Vec<u8>,
+        rev: bool,
+        with_score: bool,
+    },
+    ZRange(Vec<u8>, ZRangeQuery),
+    ZRangeStore {
+        destination: Vec<u8>,
+        source: Vec<u8>,
+        query: ZRangeQuery,
+    },
+    // ZPOPMIN / ZPOPMAX; without a count the reply is a single pair
+    ZPop {
+        key: Vec<u8>,
+        end: ZEnd,
+        count: Option<usize>,
+    },
+    // BZPOPMIN / BZPOPMAX; a None timeout waits forever
+    BZPop {
+        keys: Vec<Vec<u8>>,
+        end: ZEnd,
+        timeout: Option<Duration>,
+    },
+    // ZUNIONSTORE / ZINTERSTORE; plain sets take part with a score of 1
+    ZSetOpStore {
+        op: SetOp,
+        destination: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        // one per key
+        weights: Vec<f64>,
+        aggregate: Aggregate,
+    },
+    ZScan {
+        key: Vec<u8>,
+        cursor: u64,
+        options: ScanOptions,
+    },
+    GeoPos(Vec<u8>, Vec<Vec<u8>>),
+    // GEODIST key member1 member2 [M | KM | FT | MI]; `unit` is in meters
+    GeoDist {
+        key: Vec<u8>,
+        from: Vec<u8>,
+        to: Vec<u8>,
+        unit: f64,
+    },
+    GeoHash(Vec<u8>, Vec<Vec<u8>>),
+    GeoSearch(Vec<u8>, GeoSearch),
+    // GEOSEARCHSTORE; STOREDIST scores members by distance rather than by geohash
+    GeoSearchStore {
+        destination: Vec<u8>,
+        source: Vec<u8>,
+        query: GeoSearch,
+        store_dist: bool,
+    },
+    // XADD key [NOMKSTREAM] [MAXLEN | MINID [= | ~] threshold [LIMIT count]] id field value ...
+    XAdd {
+        key: Vec<u8>,
+        id: XAddId,
+        fields: Fields,
+        trim: Option<StreamTrim>,
+        no_mkstream: bool,
+    },
+    XTrim(Vec<u8>, StreamTrim),
+    // XRANGE / XREVRANGE, with exclusive bounds already turned into inclusive ones
+    XRange {
+        key: Vec<u8>,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    },
+    XLen(Vec<u8>),
+    XDel(Vec<u8>, Vec<StreamId>),
+    // XREAD [COUNT count] [BLOCK ms] STREAMS key ... id ...; a None timeout waits forever
+    XRead {
+        keys: Vec<Vec<u8>>,
+        ids: Vec<StreamReadId>,
+        count: Option<usize>,
+        block: bool,
+        timeout: Option<Duration>,
+    },
+    // XREADGROUP GROUP group consumer [COUNT count] [BLOCK ms] [NOACK] STREAMS key ... id ...
+    XReadGroup {
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        keys: Vec<Vec<u8>>,
+        ids: Vec<StreamReadId>,
+        count: Option<usize>,
+        no_ack: bool,
+        block: bool,
+        timeout: Option<Duration>,
+    },
+    XAck {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        ids: Vec<StreamId>,
+    },
+    // the summary form without a range
+    XPending {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        range: Option<PendingRange>,
+    },
+    XClaim {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        min_idle: u64,
+        ids: Vec<StreamId>,
+        options: ClaimOptions,
+    },
+    XAutoClaim {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        consumer: Vec<u8>,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    },
+    XGroup(XGroupCommand),
+    XInfo(XInfoQuery),
+    Object(ObjectQuery),
+    // the `count` keys with the highest access counters
+    HotKeys(usize),
+    Debug(DebugCommand),
+    // connection commands, answered by the engine rather than the storage
+    Hello {
+        protover: Option<i64>,
+        auth: Option<(Vec<u8>, Vec<u8>)>,
+        setname: Option<Vec<u8>>,
+    },
+    // answered from the command table
+    Command(CommandQuery),
+}
+
+// SET [NX | XX] [GET] [EX | PX | EXAT | PXAT | KEEPTTL]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SetOptions {
+    pub condition: Option<SetCondition>,
+    pub expiry: SetExpiry,
+    // reply with the previous value instead of OK
+    pub get: bool,
+}
+
+impl SetOptions {
+    pub fn expire_at(at: SystemTime) -> Self {
+        Self {
+            expiry: SetExpiry::At(at),
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    // only set keys that do not exist yet
+    Nx,
+    // only overwrite existing keys
+    Xx,
+}
+
+// EXPIRE [NX | XX] [GT | LT]; for the comparison a key without a ttl never expires
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpireOptions {
+    pub condition: Option<SetCondition>,
+    pub comparison: Option<ScoreComparison>,
+}
+
+// a key's deadline as TTL and EXPIRETIME report it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExpiry {
+    Missing,
+    Persistent,
+    // milliseconds since the epoch
+    At(u64),
+}
+
+// TTL, PTTL, EXPIRETIME and PEXPIRETIME differ only in unit and base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtlQuery {
+    Ttl,
+    PTtl,
+    ExpireTime,
+    PExpireTime,
+}
+
+impl TtlQuery {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TtlQuery::Ttl => "TTL",
+            TtlQuery::PTtl => "PTTL",
+            TtlQuery::ExpireTime => "EXPIRETIME",
+            TtlQuery::PExpireTime => "PEXPIRETIME",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SetExpiry {
+    // a plain SET discards any previous ttl
+    #[default]
+    Persist,
+    At(SystemTime),
+    // EX / PX; measured from when the command runs, against the storage clock
+    After(Duration),
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl SetOp {
+    // INTER, UNION or DIFF, as in SINTER / SUNIONSTORE
+    pub fn name(&self) -> &'static str {
+        match self {
+            SetOp::Inter => "INTER",
+            SetOp::Union => "UNION",
+            SetOp::Diff => "DIFF",
+        }
+    }
+}
+
+// ZADD [NX | XX] [GT | LT] [CH] [INCR]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: Option<SetCondition>,
+    pub comparison: Option<ScoreComparison>,
+    // count changed scores, not just new members
+    pub ch: bool,
+    pub incr: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreComparison {
+    // only update scores upwards
+    Gt,
+    // only update scores downwards
+    Lt,
+}
+
+// which end of a sorted set ZPOPMIN / ZPOPMAX work on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZEnd {
+    Min,
+    Max,
+}
+
+impl ZEnd {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ZEnd::Min => "MIN",
+            ZEnd::Max => "MAX",
+        }
+    }
+}
+
+// how ZUNIONSTORE / ZINTERSTORE combine the scores of a member found in several keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    // start and stop ranks, negative ones counting from the end
+    Rank(i64, i64),
+    Score(ScoreRange),
+    Lex(LexRange),
+}
+
+// ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeQuery {
+    pub by: ZRangeBy,
+    pub rev: bool,
+    pub offset: usize,
+    // None returns everything after the offset
+    pub limit: Option<usize>,
+    pub with_scores: bool,
+}
+
+// where GEOSEARCH measures from
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(Vec<u8>),
+    LonLat(f64, f64),
+}
+
+// ASC / DESC, by distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSort {
+    Asc,
+    Desc,
+}
+
+// GEOSEARCH key FROMMEMBER member | FROMLONLAT lon lat BYRADIUS radius unit | BYBOX width height unit
+//   [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoSearch {
+    pub origin: GeoOrigin,
+    // in meters
+    pub shape: GeoShape,
+    // meters per unit of the shape, which distances are replied in
+    pub unit: f64,
+    // None keeps the order the index was scanned in
+    pub sort: Option<GeoSort>,
+    pub count: Option<usize>,
+    // stop at the first `count` matches rather than the nearest ones
+    pub any: bool,
+    pub with_coord: bool,
+    pub with_dist: bool,
+    pub with_hash: bool,
+}
+
+// PFDEBUG subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PfDebug {
+    GetReg,
+    Decode,
+    Encoding,
+    ToDense,
+}
+
+// an id given to XREAD / XREADGROUP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamReadId {
+    // `$`: only entries added from now on
+    Last,
+    // `>`: entries never delivered to the group
+    New,
+    After(StreamId),
+}
+
+// XPENDING key group [IDLE min-idle-time] start end count [consumer]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRange {
+    pub min_idle: u64,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<Vec<u8>>,
+}
+
+// XGROUP subcommands; a None id stands for `$`, the stream's last id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XGroupCommand {
+    Create {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    },
+    SetId {
+        key: Vec<u8>,
+        group: Vec<u8>,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    },
+    Destroy { key: Vec<u8>, group: Vec<u8> },
+    CreateConsumer { key: Vec<u8>, group: Vec<u8>, consumer: Vec<u8> },
+    DelConsumer { key: Vec<u8>, group: Vec<u8>, consumer: Vec<u8> },
+}
+
+// XINFO subcommands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XInfoQuery {
+    Stream(Vec<u8>),
+    Groups(Vec<u8>),
+    Consumers(Vec<u8>, Vec<u8>),
+}
+
+// the MATCH / COUNT / NOVALUES options shared by the *SCAN commands
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOptions {
+    pub pattern: Option<Vec<u8>>,
+    // a hint for how much work one call does, not an exact reply size
+    pub count: usize,
+    pub no_values: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            pattern: None,
+            count: 10,
+            no_values: false,
+        }
+    }
+}
+
+// OBJECT subcommands
+#[derive(Debug)]
+pub enum ObjectQuery {
+    Encoding(Vec<u8>),
+    Freq(Vec<u8>),
+    IdleTime(Vec<u8>),
+}
+
+// DEBUG subcommands, switches for tests rather than for production use
+#[derive(Debug)]
+pub enum DebugCommand {
+    // turns the active expire cycle off and on; lazy expiry on access always runs
+    SetActiveExpire(bool),
+}
+
+// COMMAND subcommands
+#[derive(Debug)]
+pub enum CommandQuery {
+    List,
+    Count,
+    Info(Vec<Vec<u8>>),
+    Docs(Vec<Vec<u8>>),
+    // the full argv of the command whose keys are wanted
+    GetKeys(Vec<Vec<u8>>),
 }
 
 impl fmt::Display for Command {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Command::Get(key) => write!(f, "GET {}", key),
-            Command::Set(key, _, _) => write!(f, "SET {}", key),
-            Command::Del(key) => write!(f, "DEL {}", key),
-            Command::Exists(key) => write!(f, "EXISTS {}", key),
+            Command::Get(key) => write!(f, "GET {}", String::from_utf8_lossy(key)),
+            Command::Set(key, _, _) => write!(f, "SET {}", String::from_utf8_lossy(key)),
+            Command::Del(key) => write!(f, "DEL {}", String::from_utf8_lossy(key)),
+            Command::Exists(key) => write!(f, "EXISTS {}", String::from_utf8_lossy(key)),
             Command::FlushDB => write!(f, "CLEAR"),
-            Command::Expire(key, ttl) => write!(f, "EXPIRE {} {}", key, ttl),
-            Command::Incr(key) => write!(f, "INCR {}", key),
-            Command::Decr(key) => write!(f, "DECR {}", key),
-            Command::Append(key, value) => write!(f, "APPEND {} {}", key, value),
+            Command::Ping(_) => write!(f, "PING"),
+            Command::Echo(message) => write!(f, "ECHO {}", String::from_utf8_lossy(message)),
+            Command::Expire { key, ms, from_now, .. } => {
+                let name = if *from_now { "PEXPIRE" } else { "PEXPIREAT" };
+                write!(f, "{} {} {}", name, String::from_utf8_lossy(key), ms)
+            }
+            Command::Ttl(key, query) => write!(f, "{} {}", query.name(), String::from_utf8_lossy(key)),
+            Command::Persist(key) => write!(f, "PERSIST {}", String::from_utf8_lossy(key)),
+            Command::Incr(key) => write!(f, "INCR {}", String::from_utf8_lossy(key)),
+            Command::Decr(key) => write!(f, "DECR {}", String::from_utf8_lossy(key)),
+            Command::IncrBy(key, delta) => write!(f, "INCRBY {} {}", String::from_utf8_lossy(key), delta),
+            Command::IncrByFloat(key, delta) => write!(f, "INCRBYFLOAT {} {}", String::from_utf8_lossy(key), delta),
+            Command::GetRange(key, start, end) => {
+                write!(f, "GETRANGE {} {} {}", String::from_utf8_lossy(key), start, end)
+            }
+            Command::SetRange(key, offset, _) => write!(f, "SETRANGE {} {}", String::from_utf8_lossy(key), offset),
+            Command::StrLen(key) => write!(f, "STRLEN {}", String::from_utf8_lossy(key)),
+            Command::GetEx(key, _) => write!(f, "GETEX {}", String::from_utf8_lossy(key)),
+            Command::GetDel(key) => write!(f, "GETDEL {}", String::from_utf8_lossy(key)),
+            Command::MGet(keys) => {
+                write!(f, "MGET")?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::MSet { pairs, only_new } => {
+                write!(f, "{}", if *only_new { "MSETNX" } else { "MSET" })?;
+                for (key, _) in pairs {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::Hello { protover, .. } => match protover {
+                Some(version) => write!(f, "HELLO {}", version),
+                None => write!(f, "HELLO"),
+            },
+            Command::Command(_) => write!(f, "COMMAND"),
+            Command::Push { key, end, .. } => write!(f, "{}PUSH {}", end.initial(), String::from_utf8_lossy(key)),
+            Command::Pop { key, end, .. } => write!(f, "{}POP {}", end.initial(), String::from_utf8_lossy(key)),
+            Command::LLen(key) => write!(f, "LLEN {}", String::from_utf8_lossy(key)),
+            Command::LRange(key, start, stop) => write!(f, "LRANGE {} {} {}", String::from_utf8_lossy(key), start, stop),
+            Command::LIndex(key, index) => write!(f, "LINDEX {} {}", String::from_utf8_lossy(key), index),
+            Command::LSet(key, index, _) => write!(f, "LSET {} {}", String::from_utf8_lossy(key), index),
+            Command::LInsert { key, .. } => write!(f, "LINSERT {}", String::from_utf8_lossy(key)),
+            Command::LRem(key, count, _) => write!(f, "LREM {} {}", String::from_utf8_lossy(key), count),
+            Command::LTrim(key, start, stop) => write!(f, "LTRIM {} {} {}", String::from_utf8_lossy(key), start, stop),
+            Command::LPos { key, .. } => write!(f, "LPOS {}", String::from_utf8_lossy(key)),
+            Command::LMove { source, destination, .. } => write!(
+                f,
+                "LMOVE {} {}",
+                String::from_utf8_lossy(source),
+                String::from_utf8_lossy(destination)
+            ),
+            Command::BPop { keys, end, .. } => {
+                write!(f, "B{}POP", end.initial())?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::BLMove { source, destination, .. } => write!(
+                f,
+                "BLMOVE {} {}",
+                String::from_utf8_lossy(source),
+                String::from_utf8_lossy(destination)
+            ),
+            Command::HSet { key, .. } => write!(f, "HSET {}", String::from_utf8_lossy(key)),
+            Command::HSetNx { key, field, .. } => {
+                write!(f, "HSETNX {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(field))
+            }
+            Command::HGet(key, field) => {
+                write!(f, "HGET {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(field))
+            }
+            Command::HMGet(key, _) => write!(f, "HMGET {}", String::from_utf8_lossy(key)),
+            Command::HDel(key, _) => write!(f, "HDEL {}", String::from_utf8_lossy(key)),
+            Command::HExists(key, field) => {
+                write!(f, "HEXISTS {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(field))
+            }
+            Command::HLen(key) => write!(f, "HLEN {}", String::from_utf8_lossy(key)),
+            Command::HKeys(key) => write!(f, "HKEYS {}", String::from_utf8_lossy(key)),
+            Command::HVals(key) => write!(f, "HVALS {}", String::from_utf8_lossy(key)),
+            Command::HGetAll(key) => write!(f, "HGETALL {}", String::from_utf8_lossy(key)),
+            Command::HIncrBy(key, field, delta) => write!(
+                f,
+                "HINCRBY {} {} {}",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(field),
+                delta
+            ),
+            Command::HIncrByFloat(key, field, delta) => write!(
+                f,
+                "HINCRBYFLOAT {} {} {}",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(field),
+                delta
+            ),
+            Command::HStrLen(key, field) => {
+                write!(f, "HSTRLEN {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(field))
+            }
+            Command::HRandField { key, .. } => write!(f, "HRANDFIELD {}", String::from_utf8_lossy(key)),
+            Command::HScan { key, cursor, .. } => write!(f, "HSCAN {} {}", String::from_utf8_lossy(key), cursor),
+            Command::SAdd(key, _) => write!(f, "SADD {}", String::from_utf8_lossy(key)),
+            Command::SRem(key, _) => write!(f, "SREM {}", String::from_utf8_lossy(key)),
+            Command::SIsMember(key, member) => {
+                write!(f, "SISMEMBER {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(member))
+            }
+            Command::SMIsMember(key, _) => write!(f, "SMISMEMBER {}", String::from_utf8_lossy(key)),
+            Command::SMembers(key) => write!(f, "SMEMBERS {}", String::from_utf8_lossy(key)),
+            Command::SCard(key) => write!(f, "SCARD {}", String::from_utf8_lossy(key)),
+            Command::SPop { key, .. } => write!(f, "SPOP {}", String::from_utf8_lossy(key)),
+            Command::SRandMember { key, .. } => write!(f, "SRANDMEMBER {}", String::from_utf8_lossy(key)),
+            Command::SMove { source, destination, .. } => write!(
+                f,
+                "SMOVE {} {}",
+                String::from_utf8_lossy(source),
+                String::from_utf8_lossy(destination)
+            ),
+            Command::SetOp(op, keys) => {
+                write!(f, "S{}", op.name())?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::SetOpStore { op, destination, .. } => {
+                write!(f, "S{}STORE {}", op.name(), String::from_utf8_lossy(destination))
+            }
+            Command::SInterCard { keys, .. } => write!(f, "SINTERCARD {}", keys.len()),
+            Command::SScan { key, cursor, .. } => write!(f, "SSCAN {} {}", String::from_utf8_lossy(key), cursor),
+            Command::ZAdd { key, .. } => write!(f, "ZADD {}", String::from_utf8_lossy(key)),
+            Command::ZRem(key, _) => write!(f, "ZREM {}", String::from_utf8_lossy(key)),
+            Command::ZScore(key, member) => {
+                write!(f, "ZSCORE {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(member))
+            }
+            Command::ZIncrBy(key, delta, member) => write!(
+                f,
+                "ZINCRBY {} {} {}",
+                String::from_utf8_lossy(key),
+                delta,
+                String::from_utf8_lossy(member)
+            ),
+            Command::ZCard(key) => write!(f, "ZCARD {}", String::from_utf8_lossy(key)),
+            Command::ZCount(key, _) => write!(f, "ZCOUNT {}", String::from_utf8_lossy(key)),
+            Command::ZRank { key, member, rev, .. } => write!(
+                f,
+                "{} {} {}",
+                if *rev { "ZREVRANK" } else { "ZRANK" },
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(member)
+            ),
+            Command::ZRange(key, _) => write!(f, "ZRANGE {}", String::from_utf8_lossy(key)),
+            Command::ZRangeStore { destination, source, .. } => write!(
+                f,
+                "ZRANGESTORE {} {}",
+                String::from_utf8_lossy(destination),
+                String::from_utf8_lossy(source)
+            ),
+            Command::ZPop { key, end, .. } => write!(f, "ZPOP{} {}", end.name(), String::from_utf8_lossy(key)),
+            Command::BZPop { keys, end, .. } => {
+                write!(f, "BZPOP{}", end.name())?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::ZSetOpStore { op, destination, .. } => {
+                write!(f, "Z{}STORE {}", op.name(), String::from_utf8_lossy(destination))
+            }
+            Command::ZScan { key, cursor, .. } => write!(f, "ZSCAN {} {}", String::from_utf8_lossy(key), cursor),
+            Command::GeoPos(key, _) => write!(f, "GEOPOS {}", String::from_utf8_lossy(key)),
+            Command::GeoDist { key, from, to, .. } => write!(
+                f,
+                "GEODIST {} {} {}",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(from),
+                String::from_utf8_lossy(to)
+            ),
+            Command::GeoHash(key, _) => write!(f, "GEOHASH {}", String::from_utf8_lossy(key)),
+            Command::GeoSearch(key, _) => write!(f, "GEOSEARCH {}", String::from_utf8_lossy(key)),
+            Command::GeoSearchStore { destination, source, .. } => write!(
+                f,
+                "GEOSEARCHSTORE {} {}",
+                String::from_utf8_lossy(destination),
+                String::from_utf8_lossy(source)
+            ),
+            Command::XAdd { key, .. } => write!(f, "XADD {}", String::from_utf8_lossy(key)),
+            Command::XTrim(key, _) => write!(f, "XTRIM {}", String::from_utf8_lossy(key)),
+            Command::XRange { key, rev, .. } => {
+                write!(f, "{} {}", if *rev { "XREVRANGE" } else { "XRANGE" }, String::from_utf8_lossy(key))
+            }
+            Command::XLen(key) => write!(f, "XLEN {}", String::from_utf8_lossy(key)),
+            Command::XDel(key, _) => write!(f, "XDEL {}", String::from_utf8_lossy(key)),
+            Command::XRead { keys, .. } => {
+                write!(f, "XREAD")?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::XReadGroup { group, keys, .. } => {
+                write!(f, "XREADGROUP {}", String::from_utf8_lossy(group))?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::XAck { key, group, .. } => {
+                write!(f, "XACK {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(group))
+            }
+            Command::XPending { key, group, .. } => {
+                write!(f, "XPENDING {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(group))
+            }
+            Command::XClaim { key, group, .. } => {
+                write!(f, "XCLAIM {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(group))
+            }
+            Command::XAutoClaim { key, group, .. } => {
+                write!(f, "XAUTOCLAIM {} {}", String::from_utf8_lossy(key), String::from_utf8_lossy(group))
+            }
+            Command::XGroup(_) => write!(f, "XGROUP"),
+            Command::XInfo(_) => write!(f, "XINFO"),
+            Command::Object(ObjectQuery::Encoding(key)) => {
+                write!(f, "OBJECT ENCODING {}", String::from_utf8_lossy(key))
+            }
+            Command::Object(ObjectQuery::Freq(key)) => write!(f, "OBJECT FREQ {}", String::from_utf8_lossy(key)),
+            Command::Object(ObjectQuery::IdleTime(key)) => {
+                write!(f, "OBJECT IDLETIME {}", String::from_utf8_lossy(key))
+            }
+            Command::HotKeys(count) => write!(f, "HOTKEYS COUNT {}", count),
+            Command::Debug(DebugCommand::SetActiveExpire(enabled)) => {
+                write!(f, "DEBUG SET-ACTIVE-EXPIRE {}", *enabled as u8)
+            }
+            Command::SetBit(key, offset, on) => {
+                write!(f, "SETBIT {} {} {}", String::from_utf8_lossy(key), offset, *on as u8)
+            }
+            Command::GetBit(key, offset) => write!(f, "GETBIT {} {}", String::from_utf8_lossy(key), offset),
+            Command::BitCount(key, _) => write!(f, "BITCOUNT {}", String::from_utf8_lossy(key)),
+            Command::BitPos { key, bit, .. } => write!(f, "BITPOS {} {}", String::from_utf8_lossy(key), *bit as u8),
+            Command::BitOp { op, destination, .. } => {
+                write!(f, "BITOP {:?} {}", op, String::from_utf8_lossy(destination))
+            }
+            Command::BitField(key, _) => write!(f, "BITFIELD {}", String::from_utf8_lossy(key)),
+            Command::PfAdd(key, _) => write!(f, "PFADD {}", String::from_utf8_lossy(key)),
+            Command::PfCount(keys) => {
+                write!(f, "PFCOUNT")?;
+                for key in keys {
+                    write!(f, " {}", String::from_utf8_lossy(key))?;
+                }
+                Ok(())
+            }
+            Command::PfMerge(destination, _) => write!(f, "PFMERGE {}", String::from_utf8_lossy(destination)),
+            Command::PfDebug(subcommand, key) => {
+                write!(f, "PFDEBUG {:?} {}", subcommand, String::from_utf8_lossy(key))
+            }
+            Command::Append(key, value) => write!(
+                f,
+                "APPEND {} {}",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(value)
+            ),
         }
     }
 }
@@ -0,0 +1,106 @@
+// keys with a value each, with O(1) insertion, removal and uniform random sampling; the
+// keyspace index used by eviction and the volatile index used by expiry are both one of these
+use std::collections::HashMap;
+use rand::Rng;
+
+// every key is held twice, in the sampling vec and in the position map
+const KEY_COPIES: usize = 2;
+
+#[derive(Debug)]
+pub struct SampleIndex<V> {
+    entries: Vec<(Vec<u8>, V)>,
+    positions: HashMap<Vec<u8>, usize>,
+    // bytes spent on key copies, counted toward maxmemory
+    memory: usize,
+}
+
+impl<V> Default for SampleIndex<V> {
+    fn default() -> Self {
+        Self { entries: Vec::new(), positions: HashMap::new(), memory: 0 }
+    }
+}
+
+impl<V: Copy> SampleIndex<V> {
+    // adds `key` or replaces its value
+    pub fn insert(&mut self, key: &[u8], value: V) {
+        match self.positions.get(key) {
+            Some(&position) => self.entries[position].1 = value,
+            None => {
+                self.positions.insert(key.to_vec(), self.entries.len());
+                self.entries.push((key.to_vec(), value));
+                self.memory += KEY_COPIES * key.len();
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        if let Some(position) = self.positions.remove(key) {
+            self.entries.swap_remove(position);
+            self.memory -= KEY_COPIES * key.len();
+            if let Some((moved, _)) = self.entries.get(position) {
+                self.positions.insert(moved.clone(), position);
+            }
+        }
+    }
+
+    // inserts or removes `key` to match an optional value, such as an entry's expires_at
+    pub fn update(&mut self, key: &[u8], value: Option<V>) {
+        match value {
+            Some(value) => self.insert(key, value),
+            None => self.remove(key),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.positions.clear();
+        self.memory = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory
+    }
+
+    // up to `count` distinct keys with their values
+    pub fn sample<R: Rng>(&self, rng: &mut R, count: usize) -> impl Iterator<Item = (&[u8], V)> + '_ {
+        rand::seq::index::sample(rng, self.entries.len(), count.min(self.entries.len()))
+            .into_iter()
+            .map(move |i| (self.entries[i].0.as_slice(), self.entries[i].1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_index() {
+        let mut index = SampleIndex::default();
+        for (key, value) in [(&b"a"[..], 1), (b"b", 2), (b"cc", 3), (b"a", 4)] {
+            index.insert(key, value);
+        }
+        assert_eq!((index.len(), index.memory_usage()), (3, 8));
+        index.remove(b"a");
+        index.remove(b"missing");
+        index.update(b"b", Some(5));
+        index.update(b"d", None);
+
+        let mut sampled: Vec<(&[u8], i32)> = index.sample(&mut rand::thread_rng(), 10).collect();
+        sampled.sort();
+        assert_eq!(sampled, [(&b"b"[..], 5), (b"cc", 3)]);
+        assert_eq!(index.memory_usage(), 6);
+
+        index.update(b"cc", None);
+        index.remove(b"b");
+        assert!(index.is_empty());
+        assert_eq!(index.memory_usage(), 0);
+    }
+}
@@ -0,0 +1,336 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+use super::memory::MemoryStorage;
+use super::{RedisSet, ScanOptions, SetOp, Storage, StorageValue};
+use crate::utils::error::{RedisError, Result};
+use crate::utils::glob;
+
+// set commands, in the same raw-bytes shape as ListStorage
+pub trait SetStorage {
+    // returns how many members were new
+    fn sadd(&mut self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize>;
+    fn srem(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<usize>;
+    fn sismember(&mut self, key: &[u8], member: &[u8]) -> Result<bool>;
+    fn smismember(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<bool>>;
+    fn smembers(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>>;
+    fn scard(&mut self, key: &[u8]) -> Result<usize>;
+    fn spop(&mut self, key: &[u8], count: usize) -> Result<Vec<Vec<u8>>>;
+    // distinct members for a positive count, possibly repeated ones for a negative count
+    fn srandmember(&mut self, key: &[u8], count: i64) -> Result<Vec<Vec<u8>>>;
+    fn smove(&mut self, source: &[u8], destination: &[u8], member: &[u8]) -> Result<bool>;
+    // missing keys count as empty sets
+    fn set_op(&mut self, op: SetOp, keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>>;
+    // overwrites `destination` with the result, or deletes it when that is empty;
+    // returns the size of the result
+    fn set_op_store(&mut self, op: SetOp, destination: &[u8], keys: &[Vec<u8>]) -> Result<usize>;
+    // the size of the intersection, counting stops at `limit` unless that is 0
+    fn sintercard(&mut self, keys: &[Vec<u8>], limit: usize) -> Result<usize>;
+    fn sscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Vec<u8>>)>;
+}
+
+fn as_set(value: &mut StorageValue) -> Result<&mut RedisSet> {
+    match value {
+        StorageValue::Set(set) => Ok(set),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+impl MemoryStorage {
+    fn redis_set(&mut self, key: &[u8]) -> Result<Option<&RedisSet>> {
+        match self.value(key) {
+            Some(StorageValue::Set(set)) => Ok(Some(set)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // every key must hold a set or nothing at all
+    fn redis_sets(&mut self, keys: &[Vec<u8>]) -> Result<Vec<Option<&RedisSet>>> {
+        self.values(keys)?
+            .into_iter()
+            .map(|value| match value {
+                Some(StorageValue::Set(set)) => Ok(Some(set)),
+                Some(_) => Err(RedisError::WrongType),
+                None => Ok(None),
+            })
+            .collect()
+    }
+}
+
+// the intersection walks the smallest set and probes the others
+fn intersect<'a>(sets: &[Option<&'a RedisSet>]) -> Option<(&'a RedisSet, Vec<&'a RedisSet>)> {
+    let mut sets: Vec<&RedisSet> = sets.iter().copied().collect::<Option<_>>()?;
+    sets.sort_by_key(|set| set.len());
+    let (smallest, rest) = sets.split_first()?;
+    Some((*smallest, rest.to_vec()))
+}
+
+fn combine(op: SetOp, sets: &[Option<&RedisSet>]) -> Vec<Vec<u8>> {
+    match op {
+        SetOp::Inter => match intersect(sets) {
+            Some((smallest, rest)) => smallest
+                .iter()
+                .filter(|member| rest.iter().all(|set| set.contains(member)))
+                .map(Cow::into_owned)
+                .collect(),
+            None => Vec::new(),
+        },
+        SetOp::Union => {
+            let mut union = HashSet::new();
+            for set in sets.iter().flatten() {
+                union.extend(set.iter().map(Cow::into_owned));
+            }
+            union.into_iter().collect()
+        }
+        SetOp::Diff => match sets.split_first() {
+            Some((Some(first), rest)) => first
+                .iter()
+                .filter(|member| !rest.iter().flatten().any(|set| set.contains(member)))
+                .map(Cow::into_owned)
+                .collect(),
+            _ => Vec::new(),
+        },
+    }
+}
+
+impl SetStorage for MemoryStorage {
+    fn sadd(&mut self, key: &[u8], members: Vec<Vec<u8>>) -> Result<usize> {
+        let limits = self.encoding_limits().set;
+        self.modify_or_insert(key, || StorageValue::Set(RedisSet::new()), |stored| {
+            let set = as_set(stored)?;
+            let mut added = 0;
+            for member in members {
+                added += usize::from(set.insert(member, limits));
+            }
+            Ok(added)
+        })
+    }
+
+    fn srem(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<usize> {
+        Ok(self
+            .modify(key, |stored| {
+                let set = as_set(stored)?;
+                Ok(members.iter().filter(|member| set.remove(member)).count())
+            })?
+            .unwrap_or(0))
+    }
+
+    fn sismember(&mut self, key: &[u8], member: &[u8]) -> Result<bool> {
+        Ok(self.redis_set(key)?.is_some_and(|set| set.contains(member)))
+    }
+
+    fn smismember(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<bool>> {
+        let set = self.redis_set(key)?;
+        Ok(members
+            .iter()
+            .map(|member| set.is_some_and(|set| set.contains(member)))
+            .collect())
+    }
+
+    fn smembers(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .redis_set(key)?
+            .map(|set| set.iter().map(Cow::into_owned).collect())
+            .unwrap_or_default())
+    }
+
+    fn scard(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.redis_set(key)?.map_or(0, RedisSet::len))
+    }
+
+    fn spop(&mut self, key: &[u8], count: usize) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .modify(key, |stored| {
+                let set = as_set(stored)?;
+                let mut rng = rand::thread_rng();
+                Ok(match count {
+                    1 => set.pop_random(&mut rng).into_iter().collect(),
+                    // popping everything needs no sampling
+                    count if count >= set.len() => {
+                        let all = set.iter().map(Cow::into_owned).collect();
+                        *set = RedisSet::new();
+                        all
+                    }
+                    count => {
+                        let picked: Vec<Vec<u8>> =
+                            set.iter().choose_multiple(&mut rng, count).into_iter().map(Cow::into_owned).collect();
+                        for member in &picked {
+                            set.remove(member);
+                        }
+                        picked
+                    }
+                })
+            })?
+            .unwrap_or_default())
+    }
+
+    fn srandmember(&mut self, key: &[u8], count: i64) -> Result<Vec<Vec<u8>>> {
+        let set = match self.redis_set(key)? {
+            Some(set) => set,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            return Ok(set
+                .iter()
+                .choose_multiple(&mut rng, count as usize)
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect());
+        }
+
+        // with repetitions allowed, gather the members once and index into them
+        let members: Vec<Cow<[u8]>> = set.iter().collect();
+        Ok((0..count.unsigned_abs())
+            .map(|_| members[rng.gen_range(0..members.len())].to_vec())
+            .collect())
+    }
+
+    fn smove(&mut self, source: &[u8], destination: &[u8], member: &[u8]) -> Result<bool> {
+        // both ends are type checked before anything moves
+        let present = match self.redis_set(source)? {
+            Some(set) => set.contains(member),
+            None => return Ok(false),
+        };
+        self.redis_set(destination)?;
+
+        if !present || source == destination {
+            return Ok(present);
+        }
+        self.srem(source, &[member.to_vec()])?;
+        self.sadd(destination, vec![member.to_vec()])?;
+        Ok(true)
+    }
+
+    fn set_op(&mut self, op: SetOp, keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>> {
+        Ok(combine(op, &self.redis_sets(keys)?))
+    }
+
+    fn set_op_store(&mut self, op: SetOp, destination: &[u8], keys: &[Vec<u8>]) -> Result<usize> {
+        let members = self.set_op(op, keys)?;
+        if members.is_empty() {
+            self.delete(destination)?;
+            return Ok(0);
+        }
+
+        let limits = self.encoding_limits().set;
+        let mut set = RedisSet::new();
+        for member in members {
+            set.insert(member, limits);
+        }
+        let len = set.len();
+        Storage::set(self, destination.to_vec(), StorageValue::Set(set), None)?;
+        Ok(len)
+    }
+
+    fn sintercard(&mut self, keys: &[Vec<u8>], limit: usize) -> Result<usize> {
+        let sets = self.redis_sets(keys)?;
+        let (smallest, rest) = match intersect(&sets) {
+            Some(found) => found,
+            None => return Ok(0),
+        };
+
+        let common = smallest.iter().filter(|member| rest.iter().all(|set| set.contains(member)));
+        Ok(if limit == 0 { common.count() } else { common.take(limit).count() })
+    }
+
+    fn sscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Vec<u8>>)> {
+        let set = match self.redis_set(key)? {
+            Some(set) => set,
+            None => return Ok((0, Vec::new())),
+        };
+
+        let (next, members) = set.scan(cursor, options.count);
+        let members = members
+            .into_iter()
+            .filter(|member| options.pattern.as_deref().is_none_or(|pattern| glob::matches(pattern, member)))
+            .map(Cow::into_owned)
+            .collect();
+        Ok((next, members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|value| value.as_bytes().to_vec()).collect()
+    }
+
+    fn sorted(mut values: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_sadd_srem_and_memory() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.sadd(b"s", members(&["1", "2", "2"])).unwrap(), 2);
+        assert_eq!(storage.encoding(b"s"), Some("intset"));
+        assert_eq!(storage.dataset_memory(), 4);
+
+        assert_eq!(storage.sadd(b"s", members(&["abc"])).unwrap(), 1);
+        assert_eq!(storage.encoding(b"s"), Some("listpack"));
+        assert_eq!(storage.dataset_memory(), 5);
+
+        assert_eq!(storage.srem(b"s", &members(&["1", "2", "abc", "zz"])).unwrap(), 3);
+        assert!(!storage.exists(b"s").unwrap());
+        assert_eq!(storage.dataset_memory(), 0);
+    }
+
+    #[test]
+    fn test_algebra() {
+        let mut storage = MemoryStorage::new(1024);
+        storage.sadd(b"a", members(&["1", "2", "3", "x"])).unwrap();
+        storage.sadd(b"b", members(&["2", "3", "4"])).unwrap();
+        let keys = members(&["a", "b", "missing"]);
+
+        assert!(storage.set_op(SetOp::Inter, &keys).unwrap().is_empty());
+        assert_eq!(sorted(storage.set_op(SetOp::Inter, &keys[..2]).unwrap()), members(&["2", "3"]));
+        assert_eq!(
+            sorted(storage.set_op(SetOp::Union, &keys).unwrap()),
+            members(&["1", "2", "3", "4", "x"])
+        );
+        assert_eq!(sorted(storage.set_op(SetOp::Diff, &keys).unwrap()), members(&["1", "x"]));
+        assert_eq!(storage.sintercard(&keys[..2], 0).unwrap(), 2);
+        assert_eq!(storage.sintercard(&keys[..2], 1).unwrap(), 1);
+
+        assert_eq!(storage.set_op_store(SetOp::Diff, b"d", &keys).unwrap(), 2);
+        assert_eq!(sorted(storage.smembers(b"d").unwrap()), members(&["1", "x"]));
+        // an empty result deletes the destination
+        assert_eq!(storage.set_op_store(SetOp::Inter, b"d", &keys).unwrap(), 0);
+        assert!(!storage.exists(b"d").unwrap());
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut storage = MemoryStorage::new(1024);
+        Storage::set(&mut storage, b"str".to_vec(), StorageValue::String(b"x".to_vec()), None).unwrap();
+        storage.sadd(b"s", members(&["a"])).unwrap();
+        assert!(matches!(storage.sadd(b"str", members(&["a"])), Err(RedisError::WrongType)));
+        assert!(matches!(storage.set_op(SetOp::Union, &members(&["s", "str"])), Err(RedisError::WrongType)));
+        assert!(matches!(storage.smove(b"s", b"str", b"a"), Err(RedisError::WrongType)));
+        assert!(storage.sismember(b"s", b"a").unwrap());
+    }
+
+    #[test]
+    fn test_spop_and_smove() {
+        let mut storage = MemoryStorage::new(1024);
+        storage.sadd(b"s", members(&["a", "b", "c"])).unwrap();
+        assert_eq!(storage.spop(b"s", 2).unwrap().len(), 2);
+        assert_eq!(storage.scard(b"s").unwrap(), 1);
+
+        let last = storage.smembers(b"s").unwrap().pop().unwrap();
+        assert!(storage.smove(b"s", b"t", &last).unwrap());
+        assert!(!storage.exists(b"s").unwrap());
+        assert_eq!(storage.smembers(b"t").unwrap(), vec![last]);
+        assert!(storage.spop(b"s", 1).unwrap().is_empty());
+
+        assert_eq!(storage.srandmember(b"t", -5).unwrap().len(), 5);
+        assert_eq!(storage.srandmember(b"t", 5).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,501 @@
+use super::memory::MemoryStorage;
+use super::{
+    ClaimOptions, ConsumerGroup, Delivered, Fields, PendingRange, Storage, Stream, StreamEntry, StreamId, StreamReadId,
+    StreamTrim, StorageValue, XAddId, XGroupCommand,
+};
+use crate::utils::error::{RedisError, Result};
+
+// XPENDING without a range: the count, the lowest and highest ids, and per-consumer counts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub bounds: Option<(StreamId, StreamId)>,
+    pub consumers: Vec<(Vec<u8>, usize)>,
+}
+
+// one XPENDING line: id, owner, idle milliseconds and delivery count
+pub type PendingDetail = (StreamId, Vec<u8>, u64, u64);
+
+// stream commands, in the same raw-bytes shape as ListStorage
+pub trait StreamStorage {
+    // the id of the new entry, or None when NOMKSTREAM found no stream
+    fn xadd(
+        &mut self,
+        key: &[u8],
+        id: XAddId,
+        fields: Fields,
+        trim: Option<&StreamTrim>,
+        no_mkstream: bool,
+    ) -> Result<Option<StreamId>>;
+    fn xtrim(&mut self, key: &[u8], trim: &StreamTrim) -> Result<usize>;
+    fn xrange(&mut self, key: &[u8], start: StreamId, end: StreamId, count: Option<usize>, rev: bool)
+        -> Result<Vec<StreamEntry>>;
+    fn xlen(&mut self, key: &[u8]) -> Result<usize>;
+    fn xdel(&mut self, key: &[u8], ids: &[StreamId]) -> Result<usize>;
+    // the stream at `key`, for XINFO
+    fn xstream(&mut self, key: &[u8]) -> Result<Option<&Stream>>;
+    // the entries after each id, for the keys that have any; `$` must be resolved already
+    fn xread(&mut self, keys: &[Vec<u8>], after: &[StreamId], count: Option<usize>)
+        -> Result<Vec<(Vec<u8>, Vec<StreamEntry>)>>;
+    // like xread for a consumer group; keys read with `>` are left out when they had nothing
+    fn xreadgroup(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        keys: &[Vec<u8>],
+        ids: &[StreamReadId],
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<Delivered>)>>;
+    fn xack(&mut self, key: &[u8], group: &[u8], ids: &[StreamId]) -> Result<usize>;
+    fn xpending_summary(&mut self, key: &[u8], group: &[u8]) -> Result<PendingSummary>;
+    fn xpending(&mut self, key: &[u8], group: &[u8], range: &PendingRange) -> Result<Vec<PendingDetail>>;
+    fn xclaim(
+        &mut self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<Delivered>>;
+    // the cursor to continue from, what was claimed and the ids of deleted entries
+    #[allow(clippy::too_many_arguments)]
+    fn xautoclaim(
+        &mut self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Result<(StreamId, Vec<Delivered>, Vec<StreamId>)>;
+    // CREATE and SETID reply OK, the others with a number
+    fn xgroup(&mut self, command: &XGroupCommand) -> Result<i64>;
+}
+
+fn as_stream(value: &mut StorageValue) -> Result<&mut Stream> {
+    match value {
+        StorageValue::Stream(stream) => Ok(stream),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+fn no_group(key: &[u8], group: &[u8]) -> RedisError {
+    RedisError::NoGroup(format!(
+        "No such key '{}' or consumer group '{}'",
+        String::from_utf8_lossy(key),
+        String::from_utf8_lossy(group)
+    ))
+}
+
+fn no_group_for_key(key: &[u8], group: &[u8]) -> RedisError {
+    RedisError::NoGroup(format!(
+        "No such consumer group '{}' for key name '{}'",
+        String::from_utf8_lossy(group),
+        String::from_utf8_lossy(key)
+    ))
+}
+
+fn add_error(id: XAddId) -> RedisError {
+    match id {
+        XAddId::Explicit(StreamId::MIN) => {
+            RedisError::InvalidArgument("The ID specified in XADD must be greater than 0-0".to_string())
+        }
+        XAddId::Auto => RedisError::InvalidArgument(
+            "The stream has exhausted the last possible ID, unable to add more items".to_string(),
+        ),
+        _ => RedisError::InvalidArgument(
+            "The ID specified in XADD is equal or smaller than the target stream top item".to_string(),
+        ),
+    }
+}
+
+impl MemoryStorage {
+    fn redis_stream(&mut self, key: &[u8]) -> Result<Option<&Stream>> {
+        match self.value(key) {
+            Some(StorageValue::Stream(stream)) => Ok(Some(stream)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // runs `f` on the stream at `key`, or returns `missing` when there is none
+    fn with_stream<T, F>(&mut self, key: &[u8], missing: impl FnOnce() -> Result<T>, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Stream, u64) -> Result<T>,
+    {
+        let now = self.now_ms();
+        match self.modify(key, |stored| f(as_stream(stored)?, now))? {
+            Some(result) => Ok(result),
+            None => missing(),
+        }
+    }
+}
+
+impl StreamStorage for MemoryStorage {
+    fn xadd(
+        &mut self,
+        key: &[u8],
+        id: XAddId,
+        fields: Fields,
+        trim: Option<&StreamTrim>,
+        no_mkstream: bool,
+    ) -> Result<Option<StreamId>> {
+        let now = self.now_ms();
+        let add = |stream: &mut Stream| {
+            let id = stream.next_id(id, now).ok_or_else(|| add_error(id))?;
+            stream.push(id, fields);
+            if let Some(trim) = trim {
+                stream.trim(trim);
+            }
+            Ok(id)
+        };
+
+        if self.redis_stream(key)?.is_some() {
+            return self.modify(key, |stored| add(as_stream(stored)?));
+        }
+        if no_mkstream {
+            return Ok(None);
+        }
+        // built aside so a rejected id leaves no empty stream behind
+        let mut stream = Stream::new();
+        let id = add(&mut stream)?;
+        Storage::set(self, key.to_vec(), StorageValue::Stream(stream), None)?;
+        Ok(Some(id))
+    }
+
+    fn xtrim(&mut self, key: &[u8], trim: &StreamTrim) -> Result<usize> {
+        self.with_stream(key, || Ok(0), |stream, _| Ok(stream.trim(trim)))
+    }
+
+    fn xrange(&mut self, key: &[u8], start: StreamId, end: StreamId, count: Option<usize>, rev: bool)
+        -> Result<Vec<StreamEntry>> {
+        Ok(self
+            .redis_stream(key)?
+            .map(|stream| stream.range(start, end, count, rev).into_iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn xlen(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.redis_stream(key)?.map_or(0, Stream::len))
+    }
+
+    fn xdel(&mut self, key: &[u8], ids: &[StreamId]) -> Result<usize> {
+        self.with_stream(key, || Ok(0), |stream, _| Ok(stream.delete(ids)))
+    }
+
+    fn xstream(&mut self, key: &[u8]) -> Result<Option<&Stream>> {
+        self.redis_stream(key)
+    }
+
+    fn xread(&mut self, keys: &[Vec<u8>], after: &[StreamId], count: Option<usize>)
+        -> Result<Vec<(Vec<u8>, Vec<StreamEntry>)>> {
+        let mut found = Vec::new();
+        for (key, &after) in keys.iter().zip(after) {
+            let start = match after.next() {
+                Some(start) => start,
+                None => continue,
+            };
+            let entries = self.xrange(key, start, StreamId::MAX, count, false)?;
+            if !entries.is_empty() {
+                found.push((key.clone(), entries));
+            }
+        }
+        Ok(found)
+    }
+
+    fn xreadgroup(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        keys: &[Vec<u8>],
+        ids: &[StreamReadId],
+        count: Option<usize>,
+        no_ack: bool,
+    ) -> Result<Vec<(Vec<u8>, Vec<Delivered>)>> {
+        let missing = |key: &[u8]| {
+            RedisError::NoGroup(format!(
+                "No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                String::from_utf8_lossy(key),
+                String::from_utf8_lossy(group)
+            ))
+        };
+
+        // every key is checked before anything is delivered
+        for key in keys {
+            if self.redis_stream(key)?.and_then(|stream| stream.group(group)).is_none() {
+                return Err(missing(key));
+            }
+        }
+
+        let mut found = Vec::new();
+        for (key, id) in keys.iter().zip(ids) {
+            let entries = self.with_stream(key, || Err(missing(key)), |stream, now| {
+                let entries = match *id {
+                    StreamReadId::New => stream
+                        .deliver_new(group, consumer, count, no_ack, now)
+                        .map(|entries| entries.into_iter().map(|entry| (entry.id, Some(entry.fields))).collect()),
+                    StreamReadId::After(after) => stream.pending_history(group, consumer, after, count, now),
+                    StreamReadId::Last => {
+                        return Err(RedisError::InvalidArgument(
+                            "The $ ID is meaningless in the context of XREADGROUP: you want to read the history \
+                             of this consumer by specifying a proper ID, or use the > ID to get new messages. \
+                             The $ ID would just return an empty result set."
+                                .to_string(),
+                        ))
+                    }
+                };
+                entries.ok_or_else(|| missing(key))
+            })?;
+            if !entries.is_empty() || *id != StreamReadId::New {
+                found.push((key.clone(), entries));
+            }
+        }
+        Ok(found)
+    }
+
+    fn xack(&mut self, key: &[u8], group: &[u8], ids: &[StreamId]) -> Result<usize> {
+        self.with_stream(key, || Ok(0), |stream, _| Ok(stream.group_mut(group).map_or(0, |group| group.ack(ids))))
+    }
+
+    fn xpending_summary(&mut self, key: &[u8], group: &[u8]) -> Result<PendingSummary> {
+        let found = self.redis_stream(key)?.and_then(|stream| stream.group(group));
+        let group = found.ok_or_else(|| no_group(key, group))?;
+
+        let bounds = group
+            .pending
+            .keys()
+            .next()
+            .zip(group.pending.keys().next_back())
+            .map(|(&first, &last)| (first, last));
+        let consumers = group
+            .consumers
+            .iter()
+            .filter(|(_, consumer)| !consumer.pending.is_empty())
+            .map(|(name, consumer)| (name.clone(), consumer.pending.len()))
+            .collect();
+        Ok(PendingSummary { count: group.pending.len(), bounds, consumers })
+    }
+
+    fn xpending(&mut self, key: &[u8], group: &[u8], range: &PendingRange) -> Result<Vec<PendingDetail>> {
+        let now = self.now_ms();
+        let found = self.redis_stream(key)?.and_then(|stream| stream.group(group));
+        let group = found.ok_or_else(|| no_group(key, group))?;
+        if range.start > range.end {
+            return Ok(Vec::new());
+        }
+
+        Ok(group
+            .pending
+            .range(range.start..=range.end)
+            .filter(|(_, pending)| range.consumer.as_ref().is_none_or(|consumer| pending.consumer == *consumer))
+            .map(|(&id, pending)| (id, pending.consumer.clone(), now.saturating_sub(pending.delivered_at), pending.delivery_count))
+            .filter(|&(_, _, idle, _)| idle >= range.min_idle)
+            .take(range.count)
+            .collect())
+    }
+
+    fn xclaim(
+        &mut self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &ClaimOptions,
+    ) -> Result<Vec<Delivered>> {
+        self.with_stream(key, || Err(no_group(key, group)), |stream, now| {
+            stream
+                .claim(group, consumer, min_idle, ids, options, now)
+                .ok_or_else(|| no_group(key, group))
+        })
+    }
+
+    fn xautoclaim(
+        &mut self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Result<(StreamId, Vec<Delivered>, Vec<StreamId>)> {
+        self.with_stream(key, || Err(no_group(key, group)), |stream, now| {
+            stream
+                .auto_claim(group, consumer, min_idle, start, count, just_id, now)
+                .ok_or_else(|| no_group(key, group))
+        })
+    }
+
+    fn xgroup(&mut self, command: &XGroupCommand) -> Result<i64> {
+        let missing_key = || {
+            Err(RedisError::InvalidArgument(
+                "The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use \
+                 the MKSTREAM option to create an empty stream automatically."
+                    .to_string(),
+            ))
+        };
+
+        match command {
+            XGroupCommand::Create { key, group, id, mkstream, entries_read } => {
+                if *mkstream && self.redis_stream(key)?.is_none() {
+                    Storage::set(self, key.clone(), StorageValue::Stream(Stream::new()), None)?;
+                }
+                self.with_stream(key, missing_key, |stream, _| {
+                    // `$` starts at the top, having read everything so far
+                    let (last_delivered, read) = match id {
+                        Some(id) => (*id, entries_read.or((*id == StreamId::MIN).then_some(0))),
+                        None => (stream.last_id(), Some(entries_read.unwrap_or(stream.entries_added()))),
+                    };
+                    if !stream.create_group(group, ConsumerGroup::new(last_delivered, read)) {
+                        return Err(RedisError::BusyGroup);
+                    }
+                    Ok(1)
+                })
+            }
+            XGroupCommand::SetId { key, group, id, entries_read } => self.with_stream(key, missing_key, |stream, _| {
+                let last_id = stream.last_id();
+                let entries_added = stream.entries_added();
+                let found = stream.group_mut(group).ok_or_else(|| no_group_for_key(key, group))?;
+                match id {
+                    Some(id) => {
+                        found.last_delivered = *id;
+                        found.entries_read = *entries_read;
+                    }
+                    None => {
+                        found.last_delivered = last_id;
+                        found.entries_read = Some(entries_read.unwrap_or(entries_added));
+                    }
+                }
+                Ok(1)
+            }),
+            XGroupCommand::Destroy { key, group } => {
+                self.with_stream(key, missing_key, |stream, _| Ok(stream.destroy_group(group) as i64))
+            }
+            XGroupCommand::CreateConsumer { key, group, consumer } => self.with_stream(key, missing_key, |stream, now| {
+                let found = stream.group_mut(group).ok_or_else(|| no_group_for_key(key, group))?;
+                Ok(found.create_consumer(consumer, now) as i64)
+            }),
+            XGroupCommand::DelConsumer { key, group, consumer } => self.with_stream(key, missing_key, |stream, _| {
+                let found = stream.group_mut(group).ok_or_else(|| no_group_for_key(key, group))?;
+                Ok(found.delete_consumer(consumer).unwrap_or(0) as i64)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::TrimStrategy;
+
+    fn fields(value: &str) -> Fields {
+        vec![(b"v".to_vec(), value.as_bytes().to_vec())]
+    }
+
+    fn add(storage: &mut MemoryStorage, key: &[u8], ms: u64) -> StreamId {
+        storage
+            .xadd(key, XAddId::Explicit(StreamId::new(ms, 0)), fields(&ms.to_string()), None, false)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_xadd_ids_and_trimming() {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        let zero = storage.xadd(b"s", XAddId::Explicit(StreamId::MIN), fields("a"), None, false);
+        assert!(zero.unwrap_err().to_string().contains("greater than 0-0"));
+        // a rejected first entry does not leave an empty stream
+        assert!(!storage.exists(b"s").unwrap());
+        assert_eq!(storage.xadd(b"s", XAddId::Auto, fields("a"), None, true).unwrap(), None);
+
+        add(&mut storage, b"s", 5);
+        let smaller = storage.xadd(b"s", XAddId::Explicit(StreamId::new(5, 0)), fields("b"), None, false);
+        assert!(smaller.unwrap_err().to_string().contains("equal or smaller"));
+        assert_eq!(storage.xadd(b"s", XAddId::AutoSeq(5), fields("c"), None, false).unwrap(), Some(StreamId::new(5, 1)));
+
+        let trim = StreamTrim { strategy: TrimStrategy::MaxLen(1), approx: false, limit: None };
+        storage.xadd(b"s", XAddId::Auto, fields("d"), Some(&trim), false).unwrap();
+        assert_eq!(storage.xlen(b"s").unwrap(), 1);
+
+        // emptied streams stay around
+        let last = storage.xstream(b"s").unwrap().unwrap().last_id();
+        assert_eq!(storage.xdel(b"s", &[last]).unwrap(), 1);
+        assert!(storage.exists(b"s").unwrap());
+        assert_eq!(storage.xstream(b"s").unwrap().unwrap().last_id(), last);
+    }
+
+    #[test]
+    fn test_xread_and_wrong_type() {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        add(&mut storage, b"a", 1);
+        add(&mut storage, b"a", 2);
+        add(&mut storage, b"b", 1);
+        let keys = vec![b"a".to_vec(), b"b".to_vec(), b"missing".to_vec()];
+        let read = storage.xread(&keys, &[StreamId::new(1, 0), StreamId::new(1, 0), StreamId::MIN], None).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].0, b"a");
+        assert_eq!(read[0].1[0].id, StreamId::new(2, 0));
+
+        Storage::set(&mut storage, b"str".to_vec(), StorageValue::String(b"x".to_vec()), None).unwrap();
+        assert!(matches!(storage.xlen(b"str"), Err(RedisError::WrongType)));
+        assert!(matches!(storage.xadd(b"str", XAddId::Auto, fields("a"), None, false), Err(RedisError::WrongType)));
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut storage = MemoryStorage::new(1024 * 1024);
+        let create = |id, mkstream| XGroupCommand::Create {
+            key: b"s".to_vec(),
+            group: b"g".to_vec(),
+            id,
+            mkstream,
+            entries_read: None,
+        };
+        assert!(storage.xgroup(&create(None, false)).unwrap_err().to_string().contains("requires the key"));
+        assert_eq!(storage.xgroup(&create(None, true)).unwrap(), 1);
+        assert!(matches!(storage.xgroup(&create(None, false)), Err(RedisError::BusyGroup)));
+
+        add(&mut storage, b"s", 1);
+        add(&mut storage, b"s", 2);
+        let keys = vec![b"s".to_vec()];
+        let read = storage.xreadgroup(b"g", b"c1", &keys, &[StreamReadId::New], Some(1), false).unwrap();
+        assert_eq!(read[0].1, vec![(StreamId::new(1, 0), Some(fields("1")))]);
+        // nothing new for `>` leaves the key out, history always lists it
+        storage.xreadgroup(b"g", b"c2", &keys, &[StreamReadId::New], None, false).unwrap();
+        assert!(storage.xreadgroup(b"g", b"c2", &keys, &[StreamReadId::New], None, false).unwrap().is_empty());
+        let history = storage.xreadgroup(b"g", b"c1", &keys, &[StreamReadId::After(StreamId::MIN)], None, false).unwrap();
+        assert_eq!(history[0].1.len(), 1);
+
+        let summary = storage.xpending_summary(b"s", b"g").unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.bounds, Some((StreamId::new(1, 0), StreamId::new(2, 0))));
+        assert_eq!(summary.consumers, vec![(b"c1".to_vec(), 1), (b"c2".to_vec(), 1)]);
+
+        let range = PendingRange {
+            min_idle: 0,
+            start: StreamId::MIN,
+            end: StreamId::MAX,
+            count: 10,
+            consumer: Some(b"c2".to_vec()),
+        };
+        let details = storage.xpending(b"s", b"g", &range).unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!((details[0].0, details[0].3), (StreamId::new(2, 0), 1));
+
+        assert_eq!(storage.xack(b"s", b"g", &[StreamId::new(1, 0), StreamId::new(9, 0)]).unwrap(), 1);
+        assert_eq!(storage.xack(b"s", b"nope", &[StreamId::new(2, 0)]).unwrap(), 0);
+        assert!(matches!(storage.xpending_summary(b"s", b"nope"), Err(RedisError::NoGroup(_))));
+        assert!(matches!(
+            storage.xreadgroup(b"nope", b"c1", &keys, &[StreamReadId::New], None, false),
+            Err(RedisError::NoGroup(_))
+        ));
+
+        let delete = XGroupCommand::DelConsumer { key: b"s".to_vec(), group: b"g".to_vec(), consumer: b"c2".to_vec() };
+        assert_eq!(storage.xgroup(&delete).unwrap(), 1);
+        assert_eq!(storage.xpending_summary(b"s", b"g").unwrap().count, 0);
+    }
+}
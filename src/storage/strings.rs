@@ -0,0 +1,299 @@
+use super::memory::MemoryStorage;
+use super::{SetExpiry, Storage, StorageValue};
+use crate::utils::error::{RedisError, Result};
+
+// like redis' proto-max-bulk-len, the longest string SETRANGE may build
+pub const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+// the string commands beyond GET / SET, in the same raw-bytes shape as the other traits
+pub trait StringStorage {
+    // INCRBY / DECRBY; missing keys count from zero
+    fn incr_by(&mut self, key: &[u8], delta: i64) -> Result<i64>;
+    // the new value as redis formats it
+    fn incr_by_float(&mut self, key: &[u8], delta: f64) -> Result<Vec<u8>>;
+    fn getrange(&mut self, key: &[u8], start: i64, end: i64) -> Result<Vec<u8>>;
+    // the length of the string afterwards; writing nothing never creates the key
+    fn setrange(&mut self, key: &[u8], offset: usize, value: &[u8]) -> Result<usize>;
+    fn strlen(&mut self, key: &[u8]) -> Result<usize>;
+    // the value, after applying `expiry`; Keep leaves the ttl alone
+    fn getex(&mut self, key: &[u8], expiry: SetExpiry) -> Result<Option<Vec<u8>>>;
+    fn getdel(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+    // keys holding something other than a string read as missing
+    fn mget(&mut self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>>;
+    // with `only_new` nothing is written if any key exists; false when nothing was written
+    fn mset(&mut self, pairs: Vec<(Vec<u8>, Vec<u8>)>, only_new: bool) -> Result<bool>;
+}
+
+fn as_string(value: &mut StorageValue) -> Result<&mut Vec<u8>> {
+    match value {
+        StorageValue::String(bytes) => Ok(bytes),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+fn overflow() -> RedisError {
+    RedisError::InvalidArgument("increment or decrement would overflow".to_string())
+}
+
+// integers are stored as their decimal representation, like redis does
+fn parse_integer(bytes: &[u8]) -> Result<i64> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .ok_or(RedisError::NotInteger)
+}
+
+fn parse_float(bytes: &[u8]) -> Result<f64> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| text.parse::<f64>().ok())
+        .filter(|value| value.is_finite())
+        .ok_or(RedisError::NotFloat)
+}
+
+impl MemoryStorage {
+    pub(super) fn redis_string(&mut self, key: &[u8]) -> Result<Option<&[u8]>> {
+        match self.value(key) {
+            Some(StorageValue::String(bytes)) => Ok(Some(bytes)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // runs `f` on the string at `key` once it is zero padded to at least `len` bytes,
+    // creating it if needed; room for the growth is made up front, or it is refused
+    pub(super) fn grow_string<T>(&mut self, key: &[u8], len: usize, f: impl FnOnce(&mut [u8]) -> T) -> Result<T> {
+        let current = self.redis_string(key)?.map(<[u8]>::len);
+        self.make_room(len.saturating_sub(current.unwrap_or(0)), &[key])?;
+
+        self.modify_or_insert(key, || StorageValue::String(Vec::new()), |value| {
+            let bytes = as_string(value)?;
+            if bytes.len() < len {
+                bytes.resize(len, 0);
+            }
+            Ok(f(bytes))
+        })
+    }
+
+    // replaces the string at `key`, keeping its ttl, or creates it without one
+    fn replace_string(&mut self, key: &[u8], bytes: Vec<u8>) -> Result<()> {
+        let current = self.redis_string(key)?.map(<[u8]>::len);
+        match current {
+            Some(len) => {
+                self.make_room(bytes.len().saturating_sub(len), &[key])?;
+                self.modify(key, |value| {
+                    *value = StorageValue::String(bytes);
+                    Ok(())
+                })?;
+                Ok(())
+            }
+            None => Storage::set(self, key.to_vec(), StorageValue::String(bytes), None),
+        }
+    }
+}
+
+impl StringStorage for MemoryStorage {
+    fn incr_by(&mut self, key: &[u8], delta: i64) -> Result<i64> {
+        let current = self.redis_string(key)?.map_or(Ok(0), parse_integer)?;
+        let updated = current.checked_add(delta).ok_or_else(overflow)?;
+        self.replace_string(key, updated.to_string().into_bytes())?;
+        Ok(updated)
+    }
+
+    fn incr_by_float(&mut self, key: &[u8], delta: f64) -> Result<Vec<u8>> {
+        let current = self.redis_string(key)?.map_or(Ok(0.0), parse_float)?;
+        let updated = current + delta;
+        if !updated.is_finite() {
+            return Err(RedisError::InvalidArgument("increment would produce NaN or Infinity".to_string()));
+        }
+        // the shortest form that reads back the same, never in exponent notation
+        let formatted = updated.to_string().into_bytes();
+        self.replace_string(key, formatted.clone())?;
+        Ok(formatted)
+    }
+
+    fn getrange(&mut self, key: &[u8], start: i64, end: i64) -> Result<Vec<u8>> {
+        let bytes = self.redis_string(key)?.unwrap_or_default();
+        let len = bytes.len() as i64;
+        if (start < 0 && end < 0 && start > end) || len == 0 {
+            return Ok(Vec::new());
+        }
+        let start = if start < 0 { (len + start).max(0) } else { start };
+        let end = if end < 0 { (len + end).max(0) } else { end.min(len - 1) };
+        if start > end {
+            return Ok(Vec::new());
+        }
+        Ok(bytes[start as usize..=end as usize].to_vec())
+    }
+
+    fn setrange(&mut self, key: &[u8], offset: usize, value: &[u8]) -> Result<usize> {
+        if value.is_empty() {
+            return Ok(self.redis_string(key)?.map_or(0, <[u8]>::len));
+        }
+        let end = offset + value.len();
+        if end > MAX_STRING_LEN {
+            return Err(RedisError::InvalidArgument(
+                "string exceeds maximum allowed size (proto-max-bulk-len)".to_string(),
+            ));
+        }
+        self.grow_string(key, end, |bytes| {
+            bytes[offset..end].copy_from_slice(value);
+            bytes.len()
+        })
+    }
+
+    fn strlen(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.redis_string(key)?.map_or(0, <[u8]>::len))
+    }
+
+    fn getex(&mut self, key: &[u8], expiry: SetExpiry) -> Result<Option<Vec<u8>>> {
+        let value = match self.redis_string(key)? {
+            Some(bytes) => bytes.to_vec(),
+            None => return Ok(None),
+        };
+        match expiry {
+            SetExpiry::Keep => {}
+            SetExpiry::Persist => {
+                self.set_expiry(key, None);
+            }
+            // a deadline already past deletes the key straight away
+            SetExpiry::At(at) if at <= self.now() => {
+                self.delete(key)?;
+            }
+            SetExpiry::At(at) => {
+                self.set_expiry(key, Some(at));
+            }
+            SetExpiry::After(after) => {
+                let at = self.now() + after;
+                self.set_expiry(key, Some(at));
+            }
+        }
+        Ok(Some(value))
+    }
+
+    fn getdel(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let value = self.redis_string(key)?.map(<[u8]>::to_vec);
+        if value.is_some() {
+            self.delete(key)?;
+        }
+        Ok(value)
+    }
+
+    fn mget(&mut self, keys: &[Vec<u8>]) -> Result<Vec<Option<Vec<u8>>>> {
+        Ok(self
+            .values(keys)?
+            .into_iter()
+            .map(|value| match value {
+                Some(StorageValue::String(bytes)) => Some(bytes.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn mset(&mut self, pairs: Vec<(Vec<u8>, Vec<u8>)>, only_new: bool) -> Result<bool> {
+        let mut keys: Vec<Vec<u8>> = pairs.iter().map(|(key, _)| key.clone()).collect();
+        keys.sort();
+        keys.dedup();
+        let (mut freed, mut exists) = (0, false);
+        for value in self.values(&keys)?.into_iter().flatten() {
+            freed += value.memory_usage();
+            exists = true;
+        }
+        if only_new && exists {
+            return Ok(false);
+        }
+
+        // all or nothing: check the whole batch fits before writing any of it
+        let needed: usize = pairs.iter().map(|(_, value)| value.len()).sum();
+        let protect: Vec<&[u8]> = keys.iter().map(Vec::as_slice).collect();
+        self.make_room(needed.saturating_sub(freed), &protect)?;
+        for (key, value) in pairs {
+            Storage::set(self, key, StorageValue::String(value), None)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn string(storage: &mut MemoryStorage, key: &[u8]) -> Option<Vec<u8>> {
+        storage.redis_string(key).unwrap().map(<[u8]>::to_vec)
+    }
+
+    #[test]
+    fn test_incr_by() {
+        let mut storage = MemoryStorage::new(1024);
+        assert_eq!(storage.incr_by(b"n", 10).unwrap(), 10);
+        assert_eq!(storage.incr_by(b"n", -15).unwrap(), -5);
+        assert_eq!(storage.dataset_memory(), 2);
+
+        storage.incr_by(b"max", i64::MAX).unwrap();
+        assert!(storage.incr_by(b"max", 1).unwrap_err().to_string().contains("would overflow"));
+        assert_eq!(storage.incr_by(b"max", 0).unwrap(), i64::MAX);
+
+        Storage::set(&mut storage, b"l".to_vec(), StorageValue::List(vec![].into()), None).unwrap();
+        assert!(matches!(storage.incr_by(b"l", 1), Err(RedisError::WrongType)));
+
+        assert_eq!(storage.incr_by_float(b"f", 10.5).unwrap(), b"10.5");
+        assert_eq!(storage.incr_by_float(b"f", 0.1).unwrap(), b"10.6");
+        assert_eq!(storage.incr_by_float(b"f", 5.0e3).unwrap(), b"5010.6");
+        assert_eq!(storage.incr_by_float(b"n", 1.5).unwrap(), b"-3.5");
+        assert!(storage.incr_by_float(b"f", f64::MAX).is_ok());
+        assert!(storage.incr_by_float(b"f", f64::MAX).unwrap_err().to_string().contains("NaN or Infinity"));
+        assert!(!storage.exists(b"missing").unwrap());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut storage = MemoryStorage::new(1024);
+        Storage::set(&mut storage, b"s".to_vec(), StorageValue::String(b"This is a string".to_vec()), None).unwrap();
+        assert_eq!(storage.getrange(b"s", 0, 3).unwrap(), b"This");
+        assert_eq!(storage.getrange(b"s", -3, -1).unwrap(), b"ing");
+        assert_eq!(storage.getrange(b"s", 0, -1).unwrap(), b"This is a string");
+        assert_eq!(storage.getrange(b"s", 10, 100).unwrap(), b"string");
+        assert_eq!(storage.getrange(b"s", -1, -5).unwrap(), b"");
+        assert_eq!(storage.getrange(b"missing", 0, -1).unwrap(), b"");
+
+        assert_eq!(storage.setrange(b"s", 10, b"Redis").unwrap(), 16);
+        assert_eq!(string(&mut storage, b"s").unwrap(), b"This is a Redisg");
+        assert_eq!(storage.setrange(b"p", 3, b"x").unwrap(), 4);
+        assert_eq!(string(&mut storage, b"p").unwrap(), b"\0\0\0x");
+        assert_eq!(storage.setrange(b"missing", 5, b"").unwrap(), 0);
+        assert!(!storage.exists(b"missing").unwrap());
+        assert_eq!(storage.dataset_memory(), 20);
+        assert!(matches!(storage.setrange(b"p", 2000, b"x"), Err(RedisError::OutOfMemory)));
+        assert_eq!(storage.strlen(b"p").unwrap(), 4);
+    }
+
+    #[test]
+    fn test_getex_getdel_mset() {
+        let mut storage = MemoryStorage::new(1024);
+        Storage::set(&mut storage, b"k".to_vec(), StorageValue::String(b"v".to_vec()), None).unwrap();
+        let later = storage.now() + Duration::from_secs(100);
+        assert_eq!(storage.getex(b"k", SetExpiry::At(later)).unwrap(), Some(b"v".to_vec()));
+        assert_eq!(storage.get(b"k").unwrap().expires_at, Some(later));
+        storage.getex(b"k", SetExpiry::Persist).unwrap();
+        assert_eq!(storage.get(b"k").unwrap().expires_at, None);
+        assert_eq!(storage.getex(b"k", SetExpiry::At(std::time::UNIX_EPOCH)).unwrap(), Some(b"v".to_vec()));
+        assert!(!storage.exists(b"k").unwrap());
+
+        let pairs = vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"22".to_vec())];
+        assert!(storage.mset(pairs, true).unwrap());
+        assert!(!storage.mset(vec![(b"c".to_vec(), b"3".to_vec()), (b"a".to_vec(), b"x".to_vec())], true).unwrap());
+        assert!(!storage.exists(b"c").unwrap());
+        Storage::set(&mut storage, b"l".to_vec(), StorageValue::List(vec![].into()), None).unwrap();
+        let keys = [b"a".to_vec(), b"l".to_vec(), b"c".to_vec()];
+        assert_eq!(storage.mget(&keys).unwrap(), vec![Some(b"1".to_vec()), None, None]);
+
+        assert_eq!(storage.getdel(b"b").unwrap(), Some(b"22".to_vec()));
+        assert_eq!(storage.getdel(b"b").unwrap(), None);
+        assert!(matches!(storage.getdel(b"l"), Err(RedisError::WrongType)));
+
+        let big = vec![(b"big".to_vec(), vec![0; 600]), (b"bigger".to_vec(), vec![0; 600])];
+        assert!(matches!(storage.mset(big, false), Err(RedisError::OutOfMemory)));
+        assert!(!storage.exists(b"big").unwrap());
+    }
+}
@@ -0,0 +1,430 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use super::data_types::zset::ScoredRef;
+use super::memory::MemoryStorage;
+use super::{
+    Aggregate, RedisSet, RedisZSet, ScanOptions, ScoreComparison, ScoreRange, SetCondition, SetOp, Storage,
+    StorageValue, ZAddOptions, ZEnd, ZRangeBy, ZRangeQuery,
+};
+use crate::utils::error::{RedisError, Result};
+use crate::utils::glob;
+
+type Scored = (Vec<u8>, f64);
+
+// sorted set commands, in the same raw-bytes shape as ListStorage
+pub trait ZSetStorage {
+    // returns how many members were added, or added and rescored with CH
+    fn zadd(&mut self, key: &[u8], members: Vec<(f64, Vec<u8>)>, options: ZAddOptions) -> Result<usize>;
+    // ZADD INCR and ZINCRBY; None when the options kept the member from being updated
+    fn zincr(&mut self, key: &[u8], member: Vec<u8>, delta: f64, options: ZAddOptions) -> Result<Option<f64>>;
+    fn zrem(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<usize>;
+    fn zscore(&mut self, key: &[u8], member: &[u8]) -> Result<Option<f64>>;
+    fn zcard(&mut self, key: &[u8]) -> Result<usize>;
+    fn zcount(&mut self, key: &[u8], range: &ScoreRange) -> Result<usize>;
+    // the rank and the score of `member`
+    fn zrank(&mut self, key: &[u8], member: &[u8], rev: bool) -> Result<Option<(usize, f64)>>;
+    fn zrange(&mut self, key: &[u8], query: &ZRangeQuery) -> Result<Vec<Scored>>;
+    // overwrites `destination` with the range, or deletes it when that is empty;
+    // returns the size of the range
+    fn zrangestore(&mut self, destination: &[u8], source: &[u8], query: &ZRangeQuery) -> Result<usize>;
+    fn zpop(&mut self, key: &[u8], end: ZEnd, count: usize) -> Result<Vec<Scored>>;
+    fn zset_op_store(
+        &mut self,
+        op: SetOp,
+        destination: &[u8],
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<usize>;
+    fn zscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Scored>)>;
+}
+
+fn as_zset(value: &mut StorageValue) -> Result<&mut RedisZSet> {
+    match value {
+        StorageValue::ZSet(zset) => Ok(zset),
+        _ => Err(RedisError::WrongType),
+    }
+}
+
+fn to_scored((member, score): ScoredRef) -> Scored {
+    (member.to_vec(), score)
+}
+
+// an input of ZUNIONSTORE / ZINTERSTORE, which also accept plain sets
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Sorted(&'a RedisZSet),
+    Plain(&'a RedisSet),
+}
+
+impl Source<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Source::Sorted(zset) => zset.len(),
+            Source::Plain(set) => set.len(),
+        }
+    }
+
+    fn score(&self, member: &[u8]) -> Option<f64> {
+        match self {
+            Source::Sorted(zset) => zset.score(member),
+            Source::Plain(set) => set.contains(member).then_some(1.0),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Cow<'_, [u8]>, f64)> + '_> {
+        match self {
+            Source::Sorted(zset) => Box::new(zset.iter().map(|(member, score)| (Cow::Borrowed(member), score))),
+            Source::Plain(set) => Box::new(set.iter().map(|member| (member, 1.0))),
+        }
+    }
+}
+
+// like redis, inf * 0 and inf + -inf count as 0 rather than poisoning the result
+fn not_nan(score: f64) -> f64 {
+    if score.is_nan() { 0.0 } else { score }
+}
+
+fn aggregate(how: Aggregate, current: f64, score: f64) -> f64 {
+    match how {
+        Aggregate::Sum => not_nan(current + score),
+        Aggregate::Min => current.min(score),
+        Aggregate::Max => current.max(score),
+    }
+}
+
+fn combine(op: SetOp, sources: &[Option<Source>], weights: &[f64], how: Aggregate) -> HashMap<Vec<u8>, f64> {
+    let weighted = |index: usize, score: f64| not_nan(score * weights.get(index).copied().unwrap_or(1.0));
+    let mut result = HashMap::new();
+
+    match op {
+        SetOp::Union => {
+            for (index, source) in sources.iter().enumerate() {
+                let source = match source {
+                    Some(source) => source,
+                    None => continue,
+                };
+                for (member, score) in source.iter() {
+                    let score = weighted(index, score);
+                    result
+                        .entry(member.into_owned())
+                        .and_modify(|current| *current = aggregate(how, *current, score))
+                        .or_insert(score);
+                }
+            }
+        }
+        SetOp::Inter => {
+            let present: Option<Vec<Source>> = sources.iter().copied().collect();
+            let present = match present {
+                Some(present) => present,
+                None => return result,
+            };
+            // walk the smallest input and probe the others in key order
+            let smallest = match present.iter().min_by_key(|source| source.len()) {
+                Some(smallest) => smallest,
+                None => return result,
+            };
+            'members: for (member, _) in smallest.iter() {
+                let mut total: Option<f64> = None;
+                for (index, source) in present.iter().enumerate() {
+                    let score = match source.score(&member) {
+                        Some(score) => weighted(index, score),
+                        None => continue 'members,
+                    };
+                    total = Some(total.map_or(score, |current| aggregate(how, current, score)));
+                }
+                if let Some(total) = total {
+                    result.insert(member.into_owned(), total);
+                }
+            }
+        }
+        SetOp::Diff => {
+            if let Some((Some(first), rest)) = sources.split_first() {
+                for (member, score) in first.iter() {
+                    if rest.iter().flatten().all(|source| source.score(&member).is_none()) {
+                        result.insert(member.into_owned(), score);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+impl MemoryStorage {
+    pub(super) fn redis_zset(&mut self, key: &[u8]) -> Result<Option<&RedisZSet>> {
+        match self.value(key) {
+            Some(StorageValue::ZSet(zset)) => Ok(Some(zset)),
+            Some(_) => Err(RedisError::WrongType),
+            None => Ok(None),
+        }
+    }
+
+    // replaces whatever is at `key` with `zset`, or deletes it when that is empty
+    pub(super) fn store_zset(&mut self, key: &[u8], zset: RedisZSet) -> Result<usize> {
+        let len = zset.len();
+        if len == 0 {
+            self.delete(key)?;
+        } else {
+            Storage::set(self, key.to_vec(), StorageValue::ZSet(zset), None)?;
+        }
+        Ok(len)
+    }
+}
+
+fn range_of<'a>(zset: &'a RedisZSet, query: &ZRangeQuery) -> Vec<ScoredRef<'a>> {
+    match &query.by {
+        ZRangeBy::Rank(start, stop) => {
+            let len = zset.len() as i64;
+            let start = if *start < 0 { (len + start).max(0) } else { *start };
+            let stop = if *stop < 0 { len + stop } else { (*stop).min(len - 1) };
+            if stop < 0 || start > stop {
+                return Vec::new();
+            }
+            zset.range_by_rank(start as usize, stop as usize, query.rev)
+        }
+        ZRangeBy::Score(range) => zset.range_by_score(range, query.rev, query.offset, query.limit),
+        ZRangeBy::Lex(range) => zset.range_by_lex(range, query.rev, query.offset, query.limit),
+    }
+}
+
+impl ZSetStorage for MemoryStorage {
+    fn zadd(&mut self, key: &[u8], members: Vec<(f64, Vec<u8>)>, options: ZAddOptions) -> Result<usize> {
+        self.modify_or_insert(key, || StorageValue::ZSet(RedisZSet::new()), |stored| {
+            let zset = as_zset(stored)?;
+            let mut counted = 0;
+            for (score, member) in members {
+                match zset.score(&member) {
+                    None if options.condition != Some(SetCondition::Xx) => {
+                        zset.insert(member, score);
+                        counted += 1;
+                    }
+                    Some(current) if options.condition != Some(SetCondition::Nx) => {
+                        let allowed = match options.comparison {
+                            Some(ScoreComparison::Gt) => score > current,
+                            Some(ScoreComparison::Lt) => score < current,
+                            None => true,
+                        };
+                        if allowed && score != current {
+                            zset.insert(member, score);
+                            counted += usize::from(options.ch);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(counted)
+        })
+    }
+
+    fn zincr(&mut self, key: &[u8], member: Vec<u8>, delta: f64, options: ZAddOptions) -> Result<Option<f64>> {
+        self.modify_or_insert(key, || StorageValue::ZSet(RedisZSet::new()), |stored| {
+            let zset = as_zset(stored)?;
+            let current = zset.score(&member);
+            match (current, options.condition) {
+                (Some(_), Some(SetCondition::Nx)) | (None, Some(SetCondition::Xx)) => return Ok(None),
+                _ => {}
+            }
+
+            let score = current.unwrap_or(0.0) + delta;
+            if score.is_nan() {
+                return Err(RedisError::InvalidArgument("resulting score is not a number (NaN)".to_string()));
+            }
+            if let Some(current) = current {
+                let allowed = match options.comparison {
+                    Some(ScoreComparison::Gt) => score > current,
+                    Some(ScoreComparison::Lt) => score < current,
+                    None => true,
+                };
+                if !allowed {
+                    return Ok(None);
+                }
+            }
+            zset.insert(member, score);
+            Ok(Some(score))
+        })
+    }
+
+    fn zrem(&mut self, key: &[u8], members: &[Vec<u8>]) -> Result<usize> {
+        Ok(self
+            .modify(key, |stored| {
+                let zset = as_zset(stored)?;
+                Ok(members.iter().filter(|member| zset.remove(member)).count())
+            })?
+            .unwrap_or(0))
+    }
+
+    fn zscore(&mut self, key: &[u8], member: &[u8]) -> Result<Option<f64>> {
+        Ok(self.redis_zset(key)?.and_then(|zset| zset.score(member)))
+    }
+
+    fn zcard(&mut self, key: &[u8]) -> Result<usize> {
+        Ok(self.redis_zset(key)?.map_or(0, RedisZSet::len))
+    }
+
+    fn zcount(&mut self, key: &[u8], range: &ScoreRange) -> Result<usize> {
+        Ok(self.redis_zset(key)?.map_or(0, |zset| zset.count(range)))
+    }
+
+    fn zrank(&mut self, key: &[u8], member: &[u8], rev: bool) -> Result<Option<(usize, f64)>> {
+        Ok(self.redis_zset(key)?.and_then(|zset| Some((zset.rank(member, rev)?, zset.score(member)?))))
+    }
+
+    fn zrange(&mut self, key: &[u8], query: &ZRangeQuery) -> Result<Vec<Scored>> {
+        Ok(self
+            .redis_zset(key)?
+            .map(|zset| range_of(zset, query).into_iter().map(to_scored).collect())
+            .unwrap_or_default())
+    }
+
+    fn zrangestore(&mut self, destination: &[u8], source: &[u8], query: &ZRangeQuery) -> Result<usize> {
+        let mut stored = RedisZSet::new();
+        for (member, score) in self.zrange(source, query)? {
+            stored.insert(member, score);
+        }
+        self.store_zset(destination, stored)
+    }
+
+    fn zpop(&mut self, key: &[u8], end: ZEnd, count: usize) -> Result<Vec<Scored>> {
+        Ok(self
+            .modify(key, |stored| Ok(as_zset(stored)?.pop(end == ZEnd::Max, count)))?
+            .unwrap_or_default())
+    }
+
+    fn zset_op_store(
+        &mut self,
+        op: SetOp,
+        destination: &[u8],
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        how: Aggregate,
+    ) -> Result<usize> {
+        let sources = self
+            .values(keys)?
+            .into_iter()
+            .map(|value| match value {
+                Some(StorageValue::ZSet(zset)) => Ok(Some(Source::Sorted(zset))),
+                Some(StorageValue::Set(set)) => Ok(Some(Source::Plain(set))),
+                Some(_) => Err(RedisError::WrongType),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut stored = RedisZSet::new();
+        for (member, score) in combine(op, &sources, weights, how) {
+            stored.insert(member, score);
+        }
+        self.store_zset(destination, stored)
+    }
+
+    fn zscan(&mut self, key: &[u8], cursor: u64, options: &ScanOptions) -> Result<(u64, Vec<Scored>)> {
+        let zset = match self.redis_zset(key)? {
+            Some(zset) => zset,
+            None => return Ok((0, Vec::new())),
+        };
+
+        let (next, members) = zset.scan(cursor, options.count);
+        let members = members
+            .into_iter()
+            .filter(|(member, _)| options.pattern.as_deref().is_none_or(|pattern| glob::matches(pattern, member)))
+            .map(to_scored)
+            .collect();
+        Ok((next, members))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ScoreBound, SetStorage};
+
+    fn scored(items: &[(&str, f64)]) -> Vec<Scored> {
+        items.iter().map(|(member, score)| (member.as_bytes().to_vec(), *score)).collect()
+    }
+
+    fn add(storage: &mut MemoryStorage, key: &[u8], items: &[(&str, f64)]) {
+        let members = items.iter().map(|(member, score)| (*score, member.as_bytes().to_vec())).collect();
+        storage.zadd(key, members, ZAddOptions::default()).unwrap();
+    }
+
+    fn by_rank(start: i64, stop: i64, rev: bool) -> ZRangeQuery {
+        ZRangeQuery { by: ZRangeBy::Rank(start, stop), rev, offset: 0, limit: None, with_scores: false }
+    }
+
+    #[test]
+    fn test_zadd_options() {
+        let mut storage = MemoryStorage::new(1024);
+        add(&mut storage, b"z", &[("a", 1.0), ("b", 2.0)]);
+
+        let nx = ZAddOptions { condition: Some(SetCondition::Nx), ..ZAddOptions::default() };
+        assert_eq!(storage.zadd(b"z", vec![(5.0, b"a".to_vec()), (3.0, b"c".to_vec())], nx).unwrap(), 1);
+        assert_eq!(storage.zscore(b"z", b"a").unwrap(), Some(1.0));
+
+        let gt_ch = ZAddOptions { comparison: Some(ScoreComparison::Gt), ch: true, ..ZAddOptions::default() };
+        assert_eq!(storage.zadd(b"z", vec![(0.0, b"a".to_vec()), (9.0, b"b".to_vec())], gt_ch).unwrap(), 1);
+        assert_eq!(storage.zscore(b"z", b"b").unwrap(), Some(9.0));
+
+        let xx = ZAddOptions { condition: Some(SetCondition::Xx), ..ZAddOptions::default() };
+        assert_eq!(storage.zincr(b"z", b"new".to_vec(), 1.0, xx).unwrap(), None);
+        assert_eq!(storage.zincr(b"z", b"a".to_vec(), 1.5, xx).unwrap(), Some(2.5));
+        assert!(storage.zincr(b"z", b"a".to_vec(), f64::INFINITY, xx).is_ok());
+        assert!(storage.zincr(b"z", b"a".to_vec(), f64::NEG_INFINITY, xx).is_err());
+
+        // XX on a missing key does not leave an empty one behind
+        assert_eq!(storage.zadd(b"missing", vec![(1.0, b"a".to_vec())], xx).unwrap(), 0);
+        assert!(!storage.exists(b"missing").unwrap());
+    }
+
+    #[test]
+    fn test_ranks_and_ranges() {
+        let mut storage = MemoryStorage::new(1024);
+        add(&mut storage, b"z", &[("a", 1.0), ("b", 2.0), ("c", 3.0)]);
+        assert_eq!(storage.zrank(b"z", b"c", false).unwrap(), Some((2, 3.0)));
+        assert_eq!(storage.zrank(b"z", b"c", true).unwrap(), Some((0, 3.0)));
+        assert_eq!(storage.zrange(b"z", &by_rank(-2, -1, false)).unwrap(), scored(&[("b", 2.0), ("c", 3.0)]));
+        assert_eq!(storage.zrange(b"z", &by_rank(0, 100, true)).unwrap().len(), 3);
+        assert!(storage.zrange(b"z", &by_rank(2, 1, false)).unwrap().is_empty());
+
+        let all = ScoreRange {
+            min: ScoreBound { value: f64::NEG_INFINITY, exclusive: false },
+            max: ScoreBound { value: 2.0, exclusive: false },
+        };
+        assert_eq!(storage.zcount(b"z", &all).unwrap(), 2);
+        let query = ZRangeQuery { by: ZRangeBy::Score(all), rev: true, offset: 0, limit: Some(1), with_scores: true };
+        assert_eq!(storage.zrangestore(b"dst", b"z", &query).unwrap(), 1);
+        assert_eq!(storage.zrange(b"dst", &by_rank(0, -1, false)).unwrap(), scored(&[("b", 2.0)]));
+    }
+
+    #[test]
+    fn test_zpop_deletes_empty_key() {
+        let mut storage = MemoryStorage::new(1024);
+        add(&mut storage, b"z", &[("a", 1.0), ("b", 2.0)]);
+        assert_eq!(storage.zpop(b"z", ZEnd::Max, 1).unwrap(), scored(&[("b", 2.0)]));
+        assert_eq!(storage.zpop(b"z", ZEnd::Min, 5).unwrap(), scored(&[("a", 1.0)]));
+        assert!(!storage.exists(b"z").unwrap());
+        assert_eq!(storage.dataset_memory(), 0);
+    }
+
+    #[test]
+    fn test_union_and_inter_store() {
+        let mut storage = MemoryStorage::new(1024);
+        add(&mut storage, b"z1", &[("a", 1.0), ("b", 2.0)]);
+        add(&mut storage, b"z2", &[("b", 3.0), ("c", 4.0)]);
+        storage.sadd(b"s", vec![b"b".to_vec(), b"c".to_vec()]).unwrap();
+        let keys = vec![b"z1".to_vec(), b"z2".to_vec(), b"s".to_vec()];
+
+        assert_eq!(storage.zset_op_store(SetOp::Union, b"u", &keys, &[1.0, 2.0, 1.0], Aggregate::Sum).unwrap(), 3);
+        assert_eq!(
+            storage.zrange(b"u", &by_rank(0, -1, false)).unwrap(),
+            scored(&[("a", 1.0), ("b", 9.0), ("c", 9.0)])
+        );
+
+        assert_eq!(storage.zset_op_store(SetOp::Inter, b"i", &keys, &[], Aggregate::Max).unwrap(), 1);
+        assert_eq!(storage.zscore(b"i", b"b").unwrap(), Some(3.0));
+
+        // a missing input empties an intersection, which deletes the destination
+        let missing = vec![b"z1".to_vec(), b"nope".to_vec()];
+        assert_eq!(storage.zset_op_store(SetOp::Inter, b"i", &missing, &[], Aggregate::Sum).unwrap(), 0);
+        assert!(!storage.exists(b"i").unwrap());
+    }
+}
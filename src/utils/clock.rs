@@ -0,0 +1,122 @@
+// where the server gets the time from, so expiry, lfu decay, idle times and blocking
+// timeouts can be driven by hand in tests
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub trait Clock: fmt::Debug {
+    // wall-clock time, for deadlines, stream ids and the lru / lfu clocks
+    fn now(&self) -> SystemTime;
+    // monotonic time, for timeouts and measuring how long something took
+    fn instant(&self) -> Instant;
+
+    fn now_ms(&self) -> u64 {
+        self.now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock + Send + Sync>;
+
+// the real clock: unix time comes straight from the system, so EXPIREAT, EXPIRETIME and
+// stream ids follow it when it is changed, while timeouts and durations use the monotonic
+// clock and never jump
+#[derive(Debug, Default)]
+pub struct MonotonicClock;
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn shared() -> SharedClock {
+        Arc::new(Self::new())
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// a clock that only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    wall: SystemTime,
+    start: Instant,
+    elapsed: Mutex<Duration>,
+    step: Mutex<Duration>,
+}
+
+impl MockClock {
+    // starts at the given wall time
+    pub fn new(wall: SystemTime) -> Self {
+        Self { wall, start: Instant::now(), elapsed: Mutex::new(Duration::ZERO), step: Mutex::new(Duration::ZERO) }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    // from now on every read of the monotonic time also moves the clock on by `step`, so
+    // time can pass inside a single call such as an expire cycle
+    pub fn set_step(&self, step: Duration) {
+        *self.step.lock().unwrap() = step;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(SystemTime::now())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        self.wall + self.elapsed()
+    }
+
+    fn instant(&self) -> Instant {
+        let mut elapsed = self.elapsed.lock().unwrap();
+        let now = self.start + *elapsed;
+        *elapsed += *self.step.lock().unwrap();
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1000));
+        let start = clock.instant();
+        assert_eq!(clock.now_ms(), 1_000_000);
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now_ms(), 1_001_500);
+        assert_eq!(clock.instant() - start, Duration::from_millis(1500));
+
+        clock.set_step(Duration::from_millis(1));
+        let (first, second) = (clock.instant(), clock.instant());
+        assert_eq!(second - first, Duration::from_millis(1));
+        assert_eq!(clock.now_ms(), 1_001_502);
+    }
+
+    #[test]
+    fn test_monotonic_clock() {
+        let clock = MonotonicClock::new();
+        let (first, first_instant) = (clock.now(), clock.instant());
+        assert!(clock.now() >= first && clock.instant() >= first_instant);
+        let system_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        assert!(clock.now_ms().abs_diff(system_ms) < 1000);
+    }
+}
@@ -7,13 +7,27 @@ pub enum RedisError {
     // Storage errors
     KeyNotFound,
     WrongType,
+    // a string that is not a HyperLogLog given to a PF* command
+    InvalidHll,
+    // a sparse HyperLogLog whose opcodes do not add up
+    CorruptHll,
     NotInteger,
+    NotFloat,
     OutOfMemory,
     
     // Protocol errors
     ParseError(String),
     InvalidCommand(String),
     InvalidArgumentCount { cmd: String, expected: usize, got: usize },
+    NoProto,
+    WrongPass,
+    InvalidClientName,
+    SyntaxError,
+    // a command specific complaint, reported as `ERR <message>`
+    InvalidArgument(String),
+    // a missing stream consumer group, with the command specific explanation
+    NoGroup(String),
+    BusyGroup,
     
     // System errors
     IOError(io::Error),
@@ -27,14 +41,24 @@ impl fmt::Display for RedisError {
         match self {
             RedisError::KeyNotFound => write!(f, "ERR no such key"),
             RedisError::WrongType => write!(f, "WRONGTYPE Operation against a key holding the wrong kind of value"),
+            RedisError::InvalidHll => write!(f, "WRONGTYPE Key is not a valid HyperLogLog string value."),
+            RedisError::CorruptHll => write!(f, "INVALIDOBJ Corrupted HLL object detected"),
             RedisError::OutOfMemory => write!(f, "OOM command not allowed when used memory > 'maxmemory'"),
             RedisError::ParseError(msg) => write!(f, "ERR Protocol error: {}", msg),
             RedisError::InvalidCommand(cmd) => write!(f, "ERR unknown command '{}'", cmd),
             RedisError::InvalidArgumentCount { cmd, expected, got } => 
                 write!(f, "ERR wrong number of arguments for '{}' command: expected {}, got {}", cmd, expected, got),
+            RedisError::NoProto => write!(f, "NOPROTO unsupported protocol version"),
+            RedisError::WrongPass => write!(f, "WRONGPASS invalid username-password pair or user is disabled."),
+            RedisError::InvalidClientName => write!(f, "ERR Client names cannot contain spaces, newlines or special characters."),
+            RedisError::SyntaxError => write!(f, "ERR syntax error"),
+            RedisError::InvalidArgument(msg) => write!(f, "ERR {}", msg),
+            RedisError::NoGroup(msg) => write!(f, "NOGROUP {}", msg),
+            RedisError::BusyGroup => write!(f, "BUSYGROUP Consumer Group name already exists"),
             RedisError::IOError(err) => write!(f, "ERR IO error: {}", err),
             RedisError::Internal(msg) => write!(f, "ERR internal error: {}", msg),
-            RedisError::NotInteger => write!(f, "ERR value is not an integer"),
+            RedisError::NotInteger => write!(f, "ERR value is not an integer or out of range"),
+            RedisError::NotFloat => write!(f, "ERR value is not a valid float"),
         }
     }
 }
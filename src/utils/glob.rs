@@ -0,0 +1,95 @@
+// glob-style matching as used by SCAN MATCH and friends, following redis' stringmatchlen:
+// `*` and `?` wildcards, `[abc]` / `[^a-z]` classes and `\` escapes, all on raw bytes
+pub fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            // collapse runs of stars, then try every possible split of the text
+            let rest = trim_stars(rest);
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=text.len()).any(|start| matches(rest, &text[start..]))
+        }
+        Some((b'?', rest)) => !text.is_empty() && matches(rest, &text[1..]),
+        Some((b'[', rest)) => match text.split_first() {
+            Some((&byte, text)) => {
+                let (matched, rest) = class(rest, byte);
+                matched && matches(rest, text)
+            }
+            None => false,
+        },
+        Some((b'\\', rest)) if !rest.is_empty() => {
+            text.first() == Some(&rest[0]) && matches(&rest[1..], &text[1..])
+        }
+        Some((&byte, rest)) => text.first() == Some(&byte) && matches(rest, &text[1..]),
+    }
+}
+
+fn trim_stars(mut pattern: &[u8]) -> &[u8] {
+    while let Some((b'*', rest)) = pattern.split_first() {
+        pattern = rest;
+    }
+    pattern
+}
+
+// matches `byte` against the class body after `[`; returns the verdict and the pattern
+// left after the closing `]` (an unterminated class runs to the end, like in redis)
+fn class(pattern: &[u8], byte: u8) -> (bool, &[u8]) {
+    let (negate, mut pattern) = match pattern.split_first() {
+        Some((b'^', rest)) => (true, rest),
+        _ => (false, pattern),
+    };
+
+    let mut matched = false;
+    loop {
+        match pattern {
+            [] => break,
+            [b']', rest @ ..] => {
+                pattern = rest;
+                break;
+            }
+            [b'\\', escaped, rest @ ..] => {
+                matched |= *escaped == byte;
+                pattern = rest;
+            }
+            [start, b'-', end, rest @ ..] if *end != b']' => {
+                let (low, high) = if start <= end { (*start, *end) } else { (*end, *start) };
+                matched |= (low..=high).contains(&byte);
+                pattern = rest;
+            }
+            [single, rest @ ..] => {
+                matched |= *single == byte;
+                pattern = rest;
+            }
+        }
+    }
+
+    (matched != negate, pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches(b"*", b""));
+        assert!(matches(b"h*o", b"hello"));
+        assert!(matches(b"h?llo", b"hallo"));
+        assert!(!matches(b"h?llo", b"hllo"));
+        assert!(matches(b"**a**", b"banana"));
+        assert!(!matches(b"*x*", b"banana"));
+    }
+
+    #[test]
+    fn test_classes_and_escapes() {
+        assert!(matches(b"h[ae]llo", b"hello"));
+        assert!(!matches(b"h[^e]llo", b"hello"));
+        assert!(matches(b"h[a-b]llo", b"hbllo"));
+        assert!(matches(b"h[z-a]llo", b"hbllo"));
+        assert!(matches(b"user\\*", b"user*"));
+        assert!(!matches(b"user\\*", b"users"));
+        assert!(matches(b"[\\]]", b"]"));
+    }
+}
@@ -1,2 +1,4 @@
+pub mod clock;
 pub mod error;
-pub mod stats;
\ No newline at end of file
+pub mod glob;
+pub mod stats;